@@ -0,0 +1,209 @@
+//! Facts expected inline, annotating the statement which emits them:
+//!
+//! ```notrust
+//! bb0: {
+//!     y = &'L_x x; //~ clear_origin('L_x) introduce_subset('L_x, 'y)
+//!     z = copy y;  //~ introduce_subset('y, 'z)
+//! }
+//! ```
+//!
+//! An annotation lists facts without their node, the last argument of the relations, which is
+//! the node of the statement on its line. Unlike a snapshot of all the facts of a program, each
+//! annotation only checks the facts it names, at that exact node, so a regression test
+//! documents the emitter behavior it covers next to the statement causing it.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use crate::ast::Statement;
+use crate::fact_emitter::{self, FactEmitter, Options};
+use crate::facts::Node;
+
+#[cfg(test)]
+mod test;
+
+/// A fact of an annotation, e.g. `introduce_subset('a, 'x)` in `//~ introduce_subset('a, 'x)`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Annotation {
+    /// The annotated line, starting at 1.
+    pub line: usize,
+    pub relation: String,
+
+    /// The arguments of the fact, without its node.
+    pub args: Vec<String>,
+}
+
+impl fmt::Display for Annotation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}({})", self.relation, self.args.join(", "))
+    }
+}
+
+/// Parses the `//~` comments of `input`, an AST program, in order. A comment can list several
+/// facts, separated by spaces or commas. Like in rustc's UI tests, each `^` after the `//~`
+/// annotates the line above instead, e.g. to continue a long annotation on the next line with
+/// `//~^`.
+pub fn parse_annotations(input: &str) -> eyre::Result<Vec<Annotation>> {
+    let mut annotations = vec![];
+    for (i, line) in input.lines().enumerate() {
+        let rest = match line.find("//~") {
+            Some(comment) => &line[comment + 3..],
+            None => continue,
+        };
+        let carets = rest.len() - rest.trim_start_matches('^').len();
+        let mut rest = rest[carets..].trim();
+        let annotated = match (i + 1).checked_sub(carets) {
+            Some(annotated) if annotated > 0 => annotated,
+            _ => eyre::bail!(
+                "line {}: the annotation points before the first line",
+                i + 1
+            ),
+        };
+        if rest.is_empty() {
+            eyre::bail!("line {}: the annotation lists no facts", i + 1);
+        }
+        while !rest.is_empty() {
+            let (annotation, tail) = parse_fact(annotated, rest).ok_or_else(|| {
+                eyre::eyre!(
+                    "line {}: expected `<relation>(<arguments>)`, found `{}`",
+                    i + 1,
+                    rest
+                )
+            })?;
+            annotations.push(annotation);
+            rest = tail.trim_start_matches(|c: char| c == ',' || c.is_whitespace());
+        }
+    }
+    Ok(annotations)
+}
+
+/// Parses the fact at the start of `input`, and returns the rest.
+fn parse_fact(line: usize, input: &str) -> Option<(Annotation, &str)> {
+    let (relation, rest) = input.split_once('(')?;
+    let (args, rest) = rest.split_once(')')?;
+    let relation = relation.trim();
+    if relation.is_empty() || !relation.chars().all(|c| c.is_alphanumeric() || c == '_') {
+        return None;
+    }
+    let args = args
+        .split(',')
+        .map(str::trim)
+        .filter(|arg| !arg.is_empty())
+        .map(str::to_string)
+        .collect();
+    let annotation = Annotation {
+        line,
+        relation: relation.to_string(),
+        args,
+    };
+    Some((annotation, rest))
+}
+
+/// An annotation whose fact isn't emitted at the node of its statement.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UnmetAnnotation {
+    pub annotation: Annotation,
+    pub node: Node,
+
+    /// The annotated statement.
+    pub statement: String,
+
+    /// The nodes where the fact is emitted instead, if any.
+    pub emitted_at: Vec<Node>,
+}
+
+impl fmt::Display for UnmetAnnotation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "line {}: `{}` is not emitted at `{}` (`{}`)",
+            self.annotation.line, self.annotation, self.node, self.statement
+        )?;
+        if !self.emitted_at.is_empty() {
+            write!(f, ", but at `{}`", self.emitted_at.join("`, `"))?;
+        }
+        Ok(())
+    }
+}
+
+/// Emits the facts of the AST program `input`, and returns the annotations whose fact isn't
+/// emitted at the node of their statement. The nodes are named with
+/// `Options::stable_node_names`, and the other `options` are unchanged.
+///
+/// An annotation must be on the line of a single statement of the blocks of the program, which
+/// emits facts: not a ghost statement, an outlives constraint, or a macro expanding to several
+/// statements.
+pub fn check_annotations(input: &str, options: &Options) -> eyre::Result<Vec<UnmetAnnotation>> {
+    if options.introduce_temporaries {
+        eyre::bail!("annotations don't support `introduce_temporaries`, which moves statements");
+    }
+    let annotations = parse_annotations(input)?;
+    let (program, declarations) = crate::parse_ast_with_declarations(input)?;
+
+    // The statements on each line, with their node unless they emit no facts.
+    let mut lines: BTreeMap<usize, Vec<(Option<Node>, &Statement)>> = BTreeMap::new();
+    let mut statement_lines = declarations.statement_lines().iter();
+    for block in &program.basic_blocks {
+        let mut index = 0;
+        for statement in &block.statements {
+            let line = *statement_lines
+                .next()
+                .expect("the parser records the line of every statement");
+            let node = match statement {
+                Statement::Assert(_) | Statement::Outlives(..) => None,
+                _ => {
+                    index += 1;
+                    Some(fact_emitter::stable_node_name(&block.name, index - 1))
+                }
+            };
+            lines.entry(line).or_default().push((node, statement));
+        }
+    }
+
+    let facts = FactEmitter::from_program(
+        program.clone(),
+        Options {
+            stable_node_names: true,
+            ..options.clone()
+        },
+    )?;
+    let relations = facts.relations();
+
+    let mut unmet = vec![];
+    for annotation in annotations {
+        let line = annotation.line;
+        let (node, statement) = match lines.get(&line).map(Vec::as_slice) {
+            Some([(Some(node), statement)]) => (node, statement),
+            Some([(None, statement)]) => eyre::bail!(
+                "line {}: `{}` emits no facts, annotate the statement it applies to",
+                line,
+                statement
+            ),
+            Some(_) => eyre::bail!("line {}: the annotation is on several statements", line),
+            None => eyre::bail!("line {}: the annotation is not on a statement", line),
+        };
+        let (_, rows) = relations
+            .iter()
+            .find(|(relation, _)| *relation == annotation.relation)
+            .ok_or_else(|| {
+                eyre::eyre!("line {}: unknown relation `{}`", line, annotation.relation)
+            })?;
+        let args: Vec<&str> = annotation.args.iter().map(String::as_str).collect();
+        let nodes: BTreeSet<&str> = rows
+            .iter()
+            .filter_map(|row| match row.split_last() {
+                Some((node, row_args)) if *row_args == args[..] => Some(*node),
+                _ => None,
+            })
+            .collect();
+        if !nodes.contains(node.as_str()) {
+            unmet.push(UnmetAnnotation {
+                node: node.clone(),
+                statement: statement.to_string(),
+                emitted_at: nodes.into_iter().map(str::to_string).collect(),
+                annotation,
+            });
+        }
+    }
+    Ok(unmet)
+}
@@ -0,0 +1,110 @@
+use super::*;
+
+fn unmet(input: &str) -> String {
+    let unmet = check_annotations(input, &Options::default()).unwrap();
+    let lines: Vec<String> = unmet.iter().map(|u| u.to_string()).collect();
+    lines.join("\n")
+}
+
+fn error(input: &str) -> String {
+    check_annotations(input, &Options::default())
+        .unwrap_err()
+        .to_string()
+}
+
+#[test]
+fn parse() {
+    let input = "
+        x = 22; // A comment.
+        y = &'L_x x; //~ clear_origin('L_x) introduce_subset('L_x, 'y)
+        z = copy y; //~ introduce_subset('y, 'z), access_origin('y)
+        //~^ invalidate_origin('L_y)
+        //~ node_is_exit()
+    ";
+    let printed: Vec<_> = parse_annotations(input)
+        .unwrap()
+        .iter()
+        .map(|a| format!("{}: {}", a.line, a))
+        .collect();
+    insta::assert_debug_snapshot!(printed, @r###"
+    [
+        "3: clear_origin('L_x)",
+        "3: introduce_subset('L_x, 'y)",
+        "4: introduce_subset('y, 'z)",
+        "4: access_origin('y)",
+        "4: invalidate_origin('L_y)",
+        "6: node_is_exit()",
+    ]
+    "###);
+}
+
+#[test]
+fn parse_errors() {
+    let error = |input| parse_annotations(input).unwrap_err().to_string();
+    insta::assert_snapshot!(error("x = 22; //~"), @"line 1: the annotation lists no facts");
+    insta::assert_snapshot!(error("x = 22;\n//~^^ invalidate_origin('L_x)"), @"line 2: the annotation points before the first line");
+    insta::assert_snapshot!(error("\nx = 22; //~ invalidate_origin('L_x"), @"line 2: expected `<relation>(<arguments>)`, found `invalidate_origin('L_x`");
+    insta::assert_snapshot!(error("x = 22; //~ invalidate_origin('L_x) and more"), @"line 1: expected `<relation>(<arguments>)`, found `and more`");
+}
+
+#[test]
+fn met_annotations() {
+    let input = "
+        let mut x: i32;
+        let y: &'y i32;
+        let mut z: &'z i32;
+        bb0: {
+            x = 22;          //~ invalidate_origin('L_x)
+            y = &'L_x x;     //~ clear_origin('L_x) introduce_subset('L_x, 'y)
+            assert_live('y);
+            z = copy y;      //~ access_origin('y) introduce_subset('y, 'z)
+            goto bb1;
+        }
+        bb1: {
+            x = 23;          //~ invalidate_origin('L_x)
+        }
+    ";
+    assert_eq!(unmet(input), "");
+}
+
+#[test]
+fn unmet_annotations() {
+    // The facts of each statement are annotated on the next one.
+    let input = "
+        let mut x: i32;
+        let y: &'y i32;
+        let mut z: &'z i32;
+        bb0: {
+            y = &'L_x x;
+            z = copy y;  //~ introduce_subset('L_x, 'y) clear_origin('L_x)
+            x = 23;      //~ introduce_subset('y, 'z) invalidate_origin('L_x)
+        }
+    ";
+    insta::assert_snapshot!(unmet(input), @r###"
+    line 7: `introduce_subset('L_x, 'y)` is not emitted at `bb0s1` (`z = copy y`), but at `bb0s0`
+    line 7: `clear_origin('L_x)` is not emitted at `bb0s1` (`z = copy y`), but at `bb0s0`
+    line 8: `introduce_subset('y, 'z)` is not emitted at `bb0s2` (`x = 23`), but at `bb0s1`
+    "###);
+}
+
+#[test]
+fn misplaced_annotations() {
+    let program = |statements: &str| {
+        format!(
+            "
+            let mut x: i32;
+            let mut y: i32;
+            let r: &'r i32;
+            bb0: {{
+                {}
+            }}
+            ",
+            statements
+        )
+    };
+    insta::assert_snapshot!(error(&program("x = 1;\n//~ invalidate_origin('L_x)")), @"line 7: the annotation is not on a statement");
+    insta::assert_snapshot!(error(&program("assert_live('r); //~ access_origin('r)\nr = &'L_x x;")), @"line 6: `assert_live('r)` emits no facts, annotate the statement it applies to");
+    insta::assert_snapshot!(error(&program("swap!(x, y); //~ access_origin('r)")), @"line 6: the annotation is on several statements");
+    insta::assert_snapshot!(error(&program("x = 1; y = 2; //~ access_origin('r)")), @"line 6: the annotation is on several statements");
+    insta::assert_snapshot!(error(&program("r = &'L_x x; //~ borrow_region('r)")), @"line 6: unknown relation `borrow_region`");
+}
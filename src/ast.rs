@@ -1,60 +1,284 @@
-#[derive(Clone, Debug)]
+//! The AST language, a small Rust-like language for MIR-shaped programs. A program declares its
+//! modules and items, then its variables, then its blocks:
+//!
+//! ```notrust
+//! mod collections { struct Vec<'a, T> { first: &'a T } }
+//! struct Pair<'a, T: 'a> { first: &'a T, second: &'a T }
+//! impl Drop for Pair;
+//! type Ref<'a> = &'a i32;
+//! fn pick<'a, 'b>(x: &'a i32, y: &'b i32) -> &'a i32;
+//! extern fn log<'a>(x: &'a i32) -> ();
+//! let mut x: i32;
+//! let p: for<'x> fn(&'x i32) -> i32;
+//! let mut o: Option<Ref<'o>>;
+//! bb0: {
+//!     x = 22;
+//!     o = Some(&'L_x x);
+//!     goto [then] bb1, [else] bb2;
+//! }
+//! bb1: { return; }
+//! bb2: { drop(o); }
+//! ```
+//!
+//! Locals are declared `let mut` to be assigned more than once, or to be borrowed mutably. A
+//! block ends with a `goto`, whose successors can be labeled or weighted, a `match` on a builtin
+//! enum, a `switch` on an `i32`, an `if let`, or `return;`. A program can declare a `return`
+//! place, read after the locals die, and functions can be defined with a body, which is checked
+//! on its own while calls are checked through the signature. An input can hold several named
+//! programs, as `program foo { .. }` sections.
+//!
+//! Besides assignments, the statements are `drop(x);`, `pass;`, the statement macros `swap!`
+//! and `borrow_field!`, and the ghost statements stating expectations: `assert_live('a);`,
+//! `assert_error;`, `assert_empty('a);`, `assert_each_iteration(fact);`, and `'a: 'b;`, which
+//! introduces a subset. The facts a statement emits can be annotated at the end of its line,
+//! with `//~ fact`, see `annotations`.
+
+use std::fmt;
+
+use serde::Serialize;
+
+pub mod visit;
+
+#[derive(Clone, Debug, Serialize)]
 pub struct Program {
+    pub extern_types: Vec<Name>,
     pub struct_decls: Vec<StructDecl>,
+    pub type_aliases: Vec<TypeAlias>,
     pub fn_prototypes: Vec<FnPrototype>,
     pub variables: Vec<VariableDecl>,
     pub basic_blocks: Vec<BasicBlock>,
+
+    /// The bodies of the functions defined by the program, rather than only declared. Their
+    /// prototypes are in `fn_prototypes`, so that the calls are checked against them.
+    pub fn_bodies: Vec<FnBody>,
 }
 
-#[derive(Clone, Debug)]
+/// `mod collections { struct Vec<T> { .. } fn push<T>(..) -> (); }`, a namespace whose items
+/// are named by paths like `collections::Vec`. The modules are only in the parsed input: they
+/// are flattened by `resolve::flatten_modules`, which declares their items in the program under
+/// their paths.
+#[derive(Clone, Debug, Serialize)]
+pub struct Module {
+    pub name: Name,
+    pub extern_types: Vec<Name>,
+    pub modules: Vec<Module>,
+    pub struct_decls: Vec<StructDecl>,
+    pub type_aliases: Vec<TypeAlias>,
+    pub fn_prototypes: Vec<FnPrototype>,
+}
+
+#[derive(Clone, Debug, Serialize)]
 pub struct StructDecl {
     pub name: Name,
     pub generic_decls: Vec<GenericDecl>,
     pub field_decls: Vec<VariableDecl>,
+
+    /// Whether the struct has a destructor, declared by `impl Drop for S;` after the struct.
+    /// Dropping a value of the struct may then observe every origin of its type, see
+    /// `typeck::TypeEnv::drop_origins`.
+    pub has_destructor: bool,
+}
+
+/// `type IterRef<'a, T> = &'a mut Iter<'a, T>;`. The uses of an alias are replaced by the
+/// aliased type before the program is checked, see `typeck::expand_type_aliases`.
+#[derive(Clone, Debug, Serialize)]
+pub struct TypeAlias {
+    pub name: Name,
+    pub generic_decls: Vec<GenericDecl>,
+    pub ty: Ty,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize)]
 pub struct VariableDecl {
     pub name: Name,
     pub ty: Ty,
+
+    /// Whether the variable is declared with `let mut`. Always false for fields and arguments.
+    pub mutable: bool,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize)]
 pub struct FnPrototype {
     pub name: Name,
     pub generic_decls: Vec<GenericDecl>,
     pub arg_tys: Vec<Ty>,
     pub ret_ty: Ty,
+    pub kind: FnKind,
+}
+
+/// The body of `fn f<'a>(x: &'a i32) -> &'a i32 { let y: i32; bb0: { .. } }`, which is analyzed
+/// on its own, see `Program::function_program`.
+#[derive(Clone, Debug, Serialize)]
+pub struct FnBody {
+    pub name: Name,
+
+    /// The names of the parameters, whose types are the `arg_tys` of the prototype.
+    pub parameters: Vec<Name>,
+
+    /// The locals declared by the body.
+    pub variables: Vec<VariableDecl>,
+    pub basic_blocks: Vec<BasicBlock>,
+}
+
+/// What a call to a function may do besides relating its arguments and result as required by
+/// its signature.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub enum FnKind {
+    /// Unmarked functions have no hidden effects.
+    #[default]
+    Default,
+
+    /// `pure fn`: states explicitly that the function has no hidden effects, which is also what
+    /// is assumed for unmarked functions.
+    Pure,
+
+    /// `extern fn`: the body of the function is unknown, so the worst is assumed: a call
+    /// accesses every origin of its arguments, as the callee may dereference any reference
+    /// it is given.
+    Extern,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize)]
 pub enum GenericDecl {
     Origin(Name),
-    Ty(Name),
+
+    /// A type parameter, and the origins it must outlive, e.g. `T: 'a`.
+    Ty(Name, Vec<Name>),
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize)]
 pub struct BasicBlock {
     pub name: Name,
     pub statements: Vec<Statement>,
-    pub successors: Vec<Name>,
+    pub successors: Vec<Successor>,
+
+    /// The place a block ends by branching on, with a `match` or a `switch` rather than a
+    /// `goto`.
+    pub scrutinee: Option<Scrutinee>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub enum Scrutinee {
+    /// A value of a builtin enum, in `match x { Some => bb1, None => bb2 }`. Each successor is
+    /// labeled with the variant it is taken for.
+    Match(Place),
+
+    /// An `i32`, in `switch x { 0 => bb1, 1 => bb2, _ => bb3 }`. Each successor is labeled
+    /// with the value it is taken for, except the last one, the `_` arm taken for the other
+    /// values.
+    Switch(Place),
+}
+
+impl Scrutinee {
+    pub fn place(&self) -> &Place {
+        match self {
+            Scrutinee::Match(place) | Scrutinee::Switch(place) => place,
+        }
+    }
+
+    pub fn place_mut(&mut self) -> &mut Place {
+        match self {
+            Scrutinee::Match(place) | Scrutinee::Switch(place) => place,
+        }
+    }
+}
+
+/// Prints the terminator without its arms, e.g. `match x`.
+impl fmt::Display for Scrutinee {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Scrutinee::Match(place) => write!(f, "match {}", place),
+            Scrutinee::Switch(place) => write!(f, "switch {}", place),
+        }
+    }
+}
+
+/// A block in the `goto` of another, with an optional label, e.g. `goto [then] bb1, [else]
+/// bb2;`. The order of the successors, and their labels, don't change the facts: the labels
+/// are carried into `cfg_edge_label` for visualizations and experiments.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Successor {
+    pub block: Name,
+
+    /// A name like `then`, or a weight like `0.9`.
+    pub label: Option<Name>,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize)]
 pub enum Statement {
     Assign(Place, Expr),
     Drop(Expr),
+
+    /// `drop(x);`, running the destructors of the value of `x` and leaving it uninitialized,
+    /// like a `Drop` terminator in MIR. It is a deep use of `x`, but only uses the origins
+    /// its destructors may observe.
+    DropPlace(Place),
+
+    /// `pass;`, which has no effects, e.g. a placeholder to give a node a name.
+    Pass,
+
+    /// A ghost statement, which has no effects: it states an expectation about the solver
+    /// output at the node of the next statement.
+    Assert(Assertion),
+
+    /// `'a: 'b;`, a ghost statement introducing the subset `'a: 'b` at the node of the next
+    /// statement, in the direction of the bound `T: 'b`, e.g. to write an example of the rules
+    /// without an assignment inducing the subset.
+    Outlives(Name, Name),
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize)]
+pub enum Assertion {
+    /// `assert_live('a)`: the origin is live.
+    Live(Name),
+
+    /// `assert_error`: an invalidated origin is accessed.
+    Error,
+
+    /// `assert_empty('a)`: no other origin, and so no loan, flows into the origin.
+    Empty(Name),
+
+    /// `assert_each_iteration(invalidate_origin('L_x))`, at the header of a loop: every path
+    /// from the header around the loop back to it goes through a node where the fact holds,
+    /// e.g. the loan is invalidated on every iteration. The node of the fact is left out.
+    EachIteration {
+        relation: Name,
+        arguments: Vec<Name>,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize)]
 pub enum Expr {
-    Access { kind: AccessKind, place: Place },
-    Number { value: i32 },
-    Call { name: Name, arguments: Vec<Expr> },
+    Access {
+        kind: AccessKind,
+        place: Place,
+    },
+    Number {
+        value: i32,
+    },
+    Call {
+        name: Name,
+        arguments: Vec<Expr>,
+    },
+
+    /// A value of a builtin enum, e.g. `Some(move x)` or `None`, see `typeck::BUILTIN_ENUMS`.
+    Variant {
+        name: Name,
+        value: Option<Box<Expr>>,
+    },
+
+    /// A struct literal, e.g. `S { f: move y, ..move z }`. The fields not given are moved, or
+    /// copied, out of the `base`, each on its own: `z` is partially moved, and the fields given
+    /// are left in it.
+    Struct {
+        name: Name,
+        fields: Vec<(Name, Expr)>,
+        base: Option<Box<Expr>>,
+    },
     Unit,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize)]
 pub enum AccessKind {
     Copy,
     Move,
@@ -62,7 +286,7 @@ pub enum AccessKind {
     BorrowMut(Name),
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize)]
 pub enum Ty {
     Ref {
         origin: Name,
@@ -82,18 +306,484 @@ pub enum Ty {
         name: Name,
         parameters: Vec<Parameter>,
     },
+
+    /// A function pointer, e.g. `for<'x> fn(&'x i32) -> i32`. The `bound_origins` are only in
+    /// scope in the argument and return types.
+    Fn {
+        bound_origins: Vec<Name>,
+        arg_tys: Vec<Ty>,
+        ret_ty: Box<Ty>,
+    },
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize)]
 pub enum Parameter {
     Origin(Name),
     Ty(Ty),
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize)]
 pub struct Place {
     pub base: Name,
     pub fields: Vec<Name>,
 }
 
 pub type Name = String;
+
+/// The names the parser reads as something else where a type or a function is named: `i32`
+/// as the type, and the variants of the builtin enums as variant constructors. Such a type or
+/// function is written as a raw identifier, e.g. `r#Some`, and printed back that way. Other
+/// names, even `match` or `pass`, parse as names wherever a name is expected.
+pub const KEYWORDS: &[&str] = &["i32", "Some", "None", "Ok", "Err"];
+
+/// Prints the name of a type or a function so that it parses back as the same name: as a raw
+/// identifier when it is one of the `KEYWORDS`.
+pub struct Ident<'a>(pub &'a str);
+
+impl fmt::Display for Ident<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if KEYWORDS.contains(&self.0) {
+            write!(f, "r#")?;
+        }
+        write!(f, "{}", self.0)
+    }
+}
+
+impl fmt::Display for Statement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Statement::Assign(place, expr) => write!(f, "{} = {}", place, expr),
+            Statement::Drop(expr) => write!(f, "{}", expr),
+            Statement::DropPlace(place) => write!(f, "drop({})", place),
+            Statement::Pass => write!(f, "pass"),
+            Statement::Assert(assertion) => write!(f, "{}", assertion),
+            Statement::Outlives(o1, o2) => write!(f, "{}: {}", o1, o2),
+        }
+    }
+}
+
+impl fmt::Display for Assertion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Assertion::Live(origin) => write!(f, "assert_live({})", origin),
+            Assertion::Error => write!(f, "assert_error"),
+            Assertion::Empty(origin) => write!(f, "assert_empty({})", origin),
+            Assertion::EachIteration {
+                relation,
+                arguments,
+            } => write!(
+                f,
+                "assert_each_iteration({}({}))",
+                relation,
+                arguments.join(", ")
+            ),
+        }
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expr::Access { kind, place } => match kind {
+                AccessKind::Copy => write!(f, "copy {}", place),
+                AccessKind::Move => write!(f, "move {}", place),
+                AccessKind::Borrow(origin) => write!(f, "&{} {}", origin, place),
+                AccessKind::BorrowMut(origin) => write!(f, "&{} mut {}", origin, place),
+            },
+            Expr::Number { value } => write!(f, "{}", value),
+            Expr::Call { name, arguments } => {
+                write!(f, "{}(", Ident(name))?;
+                for (i, argument) in arguments.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", argument)?;
+                }
+                write!(f, ")")
+            }
+            Expr::Variant { name, value } => match value {
+                Some(value) => write!(f, "{}({})", name, value),
+                None => write!(f, "{}", name),
+            },
+            Expr::Struct { name, fields, base } => {
+                let fields = fields
+                    .iter()
+                    .map(|(field, value)| format!("{}: {}", Ident(field), value))
+                    .chain(base.iter().map(|base| format!("..{}", base)))
+                    .collect::<Vec<_>>();
+                write!(f, "{} {{ {} }}", Ident(name), fields.join(", "))
+            }
+            Expr::Unit => write!(f, "()"),
+        }
+    }
+}
+
+impl fmt::Display for Place {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.base)?;
+        for field in &self.fields {
+            write!(f, ".{}", field)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Ty {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Ty::Ref { origin, ty } => write!(f, "&{} {}", origin, ty),
+            Ty::RefMut { origin, ty } => write!(f, "&{} mut {}", origin, ty),
+            Ty::I32 => write!(f, "i32"),
+            Ty::Unit => write!(f, "()"),
+            Ty::Struct { name, parameters } => {
+                write!(f, "{}", Ident(name))?;
+                if !parameters.is_empty() {
+                    write!(f, "<")?;
+                    for (i, parameter) in parameters.iter().enumerate() {
+                        if i > 0 {
+                            write!(f, ", ")?;
+                        }
+                        match parameter {
+                            Parameter::Origin(origin) => write!(f, "{}", origin)?,
+                            Parameter::Ty(ty) => write!(f, "{}", ty)?,
+                        }
+                    }
+                    write!(f, ">")?;
+                }
+                Ok(())
+            }
+            Ty::Fn {
+                bound_origins,
+                arg_tys,
+                ret_ty,
+            } => {
+                if !bound_origins.is_empty() {
+                    write!(f, "for<{}> ", bound_origins.join(", "))?;
+                }
+                write!(f, "fn(")?;
+                for (i, arg_ty) in arg_tys.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", arg_ty)?;
+                }
+                write!(f, ")")?;
+                if **ret_ty != Ty::Unit {
+                    write!(f, " -> {}", ret_ty)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Program {
+    /// The body of the function `body` as a program of its own, with the declarations of this
+    /// program. Its variables are the parameters, the `return` place unless the function
+    /// returns `()`, and the locals of the body. The origins of the signature are chosen by the
+    /// caller: the emitter treats them as external origins, see `FactEmitter::from_functions`.
+    pub fn function_program(&self, body: &FnBody) -> eyre::Result<Program> {
+        let prototype = self
+            .fn_prototypes
+            .iter()
+            .find(|prototype| prototype.name == body.name)
+            .ok_or_else(|| eyre::eyre!("the function `{}` has no prototype", body.name))?;
+        if prototype.arg_tys.len() != body.parameters.len() {
+            eyre::bail!(
+                "the function `{}` has {} parameters, but its prototype has {}",
+                body.name,
+                body.parameters.len(),
+                prototype.arg_tys.len()
+            );
+        }
+        let parameters = body
+            .parameters
+            .iter()
+            .zip(&prototype.arg_tys)
+            .map(|(name, ty)| VariableDecl {
+                name: name.clone(),
+                ty: ty.clone(),
+                mutable: false,
+            });
+        let return_place = (prototype.ret_ty != Ty::Unit).then(|| VariableDecl {
+            name: crate::ir::RETURN_PLACE.to_string(),
+            ty: prototype.ret_ty.clone(),
+            mutable: true,
+        });
+        Ok(Program {
+            extern_types: self.extern_types.clone(),
+            struct_decls: self.struct_decls.clone(),
+            type_aliases: self.type_aliases.clone(),
+            fn_prototypes: self.fn_prototypes.clone(),
+            variables: parameters
+                .chain(return_place)
+                .chain(body.variables.iter().cloned())
+                .collect(),
+            basic_blocks: body.basic_blocks.clone(),
+            fn_bodies: vec![],
+        })
+    }
+}
+
+/// Prints the program back in the syntax of the parser, one declaration or statement per line.
+/// The items named by a path, like `collections::Vec`, are printed in their `mod`.
+impl fmt::Display for Program {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_module_items(f, self, "", "")?;
+        for variable in &self.variables {
+            writeln!(
+                f,
+                "let {}{};",
+                if variable.mutable { "mut " } else { "" },
+                variable
+            )?;
+        }
+        for block in &self.basic_blocks {
+            write!(f, "{}", block)?;
+        }
+        Ok(())
+    }
+}
+
+/// Prints the declarations of the items of the module at `path`, the root module if empty, with
+/// their names relative to it, then the ones of the items of its submodules in their `mod`.
+/// Each line is prefixed by `indent`.
+fn write_module_items(
+    f: &mut fmt::Formatter<'_>,
+    program: &Program,
+    path: &str,
+    indent: &str,
+) -> fmt::Result {
+    // The name of an item relative to the module, if it is one of its items.
+    let local = |name: &str| -> Option<String> {
+        let local = match name.rsplit_once("::") {
+            Some((module, local)) if module == path => local,
+            None if path.is_empty() => name,
+            _ => return None,
+        };
+        Some(local.to_string())
+    };
+    for name in &program.extern_types {
+        if let Some(name) = local(name) {
+            writeln!(f, "{}extern type {};", indent, Ident(&name))?;
+        }
+    }
+
+    // The submodules, in the order of their first item.
+    let prefix = if path.is_empty() {
+        String::new()
+    } else {
+        format!("{}::", path)
+    };
+    let mut modules: Vec<&str> = vec![];
+    let names = (program.extern_types.iter())
+        .chain(program.struct_decls.iter().map(|decl| &decl.name))
+        .chain(program.type_aliases.iter().map(|alias| &alias.name))
+        .chain(
+            program
+                .fn_prototypes
+                .iter()
+                .map(|prototype| &prototype.name),
+        );
+    for name in names {
+        if let Some((module, _)) = name.strip_prefix(&prefix).and_then(|n| n.split_once("::")) {
+            if !modules.contains(&module) {
+                modules.push(module);
+            }
+        }
+    }
+    for module in modules {
+        writeln!(f, "{}mod {} {{", indent, Ident(module))?;
+        let path = format!("{}{}", prefix, module);
+        write_module_items(f, program, &path, &format!("{}    ", indent))?;
+        writeln!(f, "{}}}", indent)?;
+    }
+
+    for decl in &program.struct_decls {
+        if let Some(name) = local(&decl.name) {
+            let decl = StructDecl {
+                name,
+                ..decl.clone()
+            };
+            writeln!(f, "{}{}", indent, decl)?;
+            if decl.has_destructor {
+                writeln!(f, "{}impl Drop for {};", indent, Ident(&decl.name))?;
+            }
+        }
+    }
+    for alias in &program.type_aliases {
+        if let Some(name) = local(&alias.name) {
+            let alias = TypeAlias {
+                name,
+                ..alias.clone()
+            };
+            writeln!(f, "{}{}", indent, alias)?;
+        }
+    }
+    for prototype in &program.fn_prototypes {
+        let name = match local(&prototype.name) {
+            Some(name) => name,
+            None => continue,
+        };
+        match program
+            .fn_bodies
+            .iter()
+            .find(|body| body.name == prototype.name)
+        {
+            Some(body) => write_fn_definition(f, prototype, body)?,
+            None => {
+                let prototype = FnPrototype {
+                    name,
+                    ..prototype.clone()
+                };
+                writeln!(f, "{}{}", indent, prototype)?
+            }
+        }
+    }
+    Ok(())
+}
+
+impl fmt::Display for StructDecl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "struct {}", Ident(&self.name))?;
+        write_generic_decls(f, &self.generic_decls)?;
+        write!(f, " {{ ")?;
+        for (i, field) in self.field_decls.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", field)?;
+        }
+        write!(f, " }}")
+    }
+}
+
+impl fmt::Display for TypeAlias {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "type {}", Ident(&self.name))?;
+        write_generic_decls(f, &self.generic_decls)?;
+        write!(f, " = {};", self.ty)
+    }
+}
+
+/// The arguments are unnamed in the AST, so they are printed as `arg0`, `arg1`, etc.
+impl fmt::Display for FnPrototype {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_signature(f, self, &|i| format!("arg{}", i))?;
+        write!(f, ";")
+    }
+}
+
+/// `fn f<'a>(arg0: &'a i32) -> &'a i32`, where the `i`th parameter is named `parameter(i)`.
+fn write_signature(
+    f: &mut fmt::Formatter<'_>,
+    prototype: &FnPrototype,
+    parameter: &dyn Fn(usize) -> String,
+) -> fmt::Result {
+    match prototype.kind {
+        FnKind::Default => {}
+        FnKind::Pure => write!(f, "pure ")?,
+        FnKind::Extern => write!(f, "extern ")?,
+    }
+    write!(f, "fn {}", Ident(&prototype.name))?;
+    write_generic_decls(f, &prototype.generic_decls)?;
+    write!(f, "(")?;
+    for (i, arg_ty) in prototype.arg_tys.iter().enumerate() {
+        if i > 0 {
+            write!(f, ", ")?;
+        }
+        write!(f, "{}: {}", parameter(i), arg_ty)?;
+    }
+    write!(f, ") -> {}", prototype.ret_ty)
+}
+
+/// `fn f<'a>(x: &'a i32) -> &'a i32 {`, then the locals and blocks of the body, indented.
+fn write_fn_definition(
+    f: &mut fmt::Formatter<'_>,
+    prototype: &FnPrototype,
+    body: &FnBody,
+) -> fmt::Result {
+    write_signature(f, prototype, &|i| match body.parameters.get(i) {
+        Some(name) => Ident(name).to_string(),
+        None => format!("arg{}", i),
+    })?;
+    writeln!(f, " {{")?;
+    for variable in &body.variables {
+        let mutable = if variable.mutable { "mut " } else { "" };
+        writeln!(f, "    let {}{};", mutable, variable)?;
+    }
+    for block in &body.basic_blocks {
+        for line in block.to_string().lines() {
+            writeln!(f, "    {}", line)?;
+        }
+    }
+    writeln!(f, "}}")
+}
+
+fn write_generic_decls(f: &mut fmt::Formatter<'_>, decls: &[GenericDecl]) -> fmt::Result {
+    if decls.is_empty() {
+        return Ok(());
+    }
+    write!(f, "<")?;
+    for (i, decl) in decls.iter().enumerate() {
+        if i > 0 {
+            write!(f, ", ")?;
+        }
+        match decl {
+            GenericDecl::Origin(name) => write!(f, "{}", name)?,
+            GenericDecl::Ty(name, bounds) => {
+                write!(f, "{}", Ident(name))?;
+                if !bounds.is_empty() {
+                    write!(f, ": {}", bounds.join(" + "))?;
+                }
+            }
+        }
+    }
+    write!(f, ">")
+}
+
+/// Prints `name: ty`, without the `let` of a variable declaration.
+impl fmt::Display for VariableDecl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.name, self.ty)
+    }
+}
+
+impl fmt::Display for BasicBlock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}: {{", self.name)?;
+        for statement in &self.statements {
+            writeln!(f, "    {};", statement)?;
+        }
+        if let Some(scrutinee) = &self.scrutinee {
+            write!(f, "    {} {{ ", scrutinee)?;
+            for (i, successor) in self.successors.iter().enumerate() {
+                if i > 0 {
+                    write!(f, ", ")?;
+                }
+                let variant = successor.label.as_deref().unwrap_or("_");
+                write!(f, "{} => {}", variant, successor.block)?;
+            }
+            writeln!(f, " }}")?;
+        } else if !self.successors.is_empty() {
+            write!(f, "    goto ")?;
+            for (i, successor) in self.successors.iter().enumerate() {
+                if i > 0 {
+                    write!(f, ", ")?;
+                }
+                write!(f, "{}", successor)?;
+            }
+            writeln!(f, ";")?;
+        }
+        writeln!(f, "}}")
+    }
+}
+
+impl fmt::Display for Successor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(label) = &self.label {
+            write!(f, "[{}] ", label)?;
+        }
+        write!(f, "{}", self.block)
+    }
+}
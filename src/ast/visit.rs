@@ -0,0 +1,414 @@
+//! Traversals of the AST, so that passes over a program don't each re-implement them.
+//!
+//! A `ProgramVisitor` overrides the `visit_*` methods for the nodes it cares about, and calls
+//! the matching `walk_*` function from them to keep visiting the children of a node. The
+//! default methods only walk. `ProgramVisitorMut` is the same over a mutable program, e.g. to
+//! rewrite types in place.
+//!
+//! The visits are fallible, and stop at the first error. The origins declared by generics, or
+//! bound by function pointer types, are binders rather than uses, so `visit_origin` isn't
+//! called on them: a visitor which cares about them overrides the visit of the declaration, or
+//! `visit_ty`.
+
+use super::{
+    AccessKind, Assertion, BasicBlock, Expr, FnPrototype, Name, Parameter, Place, Program,
+    Statement, StructDecl, Ty, TypeAlias, VariableDecl,
+};
+
+#[cfg(test)]
+mod test;
+
+pub trait ProgramVisitor<'ast> {
+    fn visit_program(&mut self, program: &'ast Program) -> eyre::Result<()> {
+        walk_program(self, program)
+    }
+
+    fn visit_struct_decl(&mut self, decl: &'ast StructDecl) -> eyre::Result<()> {
+        walk_struct_decl(self, decl)
+    }
+
+    fn visit_type_alias(&mut self, alias: &'ast TypeAlias) -> eyre::Result<()> {
+        self.visit_ty(&alias.ty)
+    }
+
+    fn visit_fn_prototype(&mut self, prototype: &'ast FnPrototype) -> eyre::Result<()> {
+        walk_fn_prototype(self, prototype)
+    }
+
+    /// Visits variables, as well as the fields of structs.
+    fn visit_variable_decl(&mut self, variable: &'ast VariableDecl) -> eyre::Result<()> {
+        self.visit_ty(&variable.ty)
+    }
+
+    fn visit_basic_block(&mut self, block: &'ast BasicBlock) -> eyre::Result<()> {
+        walk_basic_block(self, block)
+    }
+
+    fn visit_statement(&mut self, statement: &'ast Statement) -> eyre::Result<()> {
+        walk_statement(self, statement)
+    }
+
+    fn visit_expr(&mut self, expr: &'ast Expr) -> eyre::Result<()> {
+        walk_expr(self, expr)
+    }
+
+    fn visit_place(&mut self, _place: &'ast Place) -> eyre::Result<()> {
+        Ok(())
+    }
+
+    fn visit_ty(&mut self, ty: &'ast Ty) -> eyre::Result<()> {
+        walk_ty(self, ty)
+    }
+
+    /// Visits the origins of types, of borrows, and of assertions.
+    fn visit_origin(&mut self, _origin: &'ast Name) -> eyre::Result<()> {
+        Ok(())
+    }
+}
+
+pub fn walk_program<'ast, V: ProgramVisitor<'ast> + ?Sized>(
+    visitor: &mut V,
+    program: &'ast Program,
+) -> eyre::Result<()> {
+    for decl in &program.struct_decls {
+        visitor.visit_struct_decl(decl)?;
+    }
+    for alias in &program.type_aliases {
+        visitor.visit_type_alias(alias)?;
+    }
+    for prototype in &program.fn_prototypes {
+        visitor.visit_fn_prototype(prototype)?;
+    }
+    for variable in &program.variables {
+        visitor.visit_variable_decl(variable)?;
+    }
+    for block in &program.basic_blocks {
+        visitor.visit_basic_block(block)?;
+    }
+    Ok(())
+}
+
+pub fn walk_struct_decl<'ast, V: ProgramVisitor<'ast> + ?Sized>(
+    visitor: &mut V,
+    decl: &'ast StructDecl,
+) -> eyre::Result<()> {
+    for field in &decl.field_decls {
+        visitor.visit_variable_decl(field)?;
+    }
+    Ok(())
+}
+
+pub fn walk_fn_prototype<'ast, V: ProgramVisitor<'ast> + ?Sized>(
+    visitor: &mut V,
+    prototype: &'ast FnPrototype,
+) -> eyre::Result<()> {
+    for ty in prototype.arg_tys.iter().chain(Some(&prototype.ret_ty)) {
+        visitor.visit_ty(ty)?;
+    }
+    Ok(())
+}
+
+pub fn walk_basic_block<'ast, V: ProgramVisitor<'ast> + ?Sized>(
+    visitor: &mut V,
+    block: &'ast BasicBlock,
+) -> eyre::Result<()> {
+    for statement in &block.statements {
+        visitor.visit_statement(statement)?;
+    }
+    if let Some(scrutinee) = &block.scrutinee {
+        visitor.visit_place(scrutinee.place())?;
+    }
+    Ok(())
+}
+
+pub fn walk_statement<'ast, V: ProgramVisitor<'ast> + ?Sized>(
+    visitor: &mut V,
+    statement: &'ast Statement,
+) -> eyre::Result<()> {
+    match statement {
+        Statement::Assign(place, expr) => {
+            visitor.visit_place(place)?;
+            visitor.visit_expr(expr)
+        }
+        Statement::Drop(expr) => visitor.visit_expr(expr),
+        Statement::DropPlace(place) => visitor.visit_place(place),
+        Statement::Assert(Assertion::Live(origin) | Assertion::Empty(origin)) => {
+            visitor.visit_origin(origin)
+        }
+        Statement::Assert(Assertion::EachIteration { arguments, .. }) => {
+            for argument in arguments.iter().filter(|a| a.starts_with('\'')) {
+                visitor.visit_origin(argument)?;
+            }
+            Ok(())
+        }
+        Statement::Outlives(o1, o2) => {
+            visitor.visit_origin(o1)?;
+            visitor.visit_origin(o2)
+        }
+        Statement::Assert(Assertion::Error) | Statement::Pass => Ok(()),
+    }
+}
+
+pub fn walk_expr<'ast, V: ProgramVisitor<'ast> + ?Sized>(
+    visitor: &mut V,
+    expr: &'ast Expr,
+) -> eyre::Result<()> {
+    match expr {
+        Expr::Access { kind, place } => {
+            if let AccessKind::Borrow(origin) | AccessKind::BorrowMut(origin) = kind {
+                visitor.visit_origin(origin)?;
+            }
+            visitor.visit_place(place)
+        }
+        Expr::Call { arguments, .. } => {
+            for argument in arguments {
+                visitor.visit_expr(argument)?;
+            }
+            Ok(())
+        }
+        Expr::Variant { value, .. } => match value {
+            Some(value) => visitor.visit_expr(value),
+            None => Ok(()),
+        },
+        Expr::Struct { fields, base, .. } => {
+            for (_, value) in fields {
+                visitor.visit_expr(value)?;
+            }
+            match base {
+                Some(base) => visitor.visit_expr(base),
+                None => Ok(()),
+            }
+        }
+        Expr::Number { .. } | Expr::Unit => Ok(()),
+    }
+}
+
+pub fn walk_ty<'ast, V: ProgramVisitor<'ast> + ?Sized>(
+    visitor: &mut V,
+    ty: &'ast Ty,
+) -> eyre::Result<()> {
+    match ty {
+        Ty::Ref { origin, ty } | Ty::RefMut { origin, ty } => {
+            visitor.visit_origin(origin)?;
+            visitor.visit_ty(ty)
+        }
+        Ty::I32 | Ty::Unit => Ok(()),
+        Ty::Struct { parameters, .. } => {
+            for parameter in parameters {
+                match parameter {
+                    Parameter::Origin(origin) => visitor.visit_origin(origin)?,
+                    Parameter::Ty(ty) => visitor.visit_ty(ty)?,
+                }
+            }
+            Ok(())
+        }
+        Ty::Fn {
+            arg_tys, ret_ty, ..
+        } => {
+            for ty in arg_tys.iter().chain(Some(&**ret_ty)) {
+                visitor.visit_ty(ty)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+/// The same as `ProgramVisitor`, over a program which can be modified during the visit.
+pub trait ProgramVisitorMut {
+    fn visit_program(&mut self, program: &mut Program) -> eyre::Result<()> {
+        walk_program_mut(self, program)
+    }
+
+    fn visit_struct_decl(&mut self, decl: &mut StructDecl) -> eyre::Result<()> {
+        walk_struct_decl_mut(self, decl)
+    }
+
+    fn visit_type_alias(&mut self, alias: &mut TypeAlias) -> eyre::Result<()> {
+        self.visit_ty(&mut alias.ty)
+    }
+
+    fn visit_fn_prototype(&mut self, prototype: &mut FnPrototype) -> eyre::Result<()> {
+        walk_fn_prototype_mut(self, prototype)
+    }
+
+    fn visit_variable_decl(&mut self, variable: &mut VariableDecl) -> eyre::Result<()> {
+        self.visit_ty(&mut variable.ty)
+    }
+
+    /// Statements can be added or removed by overriding this method, rather than
+    /// `visit_statement`.
+    fn visit_basic_block(&mut self, block: &mut BasicBlock) -> eyre::Result<()> {
+        walk_basic_block_mut(self, block)
+    }
+
+    fn visit_statement(&mut self, statement: &mut Statement) -> eyre::Result<()> {
+        walk_statement_mut(self, statement)
+    }
+
+    fn visit_expr(&mut self, expr: &mut Expr) -> eyre::Result<()> {
+        walk_expr_mut(self, expr)
+    }
+
+    fn visit_place(&mut self, _place: &mut Place) -> eyre::Result<()> {
+        Ok(())
+    }
+
+    fn visit_ty(&mut self, ty: &mut Ty) -> eyre::Result<()> {
+        walk_ty_mut(self, ty)
+    }
+
+    fn visit_origin(&mut self, _origin: &mut Name) -> eyre::Result<()> {
+        Ok(())
+    }
+}
+
+pub fn walk_program_mut<V: ProgramVisitorMut + ?Sized>(
+    visitor: &mut V,
+    program: &mut Program,
+) -> eyre::Result<()> {
+    for decl in &mut program.struct_decls {
+        visitor.visit_struct_decl(decl)?;
+    }
+    for alias in &mut program.type_aliases {
+        visitor.visit_type_alias(alias)?;
+    }
+    for prototype in &mut program.fn_prototypes {
+        visitor.visit_fn_prototype(prototype)?;
+    }
+    for variable in &mut program.variables {
+        visitor.visit_variable_decl(variable)?;
+    }
+    for block in &mut program.basic_blocks {
+        visitor.visit_basic_block(block)?;
+    }
+    Ok(())
+}
+
+pub fn walk_struct_decl_mut<V: ProgramVisitorMut + ?Sized>(
+    visitor: &mut V,
+    decl: &mut StructDecl,
+) -> eyre::Result<()> {
+    for field in &mut decl.field_decls {
+        visitor.visit_variable_decl(field)?;
+    }
+    Ok(())
+}
+
+pub fn walk_fn_prototype_mut<V: ProgramVisitorMut + ?Sized>(
+    visitor: &mut V,
+    prototype: &mut FnPrototype,
+) -> eyre::Result<()> {
+    for ty in prototype
+        .arg_tys
+        .iter_mut()
+        .chain(Some(&mut prototype.ret_ty))
+    {
+        visitor.visit_ty(ty)?;
+    }
+    Ok(())
+}
+
+pub fn walk_basic_block_mut<V: ProgramVisitorMut + ?Sized>(
+    visitor: &mut V,
+    block: &mut BasicBlock,
+) -> eyre::Result<()> {
+    for statement in &mut block.statements {
+        visitor.visit_statement(statement)?;
+    }
+    if let Some(scrutinee) = &mut block.scrutinee {
+        visitor.visit_place(scrutinee.place_mut())?;
+    }
+    Ok(())
+}
+
+pub fn walk_statement_mut<V: ProgramVisitorMut + ?Sized>(
+    visitor: &mut V,
+    statement: &mut Statement,
+) -> eyre::Result<()> {
+    match statement {
+        Statement::Assign(place, expr) => {
+            visitor.visit_place(place)?;
+            visitor.visit_expr(expr)
+        }
+        Statement::Drop(expr) => visitor.visit_expr(expr),
+        Statement::DropPlace(place) => visitor.visit_place(place),
+        Statement::Assert(Assertion::Live(origin) | Assertion::Empty(origin)) => {
+            visitor.visit_origin(origin)
+        }
+        Statement::Assert(Assertion::EachIteration { arguments, .. }) => {
+            for argument in arguments.iter_mut().filter(|a| a.starts_with('\'')) {
+                visitor.visit_origin(argument)?;
+            }
+            Ok(())
+        }
+        Statement::Outlives(o1, o2) => {
+            visitor.visit_origin(o1)?;
+            visitor.visit_origin(o2)
+        }
+        Statement::Assert(Assertion::Error) | Statement::Pass => Ok(()),
+    }
+}
+
+pub fn walk_expr_mut<V: ProgramVisitorMut + ?Sized>(
+    visitor: &mut V,
+    expr: &mut Expr,
+) -> eyre::Result<()> {
+    match expr {
+        Expr::Access { kind, place } => {
+            if let AccessKind::Borrow(origin) | AccessKind::BorrowMut(origin) = kind {
+                visitor.visit_origin(origin)?;
+            }
+            visitor.visit_place(place)
+        }
+        Expr::Call { arguments, .. } => {
+            for argument in arguments {
+                visitor.visit_expr(argument)?;
+            }
+            Ok(())
+        }
+        Expr::Variant { value, .. } => match value {
+            Some(value) => visitor.visit_expr(value),
+            None => Ok(()),
+        },
+        Expr::Struct { fields, base, .. } => {
+            for (_, value) in fields {
+                visitor.visit_expr(value)?;
+            }
+            match base {
+                Some(base) => visitor.visit_expr(base),
+                None => Ok(()),
+            }
+        }
+        Expr::Number { .. } | Expr::Unit => Ok(()),
+    }
+}
+
+pub fn walk_ty_mut<V: ProgramVisitorMut + ?Sized>(
+    visitor: &mut V,
+    ty: &mut Ty,
+) -> eyre::Result<()> {
+    match ty {
+        Ty::Ref { origin, ty } | Ty::RefMut { origin, ty } => {
+            visitor.visit_origin(origin)?;
+            visitor.visit_ty(ty)
+        }
+        Ty::I32 | Ty::Unit => Ok(()),
+        Ty::Struct { parameters, .. } => {
+            for parameter in parameters {
+                match parameter {
+                    Parameter::Origin(origin) => visitor.visit_origin(origin)?,
+                    Parameter::Ty(ty) => visitor.visit_ty(ty)?,
+                }
+            }
+            Ok(())
+        }
+        Ty::Fn {
+            arg_tys, ret_ty, ..
+        } => {
+            for ty in arg_tys.iter_mut().chain(Some(&mut **ret_ty)) {
+                visitor.visit_ty(ty)?;
+            }
+            Ok(())
+        }
+    }
+}
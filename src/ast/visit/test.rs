@@ -0,0 +1,100 @@
+use super::*;
+
+const PROGRAM: &str = "
+    struct Ref<'a> { r: &'a i32 }
+    fn f<'f>(arg0: &'f i32) -> for<'x> fn(&'x i32);
+    let x: i32;
+    let p: Ref<'p>;
+    bb0: {
+        p = f(&'L_x x);
+        assert_live('p);
+        goto bb1;
+    }
+    bb1: {
+        x = 1;
+    }
+";
+
+/// Records the visited origins and places, in order.
+#[derive(Default)]
+struct Recorder {
+    visited: Vec<String>,
+}
+
+impl<'ast> ProgramVisitor<'ast> for Recorder {
+    fn visit_place(&mut self, place: &'ast Place) -> eyre::Result<()> {
+        self.visited.push(format!("place {}", place));
+        Ok(())
+    }
+
+    fn visit_origin(&mut self, origin: &'ast Name) -> eyre::Result<()> {
+        self.visited.push(format!("origin {}", origin));
+        Ok(())
+    }
+}
+
+#[test]
+fn visit_order() {
+    let program = crate::parse_ast(PROGRAM).unwrap();
+    let mut recorder = Recorder::default();
+    recorder.visit_program(&program).unwrap();
+    insta::assert_debug_snapshot!(recorder.visited, @r###"
+    [
+        "origin 'a",
+        "origin 'f",
+        "origin 'x",
+        "origin 'p",
+        "place p",
+        "origin 'L_x",
+        "place x",
+        "origin 'p",
+        "place x",
+    ]
+    "###);
+}
+
+#[test]
+fn errors_stop_the_visit() {
+    struct FailOnPlace(usize);
+    impl<'ast> ProgramVisitor<'ast> for FailOnPlace {
+        fn visit_place(&mut self, place: &'ast Place) -> eyre::Result<()> {
+            self.0 += 1;
+            eyre::bail!("visited `{}`", place)
+        }
+    }
+
+    let program = crate::parse_ast(PROGRAM).unwrap();
+    let mut visitor = FailOnPlace(0);
+    let error = visitor.visit_program(&program).unwrap_err();
+    assert_eq!(error.to_string(), "visited `p`");
+    assert_eq!(visitor.0, 1);
+}
+
+#[test]
+fn rename_origins() {
+    struct Renamer;
+    impl ProgramVisitorMut for Renamer {
+        fn visit_origin(&mut self, origin: &mut Name) -> eyre::Result<()> {
+            *origin = origin.to_uppercase();
+            Ok(())
+        }
+    }
+
+    let mut program = crate::parse_ast(PROGRAM).unwrap();
+    Renamer.visit_program(&mut program).unwrap();
+    insta::assert_snapshot!(program.to_string(), @r###"
+    struct Ref<'a> { r: &'A i32 }
+    fn f<'f>(arg0: &'F i32) -> for<'x> fn(&'X i32);
+    let x: i32;
+    let p: Ref<'P>;
+    bb0: {
+        p = f(&'L_X x);
+        assert_live('P);
+        goto bb1;
+    }
+    bb1: {
+        x = 1;
+    }
+
+    "###);
+}
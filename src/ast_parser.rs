@@ -9,54 +9,168 @@
 //! String     := "[^"]*"   /* regular expression */
 //! ```
 
-use eyre::WrapErr;
-use itertools::Itertools;
-use std::collections::HashMap;
-use std::path::Path;
 use std::str::FromStr;
 
+use eyre::WrapErr;
+
 use crate::ast;
+use crate::declarations::OriginDeclarations;
+use macros::{
+    Argument, Block, IfLet, Invocation, Item, Offsets, Terminator, Unexpanded, UnexpandedBody,
+};
 
+mod macros;
 #[cfg(test)]
 mod test;
 
 peg::parser! {
     grammar ast_parser() for str {
-        pub rule program() -> ast::Program = (
-            _ struct_decls:struct_decl()**__ _
-            fn_prototypes:fn_prototype()**__ _
-            variables:var_decl()**__ _
-            basic_blocks:basic_block()**__ _ {
-                ast::Program {
+        // The macros of the blocks are expanded once the whole program is parsed, see
+        // `macros::Unexpanded::expand`. The offsets of the items declaring origins are kept
+        // for the `OriginDeclarations`.
+        pub rule program() -> Unexpanded = (
+            _ extern_types:positioned(<extern_type_decl()>)**__ _
+            modules:module()**__ _
+            struct_decls:positioned(<struct_decl_with_offsets()>)**__ _
+            type_aliases:positioned(<type_alias()>)**__ _
+            fn_items:positioned(<fn_item()>)**__ _
+            variables:positioned(<var_decl()>)**__ _
+            blocks:basic_block()**__ _ {
+                let (extern_type_offsets, extern_types) = extern_types.into_iter().unzip();
+                let (struct_decl_offsets, struct_decls): (_, Vec<_>) =
+                    struct_decls.into_iter().unzip();
+                let (struct_decls, field_offsets) = struct_decls.into_iter().unzip();
+                let (type_alias_offsets, type_aliases) = type_aliases.into_iter().unzip();
+                let mut fn_prototype_offsets = vec![];
+                let mut fn_prototypes = vec![];
+                let mut fn_bodies = vec![];
+                for (offset, (prototype, body)) in fn_items {
+                    fn_prototype_offsets.push(offset);
+                    fn_prototypes.push(prototype);
+                    fn_bodies.extend(body);
+                }
+                let (variable_offsets, variables) = variables.into_iter().unzip();
+                let program = ast::Program {
+                    extern_types,
                     struct_decls,
+                    type_aliases,
                     fn_prototypes,
                     variables,
-                    basic_blocks,
-                }
+                    basic_blocks: vec![],
+                    fn_bodies: vec![],
+                };
+                let offsets = Offsets {
+                    extern_types: extern_type_offsets,
+                    struct_decls: struct_decl_offsets,
+                    fields: field_offsets,
+                    type_aliases: type_alias_offsets,
+                    fn_prototypes: fn_prototype_offsets,
+                    variables: variable_offsets,
+                };
+                Unexpanded { program, offsets, modules, blocks, fn_bodies }
             }
         )
 
-        rule whitespace() -> () = [' ' | '\n']
-        rule comment() -> () = "//" [^'\n']* "\n" { () }
+        rule positioned<T>(r: rule<T>) -> (usize, T) = p:position!() t:r() { (p, t) }
+
+        pub rule named_programs() -> Vec<(ast::Name, Unexpanded)> = (
+            _ programs:named_program()++__ _ { programs }
+        )
+
+        rule named_program() -> (ast::Name, Unexpanded) = (
+            "program" _ name:ident() _ "{" program:program() "}" { (name, program) }
+        )
+
+        // `\r` is whitespace, so that inputs with CRLF line endings parse the same, and the
+        // error locations, which count lines by `\n`, stay accurate.
+        rule whitespace() -> () = [' ' | '\t' | '\r' | '\n']
+        rule comment() -> () = line_comment() / block_comment()
+        rule line_comment() -> () = "//" [^'\n']* ("\n" / ![_]) { }
+
+        // Like in Rust, block comments nest.
+        rule block_comment() -> () = "/*" (block_comment() / !"*/" [_])* "*/" { }
         rule skip() -> () = whitespace() / comment()
         rule _ = quiet!{skip()*}
         rule __ = quiet!{skip()+}
 
-        rule struct_decl() -> ast::StructDecl = (
+        rule extern_type_decl() -> ast::Name = "extern" _ "type" _ name:ident() _ ";" { name }
+
+        // The items of a module are declared in the same order as in a program, and functions
+        // are only declared by their prototype.
+        rule module() -> ast::Module = (
+            "mod" __ name:ident() _ "{" _
+            extern_types:extern_type_decl()**__ _
+            modules:module()**__ _
+            struct_decls:struct_decl()**__ _
+            type_aliases:type_alias()**__ _
+            fn_prototypes:fn_prototype()**__ _ "}" {
+                ast::Module { name, extern_types, modules, struct_decls, type_aliases, fn_prototypes }
+            }
+        )
+
+        rule struct_decl() -> ast::StructDecl = decl:struct_decl_with_offsets() { decl.0 }
+
+        // The destructor of a struct is declared right after it. The offsets are the ones of
+        // the fields.
+        rule struct_decl_with_offsets() -> (ast::StructDecl, Vec<usize>) = (
             "struct" _ name:ident() _ generic_decls:generic_decls() _
-            "{" _ field_decls:field_decl()**comma() _ comma()? "}" {
-                ast::StructDecl { name, generic_decls, field_decls }
+            "{" _ field_decls:positioned(<field_decl()>)**comma() _ comma()? "}"
+            drop_impl:(_ "impl" __ "Drop" __ "for" __ n:ident() _ ";" { n })? {?
+                let (offsets, field_decls) = field_decls.into_iter().unzip();
+                match drop_impl {
+                    Some(n) if n != name => Err("the `impl Drop` of the struct before it"),
+                    _ => Ok((
+                        ast::StructDecl {
+                            name,
+                            generic_decls,
+                            field_decls,
+                            has_destructor: drop_impl.is_some(),
+                        },
+                        offsets,
+                    )),
+                }
             }
         )
 
+        rule type_alias() -> ast::TypeAlias = (
+            "type" _ name:ident() _ generic_decls:generic_decls() _ "=" _ ty:ty() _ ";" {
+                ast::TypeAlias { name, generic_decls, ty }
+            }
+        )
+
+        // A function is either declared by its prototype, or defined with a body.
+        rule fn_item() -> (ast::FnPrototype, Option<UnexpandedBody>) = (
+            kind:fn_kind() "fn" _ name:ident() _ generic_decls:generic_decls() _
+            "(" _ parameters:field_decl()**comma() _ ")" _ "->" _ ret_ty:ty() _
+            "{" _ variables:positioned(<var_decl()>)**__ _ blocks:basic_block()**__ _ "}" {
+                let (parameters, arg_tys) = parameters.into_iter().map(|p| (p.name, p.ty)).unzip();
+                let (variable_offsets, variables) = variables.into_iter().unzip();
+                let body = UnexpandedBody {
+                    name: name.clone(),
+                    parameters,
+                    variables,
+                    variable_offsets,
+                    blocks,
+                };
+                (ast::FnPrototype { name, generic_decls, arg_tys, ret_ty, kind }, Some(body))
+            } /
+            prototype:fn_prototype() { (prototype, None) }
+        )
+
         rule fn_prototype() -> ast::FnPrototype = (
-            "fn" _ name:ident() _ generic_decls:generic_decls() _
+            kind:fn_kind() "fn" _ name:ident() _ generic_decls:generic_decls() _
             "(" _ arg_decls:field_decl()**comma() _ ")" _ "->" _ ret_ty:ty() _ ";" {
                 let arg_tys = arg_decls.into_iter().map(|a| a.ty).collect();
-                ast::FnPrototype { name, generic_decls, arg_tys, ret_ty }
+                ast::FnPrototype { name, generic_decls, arg_tys, ret_ty, kind }
             }
         )
 
+        rule fn_kind() -> ast::FnKind = (
+            "extern" __ { ast::FnKind::Extern } /
+            "pure" __ { ast::FnKind::Pure } /
+            () { ast::FnKind::Default }
+        )
+
         rule generic_decls() -> Vec<ast::GenericDecl> = (
             "<" _ g:generic_decl()**comma() _ ">" { g } /
             () { vec![] }
@@ -64,18 +178,22 @@ peg::parser! {
 
         rule generic_decl() -> ast::GenericDecl = (
             o:origin_ident() { ast::GenericDecl::Origin(o) } /
-            n:ident() { ast::GenericDecl::Ty(n) }
+            n:ident() _ bounds:(":" _ b:origin_ident()++(_ "+" _) { b })? {
+                ast::GenericDecl::Ty(n, bounds.unwrap_or_default())
+            }
         )
 
         rule field_decl() -> ast::VariableDecl = name:ident() _ ":" _ ty:ty() {
-            ast::VariableDecl { name, ty }
+            ast::VariableDecl { name, ty, mutable: false }
         }
 
-        rule var_decl() -> ast::VariableDecl = "let" _ name:ident() _ ":" _ ty:ty() _ ";" {
-            ast::VariableDecl { name, ty }
-        }
+        rule var_decl() -> ast::VariableDecl = (
+            "let" _ mutable:("mut" __)? name:ident() _ ":" _ ty:ty() _ ";" {
+                ast::VariableDecl { name, ty, mutable: mutable.is_some() }
+            }
+        )
 
-        rule ty() -> ast::Ty = ref_mut_ty() / ref_ty() / i32_ty() / unit_ty() / struct_ty()
+        rule ty() -> ast::Ty = ref_mut_ty() / ref_ty() / i32_ty() / unit_ty() / fn_ty() / struct_ty()
 
         rule ref_ty() -> ast::Ty = "&" _ origin:origin_ident() _ ty:ty() {
             ast::Ty::Ref { origin, ty: Box::new(ty) }
@@ -93,7 +211,23 @@ peg::parser! {
             ast::Ty::Unit
         }
 
-        rule struct_ty() -> ast::Ty = name:ident() parameters:parameters() {
+        rule fn_ty() -> ast::Ty = (
+            bound_origins:bound_origins() "fn" _ "(" _ arg_tys:ty()**comma() _ ")" ret_ty:ret_ty() {
+                ast::Ty::Fn { bound_origins, arg_tys, ret_ty: Box::new(ret_ty) }
+            }
+        )
+
+        rule bound_origins() -> Vec<ast::Name> = (
+            "for" _ "<" _ o:origin_ident()**comma() _ ">" _ { o } /
+            () { vec![] }
+        )
+
+        rule ret_ty() -> ast::Ty = (
+            _ "->" _ ty:ty() { ty } /
+            () { ast::Ty::Unit }
+        )
+
+        rule struct_ty() -> ast::Ty = name:path() _ parameters:parameters() {
             ast::Ty::Struct { name, parameters }
         }
 
@@ -109,18 +243,94 @@ peg::parser! {
 
         rule comma() -> () = _ "," _ { }
 
-        rule basic_block() -> ast::BasicBlock = (
-            name:ident() _ ":" _ "{" _ statements:statement()**__ _ successors:goto() _ "}" {
-                ast::BasicBlock { name, statements, successors }
+        rule basic_block() -> Block = (
+            offset:position!() name:ident() _ ":" _ "{" _ items:positioned(<item()>)**__ _
+            terminator:positioned(<terminator()>) _ "}" {
+                Block { name, offset, items, terminator }
+            }
+        )
+
+        rule item() -> Item = (
+            invocation:invocation() { Item::Macro(invocation) } /
+            statement:statement() { Item::Statement(statement) }
+        )
+
+        rule invocation() -> Invocation = (
+            name:ident() "!" _ "(" _ arguments:argument()**comma() _ ")" _ ";" {
+                Invocation { name, arguments }
             }
         )
 
-        rule goto() -> Vec<ast::Name> = (
-            "goto" _ names:ident()**comma() _ ";" { names } /
+        rule argument() -> Argument = (
+            o:origin_ident() { Argument::Origin(o) } /
+            p:place() { Argument::Place(p) }
+        )
+
+        rule terminator() -> Terminator = (
+            "match" __ place:place() _ "{" _ arms:arm()**comma() _ comma()? "}" {
+                Terminator::Match(place, arms)
+            } /
+            "switch" __ place:place() _ "{" _ arms:switch_arm()**comma() _ comma()? "}" {
+                Terminator::Switch(place, arms)
+            } /
+            if_let:if_let() { Terminator::IfLet(if_let) } /
+            successors:goto() { Terminator::Goto(successors) }
+        )
+
+        rule if_let() -> IfLet = (
+            "if" __ "let" __ variant:variant() _ "(" _ binding:ident() _ ")" _ "=" _
+            kind:access_kind() _ place:place() _
+            "{" _ "goto" __ then:ident() _ ";" _ "}" _
+            "else" _ "{" _ "goto" __ otherwise:ident() _ ";" _ "}" {
+                IfLet { variant, binding, kind, place, then, otherwise }
+            }
+        )
+
+        rule arm() -> ast::Successor = variant:ident() _ "=>" _ block:ident() {
+            ast::Successor { block, label: Some(variant) }
+        }
+
+        rule switch_arm() -> ast::Successor = (
+            "_" _ "=>" _ block:ident() { ast::Successor { block, label: None } } /
+            value:$("-"? ['0'..='9']+) _ "=>" _ block:ident() {
+                ast::Successor { block, label: Some(value.to_string()) }
+            }
+        )
+
+        // Like a block without a terminator, `return;` goes to the exit of the program, where
+        // the locals die and the return place is read.
+        rule goto() -> Vec<ast::Successor> = (
+            "goto" _ successors:successor()**comma() _ ";" { successors } /
+            "return" _ ";" { vec![] } /
             () { vec![] }
         )
 
+        rule successor() -> ast::Successor = (
+            "[" _ label:label() _ "]" _ block:ident() {
+                ast::Successor { block, label: Some(label) }
+            } /
+            block:ident() { ast::Successor { block, label: None } }
+        )
+
+        rule label() -> ast::Name = t:$(['a'..='z' | 'A'..='Z' | '_' | '0'..='9' | '.']+) {
+            t.to_string()
+        }
+
         rule statement() -> ast::Statement = (
+            "assert_live" _ "(" _ o:origin_ident() _ ")" _ ";" {
+                ast::Statement::Assert(ast::Assertion::Live(o))
+            } /
+            "assert_empty" _ "(" _ o:origin_ident() _ ")" _ ";" {
+                ast::Statement::Assert(ast::Assertion::Empty(o))
+            } /
+            "assert_error" _ ";" { ast::Statement::Assert(ast::Assertion::Error) } /
+            "assert_each_iteration" _ "(" _ relation:ident() _ "(" _
+            arguments:fact_argument()**comma() _ ")" _ ")" _ ";" {
+                ast::Statement::Assert(ast::Assertion::EachIteration { relation, arguments })
+            } /
+            "pass" _ ";" { ast::Statement::Pass } /
+            "drop" _ "(" _ place:place() _ ")" _ ";" { ast::Statement::DropPlace(place) } /
+            o1:origin_ident() _ ":" _ o2:origin_ident() _ ";" { ast::Statement::Outlives(o1, o2) } /
             place:place() _ "=" _ expr:expr() _ ";" { ast::Statement::Assign(place, expr) } /
             expr:expr() _ ";" { ast::Statement::Drop(expr) }
         )
@@ -128,10 +338,23 @@ peg::parser! {
         rule expr() -> ast::Expr = (
             kind:access_kind() _ place:place() { ast::Expr::Access { kind, place } } /
             n:$(['0'..='9']+) { ast::Expr::Number { value: i32::from_str(n).unwrap() } } /
-            name:ident() _ "(" _ arguments:expr()**comma() _ ")" { ast::Expr::Call { name, arguments} } /
+            name:variant() _ "(" _ value:expr() _ ")" {
+                ast::Expr::Variant { name, value: Some(Box::new(value)) }
+            } /
+            name:variant() { ast::Expr::Variant { name, value: None } } /
+            name:path() _ "(" _ arguments:expr()**comma() _ ")" { ast::Expr::Call { name, arguments} } /
+            name:path() _ "{" _ fields:field_init()**comma() _ base:struct_base()? _ "}" {
+                ast::Expr::Struct { name, fields, base: base.map(Box::new) }
+            } /
             "(" _ ")" { ast::Expr::Unit }
         )
 
+        rule field_init() -> (ast::Name, ast::Expr) = field:ident() _ ":" _ value:expr() {
+            (field, value)
+        }
+
+        rule struct_base() -> ast::Expr = comma()? ".." _ base:expr() { base }
+
         rule place() -> ast::Place = (
             base:ident() _ dot() _ fields:ident()**dot() { ast::Place { base, fields } } /
             base:ident() { ast::Place { base, fields: vec![] } }
@@ -144,19 +367,81 @@ peg::parser! {
             "&" _ o:origin_ident() { ast::AccessKind::Borrow(o) }
         )
 
+        rule fact_argument() -> ast::Name = origin_ident() / ident()
+
         rule dot() -> () = _ "." _
 
-        rule ident() -> ast::Name = t:$(['a'..='z' | 'A'..='Z' | '_' | '0' ..= '9' | '*' ]+) {
-            t.to_string()
-        }
+        rule variant() -> ast::Name = (
+            v:$("Some" / "None" / "Ok" / "Err") !ident_char() {
+                v.to_string()
+            }
+        )
 
-        rule origin_ident() -> ast::Name = t:$("'"['a'..='z' | 'A'..='Z' | '_' | '0' ..= '9' | '*' ]+) {
-            t.to_string()
-        }
+        // Names can contain any alphanumeric character, not only ASCII ones. A raw identifier,
+        // e.g. `r#type` or `r#Some`, is the name without its `r#`, see `ast::KEYWORDS`.
+        rule ident() -> ast::Name = (
+            quiet!{ "r#"? t:$(ident_char()+) { t.to_string() } } / expected!("a name")
+        )
+
+        // The name of an item, e.g. `Vec`, or its path from a module, e.g. `collections::Vec`,
+        // resolved by `resolve::flatten_modules`.
+        rule path() -> ast::Name = segments:ident()++"::" { segments.join("::") }
+
+        rule origin_ident() -> ast::Name = (
+            quiet!{ "'" "r#"? t:$(ident_char()+) { format!("'{}", t) } } / expected!("an origin")
+        )
+
+        rule ident_char() -> () = [c if c.is_alphanumeric() || c == '_' || c == '*'] { }
 
     }
 }
 
-fn parse_ast(input: &str) -> eyre::Result<ast::Program> {
-    Ok(ast_parser::program(input)?)
+pub fn parse_ast(input: &str) -> eyre::Result<ast::Program> {
+    Ok(parse_ast_with_declarations(input)?.0)
+}
+
+/// Parses `input`, like `parse_ast`, and records where each origin of the program is declared.
+pub fn parse_ast_with_declarations(
+    input: &str,
+) -> eyre::Result<(ast::Program, OriginDeclarations)> {
+    ast_parser::program(input)?.expand(input)
+}
+
+/// The name of the program in an input without `program` sections.
+pub const DEFAULT_PROGRAM_NAME: &str = "main";
+
+/// Parses an input containing several named `program foo { .. }` sections, e.g. variations of
+/// the same example. An input without sections is a single program, named `main`.
+pub fn parse_ast_programs(input: &str) -> eyre::Result<Vec<(ast::Name, ast::Program)>> {
+    let programs = match (
+        ast_parser::named_programs(input),
+        ast_parser::program(input),
+    ) {
+        (Ok(programs), _) => programs,
+        (Err(_), Ok(program)) => {
+            return Ok(vec![(
+                DEFAULT_PROGRAM_NAME.to_string(),
+                program.expand(input)?.0,
+            )])
+        }
+        // Report the error of the parse that went the furthest.
+        (Err(named), Err(single)) if named.location.offset > single.location.offset => {
+            return Err(named.into())
+        }
+        (Err(_), Err(single)) => return Err(single.into()),
+    };
+    for (i, (name, _)) in programs.iter().enumerate() {
+        if programs[..i].iter().any(|(n, _)| n == name) {
+            eyre::bail!("there are several programs named `{}`", name);
+        }
+    }
+    programs
+        .into_iter()
+        .map(|(name, program)| {
+            let (program, _) = program
+                .expand(input)
+                .wrap_err_with(|| format!("in program `{}`", name))?;
+            Ok((name, program))
+        })
+        .collect()
 }
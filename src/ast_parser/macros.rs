@@ -0,0 +1,454 @@
+//! Statement macros, e.g. `swap!(a, b);`, which abbreviate the statement sequences that are
+//! common in hand-written examples. They are expanded when the program is parsed, so the rest
+//! of the crate only sees their canonical statements, and `parse --format=pretty` prints the
+//! expansion.
+//!
+//! * `swap!(a, b)` exchanges two places through a temporary `_swapN`, declared with the type
+//!   of `a`, with fresh origins:
+//!   `_swapN = move a; a = move b; b = move _swapN;`.
+//! * `borrow_field!(x, f, 'l)` borrows the field `f` of `x` into `x_f`:
+//!   `x_f = &'l x.f;`. Unless the program declares it, `x_f` is declared as `&'x_f T`, where
+//!   `T` is the type of the field.
+//!
+//! Likewise, a block can end with an `if let`, which binds the payload of a builtin enum in
+//! the arm where the variant matches:
+//! `if let Some(x) = &'l o { goto bb1; } else { goto bb2; }` is a `match o`, whose `Some` arm
+//! goes to bb1 through a new block `x = &'l o.Some;`, and whose other arms go to bb2. The
+//! loan is thus only issued on the path where `o` is a `Some`. The binding can also move or
+//! copy the payload, e.g. `Some(x) = move o`. Unless the program declares it, `x` is declared
+//! as `&'x T`, `&'x mut T`, or `T` with fresh origins, where `T` is the type of the payload.
+
+use std::fmt;
+
+use eyre::WrapErr;
+
+use crate::ast::{self, AccessKind, Expr, Name, Place, Statement, Ty, VariableDecl};
+use crate::declarations::{ItemKind, OriginDeclarations, Recorder};
+use crate::typeck::{self, TypeEnv};
+
+/// A statement of a block, or a macro invocation standing for several.
+pub(super) enum Item {
+    Statement(Statement),
+    Macro(Invocation),
+}
+
+/// `name!(arguments);`
+pub(super) struct Invocation {
+    pub name: Name,
+    pub arguments: Vec<Argument>,
+}
+
+pub(super) enum Argument {
+    Place(Place),
+    Origin(Name),
+}
+
+impl fmt::Display for Invocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}!(", self.name)?;
+        for (i, argument) in self.arguments.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            match argument {
+                Argument::Place(place) => write!(f, "{}", place)?,
+                Argument::Origin(origin) => write!(f, "{}", origin)?,
+            }
+        }
+        write!(f, ")")
+    }
+}
+
+/// How a block ends.
+pub(super) enum Terminator {
+    Goto(Vec<ast::Successor>),
+
+    /// `match o { Some => bb1, None => bb2 }`
+    Match(Place, Vec<ast::Successor>),
+
+    /// `switch x { 0 => bb1, _ => bb2 }`
+    Switch(Place, Vec<ast::Successor>),
+
+    IfLet(IfLet),
+}
+
+/// `if let variant(binding) = kind place { goto then; } else { goto otherwise; }`
+pub(super) struct IfLet {
+    pub variant: Name,
+    pub binding: Name,
+    pub kind: AccessKind,
+    pub place: Place,
+    pub then: Name,
+    pub otherwise: Name,
+}
+
+/// A basic block whose macros aren't expanded yet.
+pub(super) struct Block {
+    pub name: Name,
+    pub offset: usize,
+    /// The items, with their byte offsets in the input.
+    pub items: Vec<(usize, Item)>,
+    pub terminator: (usize, Terminator),
+}
+
+/// The byte offsets in the input of the declarations of a program, in order.
+pub(super) struct Offsets {
+    pub extern_types: Vec<usize>,
+    pub struct_decls: Vec<usize>,
+    /// The offsets of the fields of each struct.
+    pub fields: Vec<Vec<usize>>,
+    pub type_aliases: Vec<usize>,
+    pub fn_prototypes: Vec<usize>,
+    pub variables: Vec<usize>,
+}
+
+/// A parsed program, whose blocks are expanded by `expand`.
+pub(super) struct Unexpanded {
+    pub program: ast::Program,
+    pub offsets: Offsets,
+    pub modules: Vec<ast::Module>,
+    pub blocks: Vec<Block>,
+    pub fn_bodies: Vec<UnexpandedBody>,
+}
+
+/// The body of a function definition, whose blocks are expanded with the program.
+pub(super) struct UnexpandedBody {
+    pub name: Name,
+    pub parameters: Vec<Name>,
+    pub variables: Vec<VariableDecl>,
+    pub variable_offsets: Vec<usize>,
+    pub blocks: Vec<Block>,
+}
+
+impl Unexpanded {
+    /// Expands the macros of the blocks, adding the variables they declare, and records where
+    /// the origins are declared in `input`, the parsed text. The statements and variables of
+    /// a macro are declared at the invocation. The blocks of the function bodies are expanded
+    /// like the blocks of a program of their own, see `ast::Program::function_program`, and
+    /// the origins they declare aren't recorded.
+    ///
+    /// The items declared twice are rejected here, where their lines are known, see
+    /// `validate::check_declared_once`.
+    pub fn expand(self, input: &str) -> eyre::Result<(ast::Program, OriginDeclarations)> {
+        let Unexpanded {
+            mut program,
+            offsets,
+            modules,
+            blocks,
+            fn_bodies,
+        } = self;
+        let mut recorder = Recorder::new(input);
+        for (name, &offset) in program.extern_types.iter().zip(&offsets.extern_types) {
+            recorder.item(offset, ItemKind::Type, name);
+        }
+        for (decl, &offset) in program.struct_decls.iter().zip(&offsets.struct_decls) {
+            recorder.item(offset, ItemKind::Type, &decl.name);
+            recorder.generics(offset, &decl.name, &decl.generic_decls);
+        }
+        for (decl, offsets) in program.struct_decls.iter().zip(&offsets.fields) {
+            for (field, &offset) in decl.field_decls.iter().zip(offsets) {
+                recorder.item(offset, ItemKind::Field(decl.name.clone()), &field.name);
+            }
+        }
+        for (alias, &offset) in program.type_aliases.iter().zip(&offsets.type_aliases) {
+            recorder.generics(offset, &alias.name, &alias.generic_decls);
+        }
+        for (prototype, &offset) in program.fn_prototypes.iter().zip(&offsets.fn_prototypes) {
+            recorder.item(offset, ItemKind::Function, &prototype.name);
+            recorder.generics(offset, &prototype.name, &prototype.generic_decls);
+        }
+        for (variable, &offset) in program.variables.iter().zip(&offsets.variables) {
+            recorder.item(offset, ItemKind::Variable, &variable.name);
+            recorder.variable(offset, variable);
+        }
+        for block in &blocks {
+            recorder.item(block.offset, ItemKind::Block, &block.name);
+        }
+        crate::validate::check_declared_once(recorder.items())?;
+
+        crate::resolve::flatten_modules(&mut program, modules)?;
+
+        program.basic_blocks = expand_blocks(&mut program, blocks, &mut recorder)?;
+
+        for body in fn_bodies {
+            let mut body_recorder = Recorder::new(input);
+            for (variable, &offset) in body.variables.iter().zip(&body.variable_offsets) {
+                body_recorder.item(offset, ItemKind::Variable, &variable.name);
+            }
+            for block in &body.blocks {
+                body_recorder.item(block.offset, ItemKind::Block, &block.name);
+            }
+            crate::validate::check_declared_once(body_recorder.items())
+                .wrap_err_with(|| format!("in the body of `{}`", body.name))?;
+            let mut expanded = ast::FnBody {
+                name: body.name,
+                parameters: body.parameters,
+                variables: body.variables,
+                basic_blocks: vec![],
+            };
+            let mut body_program = program.function_program(&expanded)?;
+            let declared = body_program.variables.len();
+            expanded.basic_blocks =
+                expand_blocks(&mut body_program, body.blocks, &mut body_recorder)
+                    .wrap_err_with(|| format!("in the body of `{}`", expanded.name))?;
+            expanded
+                .variables
+                .extend(body_program.variables.drain(declared..));
+            program.fn_bodies.push(expanded);
+        }
+        Ok((program, recorder.finish()))
+    }
+}
+
+/// Expands the macros of `blocks`, adding the variables they declare to `program`.
+fn expand_blocks(
+    program: &mut ast::Program,
+    blocks: Vec<Block>,
+    recorder: &mut Recorder<'_>,
+) -> eyre::Result<Vec<ast::BasicBlock>> {
+    let mut basic_blocks = vec![];
+    let mut block_names: Vec<Name> = blocks.iter().map(|block| block.name.clone()).collect();
+    let mut counter = 0;
+    for block in blocks {
+        let mut statements = vec![];
+        for (offset, item) in block.items {
+            match item {
+                Item::Statement(statement) => {
+                    recorder.statement(offset, &statement);
+                    statements.push(statement);
+                }
+                Item::Macro(invocation) => {
+                    let env = TypeEnv::new(program);
+                    let expansion = expand(&invocation, &env, &mut counter)
+                        .wrap_err_with(|| format!("in `{}`", invocation))?;
+                    if let Some(variable) = &expansion.declaration {
+                        recorder.variable(offset, variable);
+                    }
+                    for statement in &expansion.statements {
+                        recorder.statement(offset, statement);
+                    }
+                    program.variables.extend(expansion.declaration);
+                    statements.extend(expansion.statements);
+                }
+            }
+        }
+        let (offset, terminator) = block.terminator;
+        let (scrutinee, successors, arm) = match terminator {
+            Terminator::Goto(successors) => (None, successors, None),
+            Terminator::Match(place, arms) => (Some(ast::Scrutinee::Match(place)), arms, None),
+            Terminator::Switch(place, arms) => (Some(ast::Scrutinee::Switch(place)), arms, None),
+            Terminator::IfLet(if_let) => {
+                let env = TypeEnv::new(program);
+                let name = &block.name;
+                let arm = expand_if_let(&if_let, name, &env, &mut block_names)
+                    .wrap_err_with(|| format!("in the `if let` of `{}`", name))?;
+                if let Some(variable) = &arm.declaration {
+                    recorder.variable(offset, variable);
+                }
+                recorder.statement(offset, &arm.block.statements[0]);
+                program.variables.extend(arm.declaration);
+                let scrutinee = ast::Scrutinee::Match(if_let.place);
+                (Some(scrutinee), arm.successors, Some(arm.block))
+            }
+        };
+        basic_blocks.push(ast::BasicBlock {
+            name: block.name,
+            statements,
+            successors,
+            scrutinee,
+        });
+        basic_blocks.extend(arm);
+    }
+    Ok(basic_blocks)
+}
+
+/// The statements of an invocation, and the variable it declares, if any.
+struct Expansion {
+    statements: Vec<Statement>,
+    declaration: Option<VariableDecl>,
+}
+
+/// The expansion of an `if let`: the arms of its `match`, the block binding the payload in the
+/// arm of its variant, and the binding, unless the program declares it.
+struct IfLetArm {
+    successors: Vec<ast::Successor>,
+    block: ast::BasicBlock,
+    declaration: Option<VariableDecl>,
+}
+
+/// Expands the `if let` ending the block `name`, where `block_names` are the names of the blocks
+/// of the program, including the ones already added for other `if let`s.
+fn expand_if_let(
+    if_let: &IfLet,
+    name: &str,
+    env: &TypeEnv<'_>,
+    block_names: &mut Vec<Name>,
+) -> eyre::Result<IfLetArm> {
+    let IfLet {
+        variant,
+        binding,
+        kind,
+        place,
+        then,
+        otherwise,
+    } = if_let;
+    let (_, variants) = typeck::BUILTIN_ENUMS
+        .iter()
+        .find(|(_, variants)| variants.contains(&variant.as_str()))
+        .expect("the parser only accepts the variants of the builtin enums");
+
+    let mut payload = place.clone();
+    payload.fields.push(variant.clone());
+    let payload_ty = env
+        .place_ty(&payload)
+        .wrap_err_with(|| format!("`{}` has no payload to bind", payload))?;
+    let declaration = if env.variables().iter().any(|v| &v.name == binding) {
+        None
+    } else {
+        let origin = format!("'{}", binding);
+        let ty = match kind {
+            AccessKind::Borrow(_) => Ty::Ref {
+                origin,
+                ty: Box::new(payload_ty),
+            },
+            AccessKind::BorrowMut(_) => Ty::RefMut {
+                origin,
+                ty: Box::new(payload_ty),
+            },
+            AccessKind::Copy | AccessKind::Move => typeck::freshen_ty(&payload_ty, binding),
+        };
+        Some(VariableDecl {
+            name: binding.clone(),
+            ty,
+            mutable: true,
+        })
+    };
+
+    let arm_name = {
+        let base = format!("{}_{}", name, variant.to_lowercase());
+        let mut arm_name = base.clone();
+        let mut i = 1;
+        while block_names.contains(&arm_name) {
+            arm_name = format!("{}{}", base, i);
+            i += 1;
+        }
+        block_names.push(arm_name.clone());
+        arm_name
+    };
+    let binding = Place {
+        base: binding.clone(),
+        fields: vec![],
+    };
+    let block = ast::BasicBlock {
+        name: arm_name.clone(),
+        statements: vec![Statement::Assign(
+            binding,
+            Expr::Access {
+                kind: kind.clone(),
+                place: payload,
+            },
+        )],
+        successors: vec![ast::Successor {
+            block: then.clone(),
+            label: None,
+        }],
+        scrutinee: None,
+    };
+    let successors = variants
+        .iter()
+        .map(|&v| ast::Successor {
+            block: if v == variant {
+                arm_name.clone()
+            } else {
+                otherwise.clone()
+            },
+            label: Some(v.to_string()),
+        })
+        .collect();
+    Ok(IfLetArm {
+        successors,
+        block,
+        declaration,
+    })
+}
+
+/// Expands `invocation`, where `counter` numbers the temporaries of `swap!`.
+fn expand(
+    invocation: &Invocation,
+    env: &TypeEnv<'_>,
+    counter: &mut usize,
+) -> eyre::Result<Expansion> {
+    let is_declared = |name: &str| env.variables().iter().any(|variable| variable.name == name);
+    let access = |kind, place: &Place| Expr::Access {
+        kind,
+        place: place.clone(),
+    };
+
+    match (invocation.name.as_str(), &invocation.arguments[..]) {
+        ("swap", [Argument::Place(a), Argument::Place(b)]) => {
+            let temporary = loop {
+                let name = format!("_swap{}", counter);
+                *counter += 1;
+                if !is_declared(&name) {
+                    break name;
+                }
+            };
+            let ty = typeck::freshen_ty(&env.place_ty(a)?, &temporary);
+            let temporary = Place {
+                base: temporary,
+                fields: vec![],
+            };
+            Ok(Expansion {
+                statements: vec![
+                    Statement::Assign(temporary.clone(), access(AccessKind::Move, a)),
+                    Statement::Assign(a.clone(), access(AccessKind::Move, b)),
+                    Statement::Assign(b.clone(), access(AccessKind::Move, &temporary)),
+                ],
+                declaration: Some(VariableDecl {
+                    name: temporary.base,
+                    ty,
+                    mutable: true,
+                }),
+            })
+        }
+        (
+            "borrow_field",
+            [Argument::Place(x), Argument::Place(Place { base: f, fields }), Argument::Origin(l)],
+        ) if fields.is_empty() => {
+            let mut field = x.clone();
+            field.fields.push(f.clone());
+            let name = format!("{}_{}", x, f).replace('.', "_");
+            let declaration = if is_declared(&name) {
+                None
+            } else {
+                Some(VariableDecl {
+                    ty: Ty::Ref {
+                        origin: format!("'{}", name),
+                        ty: Box::new(env.place_ty(&field)?),
+                    },
+                    name: name.clone(),
+                    mutable: true,
+                })
+            };
+            let target = Place {
+                base: name,
+                fields: vec![],
+            };
+            Ok(Expansion {
+                statements: vec![Statement::Assign(
+                    target,
+                    access(AccessKind::Borrow(l.clone()), &field),
+                )],
+                declaration,
+            })
+        }
+        ("swap", _) => eyre::bail!("`swap!` expects two places, e.g. `swap!(a, b)`"),
+        ("borrow_field", _) => eyre::bail!(
+            "`borrow_field!` expects a place, a field and an origin, e.g. `borrow_field!(x, f, 'l)`"
+        ),
+        (name, _) => eyre::bail!(
+            "unknown macro `{}!`, the macros are `swap!` and `borrow_field!`",
+            name
+        ),
+    }
+}
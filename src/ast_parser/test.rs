@@ -2,7 +2,7 @@ use super::*;
 
 fn expect_parse(s: &str) -> ast::Program {
     match super::ast_parser::program(s) {
-        Ok(p) => p,
+        Ok(p) => p.expand(s).unwrap().0,
         Err(e) => {
             let offset = e.location.offset;
             panic!(
@@ -25,15 +25,19 @@ fn let_test() {
 
     insta::assert_debug_snapshot!(p, @r###"
     Program {
+        extern_types: [],
         struct_decls: [],
+        type_aliases: [],
         fn_prototypes: [],
         variables: [
             VariableDecl {
                 name: "x",
                 ty: I32,
+                mutable: false,
             },
         ],
         basic_blocks: [],
+        fn_bodies: [],
     }
     "###);
 }
@@ -50,7 +54,9 @@ fn statement_test() {
 
     insta::assert_debug_snapshot!(p, @r###"
     Program {
+        extern_types: [],
         struct_decls: [],
+        type_aliases: [],
         fn_prototypes: [],
         variables: [],
         basic_blocks: [
@@ -68,8 +74,10 @@ fn statement_test() {
                     ),
                 ],
                 successors: [],
+                scrutinee: None,
             },
         ],
+        fn_bodies: [],
     }
     "###);
 }
@@ -92,7 +100,9 @@ fn borrow_test() {
 
     insta::assert_debug_snapshot!(p, @r###"
     Program {
+        extern_types: [],
         struct_decls: [],
+        type_aliases: [],
         fn_prototypes: [],
         variables: [],
         basic_blocks: [
@@ -140,21 +150,31 @@ fn borrow_test() {
                     ),
                 ],
                 successors: [
-                    "bb1",
-                    "bb2",
+                    Successor {
+                        block: "bb1",
+                        label: None,
+                    },
+                    Successor {
+                        block: "bb2",
+                        label: None,
+                    },
                 ],
+                scrutinee: None,
             },
             BasicBlock {
                 name: "bb1",
                 statements: [],
                 successors: [],
+                scrutinee: None,
             },
             BasicBlock {
                 name: "bb2",
                 statements: [],
                 successors: [],
+                scrutinee: None,
             },
         ],
+        fn_bodies: [],
     }
     "###);
 }
@@ -176,20 +196,25 @@ fn copy_move_test() {
 
     insta::assert_debug_snapshot!(p, @r###"
     Program {
+        extern_types: [],
         struct_decls: [],
+        type_aliases: [],
         fn_prototypes: [],
         variables: [
             VariableDecl {
                 name: "x",
                 ty: I32,
+                mutable: false,
             },
             VariableDecl {
                 name: "y",
                 ty: I32,
+                mutable: false,
             },
             VariableDecl {
                 name: "z",
                 ty: I32,
+                mutable: false,
             },
         ],
         basic_blocks: [
@@ -233,8 +258,10 @@ fn copy_move_test() {
                     ),
                 ],
                 successors: [],
+                scrutinee: None,
             },
         ],
+        fn_bodies: [],
     }
     "###);
 }
@@ -250,6 +277,7 @@ fn struct_test() {
 
     insta::assert_debug_snapshot!(p, @r###"
     Program {
+        extern_types: [],
         struct_decls: [
             StructDecl {
                 name: "Iter",
@@ -259,6 +287,7 @@ fn struct_test() {
                     ),
                     Ty(
                         "T",
+                        [],
                     ),
                 ],
                 field_decls: [
@@ -278,18 +307,22 @@ fn struct_test() {
                                 ],
                             },
                         },
+                        mutable: false,
                     },
                     VariableDecl {
                         name: "position",
                         ty: I32,
+                        mutable: false,
                     },
                 ],
+                has_destructor: false,
             },
             StructDecl {
                 name: "Vec",
                 generic_decls: [
                     Ty(
                         "T",
+                        [],
                     ),
                 ],
                 field_decls: [
@@ -299,13 +332,17 @@ fn struct_test() {
                             name: "T",
                             parameters: [],
                         },
+                        mutable: false,
                     },
                 ],
+                has_destructor: false,
             },
         ],
+        type_aliases: [],
         fn_prototypes: [],
         variables: [],
         basic_blocks: [],
+        fn_bodies: [],
     }
     "###);
 }
@@ -321,12 +358,14 @@ fn fn_test() {
 
     insta::assert_debug_snapshot!(p, @r###"
     Program {
+        extern_types: [],
         struct_decls: [
             StructDecl {
                 name: "Vec",
                 generic_decls: [
                     Ty(
                         "T",
+                        [],
                     ),
                 ],
                 field_decls: [
@@ -336,10 +375,13 @@ fn fn_test() {
                             name: "T",
                             parameters: [],
                         },
+                        mutable: false,
                     },
                 ],
+                has_destructor: false,
             },
         ],
+        type_aliases: [],
         fn_prototypes: [
             FnPrototype {
                 name: "Vec_push",
@@ -349,6 +391,7 @@ fn fn_test() {
                     ),
                     Ty(
                         "T",
+                        [],
                     ),
                 ],
                 arg_tys: [
@@ -372,10 +415,731 @@ fn fn_test() {
                     },
                 ],
                 ret_ty: Unit,
+                kind: Default,
             },
         ],
         variables: [],
         basic_blocks: [],
+        fn_bodies: [],
+    }
+    "###);
+}
+
+#[test]
+fn extern_type_test() {
+    let p = expect_parse(
+        "
+        extern type Thing;
+        let temp: &'temp mut Thing;
+    ",
+    );
+
+    insta::assert_debug_snapshot!(p, @r###"
+    Program {
+        extern_types: [
+            "Thing",
+        ],
+        struct_decls: [],
+        type_aliases: [],
+        fn_prototypes: [],
+        variables: [
+            VariableDecl {
+                name: "temp",
+                ty: RefMut {
+                    origin: "'temp",
+                    ty: Struct {
+                        name: "Thing",
+                        parameters: [],
+                    },
+                },
+                mutable: false,
+            },
+        ],
+        basic_blocks: [],
+        fn_bodies: [],
+    }
+    "###);
+}
+
+#[test]
+fn fn_pointer_ty() {
+    let p = expect_parse(
+        "
+        struct Callback<'a> { f: for<'x> fn(&'x i32, &'a i32) -> &'x i32, g: fn() }
+    ",
+    );
+
+    let tys: Vec<_> = p.struct_decls[0]
+        .field_decls
+        .iter()
+        .map(|field| field.ty.to_string())
+        .collect();
+    insta::assert_debug_snapshot!(tys, @r###"
+    [
+        "for<'x> fn(&'x i32, &'a i32) -> &'x i32",
+        "fn()",
+    ]
+    "###);
+}
+
+#[test]
+fn named_programs() {
+    let programs = parse_ast_programs(
+        "
+        program covariant {
+            let r: &'r i32;
+        }
+        program invariant {
+            let r: &'r mut i32;
+        }
+    ",
+    )
+    .unwrap();
+
+    let names: Vec<_> = programs.iter().map(|(name, _)| name.as_str()).collect();
+    assert_eq!(names, ["covariant", "invariant"]);
+}
+
+#[test]
+fn unnamed_program() {
+    let programs = parse_ast_programs("let x: i32;").unwrap();
+    assert_eq!(programs.len(), 1);
+    assert_eq!(programs[0].0, DEFAULT_PROGRAM_NAME);
+}
+
+#[test]
+fn duplicate_program_names() {
+    let err = parse_ast_programs("program a { } program a { }").unwrap_err();
+    insta::assert_snapshot!(err.to_string(), @"there are several programs named `a`");
+}
+
+#[test]
+fn error_in_named_program() {
+    let err = parse_ast_programs("program a { let x: i32 }").unwrap_err();
+    insta::assert_snapshot!(err.to_string(), @r###"
+    error at 1:24: expected ";"
+    "###);
+}
+
+#[test]
+fn comments() {
+    let p = expect_parse(
+        "
+        // A line comment.
+        let x: /* the type */ i32;
+        let v: Vec /* nested /* block */ comment */ <&'v i32>;
+        bb0: {
+            x = /* inline */ 22; // trailing
+            /*
+             * Between statements.
+             */
+            v = Vec_new(/* no arguments */);
+        }
+        // At the end, without a newline.",
+    );
+
+    let statements: Vec<_> = p.basic_blocks[0]
+        .statements
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+    insta::assert_debug_snapshot!(statements, @r###"
+    [
+        "x = 22",
+        "v = Vec_new()",
+    ]
+    "###);
+}
+
+#[test]
+fn error_after_comment() {
+    let err = parse_ast("/* one\ntwo */ let x: i32").unwrap_err();
+    insta::assert_snapshot!(err.to_string(), @r###"
+    error at 2:18: expected ";"
+    "###);
+}
+
+#[test]
+fn unterminated_block_comment() {
+    assert!(parse_ast("let x: i32; /* /* */").is_err());
+}
+
+#[test]
+fn crlf_line_endings() {
+    let input = "
+        let x: i32; // a comment
+        bb0: {
+            x = 22;
+        }
+    ";
+    let crlf_input = input.replace('\n', "\r\n");
+    assert_eq!(
+        format!("{:?}", expect_parse(input)),
+        format!("{:?}", expect_parse(&crlf_input))
+    );
+
+    let err = parse_ast("let x: i32;\r\nlet y: i32\r\n").unwrap_err();
+    insta::assert_snapshot!(err.to_string(), @r###"
+    error at 3:1: expected ";"
+    "###);
+}
+
+#[test]
+fn let_mut() {
+    let p = expect_parse("let mut x: i32; let mutable: i32;");
+    let variables: Vec<_> = p
+        .variables
+        .iter()
+        .map(|v| (v.name.as_str(), v.mutable))
+        .collect();
+    assert_eq!(variables, [("x", true), ("mutable", false)]);
+}
+
+#[test]
+fn display_round_trips() {
+    let input = include_str!("../../tests/assertions.ast");
+    let program = expect_parse(input);
+    assert_eq!(
+        format!("{:?}", program),
+        format!("{:?}", expect_parse(&program.to_string()))
+    );
+}
+
+#[test]
+fn display_program() {
+    let p = expect_parse(
+        "
+        extern type Opaque;
+        struct Pair<'a, T> { first: &'a T, second: T }
+        type Twice<'a> = Pair<'a, &'a i32>;
+        fn take<'a>(v: &'a i32, w: i32) -> ();
+        extern fn opaque() -> ();
+        pure fn known() -> ();
+        let mut x: i32;
+        let p: Pair<'p, i32>;
+        bb0: {
+            x = 1;
+            assert_error;
+            assert_empty('p);
+            take(&'L_x x, copy x);
+            goto bb1, bb0;
+        }
+        bb1: {
+        }
+    ",
+    );
+    insta::assert_snapshot!(p.to_string(), @r###"
+    extern type Opaque;
+    struct Pair<'a, T> { first: &'a T, second: T }
+    type Twice<'a> = Pair<'a, &'a i32>;
+    fn take<'a>(arg0: &'a i32, arg1: i32) -> ();
+    extern fn opaque() -> ();
+    pure fn known() -> ();
+    let mut x: i32;
+    let p: Pair<'p, i32>;
+    bb0: {
+        x = 1;
+        assert_error;
+        assert_empty('p);
+        take(&'L_x x, copy x);
+        goto bb1, bb0;
     }
+    bb1: {
+    }
+
+    "###);
+}
+
+#[test]
+fn fn_definitions() {
+    let p = expect_parse(
+        "
+        fn first<'a>(x: &'a i32, y: &'a i32) -> &'a i32 {
+            let mut z: i32;
+            bb0: {
+                z = 1;
+                return = copy x;
+            }
+        }
+        fn use(arg0: i32) -> ();
+        fn reset<'a>(r: &'a mut i32) -> () {
+            bb0: {
+                *r = 0;
+                swap!(*r, *r);
+            }
+        }
+        let mut v: i32;
+        let r: &'r i32;
+        bb0: {
+            r = first(&'L_v v, &'L_v v);
+        }
+    ",
+    );
+    insta::assert_snapshot!(p.to_string(), @r###"
+    fn first<'a>(x: &'a i32, y: &'a i32) -> &'a i32 {
+        let mut z: i32;
+        bb0: {
+            z = 1;
+            return = copy x;
+        }
+    }
+    fn use(arg0: i32) -> ();
+    fn reset<'a>(r: &'a mut i32) -> () {
+        let mut _swap0: i32;
+        bb0: {
+            *r = 0;
+            _swap0 = move *r;
+            *r = move *r;
+            *r = move _swap0;
+        }
+    }
+    let mut v: i32;
+    let r: &'r i32;
+    bb0: {
+        r = first(&'L_v v, &'L_v v);
+    }
+
+    "###);
+    assert_eq!(
+        format!("{:?}", p),
+        format!("{:?}", expect_parse(&p.to_string()))
+    );
+
+    let reset = p.function_program(&p.fn_bodies[1]).unwrap();
+    insta::assert_snapshot!(reset.to_string(), @r###"
+    fn first<'a>(arg0: &'a i32, arg1: &'a i32) -> &'a i32;
+    fn use(arg0: i32) -> ();
+    fn reset<'a>(arg0: &'a mut i32) -> ();
+    let r: &'a mut i32;
+    let mut _swap0: i32;
+    bb0: {
+        *r = 0;
+        _swap0 = move *r;
+        *r = move *r;
+        *r = move _swap0;
+    }
+
+    "###);
+}
+
+#[test]
+fn generic_bounds() {
+    let p = expect_parse("struct Wrapper<'a, 'b, T: 'a + 'b, U> { r: &'a T }");
+    insta::assert_debug_snapshot!(p.struct_decls[0].generic_decls, @r###"
+    [
+        Origin(
+            "'a",
+        ),
+        Origin(
+            "'b",
+        ),
+        Ty(
+            "T",
+            [
+                "'a",
+                "'b",
+            ],
+        ),
+        Ty(
+            "U",
+            [],
+        ),
+    ]
+    "###);
+    insta::assert_snapshot!(p.to_string(), @r###"
+    struct Wrapper<'a, 'b, T: 'a + 'b, U> { r: &'a T }
+
+    "###);
+}
+
+#[test]
+fn json() {
+    let p = expect_parse(
+        "
+        let p: Pair<'p, &'r i32>;
+        bb0: {
+            p.first = copy *p.second;
+        }
+    ",
+    );
+    insta::assert_snapshot!(serde_json::to_string_pretty(&p.basic_blocks).unwrap(), @r###"
+    [
+      {
+        "name": "bb0",
+        "statements": [
+          {
+            "Assign": [
+              {
+                "base": "p",
+                "fields": [
+                  "first"
+                ]
+              },
+              {
+                "Access": {
+                  "kind": "Copy",
+                  "place": {
+                    "base": "*p",
+                    "fields": [
+                      "second"
+                    ]
+                  }
+                }
+              }
+            ]
+          }
+        ],
+        "successors": [],
+        "scrutinee": null
+      }
+    ]
+    "###);
+}
+
+#[test]
+fn pass_statement() {
+    let p = expect_parse("let pass: i32; bb0: { pass; pass = 1; }");
+    insta::assert_debug_snapshot!(p.basic_blocks[0].statements, @r###"
+    [
+        Pass,
+        Assign(
+            Place {
+                base: "pass",
+                fields: [],
+            },
+            Number {
+                value: 1,
+            },
+        ),
+    ]
+    "###);
+    insta::assert_snapshot!(p.to_string(), @r###"
+    let pass: i32;
+    bb0: {
+        pass;
+        pass = 1;
+    }
+
+    "###);
+}
+
+#[test]
+fn labeled_successors() {
+    let p = expect_parse(
+        "
+        bb0: {
+            goto [then] bb1, [0.25] bb2, bb0;
+        }
+        bb1: {
+        }
+        bb2: {
+        }
+    ",
+    );
+    insta::assert_debug_snapshot!(p.basic_blocks[0].successors, @r###"
+    [
+        Successor {
+            block: "bb1",
+            label: Some(
+                "then",
+            ),
+        },
+        Successor {
+            block: "bb2",
+            label: Some(
+                "0.25",
+            ),
+        },
+        Successor {
+            block: "bb0",
+            label: None,
+        },
+    ]
+    "###);
+    assert_eq!(
+        p.basic_blocks[0].to_string(),
+        "bb0: {\n    goto [then] bb1, [0.25] bb2, bb0;\n}\n"
+    );
+}
+
+#[test]
+fn builtin_enums() {
+    let p = expect_parse(
+        "
+        let o: Option<&'o i32>;
+        let r: Result<i32, ()>;
+        bb0: {
+            o = None;
+            r = Err(());
+            match o { Some => bb1, None => bb2, }
+        }
+        bb1: {
+            o = Some(move o.Some);
+        }
+        bb2: {
+        }
+    ",
+    );
+    insta::assert_snapshot!(p.to_string(), @r###"
+    let o: Option<&'o i32>;
+    let r: Result<i32, ()>;
+    bb0: {
+        o = None;
+        r = Err(());
+        match o { Some => bb1, None => bb2 }
+    }
+    bb1: {
+        o = Some(move o.Some);
+    }
+    bb2: {
+    }
+
+    "###);
+}
+
+#[test]
+fn switch() {
+    let p = expect_parse(
+        "
+        let x: i32;
+        bb0: {
+            x = 1;
+            switch x { 0 => bb1, -1 => bb2, _ => bb2, }
+        }
+        bb1: {
+        }
+        bb2: {
+        }
+    ",
+    );
+    insta::assert_snapshot!(p.to_string(), @r###"
+    let x: i32;
+    bb0: {
+        x = 1;
+        switch x { 0 => bb1, -1 => bb2, _ => bb2 }
+    }
+    bb1: {
+    }
+    bb2: {
+    }
+
+    "###);
+}
+
+#[test]
+fn unicode_and_raw_identifiers() {
+    let p = expect_parse(
+        "
+        struct r#type<'a> { r#fn: &'a i32 }
+        fn r#Some<'a>(x: &'a i32) -> &'a i32;
+        let mut café: i32;
+        let r#match: r#type<'r#β>;
+        r#goto: {
+            'r#β: 'a;
+            café = 1;
+            r#match.r#fn = r#Some(&'L_café café);
+            goto 終わり;
+        }
+        終わり: {
+        }
+    ",
+    );
+    assert_eq!(p.struct_decls[0].name, "type");
+    assert_eq!(p.variables[1].name, "match");
+    assert_eq!(p.variables[1].ty.to_string(), "type<'β>");
+    assert_eq!(p.basic_blocks[0].name, "goto");
+
+    // Only the names which would parse as something else are printed back as raw identifiers.
+    insta::assert_snapshot!(p.to_string(), @r###"
+    struct type<'a> { fn: &'a i32 }
+    fn r#Some<'a>(arg0: &'a i32) -> &'a i32;
+    let mut café: i32;
+    let match: type<'β>;
+    goto: {
+        'β: 'a;
+        café = 1;
+        match.fn = r#Some(&'L_café café);
+        goto 終わり;
+    }
+    終わり: {
+    }
+
+    "###);
+    assert_eq!(
+        format!("{:?}", p),
+        format!("{:?}", expect_parse(&p.to_string()))
+    );
+
+    // Columns count characters, not bytes.
+    let err = parse_ast("let café: i32").unwrap_err();
+    insta::assert_snapshot!(err.to_string(), @r###"
+    error at 1:14: expected ";"
+    "###);
+}
+
+#[test]
+fn statement_macros() {
+    let p = expect_parse(
+        "
+        struct S<'a> { f: &'a i32, g: i32 }
+        let mut x: i32;
+        let mut s: S<'s>;
+        let mut t: S<'t>;
+        let mut s_g: &'r i32;
+        bb0: {
+            swap!(s, t);
+            borrow_field!(s, f, 'L_s_f);
+            borrow_field!(s, g, 'L_s_g);
+            swap!(x, s.g);
+        }
+    ",
+    );
+    // The temporaries get fresh origins, and `s_g`, which is declared, keeps its type.
+    insta::assert_snapshot!(p.to_string(), @r###"
+    struct S<'a> { f: &'a i32, g: i32 }
+    let mut x: i32;
+    let mut s: S<'s>;
+    let mut t: S<'t>;
+    let mut s_g: &'r i32;
+    let mut _swap0: S<'_swap0_0>;
+    let mut s_f: &'s_f &'s i32;
+    let mut _swap1: i32;
+    bb0: {
+        _swap0 = move s;
+        s = move t;
+        t = move _swap0;
+        s_f = &'L_s_f s.f;
+        s_g = &'L_s_g s.g;
+        _swap1 = move x;
+        x = move s.g;
+        s.g = move _swap1;
+    }
+
+    "###);
+
+    let err = |s: &str| format!("{:#}", crate::parse_ast(s).unwrap_err());
+    insta::assert_snapshot!(err("let x: i32; bb0: { swap!(x); }"), @"in `swap!(x)`: `swap!` expects two places, e.g. `swap!(a, b)`");
+    insta::assert_snapshot!(err("let x: i32; bb0: { borrow_field!(x, f, 'L); }"), @"in `borrow_field!(x, f, 'L)`: type `I32` has no field `f`");
+    insta::assert_snapshot!(err("let x: i32; bb0: { drop!(x); }"), @"in `drop!(x)`: unknown macro `drop!`, the macros are `swap!` and `borrow_field!`");
+}
+
+#[test]
+fn if_let() {
+    let p = expect_parse(
+        "
+        let mut o: Option<&'o i32>;
+        let mut y: &'y i32;
+        bb0: {
+            if let Some(x) = &'L_o o { goto bb1; } else { goto bb2; }
+        }
+        bb0_some: {
+            if let Some(y) = move o { goto bb2; } else { goto bb2; }
+        }
+        bb1: {
+            goto bb0_some;
+        }
+        bb2: {
+        }
+    ",
+    );
+    // The arm block gets a fresh name, and `y`, which is declared, keeps its type.
+    insta::assert_snapshot!(p.to_string(), @r###"
+    let mut o: Option<&'o i32>;
+    let mut y: &'y i32;
+    let mut x: &'x &'o i32;
+    bb0: {
+        match o { Some => bb0_some1, None => bb2 }
+    }
+    bb0_some1: {
+        x = &'L_o o.Some;
+        goto bb1;
+    }
+    bb0_some: {
+        match o { Some => bb0_some_some, None => bb2 }
+    }
+    bb0_some_some: {
+        y = move o.Some;
+        goto bb2;
+    }
+    bb1: {
+        goto bb0_some;
+    }
+    bb2: {
+    }
+
+    "###);
+
+    let err = |s: &str| format!("{:#}", crate::parse_ast(s).unwrap_err());
+    insta::assert_snapshot!(err("let o: Option<i32>; bb0: { if let None(x) = move o { goto bb0; } else { goto bb0; } }"), @r###"
+    in the `if let` of `bb0`: `o.None` has no payload to bind: type `Struct { name: "Option", parameters: [Ty(I32)] }` has no field `None`
+    "###);
+}
+
+#[test]
+fn struct_literals() {
+    let p = expect_parse(
+        "
+        struct S<'a> { f: &'a i32, g: &'a i32 }
+        let y: &'y i32;
+        let z: S<'z>;
+        let x: S<'x>;
+        bb0: {
+            x = S { f: move y, ..move z };
+            x = S{f:copy y,g:copy y};
+            x = S { ..copy z };
+            S { f: move y, g: copy y };
+        }
+    ",
+    );
+    insta::assert_snapshot!(p.to_string(), @r###"
+    struct S<'a> { f: &'a i32, g: &'a i32 }
+    let y: &'y i32;
+    let z: S<'z>;
+    let x: S<'x>;
+    bb0: {
+        x = S { f: move y, ..move z };
+        x = S { f: copy y, g: copy y };
+        x = S { ..copy z };
+        S { f: move y, g: copy y };
+    }
+
+    "###);
+}
+
+#[test]
+fn drop_impls() {
+    let p = expect_parse(
+        "
+        struct Guard<'a> { r: &'a i32 }
+        impl Drop for Guard;
+        struct Pair<'a> { g: Guard<'a> }
+        let p: Pair<'p>;
+    ",
+    );
+    assert!(p.struct_decls[0].has_destructor);
+    assert!(!p.struct_decls[1].has_destructor);
+    insta::assert_snapshot!(p.to_string(), @r###"
+    struct Guard<'a> { r: &'a i32 }
+    impl Drop for Guard;
+    struct Pair<'a> { g: Guard<'a> }
+    let p: Pair<'p>;
+
+    "###);
+
+    // The `impl Drop` follows the struct it is for.
+    let err = crate::parse_ast("struct A { } struct B { } impl Drop for A;").unwrap_err();
+    insta::assert_snapshot!(err.to_string(), @"error at 1:43: expected the `impl Drop` of the struct before it");
+}
+
+#[test]
+fn drop_statements() {
+    let p = expect_parse("let x: i32; bb0: { drop(x.f); drop (x); drop_x = 1; }");
+    insta::assert_snapshot!(p.basic_blocks[0].to_string(), @r###"
+    bb0: {
+        drop(x.f);
+        drop(x);
+        drop_x = 1;
+    }
+
     "###);
 }
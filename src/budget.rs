@@ -0,0 +1,172 @@
+//! The `budget.txt` files of the examples: limits on the time and the number of tuples of the
+//! `test_harness`, so that an accidental blow-up in the emitted facts or in solving fails the
+//! example instead of slowing down the test suite. One limit per line, e.g.
+//!
+//! ```notrust
+//! // The subsets are quadratic in the origins.
+//! time_ms 200
+//! tuples 2000
+//! ```
+//!
+//! `time_ms` bounds the time to parse, reduce and solve the facts, which is multiplied by the
+//! `BUDGET_SCALE` of the `Config` on slower machines. `tuples` bounds the tuples of the facts
+//! and of the output relations together, which don't depend on the machine.
+//!
+//! The facts emitted for the AST examples are bounded per relation instead, in a single
+//! `tests/emission-budgets.txt` file, so that a change to the emitter which multiplies the
+//! tuples of a relation is caught in review. One limit per line, e.g.
+//!
+//! ```notrust
+//! // Each borrow introduces a few subsets.
+//! loops.ast introduce_subset 20
+//! ```
+
+use std::time::Duration;
+
+use crate::facts::Facts;
+
+#[cfg(test)]
+mod test;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Budget {
+    pub time: Option<Duration>,
+    pub tuples: Option<usize>,
+}
+
+/// Parses the lines of a `budget.txt` file. Blank lines and `//` comments are ignored.
+pub fn parse_budget(input: &str) -> eyre::Result<Budget> {
+    let mut budget = Budget::default();
+    for (i, line) in input.lines().enumerate() {
+        let line = match line.find("//") {
+            Some(comment) => &line[..comment],
+            None => line,
+        }
+        .trim();
+        if line.is_empty() {
+            continue;
+        }
+        let limit = line
+            .split_once(' ')
+            .and_then(|(name, value)| Some((name, value.trim().parse::<u64>().ok()?)));
+        match limit {
+            Some(("time_ms", ms)) => budget.time = Some(Duration::from_millis(ms)),
+            Some(("tuples", tuples)) => budget.tuples = Some(tuples as usize),
+            _ => eyre::bail!(
+                "line {}: expected `time_ms <n>` or `tuples <n>`, found `{}`",
+                i + 1,
+                line
+            ),
+        }
+    }
+    Ok(budget)
+}
+
+impl Budget {
+    /// Checks that an example which took `elapsed` and has `tuples` tuples is within the
+    /// budget, with its time multiplied by `scale`.
+    pub fn check(&self, elapsed: Duration, tuples: usize, scale: f64) -> eyre::Result<()> {
+        let mut exceeded = vec![];
+        if let Some(time) = self.time {
+            let time = time.mul_f64(scale);
+            if elapsed > time {
+                exceeded.push(format!(
+                    "took {}ms, over the budget of {}ms",
+                    elapsed.as_millis(),
+                    time.as_millis()
+                ));
+            }
+        }
+        if let Some(budget) = self.tuples {
+            if tuples > budget {
+                exceeded.push(format!(
+                    "has {} tuples, over the budget of {}",
+                    tuples, budget
+                ));
+            }
+        }
+        if !exceeded.is_empty() {
+            eyre::bail!("{}", exceeded.join(", and "));
+        }
+        Ok(())
+    }
+}
+
+/// The most tuples each example may emit in a relation, see `parse_emission_budgets`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct EmissionBudgets {
+    /// The example, the relation, and its limit, in the order of the file.
+    pub limits: Vec<(String, String, usize)>,
+}
+
+/// Parses the lines of an emission budgets file, `<example> <relation> <tuples>`. Blank lines
+/// and `//` comments are ignored, and the relations must be ones of `Facts`.
+pub fn parse_emission_budgets(input: &str) -> eyre::Result<EmissionBudgets> {
+    let relations: Vec<_> = Facts::default()
+        .tuple_counts()
+        .into_iter()
+        .map(|(name, _)| name)
+        .collect();
+    let mut budgets = EmissionBudgets::default();
+    for (i, line) in input.lines().enumerate() {
+        let line = match line.find("//") {
+            Some(comment) => &line[..comment],
+            None => line,
+        }
+        .trim();
+        if line.is_empty() {
+            continue;
+        }
+        let words: Vec<_> = line.split_whitespace().collect();
+        let (example, relation, tuples) = match words[..] {
+            [example, relation, tuples] => match tuples.parse::<usize>() {
+                Ok(tuples) => (example, relation, tuples),
+                Err(_) => eyre::bail!("line {}: `{}` is not a number of tuples", i + 1, tuples),
+            },
+            _ => eyre::bail!(
+                "line {}: expected `<example> <relation> <tuples>`, found `{}`",
+                i + 1,
+                line
+            ),
+        };
+        if !relations.contains(&relation) {
+            eyre::bail!("line {}: there is no relation `{}`", i + 1, relation);
+        }
+        budgets
+            .limits
+            .push((example.to_string(), relation.to_string(), tuples));
+    }
+    Ok(budgets)
+}
+
+impl EmissionBudgets {
+    /// The examples with a limit, each once, in the order of the file.
+    pub fn examples(&self) -> Vec<&str> {
+        let mut examples = vec![];
+        for (example, ..) in &self.limits {
+            if !examples.contains(&example.as_str()) {
+                examples.push(example.as_str());
+            }
+        }
+        examples
+    }
+
+    /// Checks the `facts` emitted for `example` against its limits, describing each relation
+    /// over its budget.
+    pub fn check(&self, example: &str, facts: &Facts) -> Vec<String> {
+        let counts = facts.tuple_counts();
+        self.limits
+            .iter()
+            .filter(|(e, ..)| e == example)
+            .filter_map(|(_, relation, budget)| {
+                let (_, tuples) = counts.iter().find(|(name, _)| name == relation)?;
+                (tuples > budget).then(|| {
+                    format!(
+                        "`{}` emits {} `{}` tuples, over the budget of {}",
+                        example, tuples, relation, budget
+                    )
+                })
+            })
+            .collect()
+    }
+}
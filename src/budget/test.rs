@@ -0,0 +1,95 @@
+use super::*;
+
+#[test]
+fn parse() {
+    let budget = parse_budget("// quadratic\ntime_ms 200\n\ntuples 2000 // subsets\n").unwrap();
+    assert_eq!(
+        budget,
+        Budget {
+            time: Some(Duration::from_millis(200)),
+            tuples: Some(2000),
+        }
+    );
+    assert_eq!(parse_budget("").unwrap(), Budget::default());
+
+    let err = parse_budget("time_ms 200\ntime 1s").unwrap_err();
+    insta::assert_snapshot!(err.to_string(), @"line 2: expected `time_ms <n>` or `tuples <n>`, found `time 1s`");
+}
+
+#[test]
+fn check() {
+    let budget = Budget {
+        time: Some(Duration::from_millis(100)),
+        tuples: Some(50),
+    };
+    assert!(budget.check(Duration::from_millis(80), 50, 1.0).is_ok());
+    // A slower machine scales the time, but not the tuples.
+    assert!(budget.check(Duration::from_millis(250), 50, 3.0).is_ok());
+
+    let err = budget
+        .check(Duration::from_millis(250), 51, 2.0)
+        .unwrap_err();
+    insta::assert_snapshot!(err.to_string(), @"took 250ms, over the budget of 200ms, and has 51 tuples, over the budget of 50");
+    assert!(Budget::default()
+        .check(Duration::MAX, usize::MAX, 1.0)
+        .is_ok());
+}
+
+#[test]
+fn parse_emission() {
+    let budgets = parse_emission_budgets(
+        "// loops\na.ast cfg_edge 10\n\nb.ast access_origin 2 // few\na.ast clear_origin 3\n",
+    )
+    .unwrap();
+    assert_eq!(budgets.examples(), vec!["a.ast", "b.ast"]);
+    insta::assert_debug_snapshot!(budgets.limits, @r###"
+    [
+        (
+            "a.ast",
+            "cfg_edge",
+            10,
+        ),
+        (
+            "b.ast",
+            "access_origin",
+            2,
+        ),
+        (
+            "a.ast",
+            "clear_origin",
+            3,
+        ),
+    ]
+    "###);
+
+    let errors: Vec<_> = ["a.ast cfg_edge", "a.ast cfg_edge ten", "a.ast cfg_edges 10"]
+        .iter()
+        .map(|input| parse_emission_budgets(input).unwrap_err().to_string())
+        .collect();
+    insta::assert_debug_snapshot!(errors, @r###"
+    [
+        "line 1: expected `<example> <relation> <tuples>`, found `a.ast cfg_edge`",
+        "line 1: `ten` is not a number of tuples",
+        "line 1: there is no relation `cfg_edges`",
+    ]
+    "###);
+}
+
+#[test]
+fn check_emission() {
+    let facts = crate::emit_facts(
+        "let mut x: i32; let r: &'r i32; bb0: { x = 1; r = &'L_x x; x = 2; }",
+        Default::default(),
+    )
+    .unwrap();
+    let budgets = parse_emission_budgets(
+        "a.ast invalidate_origin 1\na.ast clear_origin 1\na.ast cfg_edge 10\nb.ast cfg_edge 0",
+    )
+    .unwrap();
+    insta::assert_debug_snapshot!(budgets.check("a.ast", &facts), @r###"
+    [
+        "`a.ast` emits 3 `invalidate_origin` tuples, over the budget of 1",
+        "`a.ast` emits 2 `clear_origin` tuples, over the budget of 1",
+    ]
+    "###);
+}
@@ -0,0 +1,180 @@
+//! A C ABI over the frontend, so that tools written in other languages, e.g. a Python notebook
+//! through `ctypes`, or a script preparing facts for souffle, can parse programs, emit their
+//! facts and check them without going through the CLI. It is only built with the `capi`
+//! feature, usually as a shared library:
+//!
+//! ```notrust
+//! cargo rustc --release --lib --features capi --crate-type cdylib
+//! ```
+//!
+//! Each function takes a request, a NUL-terminated JSON object whose `input` is the text of an
+//! AST input, and returns a JSON response, `{"ok": <result>}` or `{"error": "<message>"}`,
+//! which must be freed with `polonius_free_string`. The result has an entry per program of the
+//! input, in order, see `parse_ast_programs`.
+
+use std::collections::BTreeMap;
+use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
+use std::panic::{self, AssertUnwindSafe};
+
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+
+use crate::ast::Program;
+use crate::config::Config;
+use crate::fact_emitter::{FactEmitter, Options};
+use crate::pipeline::{Artifacts, Pipeline};
+use crate::profile::Profiler;
+use crate::results::Results;
+use crate::{facts::Facts, solver::Output};
+
+#[cfg(test)]
+mod test;
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Request {
+    input: String,
+
+    /// The settings of `check`, as the variables of `Config::from_vars`, e.g.
+    /// `{"SOLVER": "naive", "PASSES": "reachability-prune"}`.
+    #[serde(default)]
+    config: BTreeMap<String, String>,
+}
+
+/// Parses the request, and returns `[{"name": "main", "program": <ast>}]`, with the AST in the
+/// format of `parse --format=json`.
+///
+/// # Safety
+///
+/// `request` must be a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn polonius_parse(request: *const c_char) -> *mut c_char {
+    respond(request, parse)
+}
+
+/// Emits the facts of the request, and returns `[{"name": "main", "facts": {"access_origin":
+/// [["'a", "bb0[0]"], ..], ..}}]`, with the rows of every relation.
+///
+/// # Safety
+///
+/// `request` must be a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn polonius_emit_facts(request: *const c_char) -> *mut c_char {
+    respond(request, emit_facts)
+}
+
+/// Emits the facts of the request, runs the passes and the solver of its `config`, and returns
+/// `[{"name": "main", "errors": ["invalidated origin `'a` is accessed at `bb0[2]`"], "output":
+/// {"subset": [..], ..}, "functions": [..]}]`, with the rows of every output relation. The
+/// body of each function defined by a program is checked on its own, in `functions`, as
+/// `{"name": "f", "errors": [..], "output": {..}}`.
+///
+/// # Safety
+///
+/// `request` must be a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn polonius_check(request: *const c_char) -> *mut c_char {
+    respond(request, check)
+}
+
+/// Frees a response returned by the other functions.
+///
+/// # Safety
+///
+/// `response` must be null, or a response which isn't freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn polonius_free_string(response: *mut c_char) {
+    if !response.is_null() {
+        drop(CString::from_raw(response));
+    }
+}
+
+/// Handles the request with `handler`, reporting its errors and panics in the response rather
+/// than unwinding into the caller.
+unsafe fn respond(request: *const c_char, handler: fn(&str) -> eyre::Result<Value>) -> *mut c_char {
+    let response = if request.is_null() {
+        json!({ "error": "the request is null" })
+    } else {
+        let request = CStr::from_ptr(request).to_string_lossy();
+        match panic::catch_unwind(AssertUnwindSafe(|| handler(&request))) {
+            Ok(Ok(result)) => json!({ "ok": result }),
+            Ok(Err(error)) => json!({ "error": format!("{:#}", error) }),
+            Err(_) => json!({ "error": "the frontend panicked" }),
+        }
+    };
+    // JSON escapes the NUL characters of strings.
+    CString::new(response.to_string()).unwrap().into_raw()
+}
+
+fn parse_request(request: &str) -> eyre::Result<Request> {
+    serde_json::from_str(request).map_err(|error| eyre::eyre!("invalid request: {}", error))
+}
+
+fn parse(request: &str) -> eyre::Result<Value> {
+    let request = parse_request(request)?;
+    crate::parse_ast_programs(&request.input)?
+        .into_iter()
+        .map(|(name, program)| Ok(json!({ "name": name, "program": program })))
+        .collect()
+}
+
+fn emit_facts(request: &str) -> eyre::Result<Value> {
+    let request = parse_request(request)?;
+    crate::emit_facts_by_program(&request.input, Default::default())?
+        .into_iter()
+        .map(|(name, facts)| Ok(json!({ "name": name, "facts": relations(facts.relations()) })))
+        .collect()
+}
+
+fn check(request: &str) -> eyre::Result<Value> {
+    let request = parse_request(request)?;
+    let config = Config::from_vars(|name| request.config.get(name).cloned())?;
+    let mut results = vec![];
+    for (name, program) in crate::parse_ast_programs(&request.input)? {
+        let functions = FactEmitter::function_programs(&program, &config.emitter)?;
+        let mut result = solve(program, &config.emitter, &config)
+            .map_err(|error| error.wrap_err(format!("in program `{}`", name)))?;
+        let mut checked_functions = vec![];
+        for (function, program, options) in functions {
+            let mut checked = solve(program, &options, &config).map_err(|error| {
+                error.wrap_err(format!(
+                    "in the body of `{}`, in program `{}`",
+                    function, name
+                ))
+            })?;
+            checked["name"] = json!(function);
+            checked_functions.push(checked);
+        }
+        result["name"] = json!(name);
+        result["functions"] = Value::Array(checked_functions);
+        results.push(result);
+    }
+    Ok(Value::Array(results))
+}
+
+/// Solves `program`, and returns `{"errors": [..], "output": {..}}`.
+fn solve(program: Program, options: &Options, config: &Config) -> eyre::Result<Value> {
+    let pipeline = Pipeline::solve(options, &config.passes, config.solver);
+    let mut artifacts = Artifacts::default();
+    artifacts.insert(program);
+    pipeline.run(&mut artifacts, &mut Profiler::default())?;
+    let facts = artifacts.take::<Facts>()?;
+    let output = artifacts.take::<Output>()?;
+    let relations = relations(output.relations());
+    let errors: Vec<String> = Results::new(&facts, output)
+        .errors()
+        .iter()
+        .map(|error| error.to_string())
+        .collect();
+    Ok(json!({ "errors": errors, "output": relations }))
+}
+
+/// The rows of each relation, as an object keyed by relation.
+fn relations(relations: Vec<(&str, Vec<Vec<&str>>)>) -> Value {
+    let relations: Map<String, Value> = relations
+        .into_iter()
+        .map(|(name, rows)| (name.to_string(), json!(rows)))
+        .collect();
+    Value::Object(relations)
+}
@@ -0,0 +1,100 @@
+use super::*;
+
+/// Sends `request` to `function` over the C ABI, and returns the parsed response.
+fn call(function: unsafe extern "C" fn(*const c_char) -> *mut c_char, request: Value) -> Value {
+    let request = CString::new(request.to_string()).unwrap();
+    unsafe {
+        let response = function(request.as_ptr());
+        let parsed = serde_json::from_str(CStr::from_ptr(response).to_str().unwrap()).unwrap();
+        polonius_free_string(response);
+        parsed
+    }
+}
+
+const INPUT: &str = "
+    let mut x: i32;
+    let r: &'r i32;
+    let y: i32;
+    bb0: {
+        r = &'L_x x;
+        x = 1;
+        y = copy *r;
+    }
+";
+
+#[test]
+fn parse() {
+    let response = call(polonius_parse, json!({ "input": "let x: i32;" }));
+    insta::assert_snapshot!(response.to_string(), @r###"
+    {"ok":[{"name":"main","program":{"basic_blocks":[],"extern_types":[],"fn_bodies":[],"fn_prototypes":[],"struct_decls":[],"type_aliases":[],"variables":[{"mutable":false,"name":"x","ty":"I32"}]}}]}
+    "###);
+}
+
+#[test]
+fn emit_facts() {
+    let response = call(polonius_emit_facts, json!({ "input": INPUT }));
+    let facts = &response["ok"][0]["facts"];
+    insta::assert_snapshot!(facts["invalidate_origin"].to_string(), @r###"
+    [["'L_x","bb0[1]"],["'L_x","exit[0]"]]
+    "###);
+    assert_eq!(
+        facts.as_object().unwrap().len(),
+        Facts::default().relations().len()
+    );
+}
+
+#[test]
+fn check() {
+    let request = json!({
+        "input": INPUT,
+        "config": { "SOLVER": "naive", "PASSES": "reachability-prune" },
+    });
+    let response = call(polonius_check, request);
+    insta::assert_snapshot!(response["ok"][0]["errors"].to_string(), @r###"
+    ["invalidated origin `'r` is accessed at `bb0[2]`"]
+    "###);
+    insta::assert_snapshot!(response["ok"][0]["output"]["invalidated_origin_accessed"].to_string(), @r###"
+    [["'r","bb0[2]"]]
+    "###);
+}
+
+#[test]
+fn check_function_bodies() {
+    let input = "
+        fn get<'a>(v: &'a i32) -> &'a i32 {
+            let x: i32;
+            bb0: {
+                x = 1;
+                return = &'L_x x;
+            }
+        }
+    ";
+    let response = call(polonius_check, json!({ "input": input }));
+    insta::assert_snapshot!(response["ok"][0]["errors"].to_string(), @"[]");
+    insta::assert_snapshot!(response["ok"][0]["functions"][0]["name"].to_string(), @r###"
+    "get"
+    "###);
+    insta::assert_snapshot!(response["ok"][0]["functions"][0]["errors"].to_string(), @r###"
+    ["origin `'a` references a local after it died, and is returned at `return[1]`"]
+    "###);
+}
+
+#[test]
+fn errors() {
+    let error = |function, request: Value| call(function, request)["error"].to_string();
+    insta::assert_snapshot!(error(polonius_parse, json!({ "input": "let x i32;" })), @r###"
+    "error at 1:7: expected \":\""
+    "###);
+    insta::assert_snapshot!(error(polonius_emit_facts, json!({ "text": INPUT })), @r###"
+    "invalid request: unknown field `text`, expected `input` or `config` at line 1 column 7"
+    "###);
+    insta::assert_snapshot!(error(polonius_check, json!({ "input": INPUT, "config": { "SOLVER": "fast" } })), @r###"
+    "unknown algorithm `fast`, valid algorithms are `[\"naive\", \"optimized\", \"location-insensitive\"]`"
+    "###);
+
+    let response = unsafe { polonius_parse(std::ptr::null()) };
+    let parsed: Value =
+        unsafe { serde_json::from_str(CStr::from_ptr(response).to_str().unwrap()) }.unwrap();
+    unsafe { polonius_free_string(response) };
+    assert_eq!(parsed, json!({ "error": "the request is null" }));
+}
@@ -0,0 +1,112 @@
+//! The settings of a run, built once at the entry point (the CLI or a test harness) and handed
+//! down to the emitter, the passes and the solvers, instead of each of them reading the
+//! environment.
+
+use std::path::PathBuf;
+
+use crate::fact_emitter::{self, Liveness};
+use crate::facts::FrontendWriter;
+use crate::passes::{self, Pass};
+use crate::Solver;
+
+#[cfg(test)]
+mod test;
+
+/// The rules souffle runs by default.
+pub const DEFAULT_RULES_PATH: &str = "src/polonius.dl";
+
+#[derive(Clone, Debug)]
+pub struct Config {
+    /// How facts are emitted from AST programs, e.g. how nodes are named.
+    pub emitter: fact_emitter::Options,
+
+    /// How facts are printed in the frontend format, e.g. by `emit`.
+    pub writer: FrontendWriter,
+
+    /// The fact-reduction passes run before solving, e.g. pruning unreachable nodes.
+    pub passes: Vec<Pass>,
+
+    /// Which solver computes the output relations.
+    pub solver: Solver,
+
+    /// Turns lint warnings into errors.
+    pub strict: bool,
+
+    /// The datalog rules souffle runs, e.g. an older version of `polonius.dl` to compare with.
+    pub rules_path: PathBuf,
+
+    /// Overwrites the expected outputs of the test harness with the computed ones.
+    pub bless: bool,
+
+    /// Multiplies the time budgets of the examples, e.g. on a slow CI machine, see `budget`.
+    pub budget_scale: f64,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            emitter: fact_emitter::Options::default(),
+            writer: FrontendWriter::default(),
+            passes: vec![],
+            solver: Solver::default(),
+            strict: false,
+            rules_path: PathBuf::from(DEFAULT_RULES_PATH),
+            bless: false,
+            budget_scale: 1.0,
+        }
+    }
+}
+
+impl Config {
+    /// The default config, overridden by the environment variables `SOLVER`, `PASSES` (a
+    /// comma-separated list), `RULES`, `BUDGET_SCALE`, `NODE_NAMES` (`simple` or `stable`),
+    /// `LIVENESS` (`access` or `explicit`), and the flags `STRICT`, `BLESS` and `ORIGIN_KINDS`.
+    pub fn from_env() -> eyre::Result<Self> {
+        Self::from_vars(|name| std::env::var(name).ok())
+    }
+
+    /// Like `from_env`, reading the variables from `var` instead.
+    pub fn from_vars(var: impl Fn(&str) -> Option<String>) -> eyre::Result<Self> {
+        let mut config = Config::default();
+        if let Some(solver) = var("SOLVER") {
+            config.solver = solver.parse()?;
+        }
+        if let Some(list) = var("PASSES") {
+            config.passes = passes::parse_pass_list(&list)?;
+        }
+        match var("NODE_NAMES").as_deref() {
+            None => {}
+            Some("simple") => config.emitter.simple_node_names = true,
+            Some("stable") => config.emitter.stable_node_names = true,
+            Some(names) => eyre::bail!(
+                "`NODE_NAMES` must be `simple` or `stable`, found `{}`",
+                names
+            ),
+        }
+        match var("LIVENESS").as_deref() {
+            None => {}
+            Some("access") => config.emitter.liveness = Liveness::AccessImpliesLive,
+            Some("explicit") => config.emitter.liveness = Liveness::Explicit,
+            Some(liveness) => eyre::bail!(
+                "`LIVENESS` must be `access` or `explicit`, found `{}`",
+                liveness
+            ),
+        }
+        config.writer.include_origin_kinds = var("ORIGIN_KINDS").is_some();
+        if let Some(path) = var("RULES") {
+            config.rules_path = PathBuf::from(path);
+        }
+        config.strict = var("STRICT").is_some();
+        config.bless = var("BLESS").is_some();
+        if let Some(scale) = var("BUDGET_SCALE") {
+            config.budget_scale = match scale.parse() {
+                Ok(scale) if scale > 0.0 => scale,
+                _ => eyre::bail!(
+                    "`BUDGET_SCALE` must be a positive number, found `{}`",
+                    scale
+                ),
+            };
+        }
+        Ok(config)
+    }
+}
@@ -0,0 +1,77 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use super::*;
+use crate::solver::Algorithm;
+
+fn config_from(vars: &[(&str, &str)]) -> eyre::Result<Config> {
+    let vars: HashMap<_, _> = vars.iter().copied().collect();
+    Config::from_vars(|name| vars.get(name).map(|value| value.to_string()))
+}
+
+#[test]
+fn defaults_without_overrides() -> eyre::Result<()> {
+    let config = config_from(&[])?;
+    assert_eq!(config.solver, Solver::Check);
+    assert!(config.passes.is_empty());
+    assert_eq!(config.rules_path, PathBuf::from("src/polonius.dl"));
+    assert!(!config.strict);
+    assert!(!config.bless);
+    assert!(!config.emitter.simple_node_names && !config.emitter.stable_node_names);
+    assert_eq!(config.emitter.liveness, Liveness::AccessImpliesLive);
+    assert_eq!(config.writer, FrontendWriter::default());
+    Ok(())
+}
+
+#[test]
+fn env_overrides() -> eyre::Result<()> {
+    let config = config_from(&[
+        ("SOLVER", "naive"),
+        ("PASSES", "dedup,reachability-prune"),
+        ("RULES", "old/polonius.dl"),
+        ("STRICT", "1"),
+        ("BLESS", ""),
+        ("NODE_NAMES", "stable"),
+        ("LIVENESS", "explicit"),
+        ("ORIGIN_KINDS", "1"),
+    ])?;
+    assert_eq!(config.solver, Solver::Native(Algorithm::Naive));
+    assert_eq!(config.passes, vec![Pass::Dedup, Pass::ReachabilityPrune]);
+    assert_eq!(config.rules_path, PathBuf::from("old/polonius.dl"));
+    assert!(config.strict);
+    assert!(config.bless);
+    assert!(config.emitter.stable_node_names);
+    assert_eq!(config.emitter.liveness, Liveness::Explicit);
+    assert!(config.writer.include_origin_kinds);
+    assert!(
+        config_from(&[("NODE_NAMES", "simple")])?
+            .emitter
+            .simple_node_names
+    );
+    Ok(())
+}
+
+#[test]
+fn invalid_override() {
+    let err = config_from(&[("PASSES", "dedup,inline")]).unwrap_err();
+    assert!(err.to_string().contains("unknown pass `inline`"), "{}", err);
+    let err = config_from(&[("LIVENESS", "drop")]).unwrap_err();
+    assert!(
+        err.to_string().contains("must be `access` or `explicit`"),
+        "{}",
+        err
+    );
+}
+
+#[test]
+fn budget_scale() -> eyre::Result<()> {
+    assert_eq!(config_from(&[])?.budget_scale, 1.0);
+    assert_eq!(config_from(&[("BUDGET_SCALE", "2.5")])?.budget_scale, 2.5);
+    let err = config_from(&[("BUDGET_SCALE", "0")]).unwrap_err();
+    assert!(
+        err.to_string().contains("must be a positive number"),
+        "{}",
+        err
+    );
+    Ok(())
+}
@@ -0,0 +1,215 @@
+//! Where the origins of an AST program are declared, so that diagnostics can point at the
+//! source, e.g. "origin `'v` declared in the type of `v` at line 4". The AST doesn't keep
+//! source positions, so the parser fills this side table, see `parse_ast_with_declarations`.
+//! It also records the line of each statement, e.g. to find the statement of an annotation,
+//! and of each named item, e.g. to show both declarations of a duplicate.
+
+use std::fmt;
+
+use crate::ast::visit::{self, ProgramVisitor};
+use crate::ast::{AccessKind, Expr, GenericDecl, Name, Statement, VariableDecl};
+
+#[cfg(test)]
+mod test;
+
+/// What declares an origin.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Site {
+    /// The type annotation of a variable, e.g. `'v` in `let v: &'v i32;`.
+    VariableType(Name),
+
+    /// A borrow expression of a statement, e.g. `'L_x` in `r = &'L_x x;`.
+    Borrow(String),
+
+    /// The generic parameters of a struct, a type alias or a function, e.g. `'a` in
+    /// `struct S<'a>`.
+    Generics(Name),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Declaration {
+    pub origin: Name,
+    pub site: Site,
+
+    /// The line of the declaration, starting at 1.
+    pub line: usize,
+}
+
+impl fmt::Display for Declaration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "origin `{}` declared ", self.origin)?;
+        match &self.site {
+            Site::VariableType(variable) => write!(f, "in the type of `{}`", variable)?,
+            Site::Borrow(statement) => write!(f, "by the borrow in `{}`", statement)?,
+            Site::Generics(item) => write!(f, "by the generic parameters of `{}`", item)?,
+        }
+        write!(f, " at line {}", self.line)
+    }
+}
+
+/// What a named item of a program is. Each kind has its own namespace.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ItemKind {
+    /// An extern type or a struct.
+    Type,
+
+    /// A field of the struct.
+    Field(Name),
+
+    Function,
+    Variable,
+    Block,
+}
+
+impl ItemKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ItemKind::Type => "type",
+            ItemKind::Field(_) => "field",
+            ItemKind::Function => "function",
+            ItemKind::Variable => "variable",
+            ItemKind::Block => "block",
+        }
+    }
+}
+
+/// The declaration of a named item, e.g. a struct or a block.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ItemDeclaration {
+    pub kind: ItemKind,
+    pub name: Name,
+
+    /// The line of the declaration, starting at 1.
+    pub line: usize,
+}
+
+/// The declarations of the origins of a program, in source order. An origin can have several,
+/// e.g. when it is in the types of two variables, or when two structs name a parameter `'a`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct OriginDeclarations {
+    declarations: Vec<Declaration>,
+
+    /// The line of each statement of the blocks, in program order.
+    statement_lines: Vec<usize>,
+
+    /// The named items, in source order.
+    items: Vec<ItemDeclaration>,
+}
+
+impl OriginDeclarations {
+    /// The first declaration of `origin`.
+    pub fn declared_at(&self, origin: &str) -> Option<&Declaration> {
+        self.declarations
+            .iter()
+            .find(|declaration| declaration.origin == origin)
+    }
+
+    /// All the declarations of `origin`.
+    pub fn of<'a>(&'a self, origin: &'a str) -> impl Iterator<Item = &'a Declaration> + 'a {
+        self.declarations
+            .iter()
+            .filter(move |declaration| declaration.origin == origin)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Declaration> {
+        self.declarations.iter()
+    }
+
+    /// The line of each statement of the blocks, in the order of `ast::Program::basic_blocks`,
+    /// ghost statements included. The statements of a macro are at the line of its invocation.
+    pub fn statement_lines(&self) -> &[usize] {
+        &self.statement_lines
+    }
+
+    /// The declarations of the named items of the program, in source order: its types, the
+    /// fields of its structs, its functions, variables and blocks. The items of modules, and
+    /// the ones declared by macros, aren't recorded.
+    pub fn items(&self) -> &[ItemDeclaration] {
+        &self.items
+    }
+}
+
+/// Records the declarations found by the parser, at byte offsets of `input`.
+pub(crate) struct Recorder<'i> {
+    input: &'i str,
+    declarations: OriginDeclarations,
+}
+
+impl<'i> Recorder<'i> {
+    pub fn new(input: &'i str) -> Self {
+        Recorder {
+            input,
+            declarations: OriginDeclarations::default(),
+        }
+    }
+
+    pub fn generics(&mut self, offset: usize, item: &Name, generic_decls: &[GenericDecl]) {
+        for generic_decl in generic_decls {
+            if let GenericDecl::Origin(origin) = generic_decl {
+                self.push(offset, origin, Site::Generics(item.clone()));
+            }
+        }
+    }
+
+    pub fn variable(&mut self, offset: usize, variable: &VariableDecl) {
+        for origin in crate::typeck::origins_of_ty(&variable.ty) {
+            self.push(offset, origin, Site::VariableType(variable.name.clone()));
+        }
+    }
+
+    pub fn statement(&mut self, offset: usize, statement: &Statement) {
+        struct Borrows<'ast>(Vec<&'ast Name>);
+
+        impl<'ast> ProgramVisitor<'ast> for Borrows<'ast> {
+            fn visit_expr(&mut self, expr: &'ast Expr) -> eyre::Result<()> {
+                if let Expr::Access {
+                    kind: AccessKind::Borrow(origin) | AccessKind::BorrowMut(origin),
+                    ..
+                } = expr
+                {
+                    self.0.push(origin);
+                }
+                visit::walk_expr(self, expr)
+            }
+        }
+
+        let line = self.line(offset);
+        self.declarations.statement_lines.push(line);
+
+        let mut borrows = Borrows(vec![]);
+        borrows.visit_statement(statement).unwrap();
+        for origin in borrows.0 {
+            self.push(offset, origin, Site::Borrow(statement.to_string()));
+        }
+    }
+
+    pub fn item(&mut self, offset: usize, kind: ItemKind, name: &Name) {
+        let line = self.line(offset);
+        self.declarations.items.push(ItemDeclaration {
+            kind,
+            name: name.clone(),
+            line,
+        });
+    }
+
+    pub fn items(&self) -> &[ItemDeclaration] {
+        self.declarations.items()
+    }
+
+    pub fn finish(self) -> OriginDeclarations {
+        self.declarations
+    }
+
+    fn push(&mut self, offset: usize, origin: &Name, site: Site) {
+        let line = self.line(offset);
+        self.declarations.declarations.push(Declaration {
+            origin: origin.clone(),
+            site,
+            line,
+        });
+    }
+
+    fn line(&self, offset: usize) -> usize {
+        self.input[..offset].matches('\n').count() + 1
+    }
+}
@@ -0,0 +1,35 @@
+use super::*;
+
+#[test]
+fn declaration_sites() {
+    let input = "
+        struct S<'a> { r: &'a i32 }
+        fn id<'f>(x: &'f i32) -> &'f i32;
+        let mut x: i32;
+        let v: &'v i32;
+        let s: S<'s>;
+        bb0: {
+            v = id(&'L_x x);
+            borrow_field!(s, r, 'L_s_r);
+        }
+    ";
+    let (_, declarations) = crate::parse_ast_with_declarations(input).unwrap();
+    let lines: Vec<String> = declarations.iter().map(|d| d.to_string()).collect();
+    insta::assert_snapshot!(lines.join("\n"), @r###"
+    origin `'a` declared by the generic parameters of `S` at line 2
+    origin `'f` declared by the generic parameters of `id` at line 3
+    origin `'v` declared in the type of `v` at line 5
+    origin `'s` declared in the type of `s` at line 6
+    origin `'L_x` declared by the borrow in `v = id(&'L_x x)` at line 8
+    origin `'s_r` declared in the type of `s_r` at line 9
+    origin `'s` declared in the type of `s_r` at line 9
+    origin `'L_s_r` declared by the borrow in `s_r = &'L_s_r s.r` at line 9
+    "###);
+
+    let v = declarations.declared_at("'v").unwrap();
+    assert_eq!((&v.site, v.line), (&Site::VariableType("v".to_string()), 5));
+    // `'s` is also in the type of the field borrowed by the macro.
+    assert_eq!(declarations.of("'s").count(), 2);
+    assert!(declarations.declared_at("'w").is_none());
+    assert_eq!(declarations.statement_lines(), &[8, 9]);
+}
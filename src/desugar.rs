@@ -0,0 +1,133 @@
+//! Desugarings of the surface syntax, applied to the AST before emitting facts.
+
+use std::collections::HashSet;
+
+use crate::ast::visit::ProgramVisitorMut;
+use crate::ast::{AccessKind, BasicBlock, Expr, Place, Program, Statement, VariableDecl};
+use crate::typeck::{self, TypeEnv};
+
+#[cfg(test)]
+mod test;
+
+/// Hoists borrows and calls used as call arguments into temporaries, like MIR does: each
+/// temporary is assigned in its own statement (and so gets its own node) right before the
+/// statement using it, and the argument becomes a move out of the temporary.
+///
+/// The temporaries are named `_tmp0`, `_tmp1`, etc., skipping the names the program already
+/// declares, and are declared with a type whose origins are all fresh.
+pub fn introduce_temporaries(program: &mut Program) -> eyre::Result<()> {
+    let original = program.clone();
+    let declared = (original.variables.iter())
+        .chain(original.fn_bodies.iter().flat_map(|body| &body.variables))
+        .map(|decl| decl.name.as_str())
+        .chain(
+            (original.fn_bodies.iter())
+                .flat_map(|body| &body.parameters)
+                .map(|name| name.as_str()),
+        )
+        .collect();
+    let mut lowering = Lowering {
+        env: TypeEnv::new(&original),
+        declared,
+        statements: vec![],
+        variables: vec![],
+        counter: 0,
+    };
+
+    lowering.visit_program(program)?;
+    program.variables.extend(lowering.variables);
+    Ok(())
+}
+
+struct Lowering<'p> {
+    env: TypeEnv<'p>,
+
+    /// The names of the variables of the original program, which the temporaries can't use.
+    declared: HashSet<&'p str>,
+    statements: Vec<Statement>,
+    variables: Vec<VariableDecl>,
+    counter: usize,
+}
+
+impl ProgramVisitorMut for Lowering<'_> {
+    fn visit_basic_block(&mut self, block: &mut BasicBlock) -> eyre::Result<()> {
+        for statement in std::mem::take(&mut block.statements) {
+            let statement = match statement {
+                Statement::Assign(place, expr) => {
+                    Statement::Assign(place, self.hoist_arguments(expr)?)
+                }
+                Statement::Drop(expr) => Statement::Drop(self.hoist_arguments(expr)?),
+                Statement::DropPlace(place) => Statement::DropPlace(place),
+                Statement::Assert(assertion) => Statement::Assert(assertion),
+                Statement::Pass => Statement::Pass,
+                Statement::Outlives(o1, o2) => Statement::Outlives(o1, o2),
+            };
+            self.statements.push(statement);
+        }
+        block.statements = std::mem::take(&mut self.statements);
+        Ok(())
+    }
+}
+
+impl Lowering<'_> {
+    fn fresh_name(&mut self) -> String {
+        loop {
+            let name = format!("_tmp{}", self.counter);
+            self.counter += 1;
+            if !self.declared.contains(name.as_str()) {
+                return name;
+            }
+        }
+    }
+
+    fn hoist_arguments(&mut self, expr: Expr) -> eyre::Result<Expr> {
+        let (name, arguments) = match expr {
+            Expr::Call { name, arguments } => (name, arguments),
+            expr => return Ok(expr),
+        };
+
+        let arguments = arguments
+            .into_iter()
+            .map(|argument| {
+                let needs_temporary = matches!(
+                    argument,
+                    Expr::Call { .. }
+                        | Expr::Access {
+                            kind: AccessKind::Borrow(_) | AccessKind::BorrowMut(_),
+                            ..
+                        }
+                );
+                if !needs_temporary {
+                    return Ok(argument);
+                }
+
+                // The type is computed on the original expression, which only refers to
+                // variables of the original program.
+                let ty = self.env.expr_ty(&argument)?;
+                let argument = self.hoist_arguments(argument)?;
+
+                let temporary = self.fresh_name();
+                // Like MIR temporaries, they may be assigned again when their block is in a
+                // loop.
+                self.variables.push(VariableDecl {
+                    ty: typeck::freshen_ty(&ty, &temporary),
+                    name: temporary.clone(),
+                    mutable: true,
+                });
+
+                let place = Place {
+                    base: temporary,
+                    fields: vec![],
+                };
+                self.statements
+                    .push(Statement::Assign(place.clone(), argument));
+                Ok(Expr::Access {
+                    kind: AccessKind::Move,
+                    place,
+                })
+            })
+            .collect::<eyre::Result<_>>()?;
+
+        Ok(Expr::Call { name, arguments })
+    }
+}
@@ -0,0 +1,283 @@
+use super::*;
+
+fn desugar(s: &str) -> Program {
+    let mut program = crate::parse_ast(s).unwrap();
+    introduce_temporaries(&mut program).unwrap();
+    program
+}
+
+#[test]
+fn borrow_argument() {
+    let p = desugar(
+        "
+        extern type Thing;
+        fn MaybeNext<'a>(t: &'a mut Thing) -> &'a mut Thing;
+        let temp: &'temp mut Thing;
+        let v: &'v mut Thing;
+        bb0: {
+            v = MaybeNext(&'L_*temp mut *temp);
+        }
+    ",
+    );
+
+    insta::assert_debug_snapshot!((&p.variables[2..], &p.basic_blocks[0].statements), @r###"
+    (
+        [
+            VariableDecl {
+                name: "_tmp0",
+                ty: RefMut {
+                    origin: "'_tmp0_0",
+                    ty: Struct {
+                        name: "Thing",
+                        parameters: [],
+                    },
+                },
+                mutable: true,
+            },
+        ],
+        [
+            Assign(
+                Place {
+                    base: "_tmp0",
+                    fields: [],
+                },
+                Access {
+                    kind: BorrowMut(
+                        "'L_*temp",
+                    ),
+                    place: Place {
+                        base: "*temp",
+                        fields: [],
+                    },
+                },
+            ),
+            Assign(
+                Place {
+                    base: "v",
+                    fields: [],
+                },
+                Call {
+                    name: "MaybeNext",
+                    arguments: [
+                        Access {
+                            kind: Move,
+                            place: Place {
+                                base: "_tmp0",
+                                fields: [],
+                            },
+                        },
+                    ],
+                },
+            ),
+        ],
+    )
+    "###);
+}
+
+#[test]
+fn nested_call_argument() {
+    let p = desugar(
+        "
+        struct Vec<T> { item0: T }
+        fn Vec_new<T>(element: T) -> Vec<T>;
+        fn Vec_first<T>(v: Vec<T>) -> T;
+        let x: i32;
+        bb0: {
+            x = Vec_first(Vec_new(22));
+        }
+    ",
+    );
+
+    insta::assert_debug_snapshot!((&p.variables[1..], &p.basic_blocks[0].statements), @r###"
+    (
+        [
+            VariableDecl {
+                name: "_tmp0",
+                ty: Struct {
+                    name: "Vec",
+                    parameters: [
+                        Ty(
+                            I32,
+                        ),
+                    ],
+                },
+                mutable: true,
+            },
+        ],
+        [
+            Assign(
+                Place {
+                    base: "_tmp0",
+                    fields: [],
+                },
+                Call {
+                    name: "Vec_new",
+                    arguments: [
+                        Number {
+                            value: 22,
+                        },
+                    ],
+                },
+            ),
+            Assign(
+                Place {
+                    base: "x",
+                    fields: [],
+                },
+                Call {
+                    name: "Vec_first",
+                    arguments: [
+                        Access {
+                            kind: Move,
+                            place: Place {
+                                base: "_tmp0",
+                                fields: [],
+                            },
+                        },
+                    ],
+                },
+            ),
+        ],
+    )
+    "###);
+}
+
+#[test]
+fn call_chain() {
+    // `x = r.next().unwrap()`, until methods land: each step of the chain gets its own
+    // temporary, and the reborrow of the receiver `&mut *r`, implicit in a method call, is the
+    // first step.
+    let input = "
+        struct Iter<'i> { item: &'i i32 }
+        fn next<'a, 'i>(it: &'a mut Iter<'i>) -> Option<&'i i32>;
+        fn unwrap<T>(o: Option<T>) -> T;
+        let r: &'r mut Iter<'it>;
+        let x: &'x i32;
+        bb0: {
+            x = unwrap(next(&'L_r mut *r));
+        }
+    ";
+    let p = desugar(input);
+
+    insta::assert_snapshot!(p.to_string(), @r###"
+    struct Iter<'i> { item: &'i i32 }
+    fn next<'a, 'i>(arg0: &'a mut Iter<'i>) -> Option<&'i i32>;
+    fn unwrap<T>(arg0: Option<T>) -> T;
+    let r: &'r mut Iter<'it>;
+    let x: &'x i32;
+    let mut _tmp0: &'_tmp0_0 mut Iter<'_tmp0_1>;
+    let mut _tmp1: Option<&'_tmp1_0 i32>;
+    bb0: {
+        _tmp0 = &'L_r mut *r;
+        _tmp1 = next(move _tmp0);
+        x = unwrap(move _tmp1);
+    }
+
+    "###);
+
+    // Each step has its own node and facts: the reborrow at `a` goes through `r`, the call to
+    // `next` at `b` relates the temporary to its signature, and the call to `unwrap` at `c`
+    // gives `x` the items of the iterator.
+    let options = crate::fact_emitter::Options {
+        simple_node_names: true,
+        introduce_temporaries: true,
+        ..Default::default()
+    };
+    let facts = crate::emit_facts(input, options).unwrap();
+    insta::assert_snapshot!(facts.to_string(), @r###"
+    // polonius-frontend v2
+    a: "_tmp0 = &'L_r mut *r" {
+        access_origin('r)
+        access_origin('it)
+        invalidate_origin('L_r)
+        write_origin('_tmp0_0)
+        write_origin('_tmp0_1)
+        clear_origin('_tmp0_0)
+        clear_origin('_tmp0_1)
+        clear_origin('L_r)
+        introduce_subset('r, 'L_r)
+        introduce_subset('L_r, '_tmp0_0)
+        introduce_subset('it, '_tmp0_1)
+        introduce_subset('_tmp0_1, 'it)
+        goto b
+    }
+
+    b: "_tmp1 = next(move _tmp0)" {
+        access_origin('_tmp0_0)
+        access_origin('_tmp0_1)
+        write_origin('_tmp1_0)
+        clear_origin('_tmp1_0)
+        introduce_subset('_tmp0_0, 'next0_a)
+        introduce_subset('_tmp0_1, 'next0_i)
+        introduce_subset('next0_i, '_tmp0_1)
+        introduce_subset('next0_i, '_tmp1_0)
+        goto c
+    }
+
+    c: "x = unwrap(move _tmp1)" {
+        access_origin('_tmp1_0)
+        write_origin('x)
+        clear_origin('x)
+        introduce_subset('_tmp1_0, 'x)
+        goto d
+    }
+
+    d: "(storage dead)" {
+        node_is_exit()
+        goto
+    }
+
+    "###);
+
+    let output = crate::solver::check(&facts).unwrap();
+    assert!(output.invalidated_origin_accessed.is_empty());
+    // The items of the iterator, `'it`, reach one more step of the chain at each node.
+    let flows: Vec<_> = ["b", "c", "d"]
+        .iter()
+        .map(|node| {
+            let origins: Vec<_> = (output.subset.iter())
+                .filter(|(o1, o2, n)| o1 == "'it" && n == node && o2 != "'it")
+                .map(|(_, o2, _)| o2.as_str())
+                .collect();
+            format!("{}: {}", node, origins.join(", "))
+        })
+        .collect();
+    insta::assert_debug_snapshot!(flows, @r###"
+    [
+        "b: '_tmp0_1",
+        "c: '_tmp0_1, '_tmp1_0, 'next0_i",
+        "d: '_tmp0_1, '_tmp1_0, 'next0_i, 'x",
+    ]
+    "###);
+}
+
+#[test]
+fn temporary_names_are_fresh() {
+    // The temporaries skip the names declared by the program.
+    let p = desugar(
+        "
+        fn id<'a>(r: &'a i32) -> &'a i32;
+        let _tmp0: i32;
+        let _tmp2: i32;
+        let x: &'x i32;
+        bb0: {
+            x = id(id(&'L_tmp0 _tmp0));
+        }
+    ",
+    );
+
+    insta::assert_snapshot!(p.to_string(), @r###"
+    fn id<'a>(arg0: &'a i32) -> &'a i32;
+    let _tmp0: i32;
+    let _tmp2: i32;
+    let x: &'x i32;
+    let mut _tmp1: &'_tmp1_0 i32;
+    let mut _tmp3: &'_tmp3_0 i32;
+    bb0: {
+        _tmp1 = &'L_tmp0 _tmp0;
+        _tmp3 = id(move _tmp1);
+        x = id(move _tmp3);
+    }
+
+    "###);
+}
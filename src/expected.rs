@@ -0,0 +1,138 @@
+//! The `expected.txt` files of the examples: the diagnostics a program is expected to produce,
+//! one per line, as printed by `results::Diagnostic`, e.g.
+//!
+//! ```notrust
+//! // The loan is used after `x` is overwritten.
+//! error invalidated_origin_accessed('L_x) @ c
+//! warning loan_conflict('L1, 'L2) @ d
+//! ```
+//!
+//! Unlike `invalidated_origin_accessed.csv`, an example can expect several kinds of
+//! diagnostics, and the harness reports all the differences with the actual ones at once.
+
+use std::collections::BTreeSet;
+use std::fmt;
+
+use crate::facts::Node;
+use crate::results::{Diagnostic, Severity};
+
+#[cfg(test)]
+mod test;
+
+/// Parses the lines of an `expected.txt` file. Blank lines and `//` comments are ignored.
+pub fn parse_expected(input: &str) -> eyre::Result<Vec<Diagnostic>> {
+    let mut diagnostics = vec![];
+    for (i, line) in input.lines().enumerate() {
+        let line = match line.find("//") {
+            Some(comment) => &line[..comment],
+            None => line,
+        }
+        .trim();
+        if line.is_empty() {
+            continue;
+        }
+        let diagnostic = parse_diagnostic(line).ok_or_else(|| {
+            eyre::eyre!(
+                "line {}: expected `<severity> <relation>(<origins>) @ <node>`, found `{}`",
+                i + 1,
+                line
+            )
+        })?;
+        diagnostics.push(diagnostic);
+    }
+    Ok(diagnostics)
+}
+
+fn parse_diagnostic(line: &str) -> Option<Diagnostic> {
+    let (severity, rest) = line.split_once(' ')?;
+    let severity = Severity::ALL
+        .iter()
+        .copied()
+        .find(|s| s.name() == severity)?;
+    let (tuple, node) = rest.split_once('@')?;
+    let (relation, origins) = tuple.trim().strip_suffix(')')?.split_once('(')?;
+    let origins = origins
+        .split(',')
+        .map(str::trim)
+        .filter(|o| !o.is_empty())
+        .map(str::to_string)
+        .collect();
+    let node = node.trim();
+    if relation.trim().is_empty() || node.is_empty() || node.contains(' ') {
+        return None;
+    }
+    Some(Diagnostic {
+        severity,
+        relation: relation.trim().to_string(),
+        origins,
+        node: node.to_string(),
+    })
+}
+
+/// The differences between the expected and the actual diagnostics.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Discrepancies {
+    /// Expected, but not produced anywhere.
+    pub missing: Vec<Diagnostic>,
+
+    /// Produced, but not expected anywhere.
+    pub unexpected: Vec<Diagnostic>,
+
+    /// Expected, but produced at other nodes.
+    pub mismatched: Vec<(Diagnostic, Vec<Node>)>,
+}
+
+impl Discrepancies {
+    pub fn is_empty(&self) -> bool {
+        self.missing.is_empty() && self.unexpected.is_empty() && self.mismatched.is_empty()
+    }
+}
+
+/// Compares the diagnostics as sets. A diagnostic which differs from an expected one only by
+/// its node is reported as a mismatched location, rather than as both missing and unexpected.
+pub fn compare(expected: &[Diagnostic], actual: &[Diagnostic]) -> Discrepancies {
+    let expected: BTreeSet<&Diagnostic> = expected.iter().collect();
+    let actual: BTreeSet<&Diagnostic> = actual.iter().collect();
+    let same_but_node = |d1: &Diagnostic, d2: &Diagnostic| {
+        (d1.severity, &d1.relation, &d1.origins) == (d2.severity, &d2.relation, &d2.origins)
+    };
+
+    let unmatched_actual: Vec<&Diagnostic> = actual.difference(&expected).copied().collect();
+    let mut discrepancies = Discrepancies::default();
+    let mut relocated = BTreeSet::new();
+    for &diagnostic in expected.difference(&actual) {
+        let nodes: Vec<Node> = unmatched_actual
+            .iter()
+            .filter(|d| same_but_node(d, diagnostic))
+            .map(|d| {
+                relocated.insert(*d);
+                d.node.clone()
+            })
+            .collect();
+        if nodes.is_empty() {
+            discrepancies.missing.push(diagnostic.clone());
+        } else {
+            discrepancies.mismatched.push((diagnostic.clone(), nodes));
+        }
+    }
+    discrepancies.unexpected = unmatched_actual
+        .into_iter()
+        .filter(|d| !relocated.contains(d))
+        .cloned()
+        .collect();
+    discrepancies
+}
+
+/// Prints one discrepancy per line.
+impl fmt::Display for Discrepancies {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let missing = self.missing.iter().map(|d| format!("missing: {}", d));
+        let unexpected = self.unexpected.iter().map(|d| format!("unexpected: {}", d));
+        let mismatched = self
+            .mismatched
+            .iter()
+            .map(|(d, nodes)| format!("mismatched location: {}, found at {}", d, nodes.join(", ")));
+        let lines: Vec<String> = missing.chain(unexpected).chain(mismatched).collect();
+        write!(f, "{}", lines.join("\n"))
+    }
+}
@@ -0,0 +1,64 @@
+use super::*;
+
+fn diagnostics(input: &str) -> Vec<Diagnostic> {
+    parse_expected(input).unwrap()
+}
+
+#[test]
+fn parse() {
+    let input = "
+        // A comment.
+        error invalidated_origin_accessed('L_x) @ c
+
+        warning loan_conflict('L1, 'L2) @ d // Another comment.
+        note assert_empty_violated('a, 'b) @ bb0[1]
+        note unit() @ e
+    ";
+    let printed: Vec<_> = diagnostics(input).iter().map(|d| d.to_string()).collect();
+    insta::assert_debug_snapshot!(printed, @r###"
+    [
+        "error invalidated_origin_accessed('L_x) @ c",
+        "warning loan_conflict('L1, 'L2) @ d",
+        "note assert_empty_violated('a, 'b) @ bb0[1]",
+        "note unit() @ e",
+    ]
+    "###);
+}
+
+#[test]
+fn parse_errors() {
+    let error = |input| parse_expected(input).unwrap_err().to_string();
+    insta::assert_snapshot!(error("bug invalidated_origin_accessed('L_x) @ c"), @"line 1: expected `<severity> <relation>(<origins>) @ <node>`, found `bug invalidated_origin_accessed('L_x) @ c`");
+    insta::assert_snapshot!(error("\nerror invalidated_origin_accessed('L_x)"), @"line 2: expected `<severity> <relation>(<origins>) @ <node>`, found `error invalidated_origin_accessed('L_x)`");
+    insta::assert_snapshot!(error("error invalidated_origin_accessed 'L_x @ c"), @"line 1: expected `<severity> <relation>(<origins>) @ <node>`, found `error invalidated_origin_accessed 'L_x @ c`");
+    insta::assert_snapshot!(error("error ('L_x) @ c"), @"line 1: expected `<severity> <relation>(<origins>) @ <node>`, found `error ('L_x) @ c`");
+}
+
+#[test]
+fn compare_as_sets() {
+    let expected = diagnostics(
+        "
+        error invalidated_origin_accessed('L_x) @ c
+        error invalidated_origin_accessed('L_y) @ c
+        warning loan_conflict('L1, 'L2) @ d
+        note assert_empty_violated('a, 'b) @ e
+        ",
+    );
+    let actual = diagnostics(
+        "
+        error invalidated_origin_accessed('L_x) @ c
+        error invalidated_origin_accessed('L_z) @ c
+        warning loan_conflict('L1, 'L2) @ e
+        warning loan_conflict('L1, 'L2) @ f
+        ",
+    );
+    let discrepancies = compare(&expected, &actual);
+    insta::assert_snapshot!(discrepancies.to_string(), @r###"
+    missing: error invalidated_origin_accessed('L_y) @ c
+    missing: note assert_empty_violated('a, 'b) @ e
+    unexpected: error invalidated_origin_accessed('L_z) @ c
+    mismatched location: warning loan_conflict('L1, 'L2) @ d, found at e, f
+    "###);
+
+    assert!(compare(&expected, &expected).is_empty());
+}
@@ -0,0 +1,224 @@
+//! Compares the hand-written facts of an example with the facts emitted from its AST version,
+//! when porting the example to the AST. The tuples the emitter misses are classified against
+//! the known gaps of the emitter by the statement of their node in the example, so that a
+//! mismatch points at the feature to implement rather than at a list of tuples.
+
+use std::collections::BTreeSet;
+use std::fmt;
+
+use itertools::Itertools;
+
+use crate::facts::Facts;
+use crate::features::Feature;
+
+#[cfg(test)]
+mod test;
+
+/// A tuple of one of the relations of `Facts`.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Tuple {
+    pub relation: &'static str,
+    pub args: Vec<String>,
+}
+
+impl Tuple {
+    /// The node the tuple is about: the source of a CFG edge, and the last argument otherwise.
+    fn node(&self) -> Option<&str> {
+        match self.relation {
+            "cfg_edge" | "cfg_edge_label" => self.args.first(),
+            _ => self.args.last(),
+        }
+        .map(String::as_str)
+    }
+}
+
+impl fmt::Display for Tuple {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}({})", self.relation, self.args.join(", "))
+    }
+}
+
+/// A known gap of the emitter, explaining why it misses some of the facts of an example.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Gap {
+    /// The subsets between the origins of a reference and of a reborrow through it, e.g. for
+    /// `&'a *r`.
+    DerefSubset,
+
+    /// The subsets required by the signature of a called function between its arguments and
+    /// its result.
+    CallSignature,
+
+    /// The facts of a field of a struct, when the emitter treats the struct as a whole.
+    FieldSensitivity,
+}
+
+impl Gap {
+    /// The emitter feature responsible for the facts of the gap.
+    pub fn feature(self) -> Feature {
+        match self {
+            Gap::DerefSubset => Feature::Reborrows,
+            Gap::CallSignature => Feature::CallSignatures,
+            Gap::FieldSensitivity => Feature::Structs,
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Gap::DerefSubset => "subset through a dereference",
+            Gap::CallSignature => "subset from the signature of a call",
+            Gap::FieldSensitivity => "fact of a field",
+        }
+    }
+
+    /// Classifies `tuple`, missing from the generated facts, by the statement of its node in
+    /// the example.
+    fn classify(tuple: &Tuple, statement: &str) -> Option<Gap> {
+        let subset = tuple.relation == "introduce_subset";
+        if subset && calls_function(statement) {
+            Some(Gap::CallSignature)
+        } else if subset && statement.contains('*') {
+            Some(Gap::DerefSubset)
+        } else if tuple.relation.ends_with("_origin") || subset {
+            projects_field(statement).then_some(Gap::FieldSensitivity)
+        } else {
+            None
+        }
+    }
+}
+
+/// Whether `statement` calls a function, e.g. `Vec::push(tmp, p)` or `r = f(x)`. The `use(..)`
+/// of the examples isn't a call.
+fn calls_function(statement: &str) -> bool {
+    statement.match_indices('(').any(|(i, _)| {
+        let start = statement[..i]
+            .trim_end_matches(|c: char| c.is_alphanumeric() || c == '_' || c == ':')
+            .len();
+        let callee = &statement[start..i];
+        !callee.is_empty() && callee != "use"
+    })
+}
+
+/// Whether `statement` projects a field, e.g. `s.f` or `(*r).f`.
+fn projects_field(statement: &str) -> bool {
+    let chars: Vec<char> = statement.chars().collect();
+    chars.windows(3).any(|w| {
+        w[1] == '.'
+            && (w[0].is_alphanumeric() || w[0] == '_' || w[0] == ')')
+            && (w[2].is_alphabetic() || w[2] == '_')
+    })
+}
+
+/// A tuple of the example missing from the generated facts, and the gap explaining it, if it
+/// matches one.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Missing {
+    pub tuple: Tuple,
+
+    /// The statement of the node of the tuple, in the example.
+    pub statement: Option<String>,
+
+    pub gap: Option<Gap>,
+}
+
+/// The differences between the facts of an example and the generated ones. The `node_text`s
+/// are ignored, as the generated ones are printed back from the AST.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FactDiff {
+    pub missing: Vec<Missing>,
+    pub unexpected: Vec<Tuple>,
+}
+
+impl FactDiff {
+    pub fn is_empty(&self) -> bool {
+        self.missing.is_empty() && self.unexpected.is_empty()
+    }
+}
+
+/// Compares the facts of an example, `manual`, with the facts `generated` for it.
+pub fn diff_facts(manual: &Facts, generated: &Facts) -> FactDiff {
+    fn tuples(facts: &Facts) -> BTreeSet<Tuple> {
+        facts
+            .relations()
+            .into_iter()
+            .filter(|(relation, _)| *relation != "node_text")
+            .flat_map(|(relation, rows)| {
+                rows.into_iter().map(move |row| Tuple {
+                    relation,
+                    args: row.into_iter().map(str::to_string).collect(),
+                })
+            })
+            .collect()
+    }
+
+    let (manual_tuples, generated_tuples) = (tuples(manual), tuples(generated));
+    let missing = manual_tuples
+        .difference(&generated_tuples)
+        .map(|tuple| {
+            let statement = tuple.node().and_then(|node| {
+                manual
+                    .node_texts()
+                    .find(|(_, n)| *n == node)
+                    .map(|(text, _)| text.clone())
+            });
+            let gap = statement
+                .as_deref()
+                .and_then(|statement| Gap::classify(tuple, statement));
+            Missing {
+                tuple: tuple.clone(),
+                statement,
+                gap,
+            }
+        })
+        .collect();
+    let unexpected = generated_tuples
+        .difference(&manual_tuples)
+        .cloned()
+        .collect();
+    FactDiff {
+        missing,
+        unexpected,
+    }
+}
+
+/// Prints each missing tuple with its statement and gap, the unexpected tuples, and the number
+/// of missing tuples per emitter feature.
+impl fmt::Display for FactDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return writeln!(f, "the generated facts match the example");
+        }
+        for missing in &self.missing {
+            write!(f, "missing {}", missing.tuple)?;
+            if let Some(statement) = &missing.statement {
+                write!(f, ", at `{}`", statement)?;
+            }
+            match missing.gap {
+                Some(gap) => writeln!(
+                    f,
+                    ": {}, see feature `{}`",
+                    gap.description(),
+                    gap.feature().name()
+                )?,
+                None => writeln!(f)?,
+            }
+        }
+        for tuple in &self.unexpected {
+            writeln!(f, "unexpected {}", tuple)?;
+        }
+        if !self.missing.is_empty() {
+            let counts = self
+                .missing
+                .iter()
+                .map(|missing| missing.gap.map(|gap| gap.feature().name()))
+                .counts();
+            let counts = counts
+                .into_iter()
+                .sorted_by_key(|&(feature, _)| (feature.is_none(), feature))
+                .map(|(feature, count)| format!("{} {}", count, feature.unwrap_or("unclassified")))
+                .join(", ");
+            writeln!(f, "{} missing: {}", self.missing.len(), counts)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,55 @@
+use super::*;
+
+fn diff(manual: &str, generated: &str) -> String {
+    let manual = crate::parse_fact_program(manual).unwrap();
+    let generated = crate::parse_fact_program(generated).unwrap();
+    diff_facts(&manual, &generated).to_string()
+}
+
+#[test]
+fn identical() {
+    let facts = "a: \"x = 1\" { invalidate_origin('L_x) goto b } b: \"use(x)\" { goto }";
+    assert_eq!(
+        diff(facts, &facts.replace("x = 1", "x = const 1")),
+        "the generated facts match the example\n"
+    );
+}
+
+#[test]
+fn classify_gaps() {
+    let manual = "
+        a: \"r = &'L_x x\" { introduce_subset('L_x, 'r) goto b }
+        b: \"s = &'L_r *r\" { introduce_subset('L_r, 's) introduce_subset('r, 's) goto c }
+        c: \"t = Vec::push(s, p)\" { introduce_subset('p, 's) goto d }
+        d: \"p.f = 1\" { invalidate_origin('L_p) goto e }
+        e: \"use(s)\" { access_origin('s) goto }
+    ";
+    let generated = "
+        a: \"r = &'L_x x\" { introduce_subset('L_x, 'r) goto b }
+        b: \"s = &'L_r *r\" { introduce_subset('L_r, 's) goto c }
+        c: \"t = push(s, p)\" { goto d }
+        d: \"p.f = 1\" { goto e }
+        e: \"use(s)\" { clear_origin('s) goto }
+    ";
+    insta::assert_snapshot!(diff(manual, generated), @r###"
+    missing access_origin('s, e), at `use(s)`
+    missing introduce_subset('p, 's, c), at `t = Vec::push(s, p)`: subset from the signature of a call, see feature `call-signatures`
+    missing introduce_subset('r, 's, b), at `s = &'L_r *r`: subset through a dereference, see feature `reborrows`
+    missing invalidate_origin('L_p, d), at `p.f = 1`: fact of a field, see feature `structs`
+    unexpected clear_origin('s, e)
+    4 missing: 1 call-signatures, 1 reborrows, 1 structs, 1 unclassified
+    "###);
+}
+
+#[test]
+fn statement_patterns() {
+    assert!(calls_function("Vec::push(tmp, p)"));
+    assert!(calls_function("r = f(x)"));
+    assert!(!calls_function("use(x)"));
+    assert!(!calls_function("x = (y)"));
+
+    assert!(projects_field("s.f = 1"));
+    assert!(projects_field("r = &'L (*p).f"));
+    assert!(!projects_field("x = 0.5"));
+    assert!(!projects_field("x = y"));
+}
@@ -0,0 +1,1585 @@
+//! Emits the input facts of `polonius.dl` for a program, following the rules described at the
+//! top of `polonius.dl`.
+//!
+//! The program is first lowered to the IR, see `ir::lower`, and each IR statement is a node of
+//! the CFG. For a statement `place = expr`, the effects are, in order:
+//!
+//! * evaluating `expr` accesses the origins of the places it reads, and invalidates the loans
+//!   conflicting with these reads,
+//! * then `place` is overwritten: the loans of `place` are invalidated, and the origins in its
+//!   type are written (a definition, not a use) and cleared,
+//! * then storing the value of `expr` into `place` introduces subsets between their types.
+//!
+//! The effects of `expr` are themselves in evaluation order, left to right, and the facts of a
+//! node are emitted in the order of its effects. The loans issued by `expr` are cleared last,
+//! when they are recreated, so the rules can't see `place` being overwritten while borrowed by
+//! the same statement, as in `s.r = &'L_s s`: the emitter reports that conflict itself.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+
+use eyre::WrapErr;
+
+use crate::ast::{self, Program, Ty};
+use crate::facts::{Facts, Node, NodeIndex, Origin, OriginKind};
+use crate::features::{Feature, Unsupported};
+use crate::ir::move_paths::{self, MovePaths, PathUse};
+use crate::ir::{self, Body, BorrowKind, Effect, Place, PlaceElem, ReadKind, Terminator};
+use crate::pipeline::{Artifacts, Pipeline};
+use crate::profile::Profiler;
+use crate::typeck::{self, Projection, TypeEnv};
+use crate::validate;
+use crate::variance::{self, Variance};
+
+mod policy;
+mod stages;
+#[cfg(test)]
+mod test;
+mod trace;
+
+pub use policy::{Access, DefaultPolicy, InvalidationPolicy, LoanEffect, Overlap};
+pub use stages::{
+    AssumeExternalOrigins, CollectLoans, EmitFacts, EmitLiveness, EmitMovePaths, EmitOriginKinds,
+    EmitRefinements,
+};
+pub use trace::{RelateArm, Relation, Trace, TraceEntry};
+
+#[derive(Clone, Debug, Default)]
+pub struct Options {
+    /// Names the nodes `a`, `b`, `c`, etc. in program order, like the hand-written examples,
+    /// instead of `bb0[0]`, `bb0[1]`, etc.
+    pub simple_node_names: bool,
+
+    /// Names the nodes `<block>s<statement>`, e.g. `bb1s0`. Unlike `simple_node_names`, the
+    /// names don't change when a block is inserted or moved. Takes precedence over
+    /// `simple_node_names`.
+    pub stable_node_names: bool,
+
+    /// Hoists borrows and calls used as call arguments into temporaries before emitting facts,
+    /// see `desugar::introduce_temporaries`.
+    pub introduce_temporaries: bool,
+
+    /// How liveness is modeled in the facts.
+    pub liveness: Liveness,
+
+    /// Origins of the program coming from an unanalyzed caller, e.g. the origins of its
+    /// arguments. They are assumed live until the program returns, so that the program can be
+    /// checked in isolation, see `AssumeExternalOrigins`.
+    pub external_origins: Vec<ast::Name>,
+
+    /// Emits `origin_equal(o1, o2)` for the origins which must be equal, e.g. the parameters
+    /// of types related invariantly, instead of `introduce_subset` in each direction.
+    pub origin_equalities: bool,
+
+    /// Emits the move paths of the program, `child_path`, and how each statement uses them,
+    /// `path_assigned_at`, `path_accessed_at` and `path_moved_at`, see `ir::move_paths`.
+    pub move_paths: bool,
+
+    /// Emits `discriminant_known` for the arms of each `match` and `switch`, with the value of
+    /// the scrutinee known in the arm, e.g. `Some` in the arm binding the payload of an `if let`.
+    pub refinements: bool,
+
+    /// Approximates each place by its whole local when deciding which loans an access affects,
+    /// like before the emitter was field- and deref-sensitive: writing `x.a` invalidates the
+    /// loans of `x.b`, and overwriting `p` invalidates the loans of `*p` instead of killing
+    /// them. Only meant to measure the precision of the sensitive invalidations, see
+    /// `precision`.
+    pub complete_places: bool,
+}
+
+/// The two ways of modeling liveness, so that the rules can be evaluated under both.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Liveness {
+    /// The rules derive liveness from `access_origin`: an origin is live if it may be accessed
+    /// later, before being written.
+    #[default]
+    AccessImpliesLive,
+
+    /// The emitter computes liveness from the variables, like NLL, and emits it as
+    /// `origin_live_on_entry`: all the origins in the type of a variable are live if any part
+    /// of the variable may be used later, before being overwritten. A variable which may only
+    /// be dropped later is drop-live: only the origins its destructors may observe are live.
+    Explicit,
+}
+
+/// Parses `input`, and emits the facts of the program.
+pub fn emit_facts(input: &str, options: Options) -> eyre::Result<Facts> {
+    let program = crate::parse_ast(input)?;
+    FactEmitter::from_program(program, options)
+}
+
+/// Parses an input with several named `program` sections, see `parse_ast_programs`, and emits
+/// the facts of each program, keyed by name.
+pub fn emit_facts_by_program(
+    input: &str,
+    options: Options,
+) -> eyre::Result<BTreeMap<ast::Name, Facts>> {
+    crate::parse_ast_programs(input)?
+        .into_iter()
+        .map(|(name, program)| {
+            let facts = FactEmitter::from_program(program, options.clone())
+                .wrap_err_with(|| format!("in program `{}`", name))?;
+            Ok((name, facts))
+        })
+        .collect()
+}
+
+/// Parses `input`, and emits the facts of the program along with their trace.
+pub fn emit_trace(input: &str, options: Options) -> eyre::Result<(Facts, Trace)> {
+    let program = crate::parse_ast(input)?;
+    FactEmitter::trace_program(program, options)
+}
+
+/// Parses `input`, and emits the facts of the program along with the expectations of its
+/// ghost statements.
+pub fn emit_expectations(input: &str, options: Options) -> eyre::Result<(Facts, Vec<Expectation>)> {
+    let program = crate::parse_ast(input)?;
+    FactEmitter::expectations(program, options)
+}
+
+/// A ghost statement of the program, e.g. `assert_live('a);`, to check at the node of the
+/// statement following it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Expectation {
+    pub assertion: ast::Assertion,
+    pub node: Node,
+}
+
+impl fmt::Display for Expectation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "`{}` at `{}`", self.assertion, self.node)
+    }
+}
+
+/// A statement in the program: the statement at index `statement` of the block at index
+/// `block`. Locations are ordered like the statements in the program.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Location {
+    pub block: usize,
+    pub statement: usize,
+}
+
+/// Identifies a loan. Each borrow expression in the program is a distinct loan, even when
+/// several borrows of the same place use the same origin.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct LoanId(usize);
+
+/// A borrow expression `&'origin place` or `&'origin mut place` in the program, issued by the
+/// statement at `location`, whose node is `node`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Loan {
+    pub place: Place,
+    pub origin: Origin,
+    pub kind: BorrowKind,
+    pub location: Location,
+    pub node: Node,
+}
+
+/// Prints the loan as its borrow expression, e.g. `&'L_x mut x`.
+impl fmt::Display for Loan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            BorrowKind::Shared => write!(f, "`&{} {}`", self.origin, self.place),
+            BorrowKind::Mut => write!(f, "`&{} mut {}`", self.origin, self.place),
+        }
+    }
+}
+
+/// All the loans of the program, in program order. The emitter collects them before emitting
+/// any fact, and tools can query them to relate the loans to the places they borrow, e.g. to
+/// explain an `invalidate_origin`.
+#[derive(Clone, Debug, Default)]
+pub struct Loans {
+    loans: Vec<Loan>,
+
+    /// See `Options::complete_places`.
+    complete_places: bool,
+}
+
+impl Loans {
+    /// Collects the loans of `program`, which is checked and lowered like by `from_program`,
+    /// with the node names chosen by `options`, see `Pipeline::loans`.
+    pub fn of_program(program: Program, options: &Options) -> eyre::Result<Loans> {
+        let mut artifacts = Artifacts::default();
+        artifacts.insert(program);
+        Pipeline::loans(options).run(&mut artifacts, &mut Profiler::default())?;
+        artifacts.take()
+    }
+
+    fn push(&mut self, loan: Loan) -> LoanId {
+        self.loans.push(loan);
+        LoanId(self.loans.len() - 1)
+    }
+
+    pub fn get(&self, id: LoanId) -> &Loan {
+        &self.loans[id.0]
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (LoanId, &Loan)> {
+        self.loans
+            .iter()
+            .enumerate()
+            .map(|(i, loan)| (LoanId(i), loan))
+    }
+
+    /// The loans of exactly `place`, e.g. the loans of `x.f` but not those of `x` or `x.f.g`.
+    pub fn loans_of<'a>(&'a self, place: &'a Place) -> impl Iterator<Item = (LoanId, &'a Loan)> {
+        self.iter().filter(move |(_, loan)| loan.place == *place)
+    }
+
+    /// The loans whose place overlaps `place`, along with how they overlap.
+    pub fn loans_overlapping<'a>(
+        &'a self,
+        place: &'a Place,
+    ) -> impl Iterator<Item = (LoanId, &'a Loan, Overlap)> + 'a {
+        self.iter()
+            .filter_map(move |(id, loan)| match self.overlap(place, &loan.place) {
+                Overlap::Disjoint => None,
+                overlap => Some((id, loan, overlap)),
+            })
+    }
+
+    /// How the place of a loan overlaps the `accessed` place. With `Options::complete_places`,
+    /// all the places of a local overlap.
+    fn overlap(&self, accessed: &Place, loan: &Place) -> Overlap {
+        if self.complete_places && accessed.base == loan.base {
+            Overlap::Overlapping
+        } else {
+            overlap(accessed, loan)
+        }
+    }
+
+    /// The loans issued by the statement of `node`.
+    pub fn loans_issued_at<'a>(
+        &'a self,
+        node: &'a str,
+    ) -> impl Iterator<Item = (LoanId, &'a Loan)> {
+        self.iter().filter(move |(_, loan)| loan.node == node)
+    }
+
+    /// The loans issued by the statement at `location`.
+    fn issued_at(&self, location: Location) -> impl Iterator<Item = (LoanId, &Loan)> {
+        self.iter()
+            .filter(move |(_, loan)| loan.location == location)
+    }
+}
+
+pub struct FactEmitter<'p> {
+    body: Body,
+    env: TypeEnv<'p>,
+    policy: &'p dyn InvalidationPolicy,
+    node_names: BTreeMap<Location, Node>,
+    loans: Loans,
+    output: Output,
+
+    /// See `Options::origin_equalities`.
+    origin_equalities: bool,
+}
+
+/// The facts emitted so far, and their trace when it is recorded.
+#[derive(Default)]
+struct Output {
+    facts: Facts,
+    trace: Option<Trace>,
+    expectations: Vec<Expectation>,
+}
+
+impl Output {
+    /// Emits the fact `relation(origins, node)`, unless it was already emitted. The `reason`
+    /// is only computed when the trace is recorded.
+    fn push(
+        &mut self,
+        relation: Relation,
+        origins: &[&Origin],
+        node: &Node,
+        reason: impl FnOnce() -> String,
+    ) -> eyre::Result<()> {
+        let new = match (relation, origins) {
+            (
+                Relation::IntroduceSubset
+                | Relation::IncompatibleLoans
+                | Relation::OriginEqual
+                | Relation::DiscriminantKnown,
+                [o1, o2],
+            ) => {
+                let facts = match relation {
+                    Relation::IntroduceSubset => &mut self.facts.introduce_subset,
+                    Relation::IncompatibleLoans => &mut self.facts.incompatible_loans,
+                    Relation::OriginEqual => &mut self.facts.origin_equal,
+                    _ => &mut self.facts.discriminant_known,
+                };
+                push_unique(facts, ((*o1).clone(), (*o2).clone(), node.clone()))
+            }
+            (_, [o]) => {
+                let facts = match relation {
+                    Relation::AccessOrigin => &mut self.facts.access_origin,
+                    Relation::InvalidateOrigin => &mut self.facts.invalidate_origin,
+                    Relation::WriteOrigin => &mut self.facts.write_origin,
+                    Relation::OriginLiveOnEntry => &mut self.facts.origin_live_on_entry,
+                    Relation::AssertEmpty => &mut self.facts.assert_empty,
+                    Relation::LoanThroughShared => &mut self.facts.loan_through_shared,
+                    Relation::PathAssignedAt => &mut self.facts.path_assigned_at,
+                    Relation::PathAccessedAt => &mut self.facts.path_accessed_at,
+                    Relation::PathMovedAt => &mut self.facts.path_moved_at,
+                    Relation::DropUsedAt => &mut self.facts.drop_used_at,
+                    _ => &mut self.facts.clear_origin,
+                };
+                push_unique(facts, ((*o).clone(), node.clone()))
+            }
+            _ => eyre::bail!(
+                "cannot emit `{}` with {} origins",
+                relation.name(),
+                origins.len()
+            ),
+        };
+
+        if let (true, Some(trace)) = (new, &mut self.trace) {
+            trace.entries.push(TraceEntry {
+                relation,
+                origins: origins.iter().map(|o| (*o).clone()).collect(),
+                node: node.clone(),
+                reason: reason(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Records that `relate_tys` took `arm`, when the trace is recorded.
+    fn hit(&mut self, arm: RelateArm) {
+        if let Some(trace) = &mut self.trace {
+            trace.relate_arms.insert(arm);
+        }
+    }
+}
+
+impl<'p> FactEmitter<'p> {
+    /// Emits the facts of an already-built program. The `node_text` of each node is the
+    /// statement printed back from the AST, so this doesn't need the original input text.
+    pub fn from_program(program: Program, options: Options) -> eyre::Result<Facts> {
+        Self::from_program_profiled(program, options, &mut Profiler::default())
+    }
+
+    /// Emits the facts of the body of each function defined by `program`, in order, see
+    /// `function_programs`. The program's own body is emitted by `from_program`.
+    pub fn from_functions(
+        program: &Program,
+        options: Options,
+    ) -> eyre::Result<Vec<(ast::Name, Facts)>> {
+        Self::function_programs(program, &options)?
+            .into_iter()
+            .map(|(name, function, options)| {
+                let facts = Self::from_program(function, options)
+                    .wrap_err_with(|| format!("in the body of `{}`", name))?;
+                Ok((name, facts))
+            })
+            .collect()
+    }
+
+    /// The body of each function defined by `program`, as a program of its own, see
+    /// `ast::Program::function_program`, along with the options to emit it with. The origins in
+    /// the signature of a function are placeholders for the origins chosen by its callers, so
+    /// they are added to the external origins, see `Options::external_origins`.
+    pub fn function_programs(
+        program: &Program,
+        options: &Options,
+    ) -> eyre::Result<Vec<(ast::Name, Program, Options)>> {
+        program
+            .fn_bodies
+            .iter()
+            .map(|body| {
+                let function = program.function_program(body)?;
+                let prototype = function
+                    .fn_prototypes
+                    .iter()
+                    .find(|prototype| prototype.name == body.name)
+                    .unwrap();
+                let mut options = options.clone();
+                let signature_tys = prototype.arg_tys.iter().chain([&prototype.ret_ty]);
+                for origin in signature_tys.flat_map(typeck::origins_of_ty) {
+                    if !options.external_origins.contains(origin) {
+                        options.external_origins.push(origin.clone());
+                    }
+                }
+                Ok((body.name.clone(), function, options))
+            })
+            .collect()
+    }
+
+    /// Like `from_program`, recording the time taken by each phase in `profiler`.
+    pub fn from_program_profiled(
+        program: Program,
+        options: Options,
+        profiler: &mut Profiler,
+    ) -> eyre::Result<Facts> {
+        let output = Self::run(program, options, &DefaultPolicy, profiler, false)?;
+        Ok(output.facts)
+    }
+
+    /// Like `from_program`, deciding which accesses invalidate which loans with `policy`
+    /// instead of `DefaultPolicy`.
+    pub fn from_program_with_policy(
+        program: Program,
+        options: Options,
+        policy: &dyn InvalidationPolicy,
+    ) -> eyre::Result<Facts> {
+        let output = Self::run(program, options, policy, &mut Profiler::default(), false)?;
+        Ok(output.facts)
+    }
+
+    /// Like `from_program`, also returning the expectations of the ghost statements.
+    pub fn expectations(
+        program: Program,
+        options: Options,
+    ) -> eyre::Result<(Facts, Vec<Expectation>)> {
+        let output = Self::run(
+            program,
+            options,
+            &DefaultPolicy,
+            &mut Profiler::default(),
+            false,
+        )?;
+        Ok((output.facts, output.expectations))
+    }
+
+    /// Like `from_program`, also returning the trace of the emitted facts.
+    pub fn trace_program(program: Program, options: Options) -> eyre::Result<(Facts, Trace)> {
+        let output = Self::run(
+            program,
+            options,
+            &DefaultPolicy,
+            &mut Profiler::default(),
+            true,
+        )?;
+        Ok((output.facts, output.trace.unwrap()))
+    }
+
+    /// Prepares to emit the facts of `program` one statement at a time, with
+    /// `emit_statement_facts`, e.g. to show the facts of the statement under the cursor. The
+    /// program is validated and lowered, and its loans collected, only once.
+    ///
+    /// The program isn't desugared, so `options.introduce_temporaries` must be unset, and its
+    /// type aliases must already be expanded, see `typeck::expand_type_aliases`.
+    pub fn for_statements(program: &'p Program, options: &Options) -> eyre::Result<Self> {
+        if options.introduce_temporaries {
+            eyre::bail!("emitting single statements doesn't support `introduce_temporaries`");
+        }
+        if !program.type_aliases.is_empty() {
+            eyre::bail!("emitting single statements requires the type aliases to be expanded");
+        }
+        validate::validate(program)?;
+        let body = ir::lower(program)?;
+        Ok(FactEmitter::new(program, body, options, &DefaultPolicy))
+    }
+
+    /// The loans of the program, see `Loans::of_program`.
+    pub fn loans(&self) -> &Loans {
+        &self.loans
+    }
+
+    /// Emits the facts of the `statement`th statement of `block`, without its `cfg_edge`s.
+    /// Ghost statements aren't counted, and an empty block has a single `(pass)` statement.
+    pub fn emit_statement_facts(&mut self, block: &str, statement: usize) -> eyre::Result<Facts> {
+        let b = self
+            .body
+            .blocks
+            .iter()
+            .position(|b| b.name == block)
+            .ok_or_else(|| eyre::eyre!("no block named `{}`", block))?;
+        let len = self.body.blocks[b].statements.len();
+        if statement >= len {
+            eyre::bail!(
+                "`{}` has {} statements, there is no statement {}",
+                block,
+                len,
+                statement
+            );
+        }
+
+        let body = std::mem::take(&mut self.body);
+        let previous = std::mem::take(&mut self.output);
+        let location = Location {
+            block: b,
+            statement,
+        };
+        let result = self.emit_statement(&body.blocks[b], location);
+        self.body = body;
+        let output = std::mem::replace(&mut self.output, previous);
+        result.map(|()| output.facts)
+    }
+
+    fn run(
+        program: Program,
+        options: Options,
+        policy: &dyn InvalidationPolicy,
+        profiler: &mut Profiler,
+        trace: bool,
+    ) -> eyre::Result<Output> {
+        let mut artifacts = Artifacts::default();
+        artifacts.insert(program);
+        Pipeline::emit(&options, policy, trace).run(&mut artifacts, profiler)?;
+        Ok(Output {
+            facts: artifacts.take()?,
+            trace: artifacts.take().ok(),
+            expectations: artifacts.take()?,
+        })
+    }
+
+    /// Maps the simple name of each node of `program` to its stable name, to migrate
+    /// snapshots and examples using simple names, see `Options::stable_node_names`.
+    pub fn node_name_migration(program: &Program) -> eyre::Result<Vec<(Node, Node)>> {
+        let simple = Options {
+            simple_node_names: true,
+            ..Options::default()
+        };
+        let stable = Options {
+            stable_node_names: true,
+            ..Options::default()
+        };
+
+        let mut program = program.clone();
+        typeck::expand_type_aliases(&mut program)?;
+        let body = ir::lower(&program)?;
+        let mut migration = vec![];
+        let mut index = 0;
+        for block in &body.blocks {
+            for s in 0..block.statements.len() {
+                migration.push((
+                    node_name(&simple, index, &block.name, s),
+                    node_name(&stable, index, &block.name, s),
+                ));
+                index += 1;
+            }
+        }
+        Ok(migration)
+    }
+
+    fn new(
+        program: &'p Program,
+        body: Body,
+        options: &Options,
+        policy: &'p dyn InvalidationPolicy,
+    ) -> Self {
+        let (NodeNames(node_names), loans) = collect_loans(&body, options);
+        FactEmitter {
+            body,
+            env: TypeEnv::new(program),
+            policy,
+            node_names,
+            loans,
+            output: Output::default(),
+            origin_equalities: options.origin_equalities,
+        }
+    }
+
+    fn emit(&mut self) -> eyre::Result<()> {
+        let body = std::mem::take(&mut self.body);
+        for (b, block) in body.blocks.iter().enumerate() {
+            let location = |statement| Location {
+                block: b,
+                statement,
+            };
+
+            for s in 0..block.statements.len() {
+                self.emit_statement(block, location(s))?;
+                if s > 0 {
+                    let predecessor = self.node_names[&location(s - 1)].clone();
+                    let node = self.node_names[&location(s)].clone();
+                    self.output.facts.cfg_edge.push((predecessor, node));
+                }
+            }
+
+            let last = &self.node_names[&location(block.statements.len() - 1)];
+            match &block.terminator {
+                Terminator::Goto(successors)
+                | Terminator::Switch {
+                    arms: successors, ..
+                } => {
+                    for (successor, label) in successors {
+                        let first = &self.node_names[&Location {
+                            block: *successor,
+                            statement: 0,
+                        }];
+                        self.output
+                            .facts
+                            .cfg_edge
+                            .push((last.clone(), first.clone()));
+                        if let Some(label) = label {
+                            self.output.facts.cfg_edge_label.push((
+                                last.clone(),
+                                first.clone(),
+                                label.clone(),
+                            ));
+                        }
+                    }
+                }
+                Terminator::Return => self.output.facts.node_is_exit.push(last.clone()),
+            }
+        }
+        self.body = body;
+        Ok(())
+    }
+
+    /// Emits `incompatible_loans` for each pair of loans whose places overlap, where the policy
+    /// decides that taking one of them invalidates the other.
+    fn emit_incompatible_loans(&mut self) -> eyre::Result<()> {
+        let loans = &self.loans.loans;
+        for (j, l2) in loans.iter().enumerate() {
+            for l1 in &loans[..j] {
+                if l1.origin == l2.origin {
+                    continue;
+                }
+                let invalidates = |taken: &Loan, other: &Loan| {
+                    let overlap = self.loans.overlap(&taken.place, &other.place);
+                    overlap != Overlap::Disjoint
+                        && self
+                            .policy
+                            .loan_effect(Access::Borrow(taken.kind), other.kind, overlap)
+                            == Some(LoanEffect::Invalidate)
+                };
+                if invalidates(l1, l2) || invalidates(l2, l1) {
+                    self.output.push(
+                        Relation::IncompatibleLoans,
+                        &[&l1.origin, &l2.origin],
+                        &l2.node,
+                        || format!("loans {} and {} conflict", l1, l2),
+                    )?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Emits `origin_live_on_entry` for the origins in the types of the variables live on
+    /// entry to each node. Like in NLL, a variable whose value is only dropped later is
+    /// drop-live instead: only the origins its destructors may observe are live.
+    fn emit_liveness(&mut self) -> eyre::Result<()> {
+        let mut uses = vec![];
+        let mut drops = vec![];
+        for (b, block) in self.body.blocks.iter().enumerate() {
+            for (s, statement) in block.statements.iter().enumerate() {
+                let node = self.node_names[&Location {
+                    block: b,
+                    statement: s,
+                }]
+                    .as_str();
+                let (used, defs) = variable_uses_and_defs(&statement.effects);
+                let dropped = statement
+                    .effects
+                    .iter()
+                    .filter_map(|effect| match effect {
+                        Effect::StorageDead { local } => Some(local.as_str()),
+                        Effect::Drop { place } => Some(place.base.as_str()),
+                        _ => None,
+                    })
+                    .collect();
+                uses.push((node, used, defs.clone()));
+                drops.push((node, dropped, defs));
+            }
+        }
+
+        let index = self.output.facts.index_by_node();
+        let live = live_variables(&index, &uses);
+        let drop_live = live_variables(&index, &drops);
+
+        let mut tuples = vec![];
+        for (node, ..) in &uses {
+            for &variable in &live[node] {
+                for origin in typeck::origins_of_ty(self.env.variable_ty(variable)?) {
+                    let reason = format!("`{}` is live", variable);
+                    tuples.push((origin.clone(), node.to_string(), reason));
+                }
+            }
+            for &variable in drop_live[node].difference(&live[node]) {
+                for origin in self.env.drop_origins(self.env.variable_ty(variable)?)? {
+                    let reason = format!("`{}` is drop-live", variable);
+                    tuples.push((origin, node.to_string(), reason));
+                }
+            }
+        }
+        for (origin, node, reason) in tuples {
+            self.output
+                .push(Relation::OriginLiveOnEntry, &[&origin], &node, || reason)?;
+        }
+        Ok(())
+    }
+
+    /// Emits `access_origin` for each of the external `origins` at the exit nodes, and, with
+    /// explicit liveness, `origin_live_on_entry` at every node: the caller may use them after
+    /// the program returns. It is an error for an origin not to appear in the type of a
+    /// variable.
+    fn emit_external_origins(
+        &mut self,
+        origins: &[ast::Name],
+        liveness: Liveness,
+    ) -> eyre::Result<()> {
+        for origin in origins {
+            let declared = self
+                .env
+                .variables()
+                .iter()
+                .any(|variable| typeck::origins_of_ty(&variable.ty).contains(&origin));
+            if !declared {
+                eyre::bail!(
+                    "the external origin `{}` isn't in the type of any variable",
+                    origin
+                );
+            }
+        }
+
+        let exits = self.output.facts.node_is_exit.clone();
+        for origin in origins {
+            for node in &exits {
+                self.output
+                    .push(Relation::AccessOrigin, &[origin], node, || {
+                        format!("`{}` is external, and may be used by the caller", origin)
+                    })?;
+            }
+            if liveness == Liveness::Explicit {
+                for node in self.node_names.values() {
+                    self.output
+                        .push(Relation::OriginLiveOnEntry, &[origin], node, || {
+                            format!("`{}` is external", origin)
+                        })?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Emits the `origin_kind` of each origin of the facts emitted so far: the origins of the
+    /// loans, and the ones instantiated by calls and struct literals, are created by the
+    /// emitter, the `external` origins are placeholders, and the others are named by the
+    /// program.
+    fn emit_origin_kinds(&mut self, external: &[ast::Name]) {
+        let kinds: Vec<_> = self
+            .output
+            .facts
+            .origins()
+            .into_iter()
+            .map(|origin| {
+                let kind = if self.loans.iter().any(|(_, loan)| loan.origin == *origin) {
+                    OriginKind::LoanFresh
+                } else if self.body.call_origins.contains(origin) {
+                    OriginKind::CallInstance
+                } else if external.contains(origin) {
+                    OriginKind::Placeholder
+                } else {
+                    OriginKind::UserNamed
+                };
+                (origin.clone(), kind)
+            })
+            .collect();
+        self.output.facts.origin_kind = kinds;
+    }
+
+    /// Emits the `child_path` of each move path of the body, and the `path_*_at` facts of how
+    /// each statement uses them.
+    fn emit_move_paths(&mut self) -> eyre::Result<()> {
+        let paths = MovePaths::of(&self.body);
+        for path in paths.iter() {
+            if let Some(parent) = paths.parent(path) {
+                self.output
+                    .facts
+                    .child_path
+                    .push((path.to_string(), parent.to_string()));
+            }
+        }
+
+        for (b, block) in self.body.blocks.iter().enumerate() {
+            for (s, statement) in block.statements.iter().enumerate() {
+                let node = &self.node_names[&Location {
+                    block: b,
+                    statement: s,
+                }];
+                for effect in &statement.effects {
+                    for (path, path_use) in move_paths::path_uses(effect) {
+                        let (relation, reason) = match path_use {
+                            PathUse::Assigned => (Relation::PathAssignedAt, "is overwritten"),
+                            PathUse::Accessed => (Relation::PathAccessedAt, "is used"),
+                            PathUse::Moved => (Relation::PathMovedAt, "is moved out"),
+                        };
+                        let path = path.to_string();
+                        self.output.push(relation, &[&path], node, || {
+                            format!("`{}` {}", path, reason)
+                        })?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Emits the `discriminant_known` facts of each arm of a `Terminator::Switch` at the first
+    /// node of its block, when the value is known there: the arm has a label, no other arm of
+    /// the switch goes to the block, and no other block does either.
+    fn emit_refinements(&mut self) -> eyre::Result<()> {
+        let mut predecessors = vec![0; self.body.blocks.len()];
+        for block in &self.body.blocks {
+            if let Terminator::Goto(successors)
+            | Terminator::Switch {
+                arms: successors, ..
+            } = &block.terminator
+            {
+                for (successor, _) in successors {
+                    predecessors[*successor] += 1;
+                }
+            }
+        }
+
+        for block in &self.body.blocks {
+            let Terminator::Switch { place, arms } = &block.terminator else {
+                continue;
+            };
+            let place = place.to_string();
+            for (successor, value) in arms {
+                let Some(value) = value else {
+                    continue;
+                };
+                if predecessors[*successor] > 1 {
+                    continue;
+                }
+                let node = &self.node_names[&Location {
+                    block: *successor,
+                    statement: 0,
+                }];
+                self.output
+                    .push(Relation::DiscriminantKnown, &[&place, value], node, || {
+                        format!("`{}` is `{}` in the arm of `{}`", place, value, block.name)
+                    })?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Emits the facts of the statement at `location` of `block`, except for its `cfg_edge`s.
+    fn emit_statement(&mut self, block: &ir::BasicBlock, location: Location) -> eyre::Result<()> {
+        let statement = &block.statements[location.statement];
+        let node = self.node_names[&location].clone();
+        self.output
+            .facts
+            .node_text
+            .push((statement.text.clone(), node.clone()));
+        self.output
+            .expectations
+            .extend(statement.assertions.iter().map(|assertion| Expectation {
+                assertion: assertion.clone(),
+                node: node.clone(),
+            }));
+        for assertion in &statement.assertions {
+            match assertion {
+                ast::Assertion::Empty(origin) => {
+                    self.output
+                        .push(Relation::AssertEmpty, &[origin], &node, || {
+                            format!("`{}` before the statement", assertion)
+                        })?;
+                }
+                ast::Assertion::EachIteration {
+                    relation,
+                    arguments,
+                } => match crate::facts::NODE_RELATIONS
+                    .iter()
+                    .find(|(name, _)| name == relation)
+                {
+                    Some(&(_, arity)) if arity == arguments.len() => {}
+                    Some(&(_, arity)) => eyre::bail!(
+                        "in `{}`: `{}` has {} arguments besides the node",
+                        assertion,
+                        relation,
+                        arity
+                    ),
+                    None => eyre::bail!(
+                        "in `{}`: `{}` isn't a relation of the facts about a node",
+                        assertion,
+                        relation
+                    ),
+                },
+                _ => {}
+            }
+        }
+
+        self.emit_effects(&statement.effects, &node).map_err(|e| {
+            e.wrap_err(format!(
+                "in statement `{}` of `{}`",
+                statement.text, block.name
+            ))
+        })?;
+
+        // The loans issued by the statement are being recreated.
+        for (_, loan) in self.loans.issued_at(location) {
+            self.output
+                .push(Relation::ClearOrigin, &[&loan.origin], &node, || {
+                    format!("loan {} is issued", loan)
+                })?;
+        }
+        Ok(())
+    }
+
+    fn emit_effects(&mut self, effects: &[Effect], node: &Node) -> eyre::Result<()> {
+        let mut value_ty = None;
+        for effect in effects {
+            match effect {
+                Effect::Read { place, kind } => {
+                    let access = match kind {
+                        ReadKind::Copy => Access::Copy,
+                        ReadKind::Move => Access::Move,
+                        ReadKind::Discriminant => Access::Discriminant,
+                    };
+                    self.emit_read(place, access, node)?;
+                }
+                Effect::Borrow {
+                    origin,
+                    place,
+                    kind,
+                } => {
+                    self.emit_borrow(origin, place, *kind, node)?;
+                }
+                Effect::Write {
+                    place,
+                    value_ty: ty,
+                } => {
+                    let place_ty = self.emit_write(place, node)?;
+                    value_ty = Some((ty.clone(), place_ty));
+                }
+                Effect::Relate { sub, sup } => {
+                    self.relate_tys(sub, sup, Variance::Covariant, node)?;
+                    self.emit_outlives_bounds(sup, node)?;
+                }
+                Effect::Drop { place } => self.emit_drop(place, Access::Drop, node)?,
+                Effect::StorageDead { local } => {
+                    let place = Place {
+                        base: local.to_string(),
+                        projections: vec![],
+                    };
+                    self.emit_drop(&place, Access::StorageDead, node)?
+                }
+                Effect::Outlives { o1, o2 } => {
+                    self.push_subset(o1, o2, node, || format!("`{}: {}` is declared", o1, o2))?;
+                }
+                Effect::ExternCall { callee, arg_tys } => {
+                    // The loans issued by the statement are passed to the callee fresh, and
+                    // accessing them here would report their invalidations in an earlier
+                    // iteration of a loop.
+                    let issued: Vec<&Origin> = effects
+                        .iter()
+                        .filter_map(|effect| match effect {
+                            Effect::Borrow { origin, .. } => Some(origin),
+                            _ => None,
+                        })
+                        .collect();
+                    for origin in arg_tys
+                        .iter()
+                        .flat_map(typeck::origins_of_ty)
+                        .filter(|origin| !issued.contains(origin))
+                    {
+                        self.output
+                            .push(Relation::AccessOrigin, &[origin], node, || {
+                                format!("`{}` is extern, and may access its arguments", callee)
+                            })?;
+                    }
+                }
+            }
+        }
+
+        // Storing the value happens after all the other effects.
+        if let Some((value_ty, place_ty)) = value_ty {
+            self.relate_tys(&value_ty, &place_ty, Variance::Covariant, node)?;
+            self.emit_outlives_bounds(&place_ty, node)?;
+        }
+        Ok(())
+    }
+
+    /// A value of type `ty` is stored at `node`, so the bounds of the struct types in `ty`
+    /// must hold there.
+    fn emit_outlives_bounds(&mut self, ty: &Ty, node: &Node) -> eyre::Result<()> {
+        for (o1, o2, struct_ty) in self.env.outlives_bounds(ty)? {
+            self.push_subset(&o1, &o2, node, || {
+                format!("`{}` requires `{}: {}`", struct_ty, o1, o2)
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Emits the effects of `access` to `place` on the loans overlapping it, as decided by the
+    /// policy.
+    fn emit_loan_effects(
+        &mut self,
+        access: Access,
+        place: &Place,
+        node: &Node,
+    ) -> eyre::Result<()> {
+        for (_, loan, overlap) in self.loans.loans_overlapping(place) {
+            // A discriminant read is shallow: the loans of the payloads aren't affected.
+            if access == Access::Discriminant
+                && loan.place.projections.len() > place.projections.len()
+            {
+                continue;
+            }
+            let effect = match self.policy.loan_effect(access, loan.kind, overlap) {
+                Some(effect) => effect,
+                None => continue,
+            };
+            let relation = match effect {
+                LoanEffect::Invalidate => Relation::InvalidateOrigin,
+                LoanEffect::Clear => Relation::ClearOrigin,
+            };
+            self.output
+                .push(relation, &[&loan.origin], node, || match (access, effect) {
+                    (Access::Write, LoanEffect::Invalidate) => {
+                        format!("write to `{}` overwrites the data of loan {}", place, loan)
+                    }
+                    (Access::Write, LoanEffect::Clear) => format!(
+                        "write to `{}` redirects the reference of loan {}",
+                        place, loan
+                    ),
+                    (Access::Drop, LoanEffect::Invalidate) => {
+                        format!("`{}` is dropped, invalidating loan {}", place, loan)
+                    }
+                    (Access::StorageDead, LoanEffect::Invalidate) => {
+                        format!("storage of `{}` dies, invalidating loan {}", place, loan)
+                    }
+                    (_, LoanEffect::Invalidate) => {
+                        format!("access to `{}` conflicts with loan {}", place, loan)
+                    }
+                    (_, LoanEffect::Clear) => {
+                        format!("{} of `{}` clears loan {}", access, place, loan)
+                    }
+                })?;
+        }
+        Ok(())
+    }
+
+    /// The value of `place` is dropped, which uses the origins its destructors may observe,
+    /// then `access` affects the loans of the place: a `drop(x)`, or the storage of a whole
+    /// local dying.
+    fn emit_drop(&mut self, place: &Place, access: Access, node: &Node) -> eyre::Result<()> {
+        let prefix_tys = self.env.projection_tys(&place.base, place.projections())?;
+        let ty = &prefix_tys.last().unwrap().1;
+        for origin in self.env.drop_origins(ty)? {
+            self.output
+                .push(Relation::DropUsedAt, &[&origin], node, || {
+                    format!("`{}` is dropped, and its destructor may observe it", place)
+                })?;
+        }
+        self.emit_loan_effects(access, place, node)?;
+        Ok(())
+    }
+
+    /// Emits the facts for reading `place` with `access`, and returns its type.
+    fn emit_read(&mut self, place: &Place, access: Access, node: &Node) -> eyre::Result<Ty> {
+        let prefix_tys = self.env.projection_tys(&place.base, place.projections())?;
+        self.emit_deref_accesses(place, &prefix_tys, node)?;
+
+        // The origins of the payloads aren't accessed by reading the variant.
+        let ty = prefix_tys.last().unwrap().1.clone();
+        if access != Access::Discriminant {
+            for origin in typeck::origins_of_ty(&ty) {
+                self.output
+                    .push(Relation::AccessOrigin, &[origin], node, || {
+                        format!("read of `{}`", place)
+                    })?;
+            }
+        }
+
+        self.emit_loan_effects(access, place, node)?;
+        Ok(ty)
+    }
+
+    /// Emits the facts for borrowing `place`, and returns its type.
+    fn emit_borrow(
+        &mut self,
+        origin: &Origin,
+        place: &Place,
+        kind: BorrowKind,
+        node: &Node,
+    ) -> eyre::Result<Ty> {
+        let ty = self.emit_read(place, Access::Borrow(kind), node)?;
+
+        // Unroll the place: invalidating a reference the loan is reached through invalidates
+        // the loan too. Only mutable references need to be followed further, as the data
+        // behind a shared reference doesn't depend on how that reference was reached.
+        let prefix_tys = self.env.projection_tys(&place.base, place.projections())?;
+        for i in (1..prefix_tys.len()).rev() {
+            if prefix_tys[i].0 != Some(Projection::Deref) {
+                continue;
+            }
+            match &prefix_tys[i - 1].1 {
+                Ty::Ref { origin: o, .. } => {
+                    if kind == BorrowKind::Mut {
+                        eyre::bail!(
+                            "cannot borrow `{}` as mutable, as it is behind a shared reference",
+                            place
+                        );
+                    }
+                    self.output
+                        .push(Relation::LoanThroughShared, &[origin], node, || {
+                            format!("`{}` is reached through the `&{}` reference", place, o)
+                        })?;
+                    self.push_subset(o, origin, node, || {
+                        format!(
+                            "loan of `{}` is reached through a `&{}` reference",
+                            place, o
+                        )
+                    })?;
+                    break;
+                }
+                Ty::RefMut { origin: o, .. } => self.push_subset(o, origin, node, || {
+                    format!(
+                        "loan of `{}` is reached through a `&{} mut` reference",
+                        place, o
+                    )
+                })?,
+                ty => eyre::bail!("cannot dereference `{}`, of type `{}`", place, ty),
+            }
+        }
+
+        Ok(ty)
+    }
+
+    /// Emits the facts for overwriting `place`, and returns its type.
+    fn emit_write(&mut self, place: &Place, node: &Node) -> eyre::Result<Ty> {
+        let prefix_tys = self.env.projection_tys(&place.base, place.projections())?;
+        self.emit_deref_accesses(place, &prefix_tys, node)?;
+        for (i, (projection, _)) in prefix_tys.iter().enumerate() {
+            if *projection == Some(Projection::Deref) {
+                if let Ty::Ref { .. } = prefix_tys[i - 1].1 {
+                    eyre::bail!(
+                        "cannot assign to `{}`, as it is behind a shared reference",
+                        place
+                    );
+                }
+            }
+        }
+
+        // `expr` is evaluated before `place` is overwritten, so the loans it issued are live.
+        for (_, loan, overlap) in self.loans.loans_overlapping(place) {
+            let invalidates = self.policy.loan_effect(Access::Write, loan.kind, overlap)
+                == Some(LoanEffect::Invalidate);
+            if invalidates && loan.node == *node {
+                eyre::bail!(
+                    "cannot assign to `{}`, as it is borrowed by the loan {} of the same statement",
+                    place,
+                    loan
+                );
+            }
+        }
+        self.emit_loan_effects(Access::Write, place, node)?;
+
+        // Writing `x.f` invalidates the loans of `x`, `x.f` and `x.f.g`, but not of `x.g`, see
+        // `overlap`. Only complete places are cleared though: the origins of a field are shared
+        // with the rest of its variable, and the data behind a reference with the other
+        // references of the same origin, which still hold their loans.
+        let ty = prefix_tys.last().unwrap().1.clone();
+        if prefix_tys.len() == 1 {
+            for origin in typeck::origins_of_ty(&ty) {
+                self.output
+                    .push(Relation::WriteOrigin, &[origin], node, || {
+                        format!("`{}` is defined", place)
+                    })?;
+                self.output
+                    .push(Relation::ClearOrigin, &[origin], node, || {
+                        format!("`{}` is overwritten", place)
+                    })?;
+            }
+        }
+
+        Ok(ty)
+    }
+
+    /// Accessing a place through a reference accesses the origin of that reference.
+    fn emit_deref_accesses(
+        &mut self,
+        place: &Place,
+        prefix_tys: &[(Option<Projection<'_>>, Ty)],
+        node: &Node,
+    ) -> eyre::Result<()> {
+        for i in 1..prefix_tys.len() {
+            if prefix_tys[i].0 == Some(Projection::Deref) {
+                if let Ty::Ref { origin, .. } | Ty::RefMut { origin, .. } = &prefix_tys[i - 1].1 {
+                    self.output
+                        .push(Relation::AccessOrigin, &[origin], node, || {
+                            format!("`{}` goes through a reference", place)
+                        })?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Emits the subsets required for `sub` to be a subtype of `sup`, when `sub` appears in a
+    /// position of variance `variance`.
+    fn relate_tys(
+        &mut self,
+        sub: &Ty,
+        sup: &Ty,
+        variance: Variance,
+        node: &Node,
+    ) -> eyre::Result<()> {
+        match (sub, sup) {
+            (Ty::Ref { origin: o1, ty: t1 }, Ty::Ref { origin: o2, ty: t2 })
+            | (Ty::RefMut { origin: o1, ty: t1 }, Ty::Ref { origin: o2, ty: t2 }) => {
+                self.output.hit(RelateArm::SharedRefs);
+                self.relate_origins(o1, o2, variance, node)?;
+                self.relate_tys(t1, t2, variance, node)
+            }
+            (Ty::RefMut { origin: o1, ty: t1 }, Ty::RefMut { origin: o2, ty: t2 }) => {
+                self.output.hit(RelateArm::MutRefs);
+                self.relate_origins(o1, o2, variance, node)?;
+                self.relate_tys(t1, t2, variance.xform(Variance::Invariant), node)
+            }
+            (Ty::I32, Ty::I32) | (Ty::Unit, Ty::Unit) => {
+                self.output.hit(RelateArm::Scalars);
+                Ok(())
+            }
+            (
+                Ty::Struct {
+                    name: n1,
+                    parameters: p1,
+                },
+                Ty::Struct {
+                    name: n2,
+                    parameters: p2,
+                },
+            ) if n1 == n2 && p1.len() == p2.len() => {
+                self.output.hit(RelateArm::Structs);
+                if p1.is_empty() {
+                    return Ok(());
+                }
+                let variances = variance::struct_variances(&self.env, n1)?;
+                for ((p1, p2), v) in p1.iter().zip(p2).zip(variances) {
+                    match (p1, p2) {
+                        (ast::Parameter::Origin(o1), ast::Parameter::Origin(o2)) => {
+                            self.relate_origins(o1, o2, variance.xform(v), node)?
+                        }
+                        (ast::Parameter::Ty(t1), ast::Parameter::Ty(t2)) => {
+                            self.relate_tys(t1, t2, variance.xform(v), node)?
+                        }
+                        _ => eyre::bail!("cannot relate `{}` and `{}`", sub, sup),
+                    }
+                }
+                Ok(())
+            }
+            (Ty::Fn { .. }, Ty::Fn { .. }) => {
+                self.output.hit(RelateArm::FnPointers);
+                // The bound origins of function pointers don't relate to anything, and their
+                // free origins are related with the variance of their position.
+                let mut free_origins = vec![];
+                match_fn_tys(
+                    sub,
+                    sup,
+                    Variance::Covariant,
+                    &mut vec![],
+                    &mut free_origins,
+                )?;
+                for (o1, o2, v) in free_origins {
+                    self.relate_origins(o1, o2, variance.xform(v), node)?;
+                }
+                Ok(())
+            }
+            _ => eyre::bail!("cannot relate `{}` and `{}`", sub, sup),
+        }
+    }
+
+    fn relate_origins(
+        &mut self,
+        o1: &Origin,
+        o2: &Origin,
+        variance: Variance,
+        node: &Node,
+    ) -> eyre::Result<()> {
+        let reason = || {
+            let variance = format!("{:?}", variance).to_lowercase();
+            format!("`{}` and `{}` are related {}ly", o1, o2, variance)
+        };
+        match variance {
+            Variance::Covariant => self.push_subset(o1, o2, node, reason),
+            Variance::Contravariant => self.push_subset(o2, o1, node, reason),
+            Variance::Invariant if self.origin_equalities => {
+                if o1 != o2 {
+                    self.output
+                        .push(Relation::OriginEqual, &[o1, o2], node, reason)?;
+                }
+                Ok(())
+            }
+            Variance::Invariant => {
+                self.push_subset(o1, o2, node, reason)?;
+                self.push_subset(o2, o1, node, reason)
+            }
+        }
+    }
+
+    fn push_subset(
+        &mut self,
+        o1: &Origin,
+        o2: &Origin,
+        node: &Node,
+        reason: impl FnOnce() -> String,
+    ) -> eyre::Result<()> {
+        if o1 != o2 {
+            self.output
+                .push(Relation::IntroduceSubset, &[o1, o2], node, reason)?;
+        }
+        Ok(())
+    }
+}
+
+/// The variables live on entry to each of the `nodes`, given the variables each uses and
+/// defines. Liveness flows backwards, and at a node, the uses happen before the definitions.
+fn live_variables<'n>(
+    index: &NodeIndex<'_>,
+    nodes: &[(&'n str, Vec<&'n str>, Vec<&'n str>)],
+) -> HashMap<&'n str, BTreeSet<&'n str>> {
+    let mut live: HashMap<&str, BTreeSet<&str>> = HashMap::new();
+    loop {
+        let mut changed = false;
+        for (node, uses, defs) in nodes.iter().rev() {
+            let mut entry: BTreeSet<&str> = index
+                .successors(node)
+                .filter_map(|successor| live.get(successor.as_str()))
+                .flatten()
+                .filter(|variable| !defs.contains(variable))
+                .copied()
+                .collect();
+            entry.extend(uses);
+            if live.get(node) != Some(&entry) {
+                live.insert(node, entry);
+                changed = true;
+            }
+        }
+        if !changed {
+            return live;
+        }
+    }
+}
+
+/// The variables used and defined by a statement with these `effects`, for liveness. Writing
+/// a field neither uses nor defines its variable, while writing through a reference uses it.
+fn variable_uses_and_defs(effects: &[Effect]) -> (Vec<&str>, Vec<&str>) {
+    let mut uses = vec![];
+    let mut defs = vec![];
+    for effect in effects {
+        match effect {
+            Effect::Read { place, .. } | Effect::Borrow { place, .. } => uses.push(&*place.base),
+            Effect::Write { place, .. } if place.projections.is_empty() => defs.push(&*place.base),
+            Effect::Write { place, .. } if !place.is_local() => uses.push(&*place.base),
+            Effect::Drop { place } if place.projections.is_empty() => defs.push(&*place.base),
+            Effect::StorageDead { local } => defs.push(local.as_str()),
+            Effect::Write { .. }
+            | Effect::Drop { .. }
+            | Effect::Relate { .. }
+            | Effect::ExternCall { .. }
+            | Effect::Outlives { .. } => {}
+        }
+    }
+    (uses, defs)
+}
+
+/// Checks that the function pointer types `t1` and `t2` are the same up to the names of their
+/// bound origins, and collects the pairs of free origins at the same positions, with the
+/// variance of their position: `t1` and `t2` are in a position of variance `variance`. `bound`
+/// holds the pairs of origins bound by the enclosing function pointer types, innermost last.
+/// The parameters of struct types are treated as invariant here.
+///
+/// Subtyping between function pointers which bind origins differently, like
+/// `for<'x> fn(&'x i32)` and `fn(&'y i32)`, requires higher-ranked reasoning, and is an error.
+fn match_fn_tys<'t>(
+    t1: &'t Ty,
+    t2: &'t Ty,
+    variance: Variance,
+    bound: &mut Vec<(&'t Origin, &'t Origin)>,
+    free_origins: &mut Vec<(&'t Origin, &'t Origin, Variance)>,
+) -> eyre::Result<()> {
+    match (t1, t2) {
+        (Ty::Ref { origin: o1, ty: i1 }, Ty::Ref { origin: o2, ty: i2 }) => {
+            match_fn_origins(t1, t2, (o1, o2, variance), bound, free_origins)?;
+            match_fn_tys(i1, i2, variance, bound, free_origins)
+        }
+        (Ty::RefMut { origin: o1, ty: i1 }, Ty::RefMut { origin: o2, ty: i2 }) => {
+            match_fn_origins(t1, t2, (o1, o2, variance), bound, free_origins)?;
+            let invariant = variance.xform(Variance::Invariant);
+            match_fn_tys(i1, i2, invariant, bound, free_origins)
+        }
+        (Ty::I32, Ty::I32) | (Ty::Unit, Ty::Unit) => Ok(()),
+        (
+            Ty::Struct {
+                name: n1,
+                parameters: p1,
+            },
+            Ty::Struct {
+                name: n2,
+                parameters: p2,
+            },
+        ) if n1 == n2 && p1.len() == p2.len() => {
+            let invariant = variance.xform(Variance::Invariant);
+            for (p1, p2) in p1.iter().zip(p2) {
+                match (p1, p2) {
+                    (ast::Parameter::Origin(o1), ast::Parameter::Origin(o2)) => {
+                        match_fn_origins(t1, t2, (o1, o2, invariant), bound, free_origins)?
+                    }
+                    (ast::Parameter::Ty(p1), ast::Parameter::Ty(p2)) => {
+                        match_fn_tys(p1, p2, invariant, bound, free_origins)?
+                    }
+                    _ => eyre::bail!("cannot relate `{}` and `{}`", t1, t2),
+                }
+            }
+            Ok(())
+        }
+        (
+            Ty::Fn {
+                bound_origins: b1,
+                arg_tys: a1,
+                ret_ty: r1,
+            },
+            Ty::Fn {
+                bound_origins: b2,
+                arg_tys: a2,
+                ret_ty: r2,
+            },
+        ) if a1.len() == a2.len() => {
+            if b1.len() != b2.len() {
+                return Err(higher_ranked(t1, t2));
+            }
+            let depth = bound.len();
+            bound.extend(b1.iter().zip(b2));
+            let contravariant = variance.xform(Variance::Contravariant);
+            for (i1, i2) in a1.iter().zip(a2) {
+                match_fn_tys(i1, i2, contravariant, bound, free_origins)?;
+            }
+            match_fn_tys(r1, r2, variance, bound, free_origins)?;
+            bound.truncate(depth);
+            Ok(())
+        }
+        _ => eyre::bail!("cannot relate `{}` and `{}`", t1, t2),
+    }
+}
+
+/// Relating `t1` and `t2` requires higher-ranked reasoning, as they bind origins differently.
+fn higher_ranked(t1: &Ty, t2: &Ty) -> eyre::Report {
+    Unsupported {
+        feature: Feature::HigherRankedSubtyping,
+        span: format!("{} <: {}", t1, t2),
+    }
+    .into()
+}
+
+/// Matches the origins `o1` and `o2` of the types `t1` and `t2`, in a position of variance
+/// `variance`, for `match_fn_tys`.
+fn match_fn_origins<'t>(
+    t1: &Ty,
+    t2: &Ty,
+    (o1, o2, variance): (&'t Origin, &'t Origin, Variance),
+    bound: &[(&'t Origin, &'t Origin)],
+    free_origins: &mut Vec<(&'t Origin, &'t Origin, Variance)>,
+) -> eyre::Result<()> {
+    let b1 = bound.iter().rposition(|(b, _)| *b == o1);
+    let b2 = bound.iter().rposition(|(_, b)| *b == o2);
+    match (b1, b2) {
+        (None, None) => free_origins.push((o1, o2, variance)),
+        (Some(b1), Some(b2)) if b1 == b2 => {}
+        _ => return Err(higher_ranked(t1, t2)),
+    }
+    Ok(())
+}
+
+/// Pushes `tuple` into `relation` unless it is already there, and returns whether it was
+/// pushed.
+fn push_unique<T: PartialEq>(relation: &mut Vec<T>, tuple: T) -> bool {
+    if relation.contains(&tuple) {
+        return false;
+    }
+    relation.push(tuple);
+    true
+}
+
+fn overlap(accessed: &Place, loan: &Place) -> Overlap {
+    if accessed.base != loan.base {
+        return Overlap::Disjoint;
+    }
+    let (accessed, loan) = (&accessed.projections, &loan.projections);
+
+    let common = accessed
+        .iter()
+        .zip(loan)
+        .take_while(|(a, b)| a == b)
+        .count();
+    if common < accessed.len() && common < loan.len() {
+        Overlap::Disjoint
+    } else if common == accessed.len() && loan[common..].contains(&PlaceElem::Deref) {
+        Overlap::BehindReference
+    } else {
+        Overlap::Overlapping
+    }
+}
+
+/// The name of the node of each statement of a lowered program.
+struct NodeNames(BTreeMap<Location, Node>);
+
+/// Names the nodes of `body`, and collects its loans, in program order.
+fn collect_loans(body: &Body, options: &Options) -> (NodeNames, Loans) {
+    let mut node_names = BTreeMap::new();
+    let mut loans = Loans {
+        complete_places: options.complete_places,
+        ..Loans::default()
+    };
+    let mut index = 0;
+    for (b, block) in body.blocks.iter().enumerate() {
+        for (s, statement) in block.statements.iter().enumerate() {
+            let location = Location {
+                block: b,
+                statement: s,
+            };
+            let node = node_name(options, index, &block.name, s);
+            node_names.insert(location, node.clone());
+            index += 1;
+
+            for effect in &statement.effects {
+                if let Effect::Borrow {
+                    origin,
+                    place,
+                    kind,
+                } = effect
+                {
+                    loans.push(Loan {
+                        place: place.clone(),
+                        origin: origin.clone(),
+                        kind: *kind,
+                        location,
+                        node: node.clone(),
+                    });
+                }
+            }
+        }
+    }
+    (NodeNames(node_names), loans)
+}
+
+/// The name of the `statement`th statement of `block`, which is the `index`th statement of the
+/// program.
+fn node_name(options: &Options, index: usize, block: &str, statement: usize) -> Node {
+    if options.stable_node_names {
+        stable_node_name(block, statement)
+    } else if options.simple_node_names {
+        simple_node_name(index)
+    } else {
+        format!("{}[{}]", block, statement)
+    }
+}
+
+/// The name of the `statement`th statement of `block` with `Options::stable_node_names`, e.g.
+/// `bb1s0`. The ghost statements and outlives constraints of the AST aren't statements.
+pub fn stable_node_name(block: &str, statement: usize) -> Node {
+    format!("{}s{}", block, statement)
+}
+
+/// Names nodes `a`, ..., `z`, `aa`, `ab`, etc.
+fn simple_node_name(mut index: usize) -> Node {
+    let mut name = vec![];
+    loop {
+        name.push(b'a' + (index % 26) as u8);
+        if index < 26 {
+            break;
+        }
+        index = index / 26 - 1;
+    }
+    name.reverse();
+    String::from_utf8(name).unwrap()
+}
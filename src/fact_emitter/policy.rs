@@ -0,0 +1,108 @@
+//! Which accesses invalidate which loans.
+//!
+//! The emitter asks the policy about every loan whose place overlaps an accessed place, so the
+//! semantics of invalidation can be changed, e.g. to experiment with a stacked-borrows-flavored
+//! variant, without changing the emitter itself.
+
+use std::fmt;
+
+use crate::ir::BorrowKind;
+
+/// An access to a place, which may affect the loans of overlapping places.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Access {
+    Copy,
+    Move,
+    Borrow(BorrowKind),
+
+    /// The variant of a builtin enum is read, by a `match`. Only the loans of the whole enum
+    /// value, or of a place containing it, are affected: the payloads aren't read.
+    Discriminant,
+
+    /// The place is overwritten.
+    Write,
+
+    /// The value of the place is dropped, by a `drop(x)`.
+    Drop,
+
+    /// The storage of a local dies: the accessed place is the whole local.
+    StorageDead,
+}
+
+impl fmt::Display for Access {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Access::Copy => "copy",
+            Access::Move => "move",
+            Access::Borrow(BorrowKind::Shared) => "shared borrow",
+            Access::Borrow(BorrowKind::Mut) => "mutable borrow",
+            Access::Discriminant => "discriminant read",
+            Access::Write => "write",
+            Access::Drop => "drop",
+            Access::StorageDead => "storage death",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// How the place of a loan relates to an accessed place.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Overlap {
+    Disjoint,
+
+    /// One of the places is a prefix of the other.
+    Overlapping,
+
+    /// The loan is of data behind a reference stored in the accessed place, e.g. a loan of
+    /// `*x` when accessing `x`. Overwriting the accessed place doesn't affect the borrowed data,
+    /// but the loan doesn't refer to the place anymore.
+    BehindReference,
+}
+
+/// What an access does to a loan.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum LoanEffect {
+    /// The loan can't be used anymore: `invalidate_origin`.
+    Invalidate,
+
+    /// The loan is killed, as it doesn't refer to the accessed place anymore: `clear_origin`.
+    Clear,
+}
+
+pub trait InvalidationPolicy {
+    /// The effect of `access` on a loan of kind `loan`, whose place overlaps the accessed place
+    /// as `overlap`, or `None` if the loan isn't affected.
+    fn loan_effect(&self, access: Access, loan: BorrowKind, overlap: Overlap)
+        -> Option<LoanEffect>;
+}
+
+/// The invalidations of NLL: reads conflict with mutable loans, moves and mutable borrows
+/// conflict with all loans, and writes, drops and storage deaths invalidate the loans of the
+/// data they overwrite or free.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct DefaultPolicy;
+
+impl InvalidationPolicy for DefaultPolicy {
+    fn loan_effect(
+        &self,
+        access: Access,
+        loan: BorrowKind,
+        overlap: Overlap,
+    ) -> Option<LoanEffect> {
+        match (access, overlap) {
+            (_, Overlap::Disjoint) => None,
+            (Access::Write, Overlap::BehindReference) => Some(LoanEffect::Clear),
+            (
+                Access::Drop | Access::StorageDead | Access::Discriminant,
+                Overlap::BehindReference,
+            ) => None,
+            (Access::Write | Access::Drop | Access::StorageDead, Overlap::Overlapping) => {
+                Some(LoanEffect::Invalidate)
+            }
+            (Access::Copy | Access::Discriminant | Access::Borrow(BorrowKind::Shared), _) => {
+                (loan == BorrowKind::Mut).then_some(LoanEffect::Invalidate)
+            }
+            (Access::Move | Access::Borrow(BorrowKind::Mut), _) => Some(LoanEffect::Invalidate),
+        }
+    }
+}
@@ -0,0 +1,286 @@
+//! The stages of the emitter in the analysis pipeline, see `pipeline::Pipeline::emit`. The
+//! node names and the loans of the program are collected once, by `loans`, and read by the
+//! stages emitting facts.
+
+use super::{
+    collect_loans, DefaultPolicy, FactEmitter, InvalidationPolicy, Liveness, Loans, NodeNames,
+    Options, Output, Trace,
+};
+use crate::ast::{Name, Program};
+use crate::facts::Facts;
+use crate::ir::Body;
+use crate::pipeline::{Artifacts, Stage};
+use crate::typeck::TypeEnv;
+
+/// Names the nodes of the `ir::Body`, and collects its loans.
+pub struct CollectLoans {
+    pub options: Options,
+}
+
+impl Stage for CollectLoans {
+    fn name(&self) -> &'static str {
+        "loans"
+    }
+
+    fn dependencies(&self) -> &'static [&'static str] {
+        &["lower"]
+    }
+
+    fn run(&self, artifacts: &mut Artifacts) -> eyre::Result<()> {
+        let (node_names, loans) = collect_loans(artifacts.get::<Body>()?, &self.options);
+        artifacts.insert(node_names);
+        artifacts.insert(loans);
+        Ok(())
+    }
+}
+
+/// Emits the facts of each statement, the `cfg_edge`s, and the `incompatible_loans`, adding
+/// the `Facts` and the `Vec<Expectation>`, and the `Trace` when `trace` is set.
+pub struct EmitFacts<'a> {
+    pub policy: &'a dyn InvalidationPolicy,
+    pub trace: bool,
+
+    /// See `Options::origin_equalities`.
+    pub origin_equalities: bool,
+}
+
+impl Stage for EmitFacts<'_> {
+    fn name(&self) -> &'static str {
+        "emit"
+    }
+
+    fn dependencies(&self) -> &'static [&'static str] {
+        &["loans"]
+    }
+
+    fn run(&self, artifacts: &mut Artifacts) -> eyre::Result<()> {
+        let output = Output {
+            trace: self.trace.then(Trace::default),
+            ..Output::default()
+        };
+        let parts = Parts::take(artifacts)?;
+        let mut emitter = resume(parts, artifacts.get::<Program>()?, self.policy, output);
+        emitter.origin_equalities = self.origin_equalities;
+        emitter.emit()?;
+        emitter.emit_incompatible_loans()?;
+        let (parts, mut output) = suspend(emitter);
+        parts.put_back(artifacts);
+        artifacts.insert(std::mem::take(&mut output.expectations));
+        insert_output(output, artifacts);
+        Ok(())
+    }
+}
+
+/// Emits `origin_live_on_entry`, computed from the variables, into the `Facts`, see
+/// `Liveness::Explicit`.
+pub struct EmitLiveness;
+
+impl Stage for EmitLiveness {
+    fn name(&self) -> &'static str {
+        "liveness"
+    }
+
+    fn dependencies(&self) -> &'static [&'static str] {
+        &["emit"]
+    }
+
+    fn run(&self, artifacts: &mut Artifacts) -> eyre::Result<()> {
+        let output = Output {
+            facts: artifacts.take()?,
+            trace: artifacts.take().ok(),
+            expectations: vec![],
+        };
+        let parts = Parts::take(artifacts)?;
+        let mut emitter = resume(parts, artifacts.get::<Program>()?, &DefaultPolicy, output);
+        emitter.emit_liveness()?;
+        let (parts, output) = suspend(emitter);
+        parts.put_back(artifacts);
+        insert_output(output, artifacts);
+        Ok(())
+    }
+}
+
+/// Emits the facts assuming that the `origins` come from an unanalyzed caller, which may use
+/// them after the program returns, see `Options::external_origins`. Runs after `liveness`, with
+/// explicit liveness, so that its `origin_live_on_entry` aren't computed from the variables.
+pub struct AssumeExternalOrigins {
+    pub origins: Vec<Name>,
+    pub liveness: Liveness,
+}
+
+impl Stage for AssumeExternalOrigins {
+    fn name(&self) -> &'static str {
+        "external"
+    }
+
+    fn dependencies(&self) -> &'static [&'static str] {
+        match self.liveness {
+            Liveness::AccessImpliesLive => &["emit"],
+            Liveness::Explicit => &["liveness"],
+        }
+    }
+
+    fn run(&self, artifacts: &mut Artifacts) -> eyre::Result<()> {
+        let output = Output {
+            facts: artifacts.take()?,
+            trace: artifacts.take().ok(),
+            expectations: vec![],
+        };
+        let parts = Parts::take(artifacts)?;
+        let mut emitter = resume(parts, artifacts.get::<Program>()?, &DefaultPolicy, output);
+        let result = emitter.emit_external_origins(&self.origins, self.liveness);
+        let (parts, output) = suspend(emitter);
+        parts.put_back(artifacts);
+        insert_output(output, artifacts);
+        result
+    }
+}
+
+/// Emits the move paths of the `ir::Body` and how its statements use them into the `Facts`,
+/// see `Options::move_paths`.
+pub struct EmitMovePaths;
+
+impl Stage for EmitMovePaths {
+    fn name(&self) -> &'static str {
+        "move_paths"
+    }
+
+    fn dependencies(&self) -> &'static [&'static str] {
+        &["emit"]
+    }
+
+    fn run(&self, artifacts: &mut Artifacts) -> eyre::Result<()> {
+        let output = Output {
+            facts: artifacts.take()?,
+            trace: artifacts.take().ok(),
+            expectations: vec![],
+        };
+        let parts = Parts::take(artifacts)?;
+        let mut emitter = resume(parts, artifacts.get::<Program>()?, &DefaultPolicy, output);
+        emitter.emit_move_paths()?;
+        let (parts, output) = suspend(emitter);
+        parts.put_back(artifacts);
+        insert_output(output, artifacts);
+        Ok(())
+    }
+}
+
+/// Emits the `discriminant_known` facts of the arms of the `ir::Body`'s switches into the
+/// `Facts`, see `Options::refinements`.
+pub struct EmitRefinements;
+
+impl Stage for EmitRefinements {
+    fn name(&self) -> &'static str {
+        "refinements"
+    }
+
+    fn dependencies(&self) -> &'static [&'static str] {
+        &["emit"]
+    }
+
+    fn run(&self, artifacts: &mut Artifacts) -> eyre::Result<()> {
+        let output = Output {
+            facts: artifacts.take()?,
+            trace: artifacts.take().ok(),
+            expectations: vec![],
+        };
+        let parts = Parts::take(artifacts)?;
+        let mut emitter = resume(parts, artifacts.get::<Program>()?, &DefaultPolicy, output);
+        let result = emitter.emit_refinements();
+        let (parts, output) = suspend(emitter);
+        parts.put_back(artifacts);
+        insert_output(output, artifacts);
+        result
+    }
+}
+
+/// Emits the `origin_kind` of each origin of the `Facts`, where the `external_origins` are
+/// placeholders. Runs last, so that every origin of the facts has a kind.
+pub struct EmitOriginKinds {
+    pub external_origins: Vec<Name>,
+}
+
+impl Stage for EmitOriginKinds {
+    fn name(&self) -> &'static str {
+        "origin_kinds"
+    }
+
+    fn dependencies(&self) -> &'static [&'static str] {
+        &["emit"]
+    }
+
+    fn run(&self, artifacts: &mut Artifacts) -> eyre::Result<()> {
+        let output = Output {
+            facts: artifacts.take()?,
+            trace: artifacts.take().ok(),
+            expectations: vec![],
+        };
+        let parts = Parts::take(artifacts)?;
+        let mut emitter = resume(parts, artifacts.get::<Program>()?, &DefaultPolicy, output);
+        emitter.emit_origin_kinds(&self.external_origins);
+        let (parts, output) = suspend(emitter);
+        parts.put_back(artifacts);
+        insert_output(output, artifacts);
+        Ok(())
+    }
+}
+
+/// The artifacts of the earlier stages that an emitter owns while it runs.
+struct Parts {
+    body: Body,
+    node_names: NodeNames,
+    loans: Loans,
+}
+
+impl Parts {
+    fn take(artifacts: &mut Artifacts) -> eyre::Result<Self> {
+        Ok(Parts {
+            body: artifacts.take()?,
+            node_names: artifacts.take()?,
+            loans: artifacts.take()?,
+        })
+    }
+
+    fn put_back(self, artifacts: &mut Artifacts) {
+        artifacts.insert(self.body);
+        artifacts.insert(self.node_names);
+        artifacts.insert(self.loans);
+    }
+}
+
+/// Builds an emitter over `parts`, emitting into `output`.
+fn resume<'p>(
+    parts: Parts,
+    program: &'p Program,
+    policy: &'p dyn InvalidationPolicy,
+    output: Output,
+) -> FactEmitter<'p> {
+    FactEmitter {
+        body: parts.body,
+        env: TypeEnv::new(program),
+        policy,
+        node_names: parts.node_names.0,
+        loans: parts.loans,
+        output,
+        origin_equalities: false,
+    }
+}
+
+/// Takes back the parts of `emitter`, and the facts it emitted.
+fn suspend(emitter: FactEmitter<'_>) -> (Parts, Output) {
+    let parts = Parts {
+        body: emitter.body,
+        node_names: NodeNames(emitter.node_names),
+        loans: emitter.loans,
+    };
+    (parts, emitter.output)
+}
+
+/// Adds the `Facts`, and the `Trace` when it is recorded.
+fn insert_output(output: Output, artifacts: &mut Artifacts) {
+    let facts: Facts = output.facts;
+    artifacts.insert(facts);
+    if let Some(trace) = output.trace {
+        artifacts.insert(trace);
+    }
+}
@@ -0,0 +1,2917 @@
+use super::*;
+
+fn emit(s: &str) -> String {
+    let options = Options {
+        simple_node_names: true,
+        ..Options::default()
+    };
+    match emit_facts(s, options) {
+        Ok(facts) => facts.to_string(),
+        Err(e) => panic!("emitting facts failed: {:?}", e),
+    }
+}
+
+#[test]
+fn example_a() {
+    let facts = emit(
+        "
+        fn use<T>(t: T) -> ();
+        let mut x: i32;
+        let y: &'y i32;
+        bb0: {
+            x = 3;
+            y = &'L_x x;
+            x = 4;
+            use(copy y);
+        }
+    ",
+    );
+
+    insta::assert_snapshot!(facts, @r###"
+    // polonius-frontend v2
+    a: "x = 3" {
+        invalidate_origin('L_x)
+        goto b
+    }
+
+    b: "y = &'L_x x" {
+        write_origin('y)
+        clear_origin('y)
+        clear_origin('L_x)
+        introduce_subset('L_x, 'y)
+        goto c
+    }
+
+    c: "x = 4" {
+        invalidate_origin('L_x)
+        goto d
+    }
+
+    d: "use(copy y)" {
+        access_origin('y)
+        goto e
+    }
+
+    e: "(storage dead)" {
+        invalidate_origin('L_x)
+        node_is_exit()
+        goto
+    }
+
+    "###);
+}
+
+#[test]
+fn issue_47680() {
+    let facts = emit(
+        "
+        extern type Thing;
+        fn MaybeNext<'a>(t: &'a mut Thing) -> &'a mut Thing;
+        let mut thing: Thing;
+        let mut temp: &'temp mut Thing;
+        let mut t0: &'t0 mut Thing;
+        let mut v: &'v mut Thing;
+        bb0: {
+            temp = &'L_thing mut thing;
+            goto bb1;
+        }
+        bb1: {
+            t0 = &'L_*temp mut *temp;
+            v = MaybeNext(move t0);
+            goto bb2, bb3;
+        }
+        bb2: {
+            temp = move v;
+            goto bb4;
+        }
+        bb3: {
+            goto bb4;
+        }
+        bb4: {
+            goto bb1;
+        }
+    ",
+    );
+
+    insta::assert_snapshot!(facts, @r###"
+    // polonius-frontend v2
+    a: "temp = &'L_thing mut thing" {
+        invalidate_origin('L_thing)
+        write_origin('temp)
+        clear_origin('L_*temp)
+        clear_origin('temp)
+        clear_origin('L_thing)
+        introduce_subset('L_thing, 'temp)
+        goto b
+    }
+
+    b: "t0 = &'L_*temp mut *temp" {
+        access_origin('temp)
+        invalidate_origin('L_*temp)
+        write_origin('t0)
+        clear_origin('t0)
+        clear_origin('L_*temp)
+        introduce_subset('temp, 'L_*temp)
+        introduce_subset('L_*temp, 't0)
+        goto c
+    }
+
+    c: "v = MaybeNext(move t0)" {
+        access_origin('t0)
+        write_origin('v)
+        clear_origin('v)
+        introduce_subset('t0, 'MaybeNext0_a)
+        introduce_subset('MaybeNext0_a, 'v)
+        goto d e
+    }
+
+    d: "temp = move v" {
+        access_origin('v)
+        write_origin('temp)
+        clear_origin('L_*temp)
+        clear_origin('temp)
+        introduce_subset('v, 'temp)
+        goto f
+    }
+
+    e: "(pass)" {
+        goto f
+    }
+
+    f: "(pass)" {
+        goto b
+    }
+
+    g: "(storage dead)" {
+        invalidate_origin('L_thing)
+        node_is_exit()
+        goto
+    }
+
+    "###);
+}
+
+#[test]
+fn killing_and_murder() {
+    let facts = emit(
+        "
+        fn use<T>(t: T) -> ();
+        let mut p: i32;
+        let mut q: i32;
+        let mut x: &'x mut i32;
+        let y: &'y mut i32;
+        bb0: {
+            p = 22;
+            q = 44;
+            x = &'L_p mut p;
+            y = &'L_*x mut *x;
+            x = &'L_q mut q;
+            *y = 11;
+            use(move x);
+        }
+    ",
+    );
+
+    insta::assert_snapshot!(facts, @r###"
+    // polonius-frontend v2
+    a: "p = 22" {
+        invalidate_origin('L_p)
+        goto b
+    }
+
+    b: "q = 44" {
+        invalidate_origin('L_q)
+        goto c
+    }
+
+    c: "x = &'L_p mut p" {
+        invalidate_origin('L_p)
+        write_origin('x)
+        clear_origin('L_*x)
+        clear_origin('x)
+        clear_origin('L_p)
+        introduce_subset('L_p, 'x)
+        goto d
+    }
+
+    d: "y = &'L_*x mut *x" {
+        access_origin('x)
+        invalidate_origin('L_*x)
+        write_origin('y)
+        clear_origin('y)
+        clear_origin('L_*x)
+        introduce_subset('x, 'L_*x)
+        introduce_subset('L_*x, 'y)
+        goto e
+    }
+
+    e: "x = &'L_q mut q" {
+        invalidate_origin('L_q)
+        write_origin('x)
+        clear_origin('L_*x)
+        clear_origin('x)
+        clear_origin('L_q)
+        introduce_subset('L_q, 'x)
+        goto f
+    }
+
+    f: "*y = 11" {
+        access_origin('y)
+        goto g
+    }
+
+    g: "use(move x)" {
+        access_origin('x)
+        invalidate_origin('L_*x)
+        goto h
+    }
+
+    h: "(storage dead)" {
+        invalidate_origin('L_p)
+        invalidate_origin('L_q)
+        node_is_exit()
+        goto
+    }
+
+    "###);
+}
+
+#[test]
+fn vec_temp() {
+    let facts = emit(
+        "
+        struct Vec<T> { item0: T }
+        fn Vec_new<T>() -> Vec<T>;
+        fn Vec_push<'v, T>(v: &'v mut Vec<T>, element: T) -> ();
+        fn Vec_len<'v, T>(v: &'v Vec<T>) -> i32;
+        let mut x: i32;
+        let mut v: Vec<&'v i32>;
+        let p: &'p i32;
+        let tmp: &'tmp0 mut Vec<&'tmp1 i32>;
+        let len: i32;
+        bb0: {
+            x = 22;
+            v = Vec_new();
+            p = &'L_x x;
+            tmp = &'L_v mut v;
+            Vec_push(move tmp, copy p);
+            x = 23;
+            len = Vec_len(&'L_v2 v);
+        }
+    ",
+    );
+
+    insta::assert_snapshot!(facts, @r###"
+    // polonius-frontend v2
+    a: "x = 22" {
+        invalidate_origin('L_x)
+        goto b
+    }
+
+    b: "v = Vec_new()" {
+        invalidate_origin('L_v)
+        invalidate_origin('L_v2)
+        write_origin('v)
+        clear_origin('v)
+        goto c
+    }
+
+    c: "p = &'L_x x" {
+        write_origin('p)
+        clear_origin('p)
+        clear_origin('L_x)
+        introduce_subset('L_x, 'p)
+        goto d
+    }
+
+    d: "tmp = &'L_v mut v" {
+        access_origin('v)
+        invalidate_origin('L_v)
+        invalidate_origin('L_v2)
+        write_origin('tmp0)
+        write_origin('tmp1)
+        clear_origin('tmp0)
+        clear_origin('tmp1)
+        clear_origin('L_v)
+        introduce_subset('L_v, 'tmp0)
+        introduce_subset('v, 'tmp1)
+        introduce_subset('tmp1, 'v)
+        goto e
+    }
+
+    e: "Vec_push(move tmp, copy p)" {
+        access_origin('tmp0)
+        access_origin('tmp1)
+        access_origin('p)
+        introduce_subset('tmp0, 'Vec_push1_v)
+        introduce_subset('p, 'tmp1)
+        goto f
+    }
+
+    f: "x = 23" {
+        invalidate_origin('L_x)
+        goto g
+    }
+
+    g: "len = Vec_len(&'L_v2 v)" {
+        access_origin('v)
+        invalidate_origin('L_v)
+        clear_origin('L_v2)
+        introduce_subset('L_v2, 'Vec_len2_v)
+        incompatible_loans('L_v, 'L_v2)
+        goto h
+    }
+
+    h: "(storage dead)" {
+        invalidate_origin('L_x)
+        invalidate_origin('L_v)
+        invalidate_origin('L_v2)
+        node_is_exit()
+        goto
+    }
+
+    "###);
+}
+
+#[test]
+fn borrow_in_call_argument() {
+    // The loan flows into the instantiated parameter origin, and from there into the result.
+    let facts = emit(
+        "
+        fn MaybeNext<'a>(t: &'a mut i32) -> &'a i32;
+        let mut t0: i32;
+        let v: &'v i32;
+        bb0: {
+            v = MaybeNext(&'L_t0 mut t0);
+        }
+    ",
+    );
+
+    insta::assert_snapshot!(facts, @r###"
+    // polonius-frontend v2
+    a: "v = MaybeNext(&'L_t0 mut t0)" {
+        invalidate_origin('L_t0)
+        write_origin('v)
+        clear_origin('v)
+        clear_origin('L_t0)
+        introduce_subset('L_t0, 'MaybeNext0_a)
+        introduce_subset('MaybeNext0_a, 'v)
+        goto b
+    }
+
+    b: "(storage dead)" {
+        invalidate_origin('L_t0)
+        node_is_exit()
+        goto
+    }
+
+    "###);
+}
+
+#[test]
+fn struct_call_result() {
+    // Each origin of the instantiated return type is related to the origin at the same
+    // position in the type of the assigned place, whether it's an origin parameter or inside a
+    // type parameter.
+    let facts = emit(
+        "
+        struct Pair<A, B> { first: A, second: B }
+        struct Refs<'a, 'b> { first: &'a i32, second: &'b i32 }
+        fn pair<'a, 'b, T, U>(t: &'a T, u: &'b U) -> Pair<&'a T, &'b U>;
+        fn refs<'a, 'b>(t: &'a i32, u: &'b i32) -> Refs<'a, 'b>;
+        let x: i32;
+        let y: i32;
+        let p: Pair<&'p1 i32, &'p2 i32>;
+        let r: Refs<'r1, 'r2>;
+        bb0: {
+            p = pair(&'L_x x, &'L_y y);
+            r = refs(&'L_x2 x, &'L_y2 y);
+        }
+    ",
+    );
+
+    insta::assert_snapshot!(facts, @r###"
+    // polonius-frontend v2
+    a: "p = pair(&'L_x x, &'L_y y)" {
+        write_origin('p1)
+        write_origin('p2)
+        clear_origin('p1)
+        clear_origin('p2)
+        clear_origin('L_x)
+        clear_origin('L_y)
+        introduce_subset('L_x, 'pair0_a)
+        introduce_subset('L_y, 'pair0_b)
+        introduce_subset('pair0_a, 'p1)
+        introduce_subset('pair0_b, 'p2)
+        goto b
+    }
+
+    b: "r = refs(&'L_x2 x, &'L_y2 y)" {
+        write_origin('r2)
+        write_origin('r1)
+        clear_origin('r2)
+        clear_origin('r1)
+        clear_origin('L_x2)
+        clear_origin('L_y2)
+        introduce_subset('L_x2, 'refs1_a)
+        introduce_subset('L_y2, 'refs1_b)
+        introduce_subset('refs1_a, 'r1)
+        introduce_subset('refs1_b, 'r2)
+        goto c
+    }
+
+    c: "(storage dead)" {
+        invalidate_origin('L_x)
+        invalidate_origin('L_x2)
+        invalidate_origin('L_y)
+        invalidate_origin('L_y2)
+        node_is_exit()
+        goto
+    }
+
+    "###);
+}
+
+#[test]
+fn nested_call_argument() {
+    // The subsets of the inner call's signature are emitted before the outer call's.
+    let program = "
+        fn g<'a>(r: &'a i32) -> &'a i32;
+        fn f<'b>(r: &'b i32, n: i32) -> &'b i32;
+        let x: i32;
+        let p: &'p i32;
+        let v: &'v i32;
+        bb0: {
+            p = &'L_x x;
+            v = f(g(copy p), 1);
+        }
+    ";
+    insta::assert_snapshot!(emit(program), @r###"
+    // polonius-frontend v2
+    a: "p = &'L_x x" {
+        write_origin('p)
+        clear_origin('p)
+        clear_origin('L_x)
+        introduce_subset('L_x, 'p)
+        goto b
+    }
+
+    b: "v = f(g(copy p), 1)" {
+        access_origin('p)
+        write_origin('v)
+        clear_origin('v)
+        introduce_subset('p, 'g0_a)
+        introduce_subset('g0_a, 'f1_b)
+        introduce_subset('f1_b, 'v)
+        goto c
+    }
+
+    c: "(storage dead)" {
+        invalidate_origin('L_x)
+        node_is_exit()
+        goto
+    }
+
+    "###);
+
+    // With temporaries, the inner call gets its own node.
+    let options = Options {
+        simple_node_names: true,
+        introduce_temporaries: true,
+        ..Options::default()
+    };
+    let facts = emit_facts(program, options).unwrap();
+    insta::assert_snapshot!(facts.to_string(), @r###"
+    // polonius-frontend v2
+    a: "p = &'L_x x" {
+        write_origin('p)
+        clear_origin('p)
+        clear_origin('L_x)
+        introduce_subset('L_x, 'p)
+        goto b
+    }
+
+    b: "_tmp0 = g(copy p)" {
+        access_origin('p)
+        write_origin('_tmp0_0)
+        clear_origin('_tmp0_0)
+        introduce_subset('p, 'g0_a)
+        introduce_subset('g0_a, '_tmp0_0)
+        goto c
+    }
+
+    c: "v = f(move _tmp0, 1)" {
+        access_origin('_tmp0_0)
+        write_origin('v)
+        clear_origin('v)
+        introduce_subset('_tmp0_0, 'f1_b)
+        introduce_subset('f1_b, 'v)
+        goto d
+    }
+
+    d: "(storage dead)" {
+        invalidate_origin('L_x)
+        node_is_exit()
+        goto
+    }
+
+    "###);
+}
+
+#[test]
+fn literal_call_argument() {
+    let facts = emit(
+        "
+        fn id<T>(t: T) -> T;
+        let x: i32;
+        bb0: {
+            x = id(22);
+        }
+    ",
+    );
+    insta::assert_snapshot!(facts, @r###"
+    // polonius-frontend v2
+    a: "x = id(22)" {
+        goto b
+    }
+
+    b: "(storage dead)" {
+        node_is_exit()
+        goto
+    }
+
+    "###);
+}
+
+#[test]
+fn default_node_names() {
+    let facts = emit_facts(
+        "
+        let mut x: i32;
+        bb0: {
+            x = 1;
+            goto bb1;
+        }
+        bb1: {
+            goto bb0;
+        }
+    ",
+        Options::default(),
+    )
+    .unwrap();
+
+    insta::assert_snapshot!(facts.to_string(), @r###"
+    // polonius-frontend v2
+    bb0[0]: "x = 1" {
+        goto bb1[0]
+    }
+
+    bb1[0]: "(pass)" {
+        goto bb0[0]
+    }
+
+    exit[0]: "(storage dead)" {
+        node_is_exit()
+        goto
+    }
+
+    "###);
+}
+
+#[test]
+fn borrow_mut_through_shared_reference() {
+    let err = emit_facts(
+        "
+        let x: i32;
+        let r: &'r i32;
+        let m: &'m mut i32;
+        bb0: {
+            r = &'L_x x;
+            m = &'L_*r mut *r;
+        }
+    ",
+        Options::default(),
+    )
+    .unwrap_err();
+
+    insta::assert_snapshot!(format!("{:#}", err), @"in statement `m = &'L_*r mut *r` of `bb0`: cannot borrow `*r` as mutable, as it is behind a shared reference");
+}
+
+#[test]
+fn loans_with_same_origin() {
+    let program = crate::parse_ast(
+        "
+        let x: i32;
+        let r: &'r mut i32;
+        let a: &'a i32;
+        bb0: {
+            r = &'L_x mut x;
+            a = &'L_*r *r;
+            a = &'L_*r *r;
+        }
+    ",
+    )
+    .unwrap();
+    let body = ir::lower(&program).unwrap();
+    let emitter = FactEmitter::new(&program, body, &Options::default(), &DefaultPolicy);
+
+    let place = Place {
+        base: "r".to_string(),
+        projections: vec![],
+    };
+    let overlapping: Vec<_> = emitter
+        .loans
+        .loans_overlapping(&place)
+        .map(|(id, loan, overlap)| (id, loan.location, overlap))
+        .collect();
+    insta::assert_debug_snapshot!(overlapping, @r###"
+    [
+        (
+            LoanId(
+                1,
+            ),
+            Location {
+                block: 0,
+                statement: 1,
+            },
+            BehindReference,
+        ),
+        (
+            LoanId(
+                2,
+            ),
+            Location {
+                block: 0,
+                statement: 2,
+            },
+            BehindReference,
+        ),
+    ]
+    "###);
+}
+
+#[test]
+fn loan_registry() {
+    let program = crate::parse_ast(
+        "
+        struct S { f: i32, g: i32 }
+        let mut s: S;
+        let mut a: &'a i32;
+        let b: &'b mut S;
+        bb0: {
+            a = &'L_f s.f;
+            a = &'L_g s.g;
+            b = &'L_s mut s;
+        }
+    ",
+    )
+    .unwrap();
+    let options = Options {
+        simple_node_names: true,
+        ..Options::default()
+    };
+    let loans = Loans::of_program(program, &options).unwrap();
+    let describe = |(_, loan): (LoanId, &Loan)| format!("{} at {}", loan, loan.node);
+
+    let f = Place {
+        base: "s".to_string(),
+        projections: vec![PlaceElem::Field("f".to_string())],
+    };
+    let s = Place {
+        base: "s".to_string(),
+        projections: vec![],
+    };
+    let of_f: Vec<_> = loans.loans_of(&f).map(describe).collect();
+    let overlapping_f: Vec<_> = loans
+        .loans_overlapping(&f)
+        .map(|(id, loan, overlap)| format!("{} {:?}", describe((id, loan)), overlap))
+        .collect();
+    let of_s: Vec<_> = loans.loans_of(&s).map(describe).collect();
+    let issued_at_b: Vec<_> = loans.loans_issued_at("b").map(describe).collect();
+    insta::assert_debug_snapshot!((of_f, overlapping_f, of_s, issued_at_b), @r###"
+    (
+        [
+            "`&'L_f s.f` at a",
+        ],
+        [
+            "`&'L_f s.f` at a Overlapping",
+            "`&'L_s mut s` at c Overlapping",
+        ],
+        [
+            "`&'L_s mut s` at c",
+        ],
+        [
+            "`&'L_g s.g` at b",
+        ],
+    )
+    "###);
+}
+
+#[test]
+fn returns_reference_to_local() {
+    let facts = emit(
+        "
+        let x: i32;
+        let p: &'p i32;
+        let return: &'r i32;
+        bb0: {
+            x = 22;
+            return = &'L_x x;
+            goto bb1, bb2;
+        }
+        bb1: {
+            return = copy p;
+        }
+        bb2: {
+        }
+    ",
+    );
+
+    insta::assert_snapshot!(facts, @r###"
+    // polonius-frontend v2
+    a: "x = 22" {
+        invalidate_origin('L_x)
+        goto b
+    }
+
+    b: "return = &'L_x x" {
+        write_origin('r)
+        clear_origin('r)
+        clear_origin('L_x)
+        introduce_subset('L_x, 'r)
+        goto c d
+    }
+
+    c: "return = copy p" {
+        access_origin('p)
+        write_origin('r)
+        clear_origin('r)
+        introduce_subset('p, 'r)
+        goto e
+    }
+
+    d: "(pass)" {
+        goto e
+    }
+
+    e: "(storage dead)" {
+        invalidate_origin('L_x)
+        goto f
+    }
+
+    f: "(return)" {
+        access_origin('r)
+        node_is_exit()
+        goto
+    }
+
+    "###);
+}
+
+#[test]
+fn return_terminator() {
+    // `return;` ends `bb0` early: the reference escapes through the return place, which the
+    // `(return)` node reads, and `bb1` is unreachable.
+    let facts = emit(
+        "
+        let mut x: i32;
+        let p: &'p i32;
+        let return: &'r i32;
+        bb0: {
+            return = copy p;
+            return;
+        }
+        bb1: {
+            x = 1;
+        }
+    ",
+    );
+
+    insta::assert_snapshot!(facts, @r###"
+    // polonius-frontend v2
+    a: "return = copy p" {
+        access_origin('p)
+        write_origin('r)
+        clear_origin('r)
+        introduce_subset('p, 'r)
+        goto c
+    }
+
+    b: "x = 1" {
+        goto c
+    }
+
+    c: "(storage dead)" {
+        goto d
+    }
+
+    d: "(return)" {
+        access_origin('r)
+        node_is_exit()
+        goto
+    }
+
+    "###);
+}
+
+#[test]
+fn returns_reference_to_local_error() {
+    let facts = emit_facts(
+        "
+        let x: i32;
+        let y: &'y i32;
+        let p: &'p i32;
+        let return: &'r i32;
+        bb0: {
+            x = 22;
+            y = &'L_x x;
+            return = &'L_*p *p;
+            goto bb1, bb2;
+        }
+        bb1: {
+            return = copy y;
+        }
+        bb2: {
+        }
+    ",
+        Options::default(),
+    )
+    .unwrap();
+    let output = crate::solver::check(&facts).unwrap();
+
+    insta::assert_debug_snapshot!(output.returned_reference_to_local, @r###"
+    {
+        (
+            "'r",
+            "return[1]",
+        ),
+    }
+    "###);
+}
+
+#[test]
+fn struct_with_mutable_reference_is_invariant() {
+    // `'b` is behind a `&mut`, so it must be equal in both structs, while `'a` is covariant.
+    let facts = emit(
+        "
+        struct Cell<'a, 'b> { r: &'a mut &'b i32 }
+        let c1: Cell<'c1a, 'c1b>;
+        let c2: Cell<'c2a, 'c2b>;
+        bb0: {
+            c2 = move c1;
+        }
+    ",
+    );
+
+    insta::assert_snapshot!(facts, @r###"
+    // polonius-frontend v2
+    a: "c2 = move c1" {
+        access_origin('c1b)
+        access_origin('c1a)
+        write_origin('c2b)
+        write_origin('c2a)
+        clear_origin('c2b)
+        clear_origin('c2a)
+        introduce_subset('c1a, 'c2a)
+        introduce_subset('c1b, 'c2b)
+        introduce_subset('c2b, 'c1b)
+        goto b
+    }
+
+    b: "(storage dead)" {
+        node_is_exit()
+        goto
+    }
+
+    "###);
+}
+
+#[test]
+fn nested_struct_variance() {
+    // `'p` is used both invariantly in `Cell` and covariantly, so it is invariant in `Outer`.
+    let facts = emit(
+        "
+        struct Cell<'a, 'b> { r: &'a mut &'b i32 }
+        struct Outer<'o, 'p> { cell: Cell<'o, 'p>, shared: &'p i32 }
+        let o1: Outer<'o1, 'p1>;
+        let o2: Outer<'o2, 'p2>;
+        bb0: {
+            o2 = move o1;
+        }
+    ",
+    );
+
+    insta::assert_snapshot!(facts, @r###"
+    // polonius-frontend v2
+    a: "o2 = move o1" {
+        access_origin('p1)
+        access_origin('o1)
+        write_origin('p2)
+        write_origin('o2)
+        clear_origin('p2)
+        clear_origin('o2)
+        introduce_subset('o1, 'o2)
+        introduce_subset('p1, 'p2)
+        introduce_subset('p2, 'p1)
+        goto b
+    }
+
+    b: "(storage dead)" {
+        node_is_exit()
+        goto
+    }
+
+    "###);
+}
+
+fn trace(s: &str) -> Trace {
+    let options = Options {
+        simple_node_names: true,
+        ..Options::default()
+    };
+    match emit_trace(s, options) {
+        Ok((_, trace)) => trace,
+        Err(e) => panic!("emitting facts failed: {:?}", e),
+    }
+}
+
+#[test]
+fn trace_of_reborrow() {
+    let trace = trace(
+        "
+        let mut x: i32;
+        let r: &'r mut i32;
+        let a: &'a i32;
+        bb0: {
+            r = &'L_x mut x;
+            a = &'L_*r *r;
+            x = 1;
+        }
+    ",
+    );
+
+    insta::assert_snapshot!(trace.at("b").to_string(), @r###"
+    access_origin('r) @ b <- `*r` goes through a reference
+    introduce_subset('r, 'L_*r) @ b <- loan of `*r` is reached through a `&'r mut` reference
+    write_origin('a) @ b <- `a` is defined
+    clear_origin('a) @ b <- `a` is overwritten
+    introduce_subset('L_*r, 'a) @ b <- `'L_*r` and `'a` are related covariantly
+    clear_origin('L_*r) @ b <- loan `&'L_*r *r` is issued
+
+    "###);
+    insta::assert_snapshot!(trace.relation("invalidate_origin").to_string(), @r###"
+    invalidate_origin('L_x) @ a <- access to `x` conflicts with loan `&'L_x mut x`
+    invalidate_origin('L_x) @ c <- write to `x` overwrites the data of loan `&'L_x mut x`
+    invalidate_origin('L_x) @ d <- storage of `x` dies, invalidating loan `&'L_x mut x`
+
+    "###);
+    insta::assert_snapshot!(trace.origin("'r").relation("introduce_subset").to_string(), @r###"
+    introduce_subset('L_x, 'r) @ a <- `'L_x` and `'r` are related covariantly
+    introduce_subset('r, 'L_*r) @ b <- loan of `*r` is reached through a `&'r mut` reference
+
+    "###);
+}
+
+#[test]
+fn trace_matches_facts() {
+    let (facts, trace) = emit_trace(
+        "
+        struct Cell<'a, 'b> { r: &'a mut &'b i32 }
+        let c1: Cell<'c1a, 'c1b>;
+        let c2: Cell<'c2a, 'c2b>;
+        bb0: {
+            c2 = move c1;
+        }
+    ",
+        Options::default(),
+    )
+    .unwrap();
+
+    let facts = facts.access_origin.len()
+        + facts.invalidate_origin.len()
+        + facts.clear_origin.len()
+        + facts.write_origin.len()
+        + facts.introduce_subset.len();
+    assert_eq!(trace.entries.len(), facts);
+}
+
+#[test]
+fn fn_pointer_field_is_opaque() {
+    // The origin bound by the function pointer doesn't leak into the facts, while `'a`, in an
+    // argument, is related contravariantly.
+    let facts = emit(
+        "
+        struct Callback<'a> { f: for<'x> fn(&'x i32, &'a i32) -> &'x i32 }
+        let c1: Callback<'c1>;
+        let c2: Callback<'c2>;
+        bb0: {
+            c2 = move c1;
+        }
+    ",
+    );
+
+    insta::assert_snapshot!(facts, @r###"
+    // polonius-frontend v2
+    a: "c2 = move c1" {
+        access_origin('c1)
+        write_origin('c2)
+        clear_origin('c2)
+        introduce_subset('c2, 'c1)
+        goto b
+    }
+
+    b: "(storage dead)" {
+        node_is_exit()
+        goto
+    }
+
+    "###);
+}
+
+#[test]
+fn fn_pointer_subtyping() {
+    // The arguments of function pointers are contravariant, and their result covariant.
+    let trace = trace(
+        "
+        let f: fn(&'f i32, &'fm mut &'fi i32) -> &'r i32;
+        let g: fn(&'g i32, &'gm mut &'gi i32) -> &'s i32;
+        bb0: {
+            g = copy f;
+        }
+    ",
+    );
+
+    insta::assert_snapshot!(trace.relation("introduce_subset").to_string(), @r###"
+    introduce_subset('g, 'f) @ a <- `'f` and `'g` are related contravariantly
+    introduce_subset('gm, 'fm) @ a <- `'fm` and `'gm` are related contravariantly
+    introduce_subset('fi, 'gi) @ a <- `'fi` and `'gi` are related invariantly
+    introduce_subset('gi, 'fi) @ a <- `'fi` and `'gi` are related invariantly
+    introduce_subset('r, 's) @ a <- `'r` and `'s` are related covariantly
+
+    "###);
+}
+
+#[test]
+fn higher_ranked_fn_pointer_subtyping() {
+    let err = emit_facts(
+        "
+        let f: for<'x> fn(&'x i32);
+        let g: fn(&'g i32);
+        bb0: {
+            g = copy f;
+        }
+    ",
+        Options::default(),
+    )
+    .unwrap_err();
+
+    insta::assert_snapshot!(format!("{:#}", err), @"in statement `g = copy f` of `bb0`: unsupported feature `higher-ranked-subtyping`, in `for<'x> fn(&'x i32) <: fn(&'g i32)`");
+}
+
+#[test]
+fn facts_by_program() {
+    let options = Options {
+        simple_node_names: true,
+        ..Options::default()
+    };
+    let facts = emit_facts_by_program(
+        "
+        program shared {
+            let x: i32;
+            let a: &'a i32;
+            let b: &'b i32;
+            bb0: {
+                b = copy a;
+            }
+        }
+        program mutable {
+            let x: i32;
+            let a: &'a mut &'x i32;
+            let b: &'b mut &'y i32;
+            bb0: {
+                b = move a;
+            }
+        }
+    ",
+        options,
+    )
+    .unwrap();
+
+    let facts: String = facts
+        .iter()
+        .map(|(name, facts)| format!("// program {}\n{}", name, facts))
+        .collect();
+    insta::assert_snapshot!(facts, @r###"
+    // program mutable
+    // polonius-frontend v2
+    a: "b = move a" {
+        access_origin('a)
+        access_origin('x)
+        write_origin('b)
+        write_origin('y)
+        clear_origin('b)
+        clear_origin('y)
+        introduce_subset('a, 'b)
+        introduce_subset('x, 'y)
+        introduce_subset('y, 'x)
+        goto b
+    }
+
+    b: "(storage dead)" {
+        node_is_exit()
+        goto
+    }
+    // program shared
+    // polonius-frontend v2
+    a: "b = copy a" {
+        access_origin('a)
+        write_origin('b)
+        clear_origin('b)
+        introduce_subset('a, 'b)
+        goto b
+    }
+
+    b: "(storage dead)" {
+        node_is_exit()
+        goto
+    }
+
+    "###);
+}
+
+#[test]
+fn stable_node_names() {
+    let options = Options {
+        stable_node_names: true,
+        ..Options::default()
+    };
+    let facts = emit_facts(
+        "
+        let mut x: i32;
+        bb0: {
+            x = 1;
+            goto bb1;
+        }
+        bb1: {
+            goto bb0;
+        }
+    ",
+        options,
+    )
+    .unwrap();
+
+    insta::assert_snapshot!(facts.to_string(), @r###"
+    // polonius-frontend v2
+    bb0s0: "x = 1" {
+        goto bb1s0
+    }
+
+    bb1s0: "(pass)" {
+        goto bb0s0
+    }
+
+    exits0: "(storage dead)" {
+        node_is_exit()
+        goto
+    }
+
+    "###);
+}
+
+#[test]
+fn node_name_migration() {
+    let program = crate::parse_ast(
+        "
+        let x: i32;
+        bb0: {
+            x = 1;
+            x = 2;
+            goto bb1;
+        }
+        bb1: {
+        }
+    ",
+    )
+    .unwrap();
+
+    let migration = FactEmitter::node_name_migration(&program).unwrap();
+    insta::assert_debug_snapshot!(migration, @r###"
+    [
+        (
+            "a",
+            "bb0s0",
+        ),
+        (
+            "b",
+            "bb0s1",
+        ),
+        (
+            "c",
+            "bb1s0",
+        ),
+        (
+            "d",
+            "exits0",
+        ),
+    ]
+    "###);
+}
+
+#[test]
+fn custom_invalidation_policy() {
+    // Copies don't conflict with mutable loans, unlike with the default policy.
+    struct CopiesNeverConflict;
+    impl InvalidationPolicy for CopiesNeverConflict {
+        fn loan_effect(
+            &self,
+            access: Access,
+            loan: BorrowKind,
+            overlap: Overlap,
+        ) -> Option<LoanEffect> {
+            match access {
+                Access::Copy => None,
+                _ => DefaultPolicy.loan_effect(access, loan, overlap),
+            }
+        }
+    }
+
+    let program = crate::parse_ast(
+        "
+        let mut x: i32;
+        let r: &'r mut i32;
+        let y: i32;
+        bb0: {
+            r = &'L_x mut x;
+            y = copy x;
+        }
+    ",
+    )
+    .unwrap();
+    let options = Options {
+        simple_node_names: true,
+        ..Options::default()
+    };
+    let default = FactEmitter::from_program(program.clone(), options.clone()).unwrap();
+    let custom =
+        FactEmitter::from_program_with_policy(program, options, &CopiesNeverConflict).unwrap();
+    assert!(default
+        .invalidate_origin
+        .contains(&("'L_x".to_string(), "b".to_string())));
+    assert!(!custom
+        .invalidate_origin
+        .contains(&("'L_x".to_string(), "b".to_string())));
+}
+
+#[test]
+fn struct_outlives_bounds() {
+    // The origins in the type substituted for `T` must outlive the origin substituted for
+    // `'a`, wherever a `Wrapper` is stored.
+    let trace = trace(
+        "
+        struct Wrapper<'a, T: 'a> { r: &'a T }
+        fn wrap<'b, U>(r: &'b U) -> Wrapper<'b, U>;
+        let x: &'x i32;
+        let w: Wrapper<'w, &'r i32>;
+        let m: Wrapper<'m1, Wrapper<'m2, &'m3 i32>>;
+        let n: Wrapper<'n1, Wrapper<'n2, &'n3 i32>>;
+        bb0: {
+            w = wrap(&'L_x x);
+            n = move m;
+        }
+    ",
+    );
+    insta::assert_snapshot!(trace.relation("introduce_subset").to_string(), @r###"
+    introduce_subset('L_x, 'wrap0_b) @ a <- `'L_x` and `'wrap0_b` are related covariantly
+    introduce_subset('wrap0_b, 'w) @ a <- `'wrap0_b` and `'w` are related covariantly
+    introduce_subset('x, 'r) @ a <- `'x` and `'r` are related covariantly
+    introduce_subset('r, 'w) @ a <- `Wrapper<'w, &'r i32>` requires `'r: 'w`
+    introduce_subset('m1, 'n1) @ b <- `'m1` and `'n1` are related covariantly
+    introduce_subset('m2, 'n2) @ b <- `'m2` and `'n2` are related covariantly
+    introduce_subset('m3, 'n3) @ b <- `'m3` and `'n3` are related covariantly
+    introduce_subset('n2, 'n1) @ b <- `Wrapper<'n1, Wrapper<'n2, &'n3 i32>>` requires `'n2: 'n1`
+    introduce_subset('n3, 'n1) @ b <- `Wrapper<'n1, Wrapper<'n2, &'n3 i32>>` requires `'n3: 'n1`
+    introduce_subset('n3, 'n2) @ b <- `Wrapper<'n2, &'n3 i32>` requires `'n3: 'n2`
+
+    "###);
+}
+
+#[test]
+fn explicit_liveness() {
+    // `p` is live until `r` is read from it, so both of its origins are, and overwriting `r`
+    // at the end ends its liveness.
+    let options = Options {
+        simple_node_names: true,
+        liveness: Liveness::Explicit,
+        ..Options::default()
+    };
+    let (_, trace) = emit_trace(
+        "
+        struct Pair<'a, 'b> { first: &'a i32, second: &'b i32 }
+        let x: i32;
+        let mut p: Pair<'first, 'second>;
+        let mut r: &'r i32;
+        bb0: {
+            p.first = &'L_x x;
+            r = copy p.first;
+            r = copy r;
+            r = &'L_x2 x;
+        }
+    ",
+        options,
+    )
+    .unwrap();
+    insta::assert_snapshot!(trace.relation("origin_live_on_entry").to_string(), @r###"
+    origin_live_on_entry('second) @ a <- `p` is live
+    origin_live_on_entry('first) @ a <- `p` is live
+    origin_live_on_entry('second) @ b <- `p` is live
+    origin_live_on_entry('first) @ b <- `p` is live
+    origin_live_on_entry('r) @ c <- `r` is live
+
+    "###);
+}
+
+#[test]
+fn incompatible_loans() {
+    // Shared loans are compatible with each other, and disjoint fields don't overlap.
+    let facts = emit(
+        "
+        struct Pair { first: i32, second: i32 }
+        let mut p: Pair;
+        let a: &'a i32;
+        let b: &'b i32;
+        let c: &'c mut i32;
+        let d: &'d mut Pair;
+        bb0: {
+            a = &'L_first p.first;
+            b = &'L_first2 p.first;
+            c = &'L_second mut p.second;
+            d = &'L_p mut p;
+        }
+    ",
+    );
+    insta::assert_snapshot!(facts, @r###"
+    // polonius-frontend v2
+    a: "a = &'L_first p.first" {
+        invalidate_origin('L_p)
+        write_origin('a)
+        clear_origin('a)
+        clear_origin('L_first)
+        introduce_subset('L_first, 'a)
+        goto b
+    }
+
+    b: "b = &'L_first2 p.first" {
+        invalidate_origin('L_p)
+        write_origin('b)
+        clear_origin('b)
+        clear_origin('L_first2)
+        introduce_subset('L_first2, 'b)
+        goto c
+    }
+
+    c: "c = &'L_second mut p.second" {
+        invalidate_origin('L_second)
+        invalidate_origin('L_p)
+        write_origin('c)
+        clear_origin('c)
+        clear_origin('L_second)
+        introduce_subset('L_second, 'c)
+        goto d
+    }
+
+    d: "d = &'L_p mut p" {
+        invalidate_origin('L_first)
+        invalidate_origin('L_first2)
+        invalidate_origin('L_second)
+        invalidate_origin('L_p)
+        write_origin('d)
+        clear_origin('d)
+        clear_origin('L_p)
+        introduce_subset('L_p, 'd)
+        incompatible_loans('L_first, 'L_p)
+        incompatible_loans('L_first2, 'L_p)
+        incompatible_loans('L_second, 'L_p)
+        goto e
+    }
+
+    e: "(storage dead)" {
+        invalidate_origin('L_first)
+        invalidate_origin('L_first2)
+        invalidate_origin('L_second)
+        invalidate_origin('L_p)
+        node_is_exit()
+        goto
+    }
+
+    "###);
+}
+
+#[test]
+fn exit_node() {
+    // Both blocks without successors flow into the single exit node, where the locals die.
+    let facts = emit(
+        "
+        let mut x: i32;
+        let mut r: &'r i32;
+        bb0: {
+            r = &'L_x x;
+            goto bb1, bb2;
+        }
+        bb1: {
+            x = 1;
+        }
+        bb2: {
+            x = 2;
+        }
+    ",
+    );
+    insta::assert_snapshot!(facts, @r###"
+    // polonius-frontend v2
+    a: "r = &'L_x x" {
+        write_origin('r)
+        clear_origin('r)
+        clear_origin('L_x)
+        introduce_subset('L_x, 'r)
+        goto b c
+    }
+
+    b: "x = 1" {
+        invalidate_origin('L_x)
+        goto d
+    }
+
+    c: "x = 2" {
+        invalidate_origin('L_x)
+        goto d
+    }
+
+    d: "(storage dead)" {
+        invalidate_origin('L_x)
+        node_is_exit()
+        goto
+    }
+
+    "###);
+}
+
+#[test]
+fn field_write_invalidates_overlapping_loans() {
+    // Writing `x.f` invalidates the loans of `x.f`, of the places containing it and of the
+    // places it contains, but not of its sibling `x.g`.
+    let (_, trace) = emit_trace(
+        "
+        struct Inner { g: i32, h: i32 }
+        struct Outer { f: Inner, g: i32 }
+        let mut x: Outer;
+        let i: Inner;
+        let a: &'a Outer;
+        let b: &'b Inner;
+        let c: &'c i32;
+        let d: &'d i32;
+        bb0: {
+            a = &'L_x x;
+            b = &'L_xf x.f;
+            c = &'L_xfg x.f.g;
+            d = &'L_xg x.g;
+            x.f = move i;
+        }
+    ",
+        Options {
+            simple_node_names: true,
+            ..Options::default()
+        },
+    )
+    .unwrap();
+    insta::assert_snapshot!(trace.at("e").to_string(), @r###"
+    invalidate_origin('L_x) @ e <- write to `x.f` overwrites the data of loan `&'L_x x`
+    invalidate_origin('L_xf) @ e <- write to `x.f` overwrites the data of loan `&'L_xf x.f`
+    invalidate_origin('L_xfg) @ e <- write to `x.f` overwrites the data of loan `&'L_xfg x.f.g`
+
+    "###);
+}
+
+#[test]
+fn statement_facts() {
+    let program = crate::parse_ast(
+        "
+        fn use<'a>(r: &'a i32) -> ();
+        let mut x: i32;
+        let mut p: &'p i32;
+        bb0: {
+            p = &'L_x x;
+            goto bb1;
+        }
+        bb1: {
+            x = 1;
+            assert_error;
+            use(copy p);
+        }
+    ",
+    )
+    .unwrap();
+    let options = Options {
+        simple_node_names: true,
+        ..Options::default()
+    };
+    let mut emitter = FactEmitter::for_statements(&program, &options).unwrap();
+
+    // The loan of `bb0` is known when emitting a statement of `bb1`.
+    let facts = emitter.emit_statement_facts("bb1", 0).unwrap();
+    insta::assert_snapshot!(facts.to_string(), @r###"
+    // polonius-frontend v2
+    b: "x = 1" {
+        invalidate_origin('L_x)
+        goto
+    }
+
+    "###);
+    let facts = emitter.emit_statement_facts("bb1", 1).unwrap();
+    insta::assert_snapshot!(facts.to_string(), @r###"
+    // polonius-frontend v2
+    c: "use(copy p)" {
+        access_origin('p)
+        introduce_subset('p, 'use0_a)
+        goto
+    }
+
+    "###);
+
+    let err = emitter.emit_statement_facts("bb1", 2).unwrap_err();
+    insta::assert_snapshot!(format!("{:#}", err), @"`bb1` has 2 statements, there is no statement 2");
+    let err = emitter.emit_statement_facts("bb2", 0).unwrap_err();
+    insta::assert_snapshot!(format!("{:#}", err), @"no block named `bb2`");
+}
+
+#[test]
+fn pass_statements() {
+    // Placeholders keep the simple names of the later nodes, like in a fact file where `b` was
+    // removed.
+    let facts = emit(
+        "
+        let mut x: i32;
+        bb0: {
+            x = 1;
+            pass;
+            x = 2;
+        }
+    ",
+    );
+    insta::assert_snapshot!(facts, @r###"
+    // polonius-frontend v2
+    a: "x = 1" {
+        goto b
+    }
+
+    b: "(pass)" {
+        goto c
+    }
+
+    c: "x = 2" {
+        goto d
+    }
+
+    d: "(storage dead)" {
+        node_is_exit()
+        goto
+    }
+
+    "###);
+}
+
+#[test]
+fn type_aliases() {
+    // The alias is expanded with its generics substituted, including in the signature of
+    // `next`, and `Ref` is expanded within `IterRef`.
+    let facts = emit(
+        "
+        struct Iter<'a, T> { item: &'a T }
+        type Ref<'a, T> = &'a T;
+        type IterRef<'a, T> = &'a mut Iter<'a, Ref<'a, T>>;
+        fn next<'a, T>(it: IterRef<'a, T>) -> Ref<'a, T>;
+        let mut it: Iter<'i, &'x i32>;
+        let item: &'item i32;
+        bb0: {
+            item = next(&'L_it mut it);
+        }
+    ",
+    );
+    insta::assert_snapshot!(facts, @r###"
+    // polonius-frontend v2
+    a: "item = next(&'L_it mut it)" {
+        access_origin('i)
+        access_origin('x)
+        invalidate_origin('L_it)
+        write_origin('item)
+        clear_origin('item)
+        clear_origin('L_it)
+        introduce_subset('L_it, 'next0_a)
+        introduce_subset('i, 'next0_a)
+        introduce_subset('next0_a, 'i)
+        introduce_subset('x, 'next0_a)
+        introduce_subset('next0_a, 'x)
+        introduce_subset('next0_a, 'item)
+        goto b
+    }
+
+    b: "(storage dead)" {
+        invalidate_origin('L_it)
+        node_is_exit()
+        goto
+    }
+
+    "###);
+}
+
+#[test]
+fn type_alias_errors() {
+    let errors: Vec<_> = [
+        "type A = B; type B = A; let x: A; bb0: {}",
+        "type R<'a> = &'a &'b i32; let x: R<'x>; bb0: {}",
+        "type R<'a> = &'a i32; let x: R; bb0: {}",
+        "type R<T: 'a> = T; bb0: {}",
+        "struct S {} type S = i32; bb0: {}",
+        "type A = i32; type A<'a> = &'a i32; bb0: {}",
+    ]
+    .iter()
+    .map(|program| {
+        let err = emit_facts(program, Options::default()).unwrap_err();
+        format!("{:#}", err)
+    })
+    .collect();
+    insta::assert_debug_snapshot!(errors, @r###"
+    [
+        "the type alias `A` is recursive",
+        "the type alias `R` names the undeclared origin `'b`",
+        "in a use of the type alias `R`: expected 1 generic parameters, found 0",
+        "bounds on the type parameters of type aliases are not supported, as on `T` of `R`",
+        "`S` is declared both as a type and as a type alias",
+        "the type alias `A` is declared twice, as `type A = i32;` and as `type A<'a> = &'a i32;`",
+    ]
+    "###);
+}
+
+#[test]
+fn extern_and_pure_fns() {
+    // Only the call to the extern function accesses the origins of its arguments which aren't
+    // accessed by evaluating them, like the result of `id`. The calls to pure and unmarked
+    // functions have the same facts.
+    let facts = emit(
+        "
+        fn id<'a>(r: &'a i32) -> &'a i32;
+        extern fn opaque<'a>(r: &'a i32) -> ();
+        pure fn known<'a>(r: &'a i32) -> ();
+        fn unmarked<'a>(r: &'a i32) -> ();
+        let x: i32;
+        bb0: {
+            opaque(id(&'L_x x));
+            known(id(&'L_x2 x));
+            unmarked(id(&'L_x3 x));
+        }
+    ",
+    );
+    insta::assert_snapshot!(facts, @r###"
+    // polonius-frontend v2
+    a: "opaque(id(&'L_x x))" {
+        access_origin('id0_a)
+        clear_origin('L_x)
+        introduce_subset('L_x, 'id0_a)
+        introduce_subset('id0_a, 'opaque1_a)
+        goto b
+    }
+
+    b: "known(id(&'L_x2 x))" {
+        clear_origin('L_x2)
+        introduce_subset('L_x2, 'id2_a)
+        introduce_subset('id2_a, 'known3_a)
+        goto c
+    }
+
+    c: "unmarked(id(&'L_x3 x))" {
+        clear_origin('L_x3)
+        introduce_subset('L_x3, 'id4_a)
+        introduce_subset('id4_a, 'unmarked5_a)
+        goto d
+    }
+
+    d: "(storage dead)" {
+        invalidate_origin('L_x)
+        invalidate_origin('L_x2)
+        invalidate_origin('L_x3)
+        node_is_exit()
+        goto
+    }
+
+    "###);
+}
+
+#[test]
+fn effects_of_a_statement_are_ordered() {
+    // `y` is read before the loans of `x` are invalidated and `x` is overwritten, and the
+    // value is stored last.
+    let trace = trace(
+        "
+        let mut x: &'x i32;
+        let y: &'y i32;
+        let r: &'r &'x i32;
+        bb0: {
+            r = &'L_x x;
+            x = copy y;
+        }
+    ",
+    );
+    insta::assert_snapshot!(trace.at("b").to_string(), @r###"
+    access_origin('y) @ b <- read of `y`
+    invalidate_origin('L_x) @ b <- write to `x` overwrites the data of loan `&'L_x x`
+    write_origin('x) @ b <- `x` is defined
+    clear_origin('x) @ b <- `x` is overwritten
+    introduce_subset('y, 'x) @ b <- `'y` and `'x` are related covariantly
+
+    "###);
+}
+
+#[test]
+fn write_to_place_borrowed_by_the_same_statement() {
+    let err = emit_facts(
+        "
+        struct S<'a> { r: &'a S<'a> }
+        let mut s: S<'s>;
+        bb0: {
+            s.r = &'L_s s;
+        }
+    ",
+        Options::default(),
+    )
+    .unwrap_err();
+    insta::assert_snapshot!(format!("{:#}", err), @"in statement `s.r = &'L_s s` of `bb0`: cannot assign to `s.r`, as it is borrowed by the loan `&'L_s s` of the same statement");
+
+    // Borrowing a disjoint field of the place written is fine.
+    emit_facts(
+        "
+        struct P<'a> { r: &'a i32, v: i32 }
+        let mut p: P<'p>;
+        bb0: {
+            p.r = &'L_pv p.v;
+        }
+    ",
+        Options::default(),
+    )
+    .unwrap();
+}
+
+#[test]
+fn auto_deref_field_projections() {
+    // `r.f` is `(*r).f`: reading or writing it goes through the reference, so it accesses
+    // `'r`, and borrowing it reborrows through `'r`.
+    let (_, trace) = emit_trace(
+        "
+        struct S { f: i32 }
+        let mut s: S;
+        let r: &'r mut S;
+        let x: i32;
+        let p: &'p i32;
+        bb0: {
+            r = &'L_s mut s;
+            x = copy r.f;
+            r.f = 1;
+            p = &'L_rf r.f;
+        }
+    ",
+        Options {
+            simple_node_names: true,
+            ..Options::default()
+        },
+    )
+    .unwrap();
+    insta::assert_snapshot!(trace.at("b").to_string(), @r###"
+    access_origin('r) @ b <- `r.*.f` goes through a reference
+
+    "###);
+    insta::assert_snapshot!(trace.at("c").to_string(), @r###"
+    access_origin('r) @ c <- `r.*.f` goes through a reference
+    invalidate_origin('L_rf) @ c <- write to `r.*.f` overwrites the data of loan `&'L_rf r.*.f`
+
+    "###);
+    insta::assert_snapshot!(trace.at("d").to_string(), @r###"
+    access_origin('r) @ d <- `r.*.f` goes through a reference
+    introduce_subset('r, 'L_rf) @ d <- loan of `r.*.f` is reached through a `&'r mut` reference
+    write_origin('p) @ d <- `p` is defined
+    clear_origin('p) @ d <- `p` is overwritten
+    introduce_subset('L_rf, 'p) @ d <- `'L_rf` and `'p` are related covariantly
+    clear_origin('L_rf) @ d <- loan `&'L_rf r.*.f` is issued
+
+    "###);
+}
+
+#[test]
+fn labeled_successors() {
+    let facts = emit(
+        "
+        let x: i32;
+        bb0: {
+            x = 1;
+            goto [then] bb1, [else] bb2;
+        }
+        bb1: {
+            goto bb2;
+        }
+        bb2: {
+        }
+    ",
+    );
+    insta::assert_snapshot!(facts, @r###"
+    // polonius-frontend v2
+    a: "x = 1" {
+        goto [then] b [else] c
+    }
+
+    b: "(pass)" {
+        goto c
+    }
+
+    c: "(pass)" {
+        goto d
+    }
+
+    d: "(storage dead)" {
+        node_is_exit()
+        goto
+    }
+
+    "###);
+}
+
+#[test]
+fn builtin_enum_partial_moves() {
+    // Moving the payload of one variant leaves the loans of the other alone, and reading the
+    // variant only conflicts with the loans of the whole value.
+    let facts = emit(
+        "
+        let mut res: Result<i32, i32>;
+        let r: &'r i32;
+        let p: &'p mut Result<i32, i32>;
+        let v: i32;
+        bb0: {
+            res = Ok(1);
+            r = &'L_err res.Err;
+            v = move res.Ok;
+            match res { Ok => bb1, Err => bb2 }
+        }
+        bb1: {
+            p = &'L_res mut res;
+            match res { Ok => bb2, Err => bb2 }
+        }
+        bb2: {
+        }
+    ",
+    );
+    insta::assert_snapshot!(facts, @r###"
+    // polonius-frontend v2
+    a: "res = Ok(1)" {
+        invalidate_origin('L_err)
+        invalidate_origin('L_res)
+        goto b
+    }
+
+    b: "r = &'L_err res.Err" {
+        invalidate_origin('L_res)
+        write_origin('r)
+        clear_origin('r)
+        clear_origin('L_err)
+        introduce_subset('L_err, 'r)
+        goto c
+    }
+
+    c: "v = move res.Ok" {
+        invalidate_origin('L_res)
+        goto d
+    }
+
+    d: "match res" {
+        invalidate_origin('L_res)
+        goto [Ok] e [Err] g
+    }
+
+    e: "p = &'L_res mut res" {
+        invalidate_origin('L_err)
+        invalidate_origin('L_res)
+        write_origin('p)
+        clear_origin('p)
+        clear_origin('L_res)
+        introduce_subset('L_res, 'p)
+        incompatible_loans('L_err, 'L_res)
+        goto f
+    }
+
+    f: "match res" {
+        invalidate_origin('L_res)
+        goto [Ok] g [Err] g
+    }
+
+    g: "(pass)" {
+        goto h
+    }
+
+    h: "(storage dead)" {
+        invalidate_origin('L_err)
+        invalidate_origin('L_res)
+        node_is_exit()
+        goto
+    }
+
+    "###);
+}
+
+#[test]
+fn origin_arguments_of_fields() {
+    // The origin arguments of struct types are substituted in the types of their fields, at
+    // any position, and through nested structs.
+    let facts = emit(
+        "
+        struct Vec<T> { item: T }
+        struct Iter<T, 'a> { vec: &'a Vec<T> }
+        struct Wrap<'b> { it: Iter<i32, 'b> }
+        let v: Vec<i32>;
+        let mut w: Wrap<'w>;
+        let r: &'r Vec<i32>;
+        bb0: {
+            w.it.vec = &'L_v v;
+            r = copy w.it.vec;
+        }
+    ",
+    );
+    insta::assert_snapshot!(facts, @r###"
+    // polonius-frontend v2
+    a: "w.it.vec = &'L_v v" {
+        clear_origin('L_v)
+        introduce_subset('L_v, 'w)
+        goto b
+    }
+
+    b: "r = copy w.it.vec" {
+        access_origin('w)
+        write_origin('r)
+        clear_origin('r)
+        introduce_subset('w, 'r)
+        goto c
+    }
+
+    c: "(storage dead)" {
+        invalidate_origin('L_v)
+        node_is_exit()
+        goto
+    }
+
+    "###);
+}
+
+#[test]
+fn outlives_statements() {
+    // The subsets are introduced at the node of the next statement, before its own facts,
+    // without any assignment relating the origins.
+    let facts = emit(
+        "
+        let mut x: i32;
+        let r: &'r i32;
+        bb0: {
+            r = &'L_x x;
+            'r: 'a;
+            'a: 'b;
+            x = 1;
+        }
+    ",
+    );
+    insta::assert_snapshot!(facts, @r###"
+    // polonius-frontend v2
+    a: "r = &'L_x x" {
+        write_origin('r)
+        clear_origin('r)
+        clear_origin('L_x)
+        introduce_subset('L_x, 'r)
+        goto b
+    }
+
+    b: "x = 1" {
+        invalidate_origin('L_x)
+        introduce_subset('r, 'a)
+        introduce_subset('a, 'b)
+        goto c
+    }
+
+    c: "(storage dead)" {
+        invalidate_origin('L_x)
+        node_is_exit()
+        goto
+    }
+
+    "###);
+
+    let err = emit_facts(
+        "
+        let x: i32;
+        bb0: {
+            x = 1;
+            'a: 'b;
+        }
+    ",
+        Options::default(),
+    )
+    .unwrap_err();
+    insta::assert_snapshot!(format!("{:#}", err), @"`'a: 'b` must be followed by a statement of `bb0`, where it is introduced");
+}
+
+#[test]
+fn emission_is_deterministic() {
+    // Each emission uses fresh hash maps, with different seeds, so emitting the same program
+    // repeatedly would expose an order depending on the iteration of one of them.
+    let input = "
+        struct Pair<'a, 'b> { fst: &'a i32, snd: &'b mut i32 }
+        fn pick<'a, 'b>(x: &'a i32, y: &'b i32) -> &'a i32;
+        let mut x: i32;
+        let mut y: i32;
+        let mut p: Pair<'p1, 'p2>;
+        let mut r: &'r i32;
+        bb0: {
+            p.fst = &'L_x x;
+            p.snd = &'L_y mut y;
+            r = pick(copy p.fst, &'L_y2 y);
+            goto bb1, bb2;
+        }
+        bb1: {
+            x = 1;
+            goto bb3;
+        }
+        bb2: {
+            y = 2;
+            goto bb3;
+        }
+        bb3: {
+            r = copy r;
+        }
+    ";
+    let options = Options {
+        liveness: Liveness::Explicit,
+        ..Options::default()
+    };
+    let emit = || {
+        let (facts, trace) = emit_trace(input, options.clone()).unwrap();
+        (facts.to_string(), trace.to_string())
+    };
+    let first = emit();
+    for _ in 0..20 {
+        assert_eq!(emit(), first);
+    }
+}
+
+#[test]
+fn external_origins() {
+    // `'p` comes from the caller, which may still use it after the program returns, so the
+    // loan flowing into it must not be invalidated before the exit.
+    let input = "
+        let mut x: i32;
+        let p: &'p i32;
+        let r: &'r i32;
+        bb0: {
+            r = &'L_x x;
+            p = copy r;
+            x = 1;
+        }
+    ";
+    let options = Options {
+        simple_node_names: true,
+        liveness: Liveness::Explicit,
+        external_origins: vec!["'p".to_string()],
+        ..Options::default()
+    };
+    let (facts, trace) = emit_trace(input, options.clone()).unwrap();
+    insta::assert_snapshot!(facts.to_string(), @r###"
+    // polonius-frontend v2, relations: liveness
+    a: "r = &'L_x x" {
+        write_origin('r)
+        clear_origin('r)
+        clear_origin('L_x)
+        origin_live_on_entry('p)
+        introduce_subset('L_x, 'r)
+        goto b
+    }
+
+    b: "p = copy r" {
+        access_origin('r)
+        write_origin('p)
+        clear_origin('p)
+        origin_live_on_entry('r)
+        origin_live_on_entry('p)
+        introduce_subset('r, 'p)
+        goto c
+    }
+
+    c: "x = 1" {
+        invalidate_origin('L_x)
+        origin_live_on_entry('p)
+        goto d
+    }
+
+    d: "(storage dead)" {
+        access_origin('p)
+        invalidate_origin('L_x)
+        origin_live_on_entry('p)
+        node_is_exit()
+        goto
+    }
+
+    "###);
+    insta::assert_snapshot!(trace.origin("'p").at("d").to_string(), @r###"
+    access_origin('p) @ d <- `'p` is external, and may be used by the caller
+    origin_live_on_entry('p) @ d <- `'p` is external
+
+    "###);
+
+    let err = emit_facts(
+        input,
+        Options {
+            external_origins: vec!["'a".to_string()],
+            ..options
+        },
+    )
+    .unwrap_err();
+    insta::assert_snapshot!(format!("{:#}", err), @"the external origin `'a` isn't in the type of any variable");
+}
+
+#[test]
+fn loans_through_shared_references() {
+    // Only the loans reached through a shared reference, at any depth, are marked.
+    let trace = trace(
+        "
+        let mut x: i32;
+        let mut m: &'m mut i32;
+        let mut s: &'s i32;
+        let ms: &'ms mut &'i i32;
+        let a: &'a i32;
+        let b: &'b i32;
+        let c: &'c i32;
+        bb0: {
+            m = &'L_x mut x;
+            a = &'L_*m *m;
+            s = &'L_x2 x;
+            b = &'L_*s *s;
+            ms = &'L_s mut s;
+            c = &'L_**ms **ms;
+        }
+    ",
+    );
+    insta::assert_snapshot!(trace.relation("loan_through_shared").to_string(), @r###"
+    loan_through_shared('L_*s) @ d <- `*s` is reached through the `&'s` reference
+    loan_through_shared('L_**ms) @ f <- `**ms` is reached through the `&'i` reference
+
+    "###);
+}
+
+#[test]
+fn switch_reads_its_integer() {
+    // Unlike the discriminant read of a `match`, a `switch` reads the whole place, which
+    // invalidates the mutable loan used in an arm.
+    let input = "
+        let mut x: i32;
+        let r: &'r mut i32;
+        bb0: {
+            x = 0;
+            r = &'L_x mut x;
+            switch x { 0 => bb1, _ => bb2 }
+        }
+        bb1: {
+            *r = 1;
+        }
+        bb2: {
+        }
+    ";
+    insta::assert_snapshot!(emit(input), @r###"
+    // polonius-frontend v2
+    a: "x = 0" {
+        invalidate_origin('L_x)
+        goto b
+    }
+
+    b: "r = &'L_x mut x" {
+        invalidate_origin('L_x)
+        write_origin('r)
+        clear_origin('r)
+        clear_origin('L_x)
+        introduce_subset('L_x, 'r)
+        goto c
+    }
+
+    c: "switch x" {
+        invalidate_origin('L_x)
+        goto [0] d e
+    }
+
+    d: "*r = 1" {
+        access_origin('r)
+        goto f
+    }
+
+    e: "(pass)" {
+        goto f
+    }
+
+    f: "(storage dead)" {
+        invalidate_origin('L_x)
+        node_is_exit()
+        goto
+    }
+
+    "###);
+}
+
+#[test]
+fn if_let_borrows_in_the_taken_arm() {
+    // The payload is only borrowed in the `Some` arm: assigning `o` is an error there, while
+    // the loan doesn't exist on the `None` path.
+    let input = "
+        let mut o: Option<i32>;
+        let mut n: i32;
+        bb0: {
+            o = Some(1);
+            if let Some(x) = &'L_o mut o { goto bb1; } else { goto bb2; }
+        }
+        bb1: {
+            o = None;
+            n = copy *x;
+            goto bb3;
+        }
+        bb2: {
+            o = Some(2);
+            goto bb3;
+        }
+        bb3: {
+        }
+    ";
+    insta::assert_snapshot!(emit(input), @r###"
+    // polonius-frontend v2
+    a: "o = Some(1)" {
+        invalidate_origin('L_o)
+        goto b
+    }
+
+    b: "match o" {
+        goto [Some] c [None] f
+    }
+
+    c: "x = &'L_o mut o.Some" {
+        invalidate_origin('L_o)
+        write_origin('x)
+        clear_origin('x)
+        clear_origin('L_o)
+        introduce_subset('L_o, 'x)
+        goto d
+    }
+
+    d: "o = None" {
+        invalidate_origin('L_o)
+        goto e
+    }
+
+    e: "n = copy *x" {
+        access_origin('x)
+        goto g
+    }
+
+    f: "o = Some(2)" {
+        invalidate_origin('L_o)
+        goto g
+    }
+
+    g: "(pass)" {
+        goto h
+    }
+
+    h: "(storage dead)" {
+        invalidate_origin('L_o)
+        node_is_exit()
+        goto
+    }
+
+    "###);
+
+    let options = Options {
+        simple_node_names: true,
+        ..Options::default()
+    };
+    let facts = emit_facts(input, options).unwrap();
+    let output = crate::solver::check(&facts).unwrap();
+    insta::assert_debug_snapshot!(output.invalidated_origin_accessed, @r###"
+    {
+        (
+            "'x",
+            "e",
+        ),
+    }
+    "###);
+}
+
+#[test]
+fn origin_equalities() {
+    // `'y` and `'pi`, then `'pi` and `'qi`, are behind a `&mut`, so they must be equal.
+    let input = "
+        let mut x: i32;
+        let mut y: &'y i32;
+        let mut p: &'p mut &'pi i32;
+        let q: &'q mut &'qi i32;
+        let n: i32;
+        bb0: {
+            y = &'L_x x;
+            p = &'L_y mut y;
+            q = move p;
+            x = 1;
+            n = copy **q;
+        }
+    ";
+    let options = Options {
+        simple_node_names: true,
+        origin_equalities: true,
+        ..Options::default()
+    };
+    let facts = emit_facts(input, options.clone()).unwrap();
+    insta::assert_snapshot!(facts.to_string(), @r###"
+    // polonius-frontend v2, relations: equalities
+    a: "y = &'L_x x" {
+        invalidate_origin('L_y)
+        write_origin('y)
+        clear_origin('y)
+        clear_origin('L_x)
+        introduce_subset('L_x, 'y)
+        goto b
+    }
+
+    b: "p = &'L_y mut y" {
+        access_origin('y)
+        invalidate_origin('L_y)
+        write_origin('p)
+        write_origin('pi)
+        clear_origin('p)
+        clear_origin('pi)
+        clear_origin('L_y)
+        introduce_subset('L_y, 'p)
+        origin_equal('y, 'pi)
+        goto c
+    }
+
+    c: "q = move p" {
+        access_origin('p)
+        access_origin('pi)
+        write_origin('q)
+        write_origin('qi)
+        clear_origin('q)
+        clear_origin('qi)
+        introduce_subset('p, 'q)
+        origin_equal('pi, 'qi)
+        goto d
+    }
+
+    d: "x = 1" {
+        invalidate_origin('L_x)
+        goto e
+    }
+
+    e: "n = copy **q" {
+        access_origin('q)
+        access_origin('qi)
+        goto f
+    }
+
+    f: "(storage dead)" {
+        invalidate_origin('L_x)
+        invalidate_origin('L_y)
+        node_is_exit()
+        goto
+    }
+
+    "###);
+
+    // The solvers treat an equality as a subset in each direction.
+    let subsets = emit_facts(
+        input,
+        Options {
+            origin_equalities: false,
+            ..options
+        },
+    )
+    .unwrap();
+    assert!(subsets.origin_equal.is_empty());
+    let output = crate::solver::check(&facts).unwrap();
+    assert_eq!(output, crate::solver::check(&subsets).unwrap());
+    assert_eq!(output.invalidated_origin_accessed.len(), 1);
+}
+
+#[test]
+fn struct_update_partial_moves() {
+    // The base is only moved out of for the fields not given: the loan of `z.g` is invalidated
+    // by the move, while the loan of `z.f` is left alone. The origins of `y` and of `z.g` both
+    // flow into the literal, and then into `x`.
+    let facts = emit(
+        "
+        struct S<'a> { f: &'a i32, g: &'a i32 }
+        let y: &'y i32;
+        let mut z: S<'z>;
+        let x: S<'x>;
+        let rf: &'rf &'zf i32;
+        let rg: &'rg &'zg i32;
+        bb0: {
+            rf = &'L_f z.f;
+            rg = &'L_g z.g;
+            x = S { f: move y, ..move z };
+        }
+    ",
+    );
+    insta::assert_snapshot!(facts, @r###"
+    // polonius-frontend v2
+    a: "rf = &'L_f z.f" {
+        access_origin('z)
+        write_origin('rf)
+        write_origin('zf)
+        clear_origin('rf)
+        clear_origin('zf)
+        clear_origin('L_f)
+        introduce_subset('L_f, 'rf)
+        introduce_subset('z, 'zf)
+        goto b
+    }
+
+    b: "rg = &'L_g z.g" {
+        access_origin('z)
+        write_origin('rg)
+        write_origin('zg)
+        clear_origin('rg)
+        clear_origin('zg)
+        clear_origin('L_g)
+        introduce_subset('L_g, 'rg)
+        introduce_subset('z, 'zg)
+        goto c
+    }
+
+    c: "x = S { f: move y, ..move z }" {
+        access_origin('y)
+        access_origin('z)
+        invalidate_origin('L_g)
+        write_origin('x)
+        clear_origin('x)
+        introduce_subset('y, 'S0_a)
+        introduce_subset('z, 'S0_a)
+        introduce_subset('S0_a, 'x)
+        goto d
+    }
+
+    d: "(storage dead)" {
+        invalidate_origin('L_f)
+        invalidate_origin('L_g)
+        node_is_exit()
+        goto
+    }
+
+    "###);
+}
+
+#[test]
+fn origin_kinds() {
+    // The loans and the origins instantiated by the call and the struct literal are created by
+    // the emitter, and the external `'p` is a placeholder, whatever their names.
+    let options = Options {
+        simple_node_names: true,
+        external_origins: vec!["'p".to_string()],
+        ..Options::default()
+    };
+    let facts = emit_facts(
+        "
+        struct S<'a> { r: &'a i32 }
+        fn id<'a>(r: &'a i32) -> &'a i32;
+        let x: i32;
+        let p: &'p i32;
+        let r: &'r i32;
+        let s: S<'s>;
+        bb0: {
+            r = id(&'L_x x);
+            s = S { r: copy p };
+        }
+    ",
+        options,
+    )
+    .unwrap();
+    let kinds: Vec<_> = facts
+        .origin_kinds()
+        .map(|(origin, kind)| format!("{} {}", origin, kind.name()))
+        .collect();
+    insta::assert_debug_snapshot!(kinds, @r###"
+    [
+        "'L_x loan_fresh",
+        "'S1_a call_instance",
+        "'id0_a call_instance",
+        "'p placeholder",
+        "'r user_named",
+        "'s user_named",
+    ]
+    "###);
+
+    let writer = crate::facts::FrontendWriter {
+        include_origin_kinds: true,
+        ..crate::facts::FrontendWriter::default()
+    };
+    assert_eq!(
+        crate::parse_fact_program(&writer.to_string(&facts)).unwrap(),
+        facts
+    );
+}
+
+#[test]
+fn move_paths() {
+    let options = Options {
+        simple_node_names: true,
+        move_paths: true,
+        ..Options::default()
+    };
+    let facts = emit_facts(
+        "
+        struct Pair { a: i32, b: i32 }
+        let mut x: i32;
+        let mut s: Pair;
+        let mut p: &'p Pair;
+        bb0: {
+            s = Pair { a: 1, b: 2 };
+            s.a = 3;
+            p = &'L_s s;
+            x = copy p.*.b;
+            s = move s;
+        }
+    ",
+        options,
+    )
+    .unwrap();
+    let text = crate::facts::FrontendWriter::default().to_string(&facts);
+    insta::assert_snapshot!(text, @r###"
+    // polonius-frontend v2, relations: move_paths
+    child_path(s.a, s)
+
+    a: "s = Pair { a: 1, b: 2 }" {
+        invalidate_origin('L_s)
+        path_assigned_at(s)
+        goto b
+    }
+
+    b: "s.a = 3" {
+        invalidate_origin('L_s)
+        path_assigned_at(s.a)
+        goto c
+    }
+
+    c: "p = &'L_s s" {
+        write_origin('p)
+        clear_origin('p)
+        clear_origin('L_s)
+        path_assigned_at(p)
+        path_accessed_at(s)
+        introduce_subset('L_s, 'p)
+        goto d
+    }
+
+    d: "x = copy p.*.b" {
+        access_origin('p)
+        path_assigned_at(x)
+        path_accessed_at(p)
+        goto e
+    }
+
+    e: "s = move s" {
+        invalidate_origin('L_s)
+        path_assigned_at(s)
+        path_accessed_at(s)
+        path_moved_at(s)
+        goto f
+    }
+
+    f: "(storage dead)" {
+        invalidate_origin('L_s)
+        node_is_exit()
+        goto
+    }
+
+    "###);
+    // The `child_path` facts are read back from before the nodes, unlike the `origin_kind`
+    // facts, which aren't printed.
+    let facts = crate::facts::Facts {
+        origin_kind: vec![],
+        ..facts
+    };
+    assert_eq!(crate::parse_fact_program(&text).unwrap(), facts);
+
+    // Without the option, none are emitted.
+    let facts = emit_facts("let x: i32; bb0: { x = 1; }", Options::default()).unwrap();
+    assert!(facts.path_assigned_at.is_empty());
+}
+
+#[test]
+fn refinements() {
+    // The `get_default` pattern: the payload is only borrowed in the `Some` arm of the
+    // `if let`, where `o` is known to be `Some`. The blocks reached from several arms, or from
+    // other blocks, and the `_` arm, refine nothing.
+    let input = "
+        let mut o: Option<i32>;
+        let mut n: i32;
+        bb0: {
+            o = Some(1);
+            if let Some(x) = &'L_o o { goto bb1; } else { goto bb2; }
+        }
+        bb1: {
+            n = copy *x;
+            goto bb4;
+        }
+        bb2: {
+            n = 0;
+            switch n { 0 => bb3, 1 => bb4, _ => bb5 }
+        }
+        bb3: {
+            match o { Some => bb4, None => bb5 }
+        }
+        bb4: {
+        }
+        bb5: {
+        }
+    ";
+    let options = Options {
+        simple_node_names: true,
+        refinements: true,
+        ..Options::default()
+    };
+    let facts = emit_facts(input, options).unwrap();
+    let refinements: Vec<_> = facts
+        .known_discriminants()
+        .map(|(place, value, node)| {
+            let text = facts.node_texts().find(|(_, n)| *n == node).unwrap().0;
+            format!("{} = {} in `{}`", place, value, text)
+        })
+        .collect();
+    insta::assert_debug_snapshot!(refinements, @r###"
+    [
+        "o = Some in `x = &'L_o o.Some`",
+        "o = None in `n = 0`",
+        "n = 0 in `match o`",
+    ]
+    "###);
+
+    let writer = crate::facts::FrontendWriter {
+        include_origin_kinds: true,
+        ..crate::facts::FrontendWriter::default()
+    };
+    let text = writer.to_string(&facts);
+    assert!(text.starts_with("// polonius-frontend v2, relations: origin_kinds, refinements\n"));
+    assert_eq!(crate::parse_fact_program(&text).unwrap(), facts);
+
+    let facts = emit_facts(input, Options::default()).unwrap();
+    assert!(facts.discriminant_known.is_empty());
+}
+
+#[test]
+fn function_bodies() {
+    // Each body is checked on its own: the call to `first` through its signature, and the
+    // bodies against their signatures, where `'a` is a placeholder.
+    let program = crate::parse_ast(
+        "
+        fn first<'a>(x: &'a i32, y: &'a i32) -> &'a i32 {
+            bb0: {
+                return = copy x;
+            }
+        }
+        fn dangling<'a>(x: &'a i32) -> &'a i32 {
+            let y: i32;
+            bb0: {
+                y = 1;
+                return = &'L_y y;
+            }
+        }
+        let mut v: i32;
+        let r: &'r i32;
+        bb0: {
+            r = first(&'L_v v, &'L_v v);
+        }
+    ",
+    )
+    .unwrap();
+    let options = Options {
+        simple_node_names: true,
+        ..Options::default()
+    };
+    let functions = FactEmitter::from_functions(&program, options.clone()).unwrap();
+    insta::assert_snapshot!(functions[0].1.to_string(), @r###"
+    // polonius-frontend v2
+    a: "return = copy x" {
+        access_origin('a)
+        write_origin('a)
+        clear_origin('a)
+        goto b
+    }
+
+    b: "(storage dead)" {
+        goto c
+    }
+
+    c: "(return)" {
+        access_origin('a)
+        node_is_exit()
+        goto
+    }
+
+    "###);
+
+    let mut bodies = vec![(
+        "main".to_string(),
+        FactEmitter::from_program(program, options).unwrap(),
+    )];
+    bodies.extend(functions);
+    let errors: Vec<_> = bodies
+        .iter()
+        .map(|(name, facts)| {
+            let output = crate::solver::check(facts).unwrap();
+            let errors = crate::results::Results::new(facts, output).errors();
+            format!(
+                "{}: {:?}",
+                name,
+                errors.iter().map(|e| e.to_string()).collect::<Vec<_>>()
+            )
+        })
+        .collect();
+    insta::assert_debug_snapshot!(errors, @r###"
+    [
+        "main: []",
+        "first: []",
+        "dangling: [\"origin `'a` references a local after it died, and is returned at `d`\"]",
+    ]
+    "###);
+}
+
+#[test]
+fn mut_reference_reassignment_kills_first_loan() {
+    // Once `p` is reassigned, the loan of `x` doesn't flow into `'p` anymore, even if `x` was
+    // written while `p` held it: writing `x` again and then through `p` is fine.
+    let program = "
+        let mut x: i32;
+        let mut y: i32;
+        let mut p: &'p mut i32;
+        bb0: {
+            x = 0;
+            y = 0;
+            p = &'L_x mut x;
+            x = 1;
+            p = &'L_y mut y;
+            x = 2;
+            *p = 3;
+        }
+    ";
+    let trace = trace(program);
+    insta::assert_snapshot!(trace.origin("'p").to_string(), @r###"
+    write_origin('p) @ c <- `p` is defined
+    clear_origin('p) @ c <- `p` is overwritten
+    introduce_subset('L_x, 'p) @ c <- `'L_x` and `'p` are related covariantly
+    write_origin('p) @ e <- `p` is defined
+    clear_origin('p) @ e <- `p` is overwritten
+    introduce_subset('L_y, 'p) @ e <- `'L_y` and `'p` are related covariantly
+    access_origin('p) @ g <- `*p` goes through a reference
+
+    "###);
+
+    let facts = emit_facts(program, Options::default()).unwrap();
+    let output = crate::solver::check(&facts).unwrap();
+    insta::assert_debug_snapshot!(output.invalidated_origin_accessed, @"{}");
+}
+
+#[test]
+fn mut_reference_reassignment_on_one_path() {
+    // `p` still holds the loan of `x` when `bb1`, which reassigns it, is skipped.
+    let facts = emit_facts(
+        "
+        let mut x: i32;
+        let mut y: i32;
+        let mut p: &'p mut i32;
+        bb0: {
+            x = 0;
+            y = 0;
+            p = &'L_x mut x;
+            goto bb1, bb2;
+        }
+        bb1: {
+            p = &'L_y mut y;
+            goto bb2;
+        }
+        bb2: {
+            x = 1;
+            *p = 2;
+        }
+    ",
+        Options::default(),
+    )
+    .unwrap();
+    let output = crate::solver::check(&facts).unwrap();
+    insta::assert_debug_snapshot!(output.invalidated_origin_accessed, @r###"
+    {
+        (
+            "'p",
+            "bb2[1]",
+        ),
+    }
+    "###);
+}
+
+#[test]
+fn drop_liveness() {
+    // The destructor of `Guard` may observe `'p_g`, so the loan of `x` is still in use when
+    // `p` is dropped, while `'p_r` is only needed until its last use.
+    let program = "
+        struct Guard<'a> { r: &'a i32 }
+        impl Drop for Guard;
+        struct Pair<'a, 'b> { g: Guard<'a>, r: &'b i32 }
+        let mut x: i32;
+        let mut y: i32;
+        let p: Pair<'p_g, 'p_r>;
+        bb0: {
+            p = Pair { g: Guard { r: &'L_x x }, r: &'L_y y };
+            x = 1;
+            y = 2;
+        }
+    ";
+    let facts = emit_facts(program, Options::default()).unwrap();
+    let drop_uses: Vec<_> = facts.drop_uses().collect();
+    insta::assert_debug_snapshot!(drop_uses, @r###"
+    [
+        (
+            "'p_g",
+            "exit[0]",
+        ),
+    ]
+    "###);
+
+    let output = crate::solver::check(&facts).unwrap();
+    insta::assert_debug_snapshot!(output.invalidated_origin_accessed, @r###"
+    {
+        (
+            "'p_g",
+            "exit[0]",
+        ),
+    }
+    "###);
+
+    // With explicit liveness, `p` is drop-live until its storage dies.
+    let options = Options {
+        liveness: Liveness::Explicit,
+        simple_node_names: true,
+        ..Options::default()
+    };
+    let (_, trace) = emit_trace(program, options).unwrap();
+    insta::assert_snapshot!(trace.relation("origin_live_on_entry").to_string(), @r###"
+    origin_live_on_entry('p_g) @ b <- `p` is drop-live
+    origin_live_on_entry('p_g) @ c <- `p` is drop-live
+    origin_live_on_entry('p_g) @ d <- `p` is drop-live
+
+    "###);
+}
+
+#[test]
+fn drop_statements() {
+    // Dropping `p.g` runs the destructor of `Guard`, which may observe `'p_g`, and invalidates
+    // the loans of `p.g` but not the ones of `p.r`.
+    let program = "
+        struct Guard<'a> { r: &'a i32 }
+        impl Drop for Guard;
+        struct Pair<'a, 'b> { g: Guard<'a>, r: &'b i32 }
+        let mut x: i32;
+        let p: Pair<'p_g, 'p_r>;
+        let q: &'q Guard<'q_g>;
+        let s: &'s &'s_r i32;
+        bb0: {
+            p = Pair { g: Guard { r: &'L_x x }, r: &'L_x x };
+            q = &'L_p_g p.g;
+            s = &'L_p_r p.r;
+            x = 1;
+            drop(p.g);
+            pass;
+        }
+    ";
+    let options = Options {
+        simple_node_names: true,
+        ..Options::default()
+    };
+    let (_, trace) = emit_trace(program, options).unwrap();
+    insta::assert_snapshot!(trace.relation("drop_used_at").to_string(), @r###"
+    drop_used_at('p_g) @ e <- `p.g` is dropped, and its destructor may observe it
+    drop_used_at('p_g) @ g <- `p` is dropped, and its destructor may observe it
+
+    "###);
+    insta::assert_snapshot!(trace.relation("invalidate_origin").to_string(), @r###"
+    invalidate_origin('L_p_g) @ a <- write to `p` overwrites the data of loan `&'L_p_g p.g`
+    invalidate_origin('L_p_r) @ a <- write to `p` overwrites the data of loan `&'L_p_r p.r`
+    invalidate_origin('L_x) @ d <- write to `x` overwrites the data of loan `&'L_x x`
+    invalidate_origin('L_p_g) @ e <- `p.g` is dropped, invalidating loan `&'L_p_g p.g`
+    invalidate_origin('L_x) @ g <- storage of `x` dies, invalidating loan `&'L_x x`
+    invalidate_origin('L_p_g) @ g <- storage of `p` dies, invalidating loan `&'L_p_g p.g`
+    invalidate_origin('L_p_r) @ g <- storage of `p` dies, invalidating loan `&'L_p_r p.r`
+
+    "###);
+
+    let err = emit_facts("let x: &'x i32; bb0: { drop(*x); }", Options::default()).unwrap_err();
+    insta::assert_snapshot!(format!("{:#}", err), @"in statement `drop(*x)` of `bb0`: cannot drop `*x`, which is behind a reference");
+}
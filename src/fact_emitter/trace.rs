@@ -0,0 +1,155 @@
+//! The emission trace: every emitted fact, in emission order, with the reason it was emitted.
+//!
+//! Each entry is printed as `relation(args) @ node <- reason`. Tests can select the entries
+//! relevant to a feature with `at`, `relation` and `origin`, instead of snapshotting the facts
+//! of a whole program.
+//!
+//! The trace also records the arms of `relate_tys` taken while emitting the facts, so that the
+//! generated examples of `generate` can be measured by the subtyping cases they exercise.
+
+use std::collections::BTreeSet;
+use std::fmt;
+
+use crate::facts::{Node, Origin};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Relation {
+    AccessOrigin,
+    InvalidateOrigin,
+    ClearOrigin,
+    WriteOrigin,
+    IntroduceSubset,
+    OriginLiveOnEntry,
+    IncompatibleLoans,
+    NodeIsExit,
+    AssertEmpty,
+    LoanThroughShared,
+    OriginEqual,
+    PathAssignedAt,
+    PathAccessedAt,
+    PathMovedAt,
+    DropUsedAt,
+    DiscriminantKnown,
+}
+
+impl Relation {
+    pub fn name(self) -> &'static str {
+        match self {
+            Relation::AccessOrigin => "access_origin",
+            Relation::InvalidateOrigin => "invalidate_origin",
+            Relation::ClearOrigin => "clear_origin",
+            Relation::WriteOrigin => "write_origin",
+            Relation::IntroduceSubset => "introduce_subset",
+            Relation::OriginLiveOnEntry => "origin_live_on_entry",
+            Relation::IncompatibleLoans => "incompatible_loans",
+            Relation::NodeIsExit => "node_is_exit",
+            Relation::AssertEmpty => "assert_empty",
+            Relation::LoanThroughShared => "loan_through_shared",
+            Relation::OriginEqual => "origin_equal",
+            Relation::PathAssignedAt => "path_assigned_at",
+            Relation::PathAccessedAt => "path_accessed_at",
+            Relation::PathMovedAt => "path_moved_at",
+            Relation::DropUsedAt => "drop_used_at",
+            Relation::DiscriminantKnown => "discriminant_known",
+        }
+    }
+}
+
+/// The arms of `relate_tys` which relate types, by their kind. The catch-all arm is an error,
+/// which discards the trace.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum RelateArm {
+    /// `&'a T <: &'b U`, and `&'a mut T <: &'b U`.
+    SharedRefs,
+    MutRefs,
+    Scalars,
+    Structs,
+    FnPointers,
+}
+
+impl RelateArm {
+    pub const ALL: &'static [RelateArm] = &[
+        RelateArm::SharedRefs,
+        RelateArm::MutRefs,
+        RelateArm::Scalars,
+        RelateArm::Structs,
+        RelateArm::FnPointers,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            RelateArm::SharedRefs => "shared-refs",
+            RelateArm::MutRefs => "mut-refs",
+            RelateArm::Scalars => "scalars",
+            RelateArm::Structs => "structs",
+            RelateArm::FnPointers => "fn-pointers",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TraceEntry {
+    pub relation: Relation,
+    pub origins: Vec<Origin>,
+    pub node: Node,
+    pub reason: String,
+}
+
+impl fmt::Display for TraceEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}({}) @ {} <- {}",
+            self.relation.name(),
+            self.origins.join(", "),
+            self.node,
+            self.reason
+        )
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Trace {
+    pub entries: Vec<TraceEntry>,
+
+    /// The arms of `relate_tys` taken at least once.
+    pub relate_arms: BTreeSet<RelateArm>,
+}
+
+impl Trace {
+    /// The entries at `node`.
+    pub fn at(&self, node: &str) -> Trace {
+        self.filter(|entry| entry.node == node)
+    }
+
+    /// The entries of the relation named `name`, e.g. `clear_origin`.
+    pub fn relation(&self, name: &str) -> Trace {
+        self.filter(|entry| entry.relation.name() == name)
+    }
+
+    /// The entries mentioning `origin`.
+    pub fn origin(&self, origin: &str) -> Trace {
+        self.filter(|entry| entry.origins.iter().any(|o| o == origin))
+    }
+
+    fn filter(&self, predicate: impl Fn(&TraceEntry) -> bool) -> Trace {
+        Trace {
+            entries: self
+                .entries
+                .iter()
+                .filter(|entry| predicate(entry))
+                .cloned()
+                .collect(),
+            relate_arms: self.relate_arms.clone(),
+        }
+    }
+}
+
+impl fmt::Display for Trace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for entry in &self.entries {
+            writeln!(f, "{}", entry)?;
+        }
+        Ok(())
+    }
+}
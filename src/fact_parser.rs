@@ -1,27 +1,45 @@
 //! Parser for "fact files", a compact way to represent facts.
 //!
 //! ```notrust
-//! Program    := Statement,
-//! Statement  := Ident: String { Fact* goto Ident* }
+//! Program    := Header? Fact* Statement,   /* only `origin_kind` and `child_path` facts come before the nodes */
+//! Header     := // polonius-frontend vVersion (, relations: Ident,)?   /* a single line */
+//! Statement  := Ident: String { Fact* goto Successor* }
+//! Successor  := ([Label])? Ident
 //! Fact       := Ident ( Symbol, )
-//! Ident      := [a-zA-Z_][a-zA-Z_0-9]*    /* regular expression */
+//! Ident      := [\w*\[\].]+    /* regular expression, \w being any alphanumeric character */
 //! Symbol     := Ident | 'Ident
+//! Label      := [a-zA-Z_0-9.]+    /* regular expression */
 //! String     := "[^"]*"   /* regular expression */
 //! ```
 use eyre::WrapErr;
-use itertools::Itertools;
-use std::collections::HashMap;
 use std::path::Path;
 
+use crate::facts::{normalize_node_text, Facts, FRONTEND_VERSION, RELATION_GROUPS};
+
 pub struct Program {
+    pub header: Option<Header>,
+    /// The facts which aren't about a node, i.e. `origin_kind` and `child_path`, before the
+    /// first node.
+    pub preamble: Vec<Fact>,
     pub statements: Vec<Statement>,
 }
 
+/// The first line of the output of a `FrontendWriter`, see `facts::RELATION_GROUPS`.
+pub struct Header {
+    pub version: String,
+    pub relations: Vec<String>,
+}
+
 pub struct Statement {
     pub name: String,
     pub text: String,
     pub facts: Vec<Fact>,
-    pub successors: Vec<String>,
+    pub successors: Vec<Successor>,
+}
+
+pub struct Successor {
+    pub name: String,
+    pub label: Option<String>,
 }
 
 pub struct Fact {
@@ -31,28 +49,43 @@ pub struct Fact {
 
 peg::parser! {
     grammar fact_parser() for str {
-        pub rule program() -> Program = comment()* _ n:statement()**__ {
-            Program { statements: n }
+        pub rule program() -> Program = header:header()? comment()* _ preamble:fact()**__ _ n:statement()**__ _ {
+            Program { header, preamble, statements: n }
         }
 
-        rule _ = quiet!{[' ' | '\n']*}
-        rule __ = quiet!{[' ' | '\n']+}
+        // `\r` is whitespace, so that inputs with CRLF line endings parse the same.
+        rule _ = quiet!{[' ' | '\t' | '\r' | '\n']*}
+        rule __ = quiet!{[' ' | '\t' | '\r' | '\n']+}
+
+        rule comment() -> () = _ "//" [^'\n']* "\n" { }
 
-        rule comment() -> () = _ "//" [^'\n']* "\n" { () }
+        rule header() -> Header = _ "//" " "* "polonius-frontend" " "+ "v" version:$(['0'..='9']+)
+            relations:("," " "* "relations:" " "* r:ident()**("," " "*) { r })? [' ' | '\t' | '\r']* "\n" {
+            Header { version: version.to_string(), relations: relations.unwrap_or_default() }
+        }
 
-        rule statement() -> Statement = name:ident() _ ":" _ text:string() _ "{" _ facts:fact()**__ _ "goto" _ successors:ident()**__ _ "}" {
+        rule statement() -> Statement = name:ident() _ ":" _ text:string() _ "{" _ facts:fact()**__ _ "goto" _ successors:successor()**__ _ "}" {
             Statement { name, text, facts, successors }
         }
 
+        rule successor() -> Successor = label:("[" _ l:label() _ "]" _ { l })? name:ident() {
+            Successor { name, label }
+        }
+
+        rule label() -> String = t:$(['a'..='z' | 'A'..='Z' | '_' | '0'..='9' | '.']+) {
+            t.to_string()
+        }
+
         rule fact() -> Fact = comment()* _ name:ident() _ "(" _ arguments:symbol()**comma() _ ")" {
             Fact { name, arguments }
         }
 
-        rule comma() -> () =  _ "," _ { () }
+        rule comma() -> () =  _ "," _ { }
 
         rule symbol() -> String = ident() / string()
 
-        rule ident() -> String = t:$("'"?['a'..='z' | 'A'..='Z' | '_' | '0' ..= '9' | '*' ]+) {
+        // Like the names of the AST, idents can contain any alphanumeric character.
+        rule ident() -> String = t:$("'"?[c if c.is_alphanumeric() || matches!(c, '_' | '*' | '[' | ']' | '.')]+) {
             t.to_string()
         }
 
@@ -67,20 +100,14 @@ fn parse_facts(input: &str) -> eyre::Result<Program> {
 }
 
 pub fn generate_facts(input: &str, output_path: &Path) -> eyre::Result<()> {
-    let program = parse_facts(input).wrap_err("failed to parse input")?;
-    let facts = collect_facts(&program)?;
-
-    for (fact_name, fact_rows) in facts.into_iter() {
-        let fact_path = output_path.join(fact_name).with_extension("facts");
-        let file_contents: String = fact_rows
-            .into_iter()
-            .map(|fact_row| format!("{}\n", fact_row.iter().format("\t")))
-            .collect();
-        std::fs::write(&fact_path, file_contents)
-            .wrap_err_with(|| format!("failed to write facts to `{}`", fact_path.display()))?;
-    }
+    let facts = parse_fact_program(input)?;
+    facts.write_to_dir(output_path)
+}
 
-    Ok(())
+/// Parses a program written in the fact format and collects its facts.
+pub fn parse_fact_program(input: &str) -> eyre::Result<Facts> {
+    let program = parse_facts(input).wrap_err("failed to parse input")?;
+    collect_facts(&program)
 }
 
 const EXPECTED_FACT_NAMES: &[&str] = &[
@@ -89,55 +116,183 @@ const EXPECTED_FACT_NAMES: &[&str] = &[
     "clear_origin",
     "introduce_subset",
     "invalidate_origin",
+    "write_origin",
+    "origin_live_on_entry",
+    "incompatible_loans",
+    "node_is_exit",
+    "assert_empty",
+    "loan_through_shared",
+    "origin_equal",
+    "path_assigned_at",
+    "path_accessed_at",
+    "path_moved_at",
+    "drop_used_at",
+    "discriminant_known",
 ];
 
 /// Maps a program into a set of facts:
-fn collect_facts(program: &Program) -> eyre::Result<HashMap<String, Vec<Vec<String>>>> {
-    let mut facts = HashMap::new();
+fn collect_facts(program: &Program) -> eyre::Result<Facts> {
+    let mut facts = Facts::default();
 
-    for expected in EXPECTED_FACT_NAMES.iter() {
-        facts.insert(expected.to_string(), vec![]);
+    let disabled = match &program.header {
+        Some(header) => disabled_relations(header)?,
+        None => vec![],
+    };
+    let all_facts = program
+        .preamble
+        .iter()
+        .chain(program.statements.iter().flat_map(|s| &s.facts));
+    for fact in all_facts {
+        if let Some((group, _)) = disabled.iter().find(|(_, name)| *name == fact.name) {
+            eyre::bail!(
+                "fact `{}` is in the relation group `{}`, which the header doesn't list",
+                fact.name,
+                group
+            );
+        }
+    }
+
+    for fact in &program.preamble {
+        let arguments = fact.arguments.as_slice();
+        match (fact.name.as_str(), arguments) {
+            ("origin_kind", [origin, kind]) => {
+                facts.origin_kind.push((origin.clone(), kind.parse()?));
+            }
+            ("child_path", [child, parent]) => {
+                facts.child_path.push((child.clone(), parent.clone()));
+            }
+            ("origin_kind" | "child_path", _) => {
+                eyre::bail!("fact `{}` has the wrong number of arguments", fact.name)
+            }
+            _ => eyre::bail!(
+                "unexpected fact `{}` before the first node, only `origin_kind` and `child_path` \
+                 can come first",
+                fact.name
+            ),
+        }
     }
-    facts.insert("node_text".to_string(), vec![]);
-    facts.insert("cfg_edge".to_string(), vec![]);
 
     // When a statement S has a fact F(A0, .., An),
-    // we insert a mapping F -> [A0, .., An, S] into
-    // facts hashmap.
+    // we insert a tuple (A0, .., An, S) into the
+    // relation F.
     for statement in &program.statements {
+        let node = &statement.name;
         facts
-            .get_mut("node_text")
-            .unwrap()
-            .push(vec![statement.text.clone(), statement.name.clone()]);
+            .node_text
+            .push((normalize_node_text(&statement.text), node.clone()));
 
         for successor in &statement.successors {
-            facts
-                .get_mut("cfg_edge")
-                .unwrap()
-                .push(vec![statement.name.clone(), successor.clone()]);
+            facts.cfg_edge.push((node.clone(), successor.name.clone()));
+            if let Some(label) = &successor.label {
+                facts
+                    .cfg_edge_label
+                    .push((node.clone(), successor.name.clone(), label.clone()));
+            }
         }
 
         for fact in &statement.facts {
-            if !EXPECTED_FACT_NAMES
-                .iter()
-                .any(|expected| *expected == fact.name)
-            {
-                return Err(eyre::eyre!(
-                    "unexpected fact name `{}`, valid names are `{:?}`",
+            let arity_error = || {
+                eyre::eyre!(
+                    "fact `{}` at `{}` has the wrong number of arguments",
                     fact.name,
-                    EXPECTED_FACT_NAMES
-                ));
+                    node
+                )
+            };
+            let arguments = fact.arguments.as_slice();
+            match fact.name.as_str() {
+                "access_origin"
+                | "clear_origin"
+                | "invalidate_origin"
+                | "write_origin"
+                | "origin_live_on_entry"
+                | "assert_empty"
+                | "loan_through_shared"
+                | "path_assigned_at"
+                | "path_accessed_at"
+                | "path_moved_at"
+                | "drop_used_at"
+                | "cfg_edge" => {
+                    let [a] = arguments else {
+                        return Err(arity_error());
+                    };
+                    let relation = match fact.name.as_str() {
+                        "access_origin" => &mut facts.access_origin,
+                        "clear_origin" => &mut facts.clear_origin,
+                        "invalidate_origin" => &mut facts.invalidate_origin,
+                        "write_origin" => &mut facts.write_origin,
+                        "origin_live_on_entry" => &mut facts.origin_live_on_entry,
+                        "assert_empty" => &mut facts.assert_empty,
+                        "loan_through_shared" => &mut facts.loan_through_shared,
+                        "path_assigned_at" => &mut facts.path_assigned_at,
+                        "path_accessed_at" => &mut facts.path_accessed_at,
+                        "path_moved_at" => &mut facts.path_moved_at,
+                        "drop_used_at" => &mut facts.drop_used_at,
+                        _ => &mut facts.cfg_edge,
+                    };
+                    relation.push((a.clone(), node.clone()));
+                }
+                "node_is_exit" => {
+                    let [] = arguments else {
+                        return Err(arity_error());
+                    };
+                    facts.node_is_exit.push(node.clone());
+                }
+                "introduce_subset" | "incompatible_loans" | "origin_equal"
+                | "discriminant_known" => {
+                    let [a, b] = arguments else {
+                        return Err(arity_error());
+                    };
+                    let relation = match fact.name.as_str() {
+                        "introduce_subset" => &mut facts.introduce_subset,
+                        "incompatible_loans" => &mut facts.incompatible_loans,
+                        "origin_equal" => &mut facts.origin_equal,
+                        _ => &mut facts.discriminant_known,
+                    };
+                    relation.push((a.clone(), b.clone(), node.clone()));
+                }
+                _ => {
+                    return Err(eyre::eyre!(
+                        "unexpected fact name `{}`, valid names are `{:?}`",
+                        fact.name,
+                        EXPECTED_FACT_NAMES
+                    ));
+                }
             }
+        }
+    }
+
+    Ok(facts)
+}
 
-            facts.get_mut(&fact.name).unwrap().push(
-                fact.arguments
+/// The relations of the `RELATION_GROUPS` the `header` doesn't list, with their group. Version 1
+/// has no groups, so every relation is allowed.
+fn disabled_relations(header: &Header) -> eyre::Result<Vec<(&'static str, &'static str)>> {
+    let version: u32 = header.version.parse()?;
+    if version == 0 || version > FRONTEND_VERSION {
+        eyre::bail!(
+            "unsupported frontend format version {}, expected a version from 1 to {}",
+            version,
+            FRONTEND_VERSION
+        );
+    }
+    for group in &header.relations {
+        if !RELATION_GROUPS.iter().any(|(name, _)| name == group) {
+            eyre::bail!(
+                "unknown relation group `{}` in the header, valid groups are `{:?}`",
+                group,
+                RELATION_GROUPS
                     .iter()
-                    .chain(Some(&statement.name))
-                    .cloned()
-                    .collect(),
+                    .map(|(name, _)| name)
+                    .collect::<Vec<_>>()
             );
         }
     }
-
-    Ok(facts)
+    if version == 1 {
+        return Ok(vec![]);
+    }
+    Ok(RELATION_GROUPS
+        .iter()
+        .filter(|(group, _)| !header.relations.iter().any(|r| r == group))
+        .flat_map(|(group, names)| names.iter().map(move |name| (*group, *name)))
+        .collect())
 }
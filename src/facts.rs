@@ -0,0 +1,1009 @@
+//! The input relations consumed by `polonius.dl`.
+
+use eyre::WrapErr;
+use itertools::Itertools;
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+use std::path::Path;
+
+pub mod binary;
+#[cfg(test)]
+mod test;
+
+pub type Origin = String;
+pub type Node = String;
+
+/// A move path, e.g. `x.f`, see `ir::move_paths`.
+pub type MovePath = String;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Facts {
+    pub access_origin: Vec<(Origin, Node)>,
+    pub cfg_edge: Vec<(Node, Node)>,
+    pub clear_origin: Vec<(Origin, Node)>,
+    pub introduce_subset: Vec<(Origin, Origin, Node)>,
+    pub invalidate_origin: Vec<(Origin, Node)>,
+    pub node_text: Vec<(String, Node)>,
+
+    /// The origins of a place overwritten at a node: a definition, which unlike
+    /// `access_origin` is not a use of the origin for liveness.
+    pub write_origin: Vec<(Origin, Node)>,
+
+    /// The origins live on entry to a node, when the emitter computes liveness itself instead
+    /// of letting the rules derive it from `access_origin`, see `fact_emitter::Liveness`.
+    pub origin_live_on_entry: Vec<(Origin, Node)>,
+
+    /// The loans `l1` and `l2` can't be live at the same time, as their places overlap and
+    /// taking one of them invalidates the other. The node is where `l2`, the later loan in
+    /// program order, is issued.
+    pub incompatible_loans: Vec<(Origin, Origin, Node)>,
+
+    /// The nodes where the function exits, after the storage of its locals has been freed.
+    pub node_is_exit: Vec<Node>,
+
+    /// The origins which an `assert_empty` ghost statement requires to be empty at a node.
+    pub assert_empty: Vec<(Origin, Node)>,
+
+    /// The labels of the `cfg_edge`s whose `goto` names them, e.g. `then`, or a weight like
+    /// `0.9`. They are metadata, e.g. for visualizations, which the rules ignore.
+    pub cfg_edge_label: Vec<(Node, Node, String)>,
+
+    /// The loans issued at a node which borrow a place behind a shared reference, e.g. `*p`
+    /// with `p: &i32`, rather than only through mutable references. Rule variants can then
+    /// treat them as not invalidated by writes to the place the reference was borrowed from.
+    pub loan_through_shared: Vec<(Origin, Node)>,
+
+    /// The origins `o1` and `o2` are required to be equal at a node, e.g. as the parameters
+    /// of a type in an invariant position. Emitted instead of a subset in each direction with
+    /// `fact_emitter::Options::origin_equalities`, so that rule variants can unify the origins
+    /// rather than close over the subsets.
+    pub origin_equal: Vec<(Origin, Origin, Node)>,
+
+    /// How each origin of the facts was created, e.g. for a loan or by a call. Unlike the
+    /// other relations, it isn't about a node.
+    pub origin_kind: Vec<(Origin, OriginKind)>,
+
+    /// The move paths which are fields of another, e.g. `(x.f, x)`. Emitted, along with the
+    /// `path_*_at` relations, with `fact_emitter::Options::move_paths`, for rules tracking
+    /// initialization like rustc. Like `origin_kind`, it isn't about a node.
+    pub child_path: Vec<(MovePath, MovePath)>,
+
+    /// The move path is overwritten as a whole at a node, e.g. `x.f` by `x.f = 1`.
+    pub path_assigned_at: Vec<(MovePath, Node)>,
+
+    /// The value of the move path, or of a place behind it, is used at a node, e.g. `p` by
+    /// `copy *p`.
+    pub path_accessed_at: Vec<(MovePath, Node)>,
+
+    /// The value of the move path is moved out at a node, e.g. `x` by `move x`.
+    pub path_moved_at: Vec<(MovePath, Node)>,
+
+    /// The origins a destructor run at a node may observe, e.g. when a local whose type has a
+    /// destructor dies. A use for liveness, like `access_origin`, but only of the origins
+    /// the destructor may observe, see `typeck::TypeEnv::drop_origins`.
+    pub drop_used_at: Vec<(Origin, Node)>,
+
+    /// The value of a place is known on entry to a node, the first node of an arm of a
+    /// `match` or a `switch` on the place: a variant, e.g. `Some`, or an integer. Emitted with
+    /// `fact_emitter::Options::refinements`, for flow-sensitive rules, e.g. borrowing only in
+    /// the `Some` arm. The `_` arm, and arms whose block has other predecessors, refine nothing.
+    pub discriminant_known: Vec<(String, String, Node)>,
+}
+
+/// How an origin was created, so that rules and diagnostics can treat the origins the emitter
+/// creates differently from the ones named by the program, without parsing their names.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum OriginKind {
+    /// An origin named by the program, e.g. in the type of a variable.
+    UserNamed,
+
+    /// The origin of a borrow expression, i.e. a loan.
+    LoanFresh,
+
+    /// An origin of a signature or of a struct, instantiated by a call or a struct literal,
+    /// e.g. `'MaybeNext0_a`.
+    CallInstance,
+
+    /// An origin coming from an unanalyzed caller, see
+    /// `fact_emitter::Options::external_origins`.
+    Placeholder,
+}
+
+impl OriginKind {
+    pub const ALL: [OriginKind; 4] = [
+        OriginKind::UserNamed,
+        OriginKind::LoanFresh,
+        OriginKind::CallInstance,
+        OriginKind::Placeholder,
+    ];
+
+    /// The name of the kind in the facts, e.g. `call_instance`.
+    pub fn name(self) -> &'static str {
+        match self {
+            OriginKind::UserNamed => "user_named",
+            OriginKind::LoanFresh => "loan_fresh",
+            OriginKind::CallInstance => "call_instance",
+            OriginKind::Placeholder => "placeholder",
+        }
+    }
+}
+
+impl std::str::FromStr for OriginKind {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> eyre::Result<Self> {
+        OriginKind::ALL
+            .iter()
+            .copied()
+            .find(|kind| kind.name() == s)
+            .ok_or_else(|| {
+                eyre::eyre!(
+                    "unknown origin kind `{}`, the kinds are {}",
+                    s,
+                    OriginKind::ALL.iter().map(|kind| kind.name()).join(", ")
+                )
+            })
+    }
+}
+
+/// The relations whose last argument is a node, e.g. `invalidate_origin(origin, node)`, with the
+/// number of their other arguments.
+pub const NODE_RELATIONS: &[(&str, usize)] = &[
+    ("access_origin", 1),
+    ("clear_origin", 1),
+    ("introduce_subset", 2),
+    ("invalidate_origin", 1),
+    ("write_origin", 1),
+    ("origin_live_on_entry", 1),
+    ("incompatible_loans", 2),
+    ("node_is_exit", 0),
+    ("assert_empty", 1),
+    ("loan_through_shared", 1),
+    ("origin_equal", 2),
+    ("path_assigned_at", 1),
+    ("path_accessed_at", 1),
+    ("path_moved_at", 1),
+    ("drop_used_at", 1),
+    ("discriminant_known", 2),
+];
+
+impl Facts {
+    /// Returns each relation as a list of rows, keyed by the relation name.
+    pub fn relations(&self) -> Vec<(&'static str, Vec<Vec<&str>>)> {
+        fn pairs(rows: &[(String, String)]) -> Vec<Vec<&str>> {
+            rows.iter()
+                .map(|(a, b)| vec![a.as_str(), b.as_str()])
+                .collect()
+        }
+
+        vec![
+            ("access_origin", pairs(&self.access_origin)),
+            ("cfg_edge", pairs(&self.cfg_edge)),
+            ("clear_origin", pairs(&self.clear_origin)),
+            (
+                "introduce_subset",
+                self.introduce_subset
+                    .iter()
+                    .map(|(a, b, n)| vec![a.as_str(), b.as_str(), n.as_str()])
+                    .collect(),
+            ),
+            ("invalidate_origin", pairs(&self.invalidate_origin)),
+            ("node_text", pairs(&self.node_text)),
+            ("write_origin", pairs(&self.write_origin)),
+            ("origin_live_on_entry", pairs(&self.origin_live_on_entry)),
+            (
+                "incompatible_loans",
+                self.incompatible_loans
+                    .iter()
+                    .map(|(a, b, n)| vec![a.as_str(), b.as_str(), n.as_str()])
+                    .collect(),
+            ),
+            (
+                "node_is_exit",
+                self.node_is_exit.iter().map(|n| vec![n.as_str()]).collect(),
+            ),
+            ("assert_empty", pairs(&self.assert_empty)),
+            (
+                "cfg_edge_label",
+                self.cfg_edge_label
+                    .iter()
+                    .map(|(n1, n2, label)| vec![n1.as_str(), n2.as_str(), label.as_str()])
+                    .collect(),
+            ),
+            ("loan_through_shared", pairs(&self.loan_through_shared)),
+            (
+                "origin_equal",
+                self.origin_equal
+                    .iter()
+                    .map(|(a, b, n)| vec![a.as_str(), b.as_str(), n.as_str()])
+                    .collect(),
+            ),
+            (
+                "origin_kind",
+                self.origin_kind
+                    .iter()
+                    .map(|(o, kind)| vec![o.as_str(), kind.name()])
+                    .collect(),
+            ),
+            ("child_path", pairs(&self.child_path)),
+            ("path_assigned_at", pairs(&self.path_assigned_at)),
+            ("path_accessed_at", pairs(&self.path_accessed_at)),
+            ("path_moved_at", pairs(&self.path_moved_at)),
+            ("drop_used_at", pairs(&self.drop_used_at)),
+            (
+                "discriminant_known",
+                self.discriminant_known
+                    .iter()
+                    .map(|(p, v, n)| vec![p.as_str(), v.as_str(), n.as_str()])
+                    .collect(),
+            ),
+        ]
+    }
+
+    /// Number of tuples in each relation, keyed by the relation name.
+    pub fn tuple_counts(&self) -> Vec<(&'static str, usize)> {
+        self.relations()
+            .into_iter()
+            .map(|(name, rows)| (name, rows.len()))
+            .collect()
+    }
+
+    /// Writes one `<relation>.facts` file per relation into `output_path`, in the
+    /// tab-separated format expected by souffle.
+    pub fn write_to_dir(&self, output_path: &Path) -> eyre::Result<()> {
+        for (fact_name, fact_rows) in self.relations() {
+            let fact_path = output_path.join(fact_name).with_extension("facts");
+            let file_contents: String = fact_rows
+                .into_iter()
+                .map(|fact_row| format!("{}\n", fact_row.iter().format("\t")))
+                .collect();
+            std::fs::write(&fact_path, file_contents)
+                .wrap_err_with(|| format!("failed to write facts to `{}`", fact_path.display()))?;
+        }
+
+        Ok(())
+    }
+
+    /// Reads back the `<relation>.facts` files written by `write_to_dir`.
+    pub fn read_from_dir(input_path: &Path) -> eyre::Result<Facts> {
+        fn read(input_path: &Path, name: &str, arity: usize) -> eyre::Result<Vec<Vec<String>>> {
+            let path = input_path.join(name).with_extension("facts");
+            let contents = std::fs::read_to_string(&path)
+                .wrap_err_with(|| format!("failed to read facts from `{}`", path.display()))?;
+            contents
+                .lines()
+                .enumerate()
+                .map(|(i, line)| {
+                    let row: Vec<String> = line.split('\t').map(str::to_string).collect();
+                    if row.len() != arity {
+                        eyre::bail!(
+                            "`{}`, line {}: expected {} columns, found {}",
+                            path.display(),
+                            i + 1,
+                            arity,
+                            row.len()
+                        );
+                    }
+                    Ok(row)
+                })
+                .collect()
+        }
+        fn pairs(input_path: &Path, name: &str) -> eyre::Result<Vec<(String, String)>> {
+            Ok(read(input_path, name, 2)?
+                .into_iter()
+                .map(|row| row.into_iter().collect_tuple().unwrap())
+                .collect())
+        }
+
+        Ok(Facts {
+            access_origin: pairs(input_path, "access_origin")?,
+            cfg_edge: pairs(input_path, "cfg_edge")?,
+            clear_origin: pairs(input_path, "clear_origin")?,
+            introduce_subset: read(input_path, "introduce_subset", 3)?
+                .into_iter()
+                .map(|row| row.into_iter().collect_tuple().unwrap())
+                .collect(),
+            invalidate_origin: pairs(input_path, "invalidate_origin")?,
+            node_text: pairs(input_path, "node_text")?,
+            write_origin: pairs(input_path, "write_origin")?,
+            origin_live_on_entry: pairs(input_path, "origin_live_on_entry")?,
+            incompatible_loans: read(input_path, "incompatible_loans", 3)?
+                .into_iter()
+                .map(|row| row.into_iter().collect_tuple().unwrap())
+                .collect(),
+            node_is_exit: read(input_path, "node_is_exit", 1)?
+                .into_iter()
+                .map(|mut row| row.remove(0))
+                .collect(),
+            assert_empty: pairs(input_path, "assert_empty")?,
+            cfg_edge_label: read(input_path, "cfg_edge_label", 3)?
+                .into_iter()
+                .map(|row| row.into_iter().collect_tuple().unwrap())
+                .collect(),
+            loan_through_shared: pairs(input_path, "loan_through_shared")?,
+            origin_equal: read(input_path, "origin_equal", 3)?
+                .into_iter()
+                .map(|row| row.into_iter().collect_tuple().unwrap())
+                .collect(),
+            origin_kind: parse_origin_kinds(pairs(input_path, "origin_kind")?)?,
+            child_path: pairs(input_path, "child_path")?,
+            path_assigned_at: pairs(input_path, "path_assigned_at")?,
+            path_accessed_at: pairs(input_path, "path_accessed_at")?,
+            path_moved_at: pairs(input_path, "path_moved_at")?,
+            drop_used_at: pairs(input_path, "drop_used_at")?,
+            discriminant_known: read(input_path, "discriminant_known", 3)?
+                .into_iter()
+                .map(|row| row.into_iter().collect_tuple().unwrap())
+                .collect(),
+        })
+    }
+
+    /// Appends the tuples of `other`, with its nodes and origins renamed by `prefix`: the node
+    /// `b` becomes `f_b`, and the origin `'a` becomes `'f_a`. The facts of several functions,
+    /// or synthetic fragments, can then be solved together without their names colliding. The
+    /// texts and labels of the nodes are kept. With an empty prefix, the tuples are appended
+    /// unchanged, so that the fragments share their nodes and origins.
+    pub fn merge(&mut self, other: &Facts, prefix: &str) {
+        let name = |name: &String| match name.strip_prefix('\'') {
+            _ if prefix.is_empty() => name.clone(),
+            Some(origin) => format!("'{}_{}", prefix, origin),
+            None => format!("{}_{}", prefix, name),
+        };
+        let pair = |(a, b): &(String, String)| (name(a), name(b));
+        let triple = |(a, b, c): &(String, String, String)| (name(a), name(b), name(c));
+
+        self.access_origin
+            .extend(other.access_origin.iter().map(pair));
+        self.cfg_edge.extend(other.cfg_edge.iter().map(pair));
+        self.clear_origin
+            .extend(other.clear_origin.iter().map(pair));
+        self.introduce_subset
+            .extend(other.introduce_subset.iter().map(triple));
+        self.invalidate_origin
+            .extend(other.invalidate_origin.iter().map(pair));
+        self.node_text.extend(
+            other
+                .node_text
+                .iter()
+                .map(|(text, node)| (text.clone(), name(node))),
+        );
+        self.write_origin
+            .extend(other.write_origin.iter().map(pair));
+        self.origin_live_on_entry
+            .extend(other.origin_live_on_entry.iter().map(pair));
+        self.incompatible_loans
+            .extend(other.incompatible_loans.iter().map(triple));
+        self.node_is_exit
+            .extend(other.node_is_exit.iter().map(name));
+        self.assert_empty
+            .extend(other.assert_empty.iter().map(pair));
+        self.cfg_edge_label.extend(
+            other
+                .cfg_edge_label
+                .iter()
+                .map(|(n1, n2, label)| (name(n1), name(n2), label.clone())),
+        );
+        self.loan_through_shared
+            .extend(other.loan_through_shared.iter().map(pair));
+        self.origin_equal
+            .extend(other.origin_equal.iter().map(triple));
+        self.origin_kind.extend(
+            other
+                .origin_kind
+                .iter()
+                .map(|(origin, kind)| (name(origin), *kind)),
+        );
+        self.child_path.extend(other.child_path.iter().map(pair));
+        self.path_assigned_at
+            .extend(other.path_assigned_at.iter().map(pair));
+        self.path_accessed_at
+            .extend(other.path_accessed_at.iter().map(pair));
+        self.path_moved_at
+            .extend(other.path_moved_at.iter().map(pair));
+        self.drop_used_at
+            .extend(other.drop_used_at.iter().map(pair));
+        self.discriminant_known.extend(
+            other
+                .discriminant_known
+                .iter()
+                .map(|(p, v, n)| (p.clone(), v.clone(), name(n))),
+        );
+    }
+
+    /// Loads the facts of `input` from `cache_dir`, where they are stored in the binary
+    /// encoding under a hash of `input`, or computes them with `emit` and stores them there.
+    /// The cache doesn't know how `emit` computes the facts: callers emitting facts with
+    /// different options need different cache directories.
+    pub fn load_cached(
+        cache_dir: &Path,
+        input: &str,
+        emit: impl FnOnce(&str) -> eyre::Result<Facts>,
+    ) -> eyre::Result<Facts> {
+        let path = cache_dir.join(format!(
+            "{:016x}.facts",
+            binary::content_hash(input.as_bytes())
+        ));
+        if let Ok(bytes) = std::fs::read(&path) {
+            // A cache written by another version is recomputed.
+            if let Ok(facts) = binary::decode(&bytes) {
+                return Ok(facts);
+            }
+        }
+
+        let facts = emit(input)?;
+        std::fs::create_dir_all(cache_dir)
+            .wrap_err_with(|| format!("failed to create `{}`", cache_dir.display()))?;
+        std::fs::write(&path, binary::encode(&facts))
+            .wrap_err_with(|| format!("failed to write facts to `{}`", path.display()))?;
+        Ok(facts)
+    }
+
+    pub fn access_origins(&self) -> impl Iterator<Item = (&Origin, &Node)> {
+        self.access_origin.iter().map(|(o, n)| (o, n))
+    }
+
+    pub fn cfg_edges(&self) -> impl Iterator<Item = (&Node, &Node)> {
+        self.cfg_edge.iter().map(|(n1, n2)| (n1, n2))
+    }
+
+    pub fn clear_origins(&self) -> impl Iterator<Item = (&Origin, &Node)> {
+        self.clear_origin.iter().map(|(o, n)| (o, n))
+    }
+
+    pub fn introduce_subsets(&self) -> impl Iterator<Item = (&Origin, &Origin, &Node)> {
+        self.introduce_subset.iter().map(|(o1, o2, n)| (o1, o2, n))
+    }
+
+    pub fn invalidate_origins(&self) -> impl Iterator<Item = (&Origin, &Node)> {
+        self.invalidate_origin.iter().map(|(o, n)| (o, n))
+    }
+
+    pub fn node_texts(&self) -> impl Iterator<Item = (&String, &Node)> {
+        self.node_text.iter().map(|(text, n)| (text, n))
+    }
+
+    pub fn write_origins(&self) -> impl Iterator<Item = (&Origin, &Node)> {
+        self.write_origin.iter().map(|(o, n)| (o, n))
+    }
+
+    pub fn origins_live_on_entry(&self) -> impl Iterator<Item = (&Origin, &Node)> {
+        self.origin_live_on_entry.iter().map(|(o, n)| (o, n))
+    }
+
+    pub fn incompatible_loans(&self) -> impl Iterator<Item = (&Origin, &Origin, &Node)> {
+        self.incompatible_loans
+            .iter()
+            .map(|(l1, l2, n)| (l1, l2, n))
+    }
+
+    pub fn exit_nodes(&self) -> impl Iterator<Item = &Node> {
+        self.node_is_exit.iter()
+    }
+
+    pub fn asserted_empty(&self) -> impl Iterator<Item = (&Origin, &Node)> {
+        self.assert_empty.iter().map(|(o, n)| (o, n))
+    }
+
+    pub fn loans_through_shared(&self) -> impl Iterator<Item = (&Origin, &Node)> {
+        self.loan_through_shared.iter().map(|(l, n)| (l, n))
+    }
+
+    pub fn origin_equalities(&self) -> impl Iterator<Item = (&Origin, &Origin, &Node)> {
+        self.origin_equal.iter().map(|(o1, o2, n)| (o1, o2, n))
+    }
+
+    pub fn origin_kinds(&self) -> impl Iterator<Item = (&Origin, OriginKind)> {
+        self.origin_kind.iter().map(|(o, kind)| (o, *kind))
+    }
+
+    pub fn child_paths(&self) -> impl Iterator<Item = (&MovePath, &MovePath)> {
+        self.child_path
+            .iter()
+            .map(|(child, parent)| (child, parent))
+    }
+
+    pub fn paths_assigned_at(&self) -> impl Iterator<Item = (&MovePath, &Node)> {
+        self.path_assigned_at.iter().map(|(p, n)| (p, n))
+    }
+
+    pub fn paths_accessed_at(&self) -> impl Iterator<Item = (&MovePath, &Node)> {
+        self.path_accessed_at.iter().map(|(p, n)| (p, n))
+    }
+
+    pub fn paths_moved_at(&self) -> impl Iterator<Item = (&MovePath, &Node)> {
+        self.path_moved_at.iter().map(|(p, n)| (p, n))
+    }
+
+    pub fn drop_uses(&self) -> impl Iterator<Item = (&Origin, &Node)> {
+        self.drop_used_at.iter().map(|(o, n)| (o, n))
+    }
+
+    pub fn known_discriminants(&self) -> impl Iterator<Item = (&String, &String, &Node)> {
+        self.discriminant_known.iter().map(|(p, v, n)| (p, v, n))
+    }
+
+    /// Every origin of the relations about nodes.
+    pub fn origins(&self) -> BTreeSet<&Origin> {
+        let unary = self
+            .access_origin
+            .iter()
+            .chain(&self.clear_origin)
+            .chain(&self.invalidate_origin)
+            .chain(&self.write_origin)
+            .chain(&self.origin_live_on_entry)
+            .chain(&self.assert_empty)
+            .chain(&self.loan_through_shared)
+            .chain(&self.drop_used_at)
+            .map(|(o, _)| o);
+        let binary = self
+            .introduce_subset
+            .iter()
+            .chain(&self.incompatible_loans)
+            .chain(&self.origin_equal)
+            .flat_map(|(o1, o2, _)| [o1, o2]);
+        unary.chain(binary).collect()
+    }
+
+    /// The kind of `origin`, if the facts record it.
+    pub fn origin_kind(&self, origin: &str) -> Option<OriginKind> {
+        self.origin_kinds()
+            .find(|(o, _)| *o == origin)
+            .map(|(_, kind)| kind)
+    }
+
+    pub fn cfg_edge_labels(&self) -> impl Iterator<Item = (&Node, &Node, &String)> {
+        self.cfg_edge_label
+            .iter()
+            .map(|(n1, n2, label)| (n1, n2, label))
+    }
+
+    /// Indexes the relations by node, e.g. for solvers, which mostly join on the node.
+    pub fn index_by_node(&self) -> NodeIndex<'_> {
+        fn at<'a, 'f>(
+            nodes: &'a mut HashMap<&'f str, NodeFacts<'f>>,
+            node: &'f Node,
+        ) -> &'a mut NodeFacts<'f> {
+            nodes.entry(node.as_str()).or_default()
+        }
+
+        let mut nodes = HashMap::new();
+
+        for (o, n) in self.access_origins() {
+            at(&mut nodes, n).access_origin.push(o);
+        }
+        for (o, n) in self.invalidate_origins() {
+            at(&mut nodes, n).invalidate_origin.push(o);
+        }
+        for (o, n) in self.write_origins() {
+            at(&mut nodes, n).write_origin.push(o);
+        }
+        for (o, n) in self.clear_origins() {
+            at(&mut nodes, n).clear_origin.push(o);
+        }
+        for (o, n) in self.origins_live_on_entry() {
+            at(&mut nodes, n).origin_live_on_entry.push(o);
+        }
+        for (o1, o2, n) in self.introduce_subsets() {
+            at(&mut nodes, n).introduce_subset.push((o1, o2));
+        }
+        for (l1, l2, n) in self.incompatible_loans() {
+            at(&mut nodes, n).incompatible_loans.push((l1, l2));
+        }
+        for n in self.exit_nodes() {
+            at(&mut nodes, n).is_exit = true;
+        }
+        for (o, n) in self.asserted_empty() {
+            at(&mut nodes, n).assert_empty.push(o);
+        }
+        for (l, n) in self.loans_through_shared() {
+            at(&mut nodes, n).loan_through_shared.push(l);
+        }
+        for (o1, o2, n) in self.origin_equalities() {
+            at(&mut nodes, n).origin_equal.push((o1, o2));
+        }
+        for (p, n) in self.paths_assigned_at() {
+            at(&mut nodes, n).path_assigned_at.push(p);
+        }
+        for (p, n) in self.paths_accessed_at() {
+            at(&mut nodes, n).path_accessed_at.push(p);
+        }
+        for (p, n) in self.paths_moved_at() {
+            at(&mut nodes, n).path_moved_at.push(p);
+        }
+        for (o, n) in self.drop_uses() {
+            at(&mut nodes, n).drop_used_at.push(o);
+        }
+        for (p, v, n) in self.known_discriminants() {
+            at(&mut nodes, n).discriminant_known.push((p, v));
+        }
+        for (n1, n2) in self.cfg_edges() {
+            at(&mut nodes, n1).successors.push(n2);
+            at(&mut nodes, n2).predecessors.push(n1);
+        }
+        for (n1, n2, label) in self.cfg_edge_labels() {
+            at(&mut nodes, n1).successor_labels.push((n2, label));
+        }
+
+        NodeIndex { nodes }
+    }
+
+    /// Returns the nodes of the CFG, i.e. the nodes with a `node_text`.
+    pub fn nodes(&self) -> Vec<&Node> {
+        self.node_texts().map(|(_, node)| node).collect()
+    }
+
+    /// Returns every node mentioned by a relation, along with the relation's name.
+    pub fn node_references(&self) -> Vec<(&'static str, &Node)> {
+        let unary = [
+            ("access_origin", &self.access_origin),
+            ("clear_origin", &self.clear_origin),
+            ("invalidate_origin", &self.invalidate_origin),
+            ("write_origin", &self.write_origin),
+            ("origin_live_on_entry", &self.origin_live_on_entry),
+            ("assert_empty", &self.assert_empty),
+            ("loan_through_shared", &self.loan_through_shared),
+            ("path_assigned_at", &self.path_assigned_at),
+            ("path_accessed_at", &self.path_accessed_at),
+            ("path_moved_at", &self.path_moved_at),
+            ("drop_used_at", &self.drop_used_at),
+        ];
+        let mut references = vec![];
+        for (name, relation) in unary {
+            references.extend(relation.iter().map(|(_, node)| (name, node)));
+        }
+        references.extend(
+            self.introduce_subsets()
+                .map(|(.., node)| ("introduce_subset", node)),
+        );
+        references.extend(
+            self.incompatible_loans()
+                .map(|(.., node)| ("incompatible_loans", node)),
+        );
+        references.extend(
+            self.origin_equalities()
+                .map(|(.., node)| ("origin_equal", node)),
+        );
+        references.extend(
+            self.known_discriminants()
+                .map(|(.., node)| ("discriminant_known", node)),
+        );
+        references.extend(self.exit_nodes().map(|node| ("node_is_exit", node)));
+        for (n1, n2) in self.cfg_edges() {
+            references.push(("cfg_edge", n1));
+            references.push(("cfg_edge", n2));
+        }
+        for (n1, n2, _) in self.cfg_edge_labels() {
+            references.push(("cfg_edge_label", n1));
+            references.push(("cfg_edge_label", n2));
+        }
+        references
+    }
+}
+
+/// The facts of a single node, in the order of the relations.
+#[derive(Clone, Debug, Default)]
+pub struct NodeFacts<'f> {
+    pub access_origin: Vec<&'f Origin>,
+    pub invalidate_origin: Vec<&'f Origin>,
+    pub write_origin: Vec<&'f Origin>,
+    pub clear_origin: Vec<&'f Origin>,
+    pub origin_live_on_entry: Vec<&'f Origin>,
+    pub introduce_subset: Vec<(&'f Origin, &'f Origin)>,
+    pub incompatible_loans: Vec<(&'f Origin, &'f Origin)>,
+    pub is_exit: bool,
+    pub assert_empty: Vec<&'f Origin>,
+    pub loan_through_shared: Vec<&'f Origin>,
+    pub origin_equal: Vec<(&'f Origin, &'f Origin)>,
+    pub path_assigned_at: Vec<&'f MovePath>,
+    pub path_accessed_at: Vec<&'f MovePath>,
+    pub path_moved_at: Vec<&'f MovePath>,
+    pub drop_used_at: Vec<&'f Origin>,
+    pub discriminant_known: Vec<(&'f String, &'f String)>,
+    pub successors: Vec<&'f Node>,
+    pub predecessors: Vec<&'f Node>,
+    pub successor_labels: Vec<(&'f Node, &'f String)>,
+}
+
+/// The relations of a `Facts`, indexed by node, built by `Facts::index_by_node`.
+#[derive(Clone, Debug)]
+pub struct NodeIndex<'f> {
+    nodes: HashMap<&'f str, NodeFacts<'f>>,
+}
+
+static NO_FACTS: NodeFacts<'static> = NodeFacts {
+    access_origin: Vec::new(),
+    invalidate_origin: Vec::new(),
+    write_origin: Vec::new(),
+    clear_origin: Vec::new(),
+    origin_live_on_entry: Vec::new(),
+    introduce_subset: Vec::new(),
+    incompatible_loans: Vec::new(),
+    is_exit: false,
+    assert_empty: Vec::new(),
+    loan_through_shared: Vec::new(),
+    origin_equal: Vec::new(),
+    path_assigned_at: Vec::new(),
+    path_accessed_at: Vec::new(),
+    path_moved_at: Vec::new(),
+    drop_used_at: Vec::new(),
+    discriminant_known: Vec::new(),
+    successors: Vec::new(),
+    predecessors: Vec::new(),
+    successor_labels: Vec::new(),
+};
+
+impl NodeFacts<'_> {
+    /// Whether the node has no facts, besides its `cfg_edge`s and their labels.
+    pub fn is_empty(&self) -> bool {
+        self.access_origin.is_empty()
+            && self.invalidate_origin.is_empty()
+            && self.write_origin.is_empty()
+            && self.clear_origin.is_empty()
+            && self.origin_live_on_entry.is_empty()
+            && self.introduce_subset.is_empty()
+            && self.incompatible_loans.is_empty()
+            && !self.is_exit
+            && self.assert_empty.is_empty()
+            && self.loan_through_shared.is_empty()
+            && self.origin_equal.is_empty()
+            && self.path_assigned_at.is_empty()
+            && self.path_accessed_at.is_empty()
+            && self.path_moved_at.is_empty()
+            && self.drop_used_at.is_empty()
+            && self.discriminant_known.is_empty()
+    }
+}
+
+impl<'f> NodeIndex<'f> {
+    /// The facts of `node`, which are empty if no relation mentions it.
+    pub fn at(&self, node: &str) -> &NodeFacts<'f> {
+        self.nodes.get(node).unwrap_or(&NO_FACTS)
+    }
+
+    pub fn is_cleared(&self, origin: &str, node: &str) -> bool {
+        self.at(node).clear_origin.iter().any(|o| *o == origin)
+    }
+
+    pub fn is_written(&self, origin: &str, node: &str) -> bool {
+        self.at(node).write_origin.iter().any(|o| *o == origin)
+    }
+
+    pub fn is_invalidated(&self, origin: &str, node: &str) -> bool {
+        self.at(node).invalidate_origin.iter().any(|o| *o == origin)
+    }
+
+    pub fn successors(&self, node: &str) -> impl Iterator<Item = &'f Node> + '_ {
+        self.at(node).successors.iter().copied()
+    }
+
+    pub fn predecessors(&self, node: &str) -> impl Iterator<Item = &'f Node> + '_ {
+        self.at(node).predecessors.iter().copied()
+    }
+}
+
+/// Parses the second column of the `origin_kind` rows.
+pub(crate) fn parse_origin_kinds(
+    rows: Vec<(Origin, String)>,
+) -> eyre::Result<Vec<(Origin, OriginKind)>> {
+    rows.into_iter()
+        .map(|(origin, kind)| Ok((origin, kind.parse()?)))
+        .collect()
+}
+
+/// The version of the frontend format, written in its header. Version 1 had no header: an
+/// output without one is read as version 1, where every relation is allowed.
+pub const FRONTEND_VERSION: u32 = 2;
+
+/// The relations added to the frontend format over time, by group. The header lists the groups
+/// with facts in the output, e.g. `// polonius-frontend v2, relations: liveness`, and a reader
+/// rejects the facts of a group the header doesn't list. The other relations are always allowed.
+pub const RELATION_GROUPS: &[(&str, &[&str])] = &[
+    ("liveness", &["origin_live_on_entry"]),
+    ("equalities", &["origin_equal"]),
+    ("origin_kinds", &["origin_kind"]),
+    (
+        "move_paths",
+        &[
+            "child_path",
+            "path_assigned_at",
+            "path_accessed_at",
+            "path_moved_at",
+        ],
+    ),
+    ("drop_liveness", &["drop_used_at"]),
+    ("refinements", &["discriminant_known"]),
+];
+
+/// Writes facts in the frontend format read by `parse_fact_program`, one entry per node in the
+/// order of `node_text`. Leaving out the text or the `goto` lines makes the output easier to
+/// compare for consumers which only care about the facts, but it can't be parsed back.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct FrontendWriter {
+    /// Whether each node is printed with its text, as `a: "x = 1" {` instead of `a: {`.
+    pub include_text: bool,
+
+    /// Whether each node ends with a `goto` line listing its successors.
+    pub include_gotos: bool,
+
+    /// Whether the nodes without facts are printed. Their `goto` lines aren't facts.
+    pub include_empty_nodes: bool,
+
+    /// Whether the `origin_kind` facts are printed, before the nodes.
+    pub include_origin_kinds: bool,
+
+    /// Whether the output starts with the `// polonius-frontend` header line, with the version
+    /// of the format and the `RELATION_GROUPS` of the facts printed.
+    pub include_header: bool,
+}
+
+impl Default for FrontendWriter {
+    fn default() -> Self {
+        FrontendWriter {
+            include_text: true,
+            include_gotos: true,
+            include_empty_nodes: true,
+            include_origin_kinds: false,
+            include_header: true,
+        }
+    }
+}
+
+impl FrontendWriter {
+    pub fn write(&self, facts: &Facts, f: &mut dyn fmt::Write) -> fmt::Result {
+        let index = facts.index_by_node();
+        if self.include_header {
+            write!(f, "// polonius-frontend v{}", FRONTEND_VERSION)?;
+            let groups = self.relation_groups(facts);
+            if !groups.is_empty() {
+                write!(f, ", relations: {}", groups.join(", "))?;
+            }
+            writeln!(f)?;
+        }
+        let mut first = true;
+        if self.include_origin_kinds && !facts.origin_kind.is_empty() {
+            for (origin, kind) in facts.origin_kinds() {
+                writeln!(f, "origin_kind({}, {})", origin, kind.name())?;
+            }
+            first = false;
+        }
+        if !facts.child_path.is_empty() {
+            for (child, parent) in facts.child_paths() {
+                writeln!(f, "child_path({}, {})", child, parent)?;
+            }
+            first = false;
+        }
+        for (text, node) in facts.node_texts() {
+            let node_facts = index.at(node);
+            if !self.include_empty_nodes && node_facts.is_empty() {
+                continue;
+            }
+            if !first {
+                writeln!(f)?;
+            }
+            first = false;
+
+            if self.include_text {
+                writeln!(f, "{}: \"{}\" {{", node, text)?;
+            } else {
+                writeln!(f, "{}: {{", node)?;
+            }
+
+            let unary = [
+                ("access_origin", &node_facts.access_origin),
+                ("invalidate_origin", &node_facts.invalidate_origin),
+                ("write_origin", &node_facts.write_origin),
+                ("clear_origin", &node_facts.clear_origin),
+                ("origin_live_on_entry", &node_facts.origin_live_on_entry),
+                ("path_assigned_at", &node_facts.path_assigned_at),
+                ("path_accessed_at", &node_facts.path_accessed_at),
+                ("path_moved_at", &node_facts.path_moved_at),
+                ("drop_used_at", &node_facts.drop_used_at),
+            ];
+            for (name, origins) in unary {
+                for origin in origins {
+                    writeln!(f, "    {}({})", name, origin)?;
+                }
+            }
+            for (o1, o2) in &node_facts.introduce_subset {
+                writeln!(f, "    introduce_subset({}, {})", o1, o2)?;
+            }
+            for (l1, l2) in &node_facts.incompatible_loans {
+                writeln!(f, "    incompatible_loans({}, {})", l1, l2)?;
+            }
+            if node_facts.is_exit {
+                writeln!(f, "    node_is_exit()")?;
+            }
+            for origin in &node_facts.assert_empty {
+                writeln!(f, "    assert_empty({})", origin)?;
+            }
+            for loan in &node_facts.loan_through_shared {
+                writeln!(f, "    loan_through_shared({})", loan)?;
+            }
+            for (o1, o2) in &node_facts.origin_equal {
+                writeln!(f, "    origin_equal({}, {})", o1, o2)?;
+            }
+            for (place, value) in &node_facts.discriminant_known {
+                writeln!(f, "    discriminant_known({}, {})", place, value)?;
+            }
+
+            if self.include_gotos {
+                write!(f, "    goto")?;
+                // Several edges to the same node, e.g. two arms of a `match`, each have their
+                // own label, in order.
+                for (i, successor) in node_facts.successors.iter().enumerate() {
+                    let earlier = node_facts.successors[..i]
+                        .iter()
+                        .filter(|s| *s == successor)
+                        .count();
+                    match node_facts
+                        .successor_labels
+                        .iter()
+                        .filter(|(n, _)| n == successor)
+                        .nth(earlier)
+                    {
+                        Some((_, label)) => write!(f, " [{}] {}", label, successor)?,
+                        None => write!(f, " {}", successor)?,
+                    }
+                }
+                writeln!(f)?;
+            }
+            writeln!(f, "}}")?;
+        }
+        Ok(())
+    }
+
+    /// The `RELATION_GROUPS` with facts printed by this writer.
+    fn relation_groups(&self, facts: &Facts) -> Vec<&'static str> {
+        let relations = facts.relations();
+        RELATION_GROUPS
+            .iter()
+            .filter(|(group, names)| {
+                (*group != "origin_kinds" || self.include_origin_kinds)
+                    && relations
+                        .iter()
+                        .any(|(name, rows)| names.contains(name) && !rows.is_empty())
+            })
+            .map(|(group, _)| *group)
+            .collect()
+    }
+
+    /// Writes `facts` into a string.
+    pub fn to_string(&self, facts: &Facts) -> String {
+        let mut output = String::new();
+        self.write(facts, &mut output)
+            .expect("writing to a string can't fail");
+        output
+    }
+}
+
+/// Prints the facts with the default `FrontendWriter`, in the format read by
+/// `parse_fact_program`.
+impl fmt::Display for Facts {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        FrontendWriter::default().write(self, f)
+    }
+}
+
+/// Normalizes the text of a statement for `node_text`: each line is stripped of its
+/// indentation, and the lines are joined with a single space. The result is always a single
+/// line, since facts are written one tuple per line. The text is data, e.g. the quoted text of
+/// a node in a fact program, so a `//` in it is kept.
+pub fn normalize_node_text(text: &str) -> String {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .join(" ")
+}
+
+/// Like `normalize_node_text`, for a statement sliced from the source of a program, where a
+/// `//` starts a comment, which is removed.
+pub fn normalize_source_text(text: &str) -> String {
+    let uncommented = text
+        .lines()
+        .map(|line| match line.find("//") {
+            Some(comment) => &line[..comment],
+            None => line,
+        })
+        .join("\n");
+    normalize_node_text(&uncommented)
+}
@@ -0,0 +1,203 @@
+//! A compact binary encoding of `Facts`, to cache the facts emitted for large inputs.
+//!
+//! The encoding starts with a header, the magic bytes `PLNF` and the format version, then
+//! interns every string of the facts into a table. Each relation is then written as its name
+//! and arity (0 when it is empty), followed by its rows as indices into the table. Integers
+//! are little-endian `u32`s.
+
+use std::collections::HashMap;
+use std::convert::TryInto;
+
+use itertools::Itertools;
+
+use super::Facts;
+
+const MAGIC: &[u8; 4] = b"PLNF";
+
+/// Bumped whenever the encoding, or the relations of `Facts`, change.
+pub const VERSION: u32 = 8;
+
+pub fn encode(facts: &Facts) -> Vec<u8> {
+    let relations = facts.relations();
+
+    let mut strings: Vec<&str> = vec![];
+    let mut indices: HashMap<&str, u32> = HashMap::new();
+    let mut intern = |s| {
+        *indices.entry(s).or_insert_with(|| {
+            strings.push(s);
+            strings.len() as u32 - 1
+        })
+    };
+    let relations: Vec<(u32, Vec<Vec<u32>>)> = relations
+        .iter()
+        .map(|(name, rows)| {
+            let rows = rows
+                .iter()
+                .map(|row| row.iter().map(|s| intern(s)).collect())
+                .collect();
+            (intern(name), rows)
+        })
+        .collect();
+
+    let mut bytes = MAGIC.to_vec();
+    let write = |n: u32, bytes: &mut Vec<u8>| bytes.extend(n.to_le_bytes());
+    write(VERSION, &mut bytes);
+    write(strings.len() as u32, &mut bytes);
+    for s in &strings {
+        write(s.len() as u32, &mut bytes);
+        bytes.extend(s.as_bytes());
+    }
+    write(relations.len() as u32, &mut bytes);
+    for (name, rows) in &relations {
+        write(*name, &mut bytes);
+        write(rows.first().map_or(0, Vec::len) as u32, &mut bytes);
+        write(rows.len() as u32, &mut bytes);
+        for index in rows.iter().flatten() {
+            write(*index, &mut bytes);
+        }
+    }
+    bytes
+}
+
+pub fn decode(bytes: &[u8]) -> eyre::Result<Facts> {
+    let mut reader = Reader { bytes, position: 0 };
+    if reader.take(MAGIC.len())? != MAGIC {
+        eyre::bail!("not a binary facts file");
+    }
+    let version = reader.u32()?;
+    if version != VERSION {
+        eyre::bail!(
+            "binary facts file has version {}, expected version {}",
+            version,
+            VERSION
+        );
+    }
+
+    let strings = (0..reader.u32()?)
+        .map(|_| {
+            let len = reader.u32()? as usize;
+            let s = std::str::from_utf8(reader.take(len)?)?;
+            Ok(s.to_string())
+        })
+        .collect::<eyre::Result<Vec<String>>>()?;
+    let string = |index: u32| {
+        strings
+            .get(index as usize)
+            .cloned()
+            .ok_or_else(|| eyre::eyre!("string index {} is out of bounds", index))
+    };
+
+    let mut relations: HashMap<String, Vec<Vec<String>>> = HashMap::new();
+    for _ in 0..reader.u32()? {
+        let name = string(reader.u32()?)?;
+        let arity = reader.u32()?;
+        let rows = (0..reader.u32()?)
+            .map(|_| (0..arity).map(|_| string(reader.u32()?)).collect())
+            .collect::<eyre::Result<_>>()?;
+        relations.insert(name, rows);
+    }
+    if reader.position != bytes.len() {
+        eyre::bail!("unexpected bytes after the relations");
+    }
+
+    let mut relation = |name: &str, arity: usize| -> eyre::Result<Vec<Vec<String>>> {
+        let rows = relations
+            .remove(name)
+            .ok_or_else(|| eyre::eyre!("missing relation `{}`", name))?;
+        if let Some(row) = rows.iter().find(|row| row.len() != arity) {
+            eyre::bail!("`{}` has {} columns, expected {}", name, row.len(), arity);
+        }
+        Ok(rows)
+    };
+    let mut pairs = |name| -> eyre::Result<Vec<(String, String)>> {
+        Ok(relation(name, 2)?
+            .into_iter()
+            .map(|row| row.into_iter().collect_tuple().unwrap())
+            .collect())
+    };
+    let access_origin = pairs("access_origin")?;
+    let cfg_edge = pairs("cfg_edge")?;
+    let clear_origin = pairs("clear_origin")?;
+    let invalidate_origin = pairs("invalidate_origin")?;
+    let node_text = pairs("node_text")?;
+    let write_origin = pairs("write_origin")?;
+    let origin_live_on_entry = pairs("origin_live_on_entry")?;
+    let assert_empty = pairs("assert_empty")?;
+    let loan_through_shared = pairs("loan_through_shared")?;
+    let origin_kind = super::parse_origin_kinds(pairs("origin_kind")?)?;
+    let child_path = pairs("child_path")?;
+    let path_assigned_at = pairs("path_assigned_at")?;
+    let path_accessed_at = pairs("path_accessed_at")?;
+    let path_moved_at = pairs("path_moved_at")?;
+    let drop_used_at = pairs("drop_used_at")?;
+    let mut triples = |name| -> eyre::Result<Vec<(String, String, String)>> {
+        Ok(relation(name, 3)?
+            .into_iter()
+            .map(|row| row.into_iter().collect_tuple().unwrap())
+            .collect())
+    };
+    let introduce_subset = triples("introduce_subset")?;
+    let incompatible_loans = triples("incompatible_loans")?;
+    let cfg_edge_label = triples("cfg_edge_label")?;
+    let origin_equal = triples("origin_equal")?;
+    let discriminant_known = triples("discriminant_known")?;
+    let node_is_exit = relation("node_is_exit", 1)?
+        .into_iter()
+        .map(|mut row| row.remove(0))
+        .collect();
+
+    Ok(Facts {
+        access_origin,
+        cfg_edge,
+        clear_origin,
+        introduce_subset,
+        invalidate_origin,
+        node_text,
+        write_origin,
+        origin_live_on_entry,
+        incompatible_loans,
+        node_is_exit,
+        assert_empty,
+        cfg_edge_label,
+        loan_through_shared,
+        origin_equal,
+        origin_kind,
+        child_path,
+        path_assigned_at,
+        path_accessed_at,
+        path_moved_at,
+        drop_used_at,
+        discriminant_known,
+    })
+}
+
+/// A 64-bit FNV-1a hash of `bytes`. Unlike the hashers of the standard library, it is stable
+/// across Rust versions, so it can key files written by another build.
+pub fn content_hash(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+struct Reader<'b> {
+    bytes: &'b [u8],
+    position: usize,
+}
+
+impl<'b> Reader<'b> {
+    fn take(&mut self, len: usize) -> eyre::Result<&'b [u8]> {
+        let end = self
+            .position
+            .checked_add(len)
+            .filter(|&end| end <= self.bytes.len())
+            .ok_or_else(|| eyre::eyre!("binary facts file is truncated"))?;
+        let bytes = &self.bytes[self.position..end];
+        self.position = end;
+        Ok(bytes)
+    }
+
+    fn u32(&mut self) -> eyre::Result<u32> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_le_bytes(bytes.try_into().unwrap()))
+    }
+}
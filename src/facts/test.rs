@@ -0,0 +1,418 @@
+use super::*;
+
+#[test]
+fn node_text_single_line() {
+    assert_eq!(normalize_node_text("x = &'L_p p"), "x = &'L_p p");
+    assert_eq!(normalize_node_text("  use(x)  "), "use(x)");
+}
+
+#[test]
+fn node_text_multi_line() {
+    let text = "v = MaybeNext(
+            t0, // the reborrow
+            t1
+        )";
+    assert_eq!(normalize_source_text(text), "v = MaybeNext( t0, t1 )");
+}
+
+#[test]
+fn node_text_in_fact_program() {
+    let facts = crate::parse_fact_program(
+        r#"
+        a: "x = f(
+                y
+            ) // call" {
+            goto
+        }
+        "#,
+    )
+    .unwrap();
+    assert_eq!(
+        facts.node_text,
+        vec![("x = f( y ) // call".to_string(), "a".to_string())]
+    );
+}
+
+#[test]
+fn node_text_keeps_slashes() {
+    // The text of a node is data, so a `//` in it isn't a comment.
+    let facts = crate::parse_fact_program(r#"a: "x = y // z" { goto }"#).unwrap();
+    assert_eq!(
+        facts.node_text,
+        vec![("x = y // z".to_string(), "a".to_string())]
+    );
+    assert_eq!(
+        crate::parse_fact_program(&facts.to_string()).unwrap(),
+        facts
+    );
+}
+
+#[test]
+fn crlf_line_endings() {
+    for input in [
+        include_str!("../../tests/example-a/program.txt"),
+        include_str!("../../tests/issue-47680/program.txt"),
+        include_str!("../../tests/vec-temp/program.txt"),
+    ] {
+        let facts = crate::parse_fact_program(input).unwrap();
+        let crlf_facts = crate::parse_fact_program(&input.replace('\n', "\r\n")).unwrap();
+        assert_eq!(facts, crlf_facts);
+    }
+}
+
+#[test]
+fn unicode_names_round_trip() {
+    let facts = crate::emit_facts(
+        "
+        let mut café: i32;
+        let 参照: &'β i32;
+        r#match: {
+            参照 = &'L_café café;
+            café = 1;
+        }
+    ",
+        Default::default(),
+    )
+    .unwrap();
+    assert!(facts
+        .invalidate_origin
+        .contains(&("'L_café".to_string(), "match[1]".to_string())));
+    let writer = FrontendWriter {
+        include_origin_kinds: true,
+        ..FrontendWriter::default()
+    };
+    let written = writer.to_string(&facts);
+    assert_eq!(crate::parse_fact_program(&written).unwrap(), facts);
+}
+
+#[test]
+fn index_by_node() {
+    let facts = crate::parse_fact_program(
+        r#"
+        a: "x = &'L_y y" {
+            clear_origin('L_y)
+            introduce_subset('L_y, 'x)
+            goto b c
+        }
+
+        b: "use(x)" {
+            access_origin('x)
+            goto c
+        }
+
+        c: "pass" {
+            goto
+        }
+        "#,
+    )
+    .unwrap();
+    let index = facts.index_by_node();
+
+    assert!(index.is_cleared("'L_y", "a"));
+    assert!(!index.is_cleared("'L_y", "b"));
+    assert_eq!(index.at("a").introduce_subset.len(), 1);
+    assert_eq!(index.at("b").access_origin, ["'x"]);
+    assert_eq!(index.successors("a").collect::<Vec<_>>(), ["b", "c"]);
+    assert_eq!(index.predecessors("c").collect::<Vec<_>>(), ["a", "b"]);
+    assert!(index.at("unknown").successors.is_empty());
+}
+
+#[test]
+fn node_is_exit_round_trips() {
+    let facts = crate::parse_fact_program(
+        r#"
+        a: "(storage dead)" {
+            node_is_exit()
+            goto
+        }
+        "#,
+    )
+    .unwrap();
+    assert_eq!(facts.node_is_exit, vec!["a".to_string()]);
+    assert_eq!(
+        crate::parse_fact_program(&facts.to_string()).unwrap(),
+        facts
+    );
+}
+
+#[test]
+fn discriminant_known_round_trips() {
+    let input = r#"
+        // polonius-frontend v2, relations: refinements
+        a: "match o" {
+            goto [Some] b [None] c
+        }
+        b: "x = &'L_o o.Some" {
+            discriminant_known(o, Some)
+            goto
+        }
+        c: "switch *p.n" {
+            discriminant_known(o, None)
+            goto [0] d
+        }
+        d: "pass" {
+            discriminant_known(*p.n, 0)
+            goto
+        }
+        "#;
+    let facts = crate::parse_fact_program(input).unwrap();
+    insta::assert_debug_snapshot!(facts.discriminant_known, @r###"
+    [
+        (
+            "o",
+            "Some",
+            "b",
+        ),
+        (
+            "o",
+            "None",
+            "c",
+        ),
+        (
+            "*p.n",
+            "0",
+            "d",
+        ),
+    ]
+    "###);
+    assert_eq!(
+        crate::parse_fact_program(&facts.to_string()).unwrap(),
+        facts
+    );
+    assert_eq!(binary::decode(&binary::encode(&facts)).unwrap(), facts);
+
+    let without_group = input.replace(", relations: refinements", "");
+    let error = crate::parse_fact_program(&without_group).unwrap_err();
+    insta::assert_snapshot!(format!("{:#}", error), @"fact `discriminant_known` is in the relation group `refinements`, which the header doesn't list");
+}
+
+#[test]
+fn frontend_header() {
+    let facts = crate::parse_fact_program(
+        r#"
+        a: "x = 1" {
+            origin_live_on_entry('x)
+            goto
+        }
+        "#,
+    )
+    .unwrap();
+    let written = facts.to_string();
+    insta::assert_snapshot!(written, @r###"
+    // polonius-frontend v2, relations: liveness
+    a: "x = 1" {
+        origin_live_on_entry('x)
+        goto
+    }
+
+    "###);
+    assert_eq!(crate::parse_fact_program(&written).unwrap(), facts);
+
+    let parse = |header: &str| {
+        let input = format!("{}\n{}", header, written.lines().skip(1).join("\n"));
+        crate::parse_fact_program(&input).map(|_| ())
+    };
+    assert!(parse("// polonius-frontend v1").is_ok());
+    assert!(parse("// polonius-frontend v2, relations: move_paths, liveness").is_ok());
+    let errors: Vec<_> = [
+        "// polonius-frontend v2",
+        "// polonius-frontend v2, relations: move_paths",
+        "// polonius-frontend v2, relations: liveness, borrows",
+        "// polonius-frontend v3, relations: liveness",
+    ]
+    .iter()
+    .map(|header| format!("{:#}", parse(header).unwrap_err()))
+    .collect();
+    insta::assert_debug_snapshot!(errors, @r###"
+    [
+        "fact `origin_live_on_entry` is in the relation group `liveness`, which the header doesn't list",
+        "fact `origin_live_on_entry` is in the relation group `liveness`, which the header doesn't list",
+        "unknown relation group `borrows` in the header, valid groups are `[\"liveness\", \"equalities\", \"origin_kinds\", \"move_paths\", \"drop_liveness\", \"refinements\"]`",
+        "unsupported frontend format version 3, expected a version from 1 to 2",
+    ]
+    "###);
+}
+
+#[test]
+fn frontend_writer_options() {
+    let facts = crate::parse_fact_program(
+        r#"
+        a: "x = &'L_y y" {
+            clear_origin('L_y)
+            introduce_subset('L_y, 'x)
+            goto b
+        }
+
+        b: "pass" {
+            goto c
+        }
+
+        c: "use(x)" {
+            access_origin('x)
+            goto
+        }
+        "#,
+    )
+    .unwrap();
+    let writer = FrontendWriter {
+        include_text: false,
+        include_gotos: false,
+        include_empty_nodes: false,
+        include_origin_kinds: false,
+        include_header: false,
+    };
+    insta::assert_snapshot!(writer.to_string(&facts), @r###"
+    a: {
+        clear_origin('L_y)
+        introduce_subset('L_y, 'x)
+    }
+
+    c: {
+        access_origin('x)
+    }
+
+    "###);
+    assert_eq!(
+        FrontendWriter::default().to_string(&facts),
+        facts.to_string()
+    );
+}
+
+#[test]
+fn binary_round_trip() {
+    for input in [
+        include_str!("../../tests/example-a/program.txt"),
+        include_str!("../../tests/issue-47680/program.txt"),
+        include_str!("../../tests/vec-temp/program.txt"),
+    ] {
+        let mut facts = crate::parse_fact_program(input).unwrap();
+        facts.node_is_exit.push(facts.node_text[0].1.clone());
+        facts
+            .assert_empty
+            .push(("'a".to_string(), facts.node_text[0].1.clone()));
+        let bytes = binary::encode(&facts);
+        assert_eq!(binary::decode(&bytes).unwrap(), facts);
+    }
+}
+
+#[test]
+fn binary_errors() {
+    let facts = crate::parse_fact_program(include_str!("../../tests/example-a/program.txt"));
+    let mut bytes = binary::encode(&facts.unwrap());
+
+    let truncated = binary::decode(&bytes[..bytes.len() - 1]).unwrap_err();
+    insta::assert_snapshot!(truncated.to_string(), @"binary facts file is truncated");
+
+    bytes[4] = 0;
+    let other_version = binary::decode(&bytes).unwrap_err();
+    insta::assert_snapshot!(other_version.to_string(), @"binary facts file has version 0, expected version 8");
+
+    let not_facts = binary::decode(b"digraph").unwrap_err();
+    insta::assert_snapshot!(not_facts.to_string(), @"not a binary facts file");
+}
+
+#[test]
+fn load_cached() {
+    let cache_dir =
+        std::env::temp_dir().join(format!("polonius-facts-cache-{}", std::process::id()));
+    let input = include_str!("../../tests/example-a/program.txt");
+
+    let mut emitted = 0;
+    let mut emit = |input: &str| {
+        emitted += 1;
+        crate::parse_fact_program(input)
+    };
+    let first = Facts::load_cached(&cache_dir, input, &mut emit).unwrap();
+    let second = Facts::load_cached(&cache_dir, input, &mut emit).unwrap();
+    let other = Facts::load_cached(&cache_dir, "a: \"pass\" { goto }", &mut emit).unwrap();
+    std::fs::remove_dir_all(&cache_dir).unwrap();
+
+    assert_eq!(emitted, 2);
+    assert_eq!(first, second);
+    assert_eq!(other.node_text.len(), 1);
+}
+
+#[test]
+fn labeled_gotos_round_trip() {
+    let facts = crate::parse_fact_program(
+        r#"
+        a: "x = 1" {
+            goto [then] b [0.25] c a
+        }
+        b: "pass" {
+            goto
+        }
+        c: "pass" {
+            goto
+        }
+        "#,
+    )
+    .unwrap();
+    insta::assert_debug_snapshot!(facts.cfg_edge_label, @r###"
+    [
+        (
+            "a",
+            "b",
+            "then",
+        ),
+        (
+            "a",
+            "c",
+            "0.25",
+        ),
+    ]
+    "###);
+    assert_eq!(
+        crate::parse_fact_program(&facts.to_string()).unwrap(),
+        facts
+    );
+    assert_eq!(binary::decode(&binary::encode(&facts)).unwrap(), facts);
+}
+
+#[test]
+fn merge() {
+    let fragment = crate::parse_fact_program(
+        "
+        a: \"r = &'L_x x\" { introduce_subset('L_x, 'r) goto [loop] b }
+        b: \"x = 1\" { invalidate_origin('L_x) node_is_exit() goto }
+        ",
+    )
+    .unwrap();
+    let mut facts = Facts::default();
+    facts.merge(&fragment, "f");
+    facts.merge(&fragment, "g");
+    insta::assert_snapshot!(facts.to_string(), @r###"
+    // polonius-frontend v2
+    f_a: "r = &'L_x x" {
+        introduce_subset('f_L_x, 'f_r)
+        goto [loop] f_b
+    }
+
+    f_b: "x = 1" {
+        invalidate_origin('f_L_x)
+        node_is_exit()
+        goto
+    }
+
+    g_a: "r = &'L_x x" {
+        introduce_subset('g_L_x, 'g_r)
+        goto [loop] g_b
+    }
+
+    g_b: "x = 1" {
+        invalidate_origin('g_L_x)
+        node_is_exit()
+        goto
+    }
+
+    "###);
+
+    // Without a prefix, the fragments share their names.
+    let mut facts = fragment.clone();
+    facts.merge(&fragment, "");
+    assert_eq!(facts.invalidate_origin.len(), 2);
+    assert!(facts
+        .invalidate_origin
+        .iter()
+        .all(|(o, n)| o == "'L_x" && n == "b"));
+}
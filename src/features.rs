@@ -0,0 +1,123 @@
+//! The language features the fact emitter supports, and the error it returns when a program
+//! uses one it doesn't, so that failures over a corpus of programs can be bucketed by missing
+//! feature.
+
+use std::fmt;
+
+#[cfg(test)]
+mod test;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Feature {
+    SharedBorrows,
+    MutableBorrows,
+    Reborrows,
+    Structs,
+    StructVariance,
+    GenericFunctions,
+    ExternTypes,
+    FnPointers,
+    ReturnPlace,
+    TypeAliases,
+    ExternFns,
+
+    /// Relating function pointers which bind origins differently, like `for<'x> fn(&'x i32)`
+    /// and `fn(&'y i32)`.
+    HigherRankedSubtyping,
+
+    /// Relating generic types which are neither declared as structs nor as extern types.
+    OpaqueGenericTypes,
+
+    /// Bounds of type parameters naming a type rather than an origin, like `T: U`.
+    TypeBounds,
+
+    /// Relating the origins of the arguments of a call to the origins of its result, according
+    /// to the signature of the function.
+    CallSignatures,
+}
+
+impl Feature {
+    pub const ALL: &'static [Feature] = &[
+        Feature::SharedBorrows,
+        Feature::MutableBorrows,
+        Feature::Reborrows,
+        Feature::Structs,
+        Feature::StructVariance,
+        Feature::GenericFunctions,
+        Feature::ExternTypes,
+        Feature::FnPointers,
+        Feature::ReturnPlace,
+        Feature::TypeAliases,
+        Feature::ExternFns,
+        Feature::HigherRankedSubtyping,
+        Feature::OpaqueGenericTypes,
+        Feature::TypeBounds,
+        Feature::CallSignatures,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Feature::SharedBorrows => "shared-borrows",
+            Feature::MutableBorrows => "mutable-borrows",
+            Feature::Reborrows => "reborrows",
+            Feature::Structs => "structs",
+            Feature::StructVariance => "struct-variance",
+            Feature::GenericFunctions => "generic-functions",
+            Feature::ExternTypes => "extern-types",
+            Feature::FnPointers => "fn-pointers",
+            Feature::ReturnPlace => "return-place",
+            Feature::TypeAliases => "type-aliases",
+            Feature::ExternFns => "extern-fns",
+            Feature::HigherRankedSubtyping => "higher-ranked-subtyping",
+            Feature::OpaqueGenericTypes => "opaque-generic-types",
+            Feature::TypeBounds => "type-bounds",
+            Feature::CallSignatures => "call-signatures",
+        }
+    }
+
+    pub fn is_supported(self) -> bool {
+        !matches!(
+            self,
+            Feature::HigherRankedSubtyping | Feature::OpaqueGenericTypes | Feature::TypeBounds
+        )
+    }
+}
+
+/// Each feature, and whether the emitter supports it.
+pub fn capabilities() -> Vec<(Feature, bool)> {
+    Feature::ALL
+        .iter()
+        .map(|&feature| (feature, feature.is_supported()))
+        .collect()
+}
+
+/// The error returned when a program uses an unsupported feature.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Unsupported {
+    pub feature: Feature,
+
+    /// The construct using the feature, printed back from the AST, as it has no source
+    /// positions.
+    pub span: String,
+}
+
+impl fmt::Display for Unsupported {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "unsupported feature `{}`, in `{}`",
+            self.feature.name(),
+            self.span
+        )
+    }
+}
+
+impl std::error::Error for Unsupported {}
+
+/// The unsupported feature which caused `error`, if any.
+pub fn unsupported_feature(error: &eyre::Report) -> Option<Feature> {
+    error
+        .chain()
+        .find_map(|e| e.downcast_ref::<Unsupported>())
+        .map(|unsupported| unsupported.feature)
+}
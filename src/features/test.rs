@@ -0,0 +1,54 @@
+use super::*;
+use crate::fact_emitter::Options;
+
+#[test]
+fn capability_matrix() {
+    let unsupported: Vec<_> = capabilities()
+        .into_iter()
+        .filter(|(_, supported)| !supported)
+        .map(|(feature, _)| feature.name())
+        .collect();
+    insta::assert_debug_snapshot!(unsupported, @r###"
+    [
+        "higher-ranked-subtyping",
+        "opaque-generic-types",
+        "type-bounds",
+    ]
+    "###);
+}
+
+#[test]
+fn opaque_generic_types() {
+    let err = crate::emit_facts(
+        "
+        let a: Opaque<'a>;
+        let b: Opaque<'b>;
+        bb0: {
+            b = move a;
+        }
+    ",
+        Options::default(),
+    )
+    .unwrap_err();
+
+    assert_eq!(unsupported_feature(&err), Some(Feature::OpaqueGenericTypes));
+    insta::assert_snapshot!(format!("{:#}", err), @"in statement `b = move a` of `bb0`: unsupported feature `opaque-generic-types`, in `Opaque`");
+}
+
+#[test]
+fn type_bounds() {
+    // The parser only accepts origins as bounds, but the bound of an AST built by another tool
+    // may name a type parameter.
+    let mut program = crate::parse_ast(
+        "struct S<'a, T: 'a, U> { x: &'a T, u: U } let s: S<'s, &'t i32, i32>; bb0: {}",
+    )
+    .unwrap();
+    program.struct_decls[0].generic_decls[1] =
+        crate::ast::GenericDecl::Ty("T".to_string(), vec!["U".to_string()]);
+    let err = crate::typeck::TypeEnv::new(&program)
+        .outlives_bounds(&program.variables[0].ty)
+        .unwrap_err();
+
+    assert_eq!(unsupported_feature(&err), Some(Feature::TypeBounds));
+    insta::assert_snapshot!(format!("{:#}", err), @"unsupported feature `type-bounds`, in `T: U`");
+}
@@ -0,0 +1,277 @@
+//! A generator of random programs, biased toward the constructs exercising a given path of the
+//! emitter: borrows through references, assignments of structs, or values returned by calls.
+//!
+//! Each generated program is checked for the failures `shrink` looks for, a panic or solvers
+//! which disagree, and shrunk to a reproducer when it fails. The arms of `relate_tys` each
+//! program takes are read from its trace, to find the subtyping cases no program reaches.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+use std::str::FromStr;
+
+use crate::ast::Program;
+use crate::fact_emitter::{FactEmitter, Options, RelateArm};
+use crate::shrink::{self, Failure};
+
+#[cfg(test)]
+mod test;
+
+/// The emitter paths a generated program can be biased toward.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Focus {
+    /// Borrows of places behind references, like `&'L *p` and `&'L mut *m`.
+    DerefBorrows,
+
+    /// Assignments of structs, and of their fields.
+    StructAssignments,
+
+    /// Values returned by calls, and copies of function pointers.
+    CallReturns,
+}
+
+impl Focus {
+    pub const ALL: &'static [Focus] = &[
+        Focus::DerefBorrows,
+        Focus::StructAssignments,
+        Focus::CallReturns,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Focus::DerefBorrows => "deref-borrows",
+            Focus::StructAssignments => "struct-assignments",
+            Focus::CallReturns => "call-returns",
+        }
+    }
+
+    fn templates(self) -> &'static [&'static str] {
+        match self {
+            Focus::DerefBorrows => DEREF_BORROWS,
+            Focus::StructAssignments => STRUCT_ASSIGNMENTS,
+            Focus::CallReturns => CALL_RETURNS,
+        }
+    }
+}
+
+impl FromStr for Focus {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> eyre::Result<Self> {
+        Focus::ALL
+            .iter()
+            .copied()
+            .find(|focus| focus.name() == s)
+            .ok_or_else(|| {
+                let names: Vec<_> = Focus::ALL.iter().map(|f| f.name()).collect();
+                eyre::eyre!("unknown focus `{}`, valid focuses are `{:?}`", s, names)
+            })
+    }
+}
+
+/// The declarations shared by the generated programs, which the statements use.
+const PRELUDE: &str = "
+struct Pair<'a> { first: &'a i32, second: &'a i32 }
+fn id<'a>(r: &'a i32) -> &'a i32;
+fn pick<'a, 'b>(x: &'a i32, y: &'b i32) -> &'a i32;
+let mut x: i32;
+let mut y: i32;
+let mut p: &'p i32;
+let mut q: &'q i32;
+let mut m: &'m mut i32;
+let mut n: &'n mut i32;
+let mut pair: Pair<'pair>;
+let mut g: fn(&'g i32) -> &'g i32;
+let mut h: fn(&'h i32) -> &'h i32;
+";
+
+// The statement templates, where `{L}` is replaced by a fresh loan.
+const COMMON: &[&str] = &[
+    "x = 1;",
+    "y = copy x;",
+    "p = &'{L} x;",
+    "q = &'{L} y;",
+    "m = &'{L} mut y;",
+    "x = copy *p;",
+    "y = copy *m;",
+];
+const DEREF_BORROWS: &[&str] = &[
+    "q = &'{L} *p;",
+    "p = &'{L} *m;",
+    "n = &'{L} mut *m;",
+    "m = move n;",
+];
+const STRUCT_ASSIGNMENTS: &[&str] = &[
+    "pair = Pair { first: copy p, second: copy q };",
+    "pair.first = copy q;",
+    "pair.second = &'{L} x;",
+    "p = copy pair.second;",
+];
+const CALL_RETURNS: &[&str] = &[
+    "q = id(copy p);",
+    "p = pick(copy q, &'{L} x);",
+    "p = id(&'{L} *q);",
+    "h = copy g;",
+];
+
+/// A xorshift generator of programs, seeded so that a program can be generated again from its
+/// seed.
+pub struct Generator {
+    state: u64,
+    loans: usize,
+}
+
+impl Generator {
+    pub fn new(seed: u64) -> Self {
+        // The state must not be zero, and close seeds should diverge right away.
+        Generator {
+            state: seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1,
+            loans: 0,
+        }
+    }
+
+    fn next(&mut self) -> u64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        self.state
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    /// Generates a program of 1 to 3 blocks, each of 1 to 4 statements, which may loop. With a
+    /// `focus`, two statements out of three are drawn from its templates.
+    pub fn program(&mut self, focus: Option<Focus>) -> eyre::Result<Program> {
+        let mut text = PRELUDE.to_string();
+        let blocks = 1 + self.below(3);
+        for b in 0..blocks {
+            text.push_str(&format!("bb{}: {{\n", b));
+            for _ in 0..1 + self.below(4) {
+                let templates = match focus {
+                    Some(focus) if self.below(3) > 0 => focus.templates(),
+                    _ => COMMON,
+                };
+                let template = templates[self.below(templates.len())];
+                let loan = format!("L{}", self.loans);
+                if template.contains("{L}") {
+                    self.loans += 1;
+                }
+                text.push_str(&format!("    {}\n", template.replace("{L}", &loan)));
+            }
+            // Each block goes to the next one, so that they are all reachable, and sometimes
+            // to another one, which may loop. The last block may return.
+            let mut targets = vec![];
+            if b + 1 < blocks {
+                targets.push(b + 1);
+            }
+            if self.below(2) == 0 {
+                let target = self.below(blocks);
+                if !targets.contains(&target) {
+                    targets.push(target);
+                }
+            }
+            if !targets.is_empty() {
+                let targets: Vec<_> = targets.iter().map(|t| format!("bb{}", t)).collect();
+                text.push_str(&format!("    goto {};\n", targets.join(", ")));
+            }
+            text.push_str("}\n");
+        }
+        crate::parse_ast(&text)
+    }
+}
+
+/// The errors of the analysis which are failures, rather than programs the emitter rejects.
+const FAILING_ERRORS: &[&str] = &[
+    "the naive and optimized algorithms disagree",
+    "the location-insensitive algorithm missed",
+];
+
+/// How analyzing `program` fails, if it does.
+fn failure(program: &Program) -> Option<Failure> {
+    let analysis = panic::catch_unwind(AssertUnwindSafe(|| shrink::analyze(program)));
+    let error = match analysis {
+        Err(_) => return Some(Failure::Panics),
+        Ok(Ok(_)) => return None,
+        Ok(Err(error)) => format!("{:#}", error),
+    };
+    FAILING_ERRORS
+        .iter()
+        .find(|text| error.contains(*text))
+        .map(|text| Failure::ErrorContains(text.to_string()))
+}
+
+/// A generated program on which the analysis fails, shrunk.
+#[derive(Clone, Debug)]
+pub struct Failing {
+    pub seed: u64,
+    pub failure: Failure,
+    pub program: Program,
+}
+
+/// The arms of `relate_tys` the generated programs took, and the programs which failed.
+#[derive(Clone, Debug, Default)]
+pub struct Report {
+    pub programs: usize,
+
+    /// The programs the emitter returns an error for, e.g. for a conflicting borrow of a
+    /// place behind a shared reference.
+    pub rejected: usize,
+
+    /// The number of programs taking each arm.
+    pub arms: BTreeMap<RelateArm, usize>,
+    pub failing: Vec<Failing>,
+}
+
+/// Generates a program for each of the seeds `seeds`, and checks it.
+pub fn run(seeds: std::ops::Range<u64>, focus: Option<Focus>) -> eyre::Result<Report> {
+    let mut report = Report::default();
+    for seed in seeds {
+        let program = Generator::new(seed).program(focus)?;
+        report.programs += 1;
+
+        if let Some(failure) = failure(&program) {
+            let program = shrink::shrink(&program, |p| failure.occurs_in(p))?;
+            report.failing.push(Failing {
+                seed,
+                failure,
+                program,
+            });
+            continue;
+        }
+
+        match FactEmitter::trace_program(program, Options::default()) {
+            Ok((_, trace)) => {
+                for arm in trace.relate_arms {
+                    *report.arms.entry(arm).or_default() += 1;
+                }
+            }
+            Err(_) => report.rejected += 1,
+        }
+    }
+    Ok(report)
+}
+
+/// Prints the number of programs taking each arm, including the arms no program took, then
+/// the failing programs.
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} programs, {} rejected, {} failing",
+            self.programs,
+            self.rejected,
+            self.failing.len()
+        )?;
+        for &arm in RelateArm::ALL {
+            let count = self.arms.get(&arm).copied().unwrap_or(0);
+            writeln!(f, "{}: {}", arm.name(), count)?;
+        }
+        for failing in &self.failing {
+            writeln!(f, "\nseed {}, {:?}:", failing.seed, failing.failure)?;
+            write!(f, "{}", failing.program)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,148 @@
+//! Checks that a `tests/<example>` directory is internally consistent, so that a broken
+//! example fails with a description of what is wrong instead of an opaque diff of the
+//! solver output.
+
+use std::collections::BTreeSet;
+use std::path::Path;
+
+use eyre::WrapErr;
+
+use crate::facts::Facts;
+
+#[cfg(test)]
+mod test;
+
+/// Checks the example in `path`:
+///
+/// * `program.txt` parses, and every node it mentions is declared,
+/// * if present, the `facts` directory parses, and agrees with `program.txt` on the nodes
+///   and the edges of the CFG,
+/// * the expected `invalidated_origin_accessed.csv` and `expected.txt` only mention declared
+///   nodes, and `expected.txt` parses,
+/// * if present, `budget.txt` parses.
+///
+/// All the problems found are reported in a single error.
+pub fn check_test_dir(path: &Path) -> eyre::Result<()> {
+    let input_path = path.join("program.txt");
+    let input = std::fs::read_to_string(&input_path)
+        .wrap_err_with(|| format!("failed to read `{}`", input_path.display()))?;
+    let facts = crate::parse_fact_program(&input)
+        .wrap_err_with(|| format!("failed to parse `{}`", input_path.display()))?;
+
+    let mut problems = vec![];
+    check_node_references(&facts, "program.txt", &mut problems);
+
+    let facts_path = path.join("facts");
+    if facts_path.exists() {
+        match Facts::read_from_dir(&facts_path) {
+            Ok(written) => {
+                check_node_references(&written, "facts", &mut problems);
+                check_same_cfg(&facts, &written, &mut problems);
+            }
+            Err(e) => problems.push(format!("{:#}", e)),
+        }
+    }
+
+    let expected_path = path.join("invalidated_origin_accessed.csv");
+    if expected_path.exists() {
+        let expected = std::fs::read_to_string(&expected_path)?;
+        let nodes: BTreeSet<_> = facts.nodes().into_iter().collect();
+        for (i, line) in expected.lines().enumerate() {
+            let row: Vec<&str> = line.split('\t').collect();
+            match row[..] {
+                [_, node] if !nodes.contains(&node.to_string()) => problems.push(format!(
+                    "invalidated_origin_accessed.csv, line {}: unknown node `{}`",
+                    i + 1,
+                    node
+                )),
+                [_, _] => {}
+                _ => problems.push(format!(
+                    "invalidated_origin_accessed.csv, line {}: expected 2 columns, found {}",
+                    i + 1,
+                    row.len()
+                )),
+            }
+        }
+    }
+
+    let expected_path = path.join("expected.txt");
+    if expected_path.exists() {
+        let expected = std::fs::read_to_string(&expected_path)?;
+        match crate::expected::parse_expected(&expected) {
+            Ok(diagnostics) => {
+                let nodes: BTreeSet<_> = facts.nodes().into_iter().collect();
+                for diagnostic in diagnostics.iter().filter(|d| !nodes.contains(&d.node)) {
+                    problems.push(format!(
+                        "expected.txt: unknown node `{}` in `{}`",
+                        diagnostic.node, diagnostic
+                    ));
+                }
+            }
+            Err(e) => problems.push(format!("expected.txt, {}", e)),
+        }
+    }
+
+    let budget_path = path.join("budget.txt");
+    if budget_path.exists() {
+        let budget = std::fs::read_to_string(&budget_path)?;
+        if let Err(e) = crate::budget::parse_budget(&budget) {
+            problems.push(format!("budget.txt, {}", e));
+        }
+    }
+
+    if !problems.is_empty() {
+        eyre::bail!(
+            "`{}` is inconsistent:\n{}",
+            path.display(),
+            problems.join("\n")
+        );
+    }
+    Ok(())
+}
+
+/// Checks that every node mentioned in `facts` is declared with a `node_text`, exactly once.
+fn check_node_references(facts: &Facts, source: &str, problems: &mut Vec<String>) {
+    let mut nodes = BTreeSet::new();
+    for node in facts.nodes() {
+        if !nodes.insert(node) {
+            problems.push(format!("{}: node `{}` is declared twice", source, node));
+        }
+    }
+
+    let mut reported = BTreeSet::new();
+    for (relation, node) in facts.node_references() {
+        if !nodes.contains(node) && reported.insert((relation, node)) {
+            problems.push(format!(
+                "{}: `{}` mentions the unknown node `{}`",
+                source, relation, node
+            ));
+        }
+    }
+}
+
+/// Checks that the written facts have the same nodes and gotos as the program.
+fn check_same_cfg(program: &Facts, written: &Facts, problems: &mut Vec<String>) {
+    let program_nodes: BTreeSet<_> = program.nodes().into_iter().collect();
+    let written_nodes: BTreeSet<_> = written.nodes().into_iter().collect();
+    for node in program_nodes.symmetric_difference(&written_nodes) {
+        problems.push(format!(
+            "facts: node `{}` is only declared in one of `program.txt` and `facts`",
+            node
+        ));
+    }
+
+    let program_edges: BTreeSet<_> = program.cfg_edge.iter().collect();
+    let written_edges: BTreeSet<_> = written.cfg_edge.iter().collect();
+    for (n1, n2) in program_edges.difference(&written_edges) {
+        problems.push(format!(
+            "facts: `goto {}` in node `{}` is missing from `cfg_edge`",
+            n2, n1
+        ));
+    }
+    for (n1, n2) in written_edges.difference(&program_edges) {
+        problems.push(format!(
+            "facts: `cfg_edge({}, {})` has no matching `goto` in `program.txt`",
+            n1, n2
+        ));
+    }
+}
@@ -0,0 +1,145 @@
+use super::*;
+
+/// Writes the files of an example into a fresh directory.
+fn example_dir(name: &str, files: &[(&str, &str)]) -> std::path::PathBuf {
+    let path =
+        std::env::temp_dir().join(format!("polonius-golden-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&path);
+    std::fs::create_dir_all(&path).unwrap();
+    for (file, contents) in files {
+        std::fs::write(path.join(file), contents).unwrap();
+    }
+    path
+}
+
+fn check(name: &str, files: &[(&str, &str)]) -> String {
+    let path = example_dir(name, files);
+    let result = check_test_dir(&path);
+    std::fs::remove_dir_all(&path).unwrap();
+    match result {
+        Ok(()) => "ok".to_string(),
+        // The directory name is different on each run.
+        Err(e) => format!("{:#}", e).replace(&path.display().to_string(), "<dir>"),
+    }
+}
+
+#[test]
+fn consistent() {
+    let result = check(
+        "consistent",
+        &[
+            (
+                "program.txt",
+                "a: \"x = 1\" { goto b } b: \"use(x)\" { goto }",
+            ),
+            ("invalidated_origin_accessed.csv", "'x\tb\n"),
+        ],
+    );
+    assert_eq!(result, "ok");
+}
+
+#[test]
+fn unknown_nodes() {
+    let result = check(
+        "unknown-nodes",
+        &[
+            (
+                "program.txt",
+                "a: \"x = 1\" { goto c } b: \"use(x)\" { goto }",
+            ),
+            ("invalidated_origin_accessed.csv", "'x\td\n'y\n"),
+        ],
+    );
+    insta::assert_snapshot!(result, @r###"
+    `<dir>` is inconsistent:
+    program.txt: `cfg_edge` mentions the unknown node `c`
+    invalidated_origin_accessed.csv, line 1: unknown node `d`
+    invalidated_origin_accessed.csv, line 2: expected 2 columns, found 1
+    "###);
+}
+
+#[test]
+fn stale_facts() {
+    let path = example_dir(
+        "stale-facts",
+        &[(
+            "program.txt",
+            "a: \"x = 1\" { goto b } b: \"use(x)\" { goto }",
+        )],
+    );
+    let mut facts =
+        crate::parse_fact_program("a: \"x = 1\" { goto } b: \"use(x)\" { goto a }").unwrap();
+    facts.node_text.pop();
+    std::fs::create_dir_all(path.join("facts")).unwrap();
+    facts.write_to_dir(&path.join("facts")).unwrap();
+
+    let result = check_test_dir(&path);
+    std::fs::remove_dir_all(&path).unwrap();
+    let result = format!("{:#}", result.unwrap_err()).replace(&path.display().to_string(), "<dir>");
+    insta::assert_snapshot!(result, @r###"
+    `<dir>` is inconsistent:
+    facts: `cfg_edge` mentions the unknown node `b`
+    facts: node `b` is only declared in one of `program.txt` and `facts`
+    facts: `goto b` in node `a` is missing from `cfg_edge`
+    facts: `cfg_edge(b, a)` has no matching `goto` in `program.txt`
+    "###);
+}
+
+#[test]
+fn expected_diagnostics() {
+    let program = (
+        "program.txt",
+        "a: \"x = 1\" { goto b } b: \"use(x)\" { goto }",
+    );
+    let result = check(
+        "expected-diagnostics",
+        &[
+            program,
+            (
+                "expected.txt",
+                "error invalidated_origin_accessed('x) @ b\n",
+            ),
+        ],
+    );
+    assert_eq!(result, "ok");
+
+    let result = check(
+        "bad-expected-diagnostics",
+        &[
+            program,
+            (
+                "expected.txt",
+                "error invalidated_origin_accessed('x) @ c\n",
+            ),
+        ],
+    );
+    insta::assert_snapshot!(result, @r###"
+    `<dir>` is inconsistent:
+    expected.txt: unknown node `c` in `error invalidated_origin_accessed('x) @ c`
+    "###);
+
+    let result = check(
+        "malformed-expected-diagnostics",
+        &[program, ("expected.txt", "error 'x b\n")],
+    );
+    insta::assert_snapshot!(result, @r###"
+    `<dir>` is inconsistent:
+    expected.txt, line 1: expected `<severity> <relation>(<origins>) @ <node>`, found `error 'x b`
+    "###);
+}
+
+#[test]
+fn malformed_budget() {
+    let program = (
+        "program.txt",
+        "a: \"x = 1\" { goto b } b: \"use(x)\" { goto }",
+    );
+    let result = check(
+        "malformed-budget",
+        &[program, ("budget.txt", "time_ms 100\ntuples many\n")],
+    );
+    insta::assert_snapshot!(result, @r###"
+    `<dir>` is inconsistent:
+    budget.txt, line 2: expected `time_ms <n>` or `tuples <n>`, found `tuples many`
+    "###);
+}
@@ -1,5 +1,4 @@
 use glob::glob;
-use html_escape;
 use itertools::Itertools;
 use std::{collections::HashMap, fs, io::Write, path::Path, process::Command};
 const IMPORTANT_RELATIONS: &[&str] = &["invalidated_origin_accessed"];
@@ -9,6 +8,7 @@ struct Data {
     pub(crate) node_texts: HashMap<String, String>,
     pub(crate) input_per_node: HashMap<String, Vec<(String, Importance)>>,
     pub(crate) node_predecessors: HashMap<String, Vec<String>>,
+    pub(crate) edge_labels: HashMap<(String, String), String>,
     pub(crate) output_per_node: HashMap<String, Vec<(String, Importance)>>,
 }
 
@@ -18,18 +18,13 @@ impl Data {
     }
 }
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
 enum Importance {
     High,
+    #[default]
     Low,
 }
 
-impl Default for Importance {
-    fn default() -> Self {
-        Self::Low
-    }
-}
-
 impl Importance {
     fn style(&self) -> &'static str {
         match self {
@@ -67,12 +62,12 @@ pub(crate) fn create_graph(fact_directory: &Path, output_file_path: &Path) {
         .filter_map(Result::ok)
     {
         let relation = path.file_stem().unwrap().to_str().unwrap();
-        let facts = fs::read_to_string(&path).expect(&format!(
-            "could not read relation file '{}'",
-            path.to_string_lossy()
-        ));
+        let facts = fs::read_to_string(&path).unwrap_or_else(|_| {
+            panic!("could not read relation file '{}'", path.to_string_lossy())
+        });
 
-        // Except `cfg_edge`, all input relations have the node location as the last atom
+        // Except `cfg_edge` and `cfg_edge_label`, all input relations have the node location as
+        // the last atom
         for line in facts.lines() {
             let mut atoms = line.split('\t');
             match relation {
@@ -101,6 +96,14 @@ pub(crate) fn create_graph(fact_directory: &Path, output_file_path: &Path) {
                         .unwrap()
                         .push(p.to_string());
                 }
+                "cfg_edge_label" => {
+                    // The labels of the graphviz edges
+                    let p = atoms.next().unwrap();
+                    let q = atoms.next().unwrap();
+                    let label = atoms.next().unwrap();
+                    data.edge_labels
+                        .insert((p.to_string(), q.to_string()), label.to_string());
+                }
                 _ => {
                     // Actual facts happening at the node
                     let node = atoms.next_back().unwrap();
@@ -125,10 +128,9 @@ pub(crate) fn create_graph(fact_directory: &Path, output_file_path: &Path) {
         .filter_map(Result::ok)
     {
         let relation = path.file_stem().unwrap().to_str().unwrap();
-        let facts = fs::read_to_string(&path).expect(&format!(
-            "could not read relation file '{}'",
-            path.to_string_lossy()
-        ));
+        let facts = fs::read_to_string(&path).unwrap_or_else(|_| {
+            panic!("could not read relation file '{}'", path.to_string_lossy())
+        });
 
         for line in facts.lines() {
             let mut atoms = line.split('\t');
@@ -163,7 +165,7 @@ pub(crate) fn create_graph(fact_directory: &Path, output_file_path: &Path) {
         // - if output facts exists, a separator, then a row per output fact
         // - edges from the predecessors to the node, if any
         let mut rows: Vec<_> = input_facts
-            .into_iter()
+            .iter()
             .sorted()
             .map(|(fact, importance)| {
                 format!(r#"    <tr><td{}>{}</td></tr>"#, importance.style(), fact)
@@ -172,13 +174,13 @@ pub(crate) fn create_graph(fact_directory: &Path, output_file_path: &Path) {
         if data.output_per_node.contains_key(node) {
             let output_facts = &data.output_per_node[node];
             rows.push("    <tr><td>-------------------</td></tr>".into());
-            rows.extend(output_facts.into_iter().sorted().map(|(fact, importance)| {
+            rows.extend(output_facts.iter().sorted().map(|(fact, importance)| {
                 format!(r#"    <tr><td{}>{}</td></tr>"#, importance.style(), fact)
             }));
         }
         let lines: String = Itertools::intersperse(rows.iter().map(|s| s.as_str()), "\n").collect();
         output_dot += &format!(
-            r#"    {} [ label = <<table border="0">
+            r#"    "{}" [ label = <<table border="0">
     <tr><td>{}</td></tr>
     <tr><td>-------------------</td></tr>
 {}
@@ -188,7 +190,10 @@ pub(crate) fn create_graph(fact_directory: &Path, output_file_path: &Path) {
 
         if let Some(preds) = data.node_predecessors.get(node) {
             for pred in preds {
-                output_dot += &format!("    {} -> {}", pred, node);
+                output_dot += &format!(r#"    "{}" -> "{}""#, pred, node);
+                if let Some(label) = data.edge_labels.get(&(pred.clone(), node.clone())) {
+                    output_dot += &format!(r#" [ label = "{}" ]"#, label);
+                }
             }
             output_dot += "\n";
         }
@@ -199,21 +204,19 @@ pub(crate) fn create_graph(fact_directory: &Path, output_file_path: &Path) {
     let mut output_file = fs::OpenOptions::new()
         .write(true)
         .create(true)
+        .truncate(true)
         .open(&output_file_path)
         .expect("could not open output file");
     output_file
-        .write(output_dot.as_bytes())
+        .write_all(output_dot.as_bytes())
         .expect("could not write to output file");
 
     // Try producing a PDF image from the dotfile
-    match Command::new("dot")
-        .args(&[
+    let _ = Command::new("dot")
+        .args([
             "-Tpdf",
             "-O",
             output_file_path.display().to_string().as_str(),
         ])
-        .output()
-    {
-        _ => {} // ignore Result
-    }
+        .output(); // ignore Result
 }
@@ -0,0 +1,312 @@
+//! Inlining a callee into a caller: the call is replaced by the blocks of the callee's body, to
+//! compare the results of checking the call through the callee's signature with the ground truth
+//! of checking the body itself, on the same pipeline.
+//!
+//! The callee is a program of its own, whose arguments are the variables `arg0`, `arg1`, etc.,
+//! named like in the printed prototypes, and whose result is its `return` place. Its blocks,
+//! variables and origins are renamed with a prefix unused in the caller, e.g. `f0_`.
+
+use std::collections::BTreeSet;
+
+use crate::ast::visit::{self, ProgramVisitor, ProgramVisitorMut};
+use crate::ast::{
+    AccessKind, BasicBlock, Expr, Name, Place, Program, Statement, Successor, Ty, VariableDecl,
+};
+use crate::ir::RETURN_PLACE;
+
+#[cfg(test)]
+mod test;
+
+/// A call statement of a program: `x = f(..)` or `f(..)`, the `statement`-th of the `block`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CallSite {
+    pub block: Name,
+    pub statement: usize,
+}
+
+impl Program {
+    /// Replaces the call at `call_site` with the body of `callee`. The call's block is split at
+    /// the call: its first half assigns the arguments to the callee's `argN` variables and goes
+    /// to the callee's first block, and the callee's blocks without successors go to the second
+    /// half, which assigns the callee's `return` place to the destination of the call.
+    ///
+    /// The callee's variables are declared `mut`, as a call in a loop assigns them on each
+    /// iteration, and they only die at the exit of the caller. The callee's declarations missing
+    /// from the caller are added to it, and it is an error for both to declare an item
+    /// differently.
+    pub fn inline(&mut self, call_site: &CallSite, callee: &Program) -> eyre::Result<()> {
+        let block_index = self
+            .basic_blocks
+            .iter()
+            .position(|block| block.name == call_site.block)
+            .ok_or_else(|| eyre::eyre!("there is no block `{}`", call_site.block))?;
+        let block = &self.basic_blocks[block_index];
+        let (destination, name, arguments) = match block.statements.get(call_site.statement) {
+            Some(Statement::Assign(place, Expr::Call { name, arguments })) => {
+                (Some(place.clone()), name.clone(), arguments.clone())
+            }
+            Some(Statement::Drop(Expr::Call { name, arguments })) => {
+                (None, name.clone(), arguments.clone())
+            }
+            Some(statement) => eyre::bail!(
+                "statement {} of `{}` is not a call: `{}`",
+                call_site.statement,
+                call_site.block,
+                statement
+            ),
+            None => eyre::bail!(
+                "`{}` has no statement {}",
+                call_site.block,
+                call_site.statement
+            ),
+        };
+
+        for i in 0..arguments.len() {
+            let parameter = format!("arg{}", i);
+            if !callee.variables.iter().any(|v| v.name == parameter) {
+                eyre::bail!("the callee of `{}` has no parameter `{}`", name, parameter);
+            }
+        }
+        let extra = format!("arg{}", arguments.len());
+        if callee.variables.iter().any(|v| v.name == extra) {
+            eyre::bail!(
+                "the callee of `{}` has a parameter `{}`, but the call has {} arguments",
+                name,
+                extra,
+                arguments.len()
+            );
+        }
+        let returns = callee.variables.iter().any(|v| v.name == RETURN_PLACE);
+        if let (Some(destination), false) = (&destination, returns) {
+            eyre::bail!(
+                "the callee of `{}` has no `{}` place to assign to `{}`",
+                name,
+                RETURN_PLACE,
+                destination
+            );
+        }
+        let entry = callee
+            .basic_blocks
+            .first()
+            .ok_or_else(|| eyre::eyre!("the callee of `{}` has no blocks", name))?;
+
+        self.merge_declarations(callee)?;
+
+        let prefix = self.unused_prefix(&name, callee);
+        let mut body = callee.clone();
+        Renamer { prefix: &prefix }.visit_program(&mut body)?;
+        let renamed = |name: &str| format!("{}{}", prefix, name);
+        let continuation = renamed("exit");
+
+        // The second half of the call's block, after the callee returns.
+        let block = &mut self.basic_blocks[block_index];
+        let mut rest: Vec<_> = block.statements.drain(call_site.statement..).collect();
+        rest.remove(0);
+        let result = Expr::Access {
+            kind: AccessKind::Move,
+            place: Place {
+                base: renamed(RETURN_PLACE),
+                fields: vec![],
+            },
+        };
+        let result = match destination {
+            Some(destination) => Some(Statement::Assign(destination, result)),
+            None if returns => Some(Statement::Drop(result)),
+            None => None,
+        };
+        let exit = BasicBlock {
+            name: continuation.clone(),
+            statements: result.into_iter().chain(rest).collect(),
+            successors: std::mem::take(&mut block.successors),
+            scrutinee: block.scrutinee.take(),
+        };
+
+        // The first half, passing the arguments.
+        for (i, argument) in arguments.into_iter().enumerate() {
+            let parameter = Place {
+                base: renamed(&format!("arg{}", i)),
+                fields: vec![],
+            };
+            block
+                .statements
+                .push(Statement::Assign(parameter, argument));
+        }
+        block.successors = vec![Successor {
+            block: renamed(&entry.name),
+            label: None,
+        }];
+
+        for callee_block in &mut body.basic_blocks {
+            if callee_block.successors.is_empty() {
+                callee_block.successors.push(Successor {
+                    block: continuation.clone(),
+                    label: None,
+                });
+            }
+        }
+        let inserted = body.basic_blocks.into_iter().chain(Some(exit));
+        self.basic_blocks
+            .splice(block_index + 1..block_index + 1, inserted);
+        self.variables
+            .extend(body.variables.into_iter().map(|variable| VariableDecl {
+                mutable: true,
+                ..variable
+            }));
+        Ok(())
+    }
+
+    /// Adds the extern types, structs, type aliases and prototypes of `callee` missing from
+    /// this program.
+    fn merge_declarations(&mut self, callee: &Program) -> eyre::Result<()> {
+        for name in &callee.extern_types {
+            if !self.extern_types.contains(name) {
+                self.extern_types.push(name.clone());
+            }
+        }
+        merge_items(&mut self.struct_decls, &callee.struct_decls, |d| &d.name)?;
+        merge_items(&mut self.type_aliases, &callee.type_aliases, |a| &a.name)?;
+        merge_items(&mut self.fn_prototypes, &callee.fn_prototypes, |p| &p.name)
+    }
+
+    /// The first of `f0_`, `f1_`, etc. under which none of the blocks, variables and origins of
+    /// `callee`, nor the continuation block, collide with a name of this program.
+    fn unused_prefix(&self, name: &str, callee: &Program) -> String {
+        let names = Names::of(self);
+        let callee_names = Names::of(callee);
+        (0..)
+            .map(|n| format!("{}{}_", name, n))
+            .find(|prefix| {
+                let renamed = |name: &&Name| format!("{}{}", prefix, name);
+                let mut blocks = callee_names
+                    .blocks
+                    .iter()
+                    .map(renamed)
+                    .chain(Some(format!("{}exit", prefix)));
+                let mut variables = callee_names.variables.iter().map(renamed);
+                let mut origins = callee_names
+                    .origins
+                    .iter()
+                    .map(|origin| rename_origin(prefix, origin));
+                !blocks.any(|block| names.blocks.contains(&block))
+                    && !variables.any(|variable| names.variables.contains(&variable))
+                    && !origins.any(|origin| names.origins.contains(&origin))
+            })
+            .unwrap()
+    }
+}
+
+/// Appends the `items` missing from `into`, by name. Items of the same name must be printed the
+/// same way.
+fn merge_items<T: Clone + std::fmt::Display>(
+    into: &mut Vec<T>,
+    items: &[T],
+    name: impl Fn(&T) -> &Name,
+) -> eyre::Result<()> {
+    for item in items {
+        match into.iter().find(|existing| name(existing) == name(item)) {
+            Some(existing) if existing.to_string() != item.to_string() => eyre::bail!(
+                "the callee declares `{}` differently from the caller: `{}`",
+                name(item),
+                item
+            ),
+            Some(_) => {}
+            None => into.push(item.clone()),
+        }
+    }
+    Ok(())
+}
+
+/// `'a` becomes `'f0_a`.
+fn rename_origin(prefix: &str, origin: &str) -> Name {
+    format!("'{}{}", prefix, origin.trim_start_matches('\''))
+}
+
+/// The names of the blocks, variables and origins of a program's body.
+#[derive(Default)]
+struct Names<'p> {
+    blocks: BTreeSet<&'p Name>,
+    variables: BTreeSet<&'p Name>,
+    origins: BTreeSet<Name>,
+}
+
+impl<'p> Names<'p> {
+    fn of(program: &'p Program) -> Self {
+        let mut names = Names::default();
+        names.visit_program(program).unwrap();
+        names
+    }
+}
+
+impl<'p> ProgramVisitor<'p> for Names<'p> {
+    fn visit_program(&mut self, program: &'p Program) -> eyre::Result<()> {
+        self.blocks
+            .extend(program.basic_blocks.iter().map(|block| &block.name));
+        self.variables
+            .extend(program.variables.iter().map(|variable| &variable.name));
+        for variable in &program.variables {
+            self.visit_variable_decl(variable)?;
+        }
+        for block in &program.basic_blocks {
+            self.visit_basic_block(block)?;
+        }
+        Ok(())
+    }
+
+    fn visit_ty(&mut self, ty: &'p Ty) -> eyre::Result<()> {
+        if let Ty::Fn { bound_origins, .. } = ty {
+            self.origins.extend(bound_origins.iter().cloned());
+        }
+        visit::walk_ty(self, ty)
+    }
+
+    fn visit_origin(&mut self, origin: &'p Name) -> eyre::Result<()> {
+        self.origins.insert(origin.clone());
+        Ok(())
+    }
+}
+
+/// Prefixes the blocks, variables and origins of a body. The declarations are left alone: the
+/// origins of a struct or a prototype are its parameters.
+struct Renamer<'a> {
+    prefix: &'a str,
+}
+
+impl ProgramVisitorMut for Renamer<'_> {
+    fn visit_program(&mut self, program: &mut Program) -> eyre::Result<()> {
+        for variable in &mut program.variables {
+            variable.name = format!("{}{}", self.prefix, variable.name);
+            self.visit_variable_decl(variable)?;
+        }
+        for block in &mut program.basic_blocks {
+            self.visit_basic_block(block)?;
+        }
+        Ok(())
+    }
+
+    fn visit_basic_block(&mut self, block: &mut BasicBlock) -> eyre::Result<()> {
+        block.name = format!("{}{}", self.prefix, block.name);
+        for successor in &mut block.successors {
+            successor.block = format!("{}{}", self.prefix, successor.block);
+        }
+        visit::walk_basic_block_mut(self, block)
+    }
+
+    fn visit_place(&mut self, place: &mut Place) -> eyre::Result<()> {
+        place.base = format!("{}{}", self.prefix, place.base);
+        Ok(())
+    }
+
+    fn visit_ty(&mut self, ty: &mut Ty) -> eyre::Result<()> {
+        if let Ty::Fn { bound_origins, .. } = ty {
+            for origin in bound_origins {
+                self.visit_origin(origin)?;
+            }
+        }
+        visit::walk_ty_mut(self, ty)
+    }
+
+    fn visit_origin(&mut self, origin: &mut Name) -> eyre::Result<()> {
+        *origin = rename_origin(self.prefix, origin);
+        Ok(())
+    }
+}
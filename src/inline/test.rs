@@ -0,0 +1,294 @@
+use super::*;
+use crate::fact_emitter::{FactEmitter, Options};
+use crate::results::Results;
+use crate::solver;
+
+fn parse(s: &str) -> Program {
+    crate::parse_ast(s).unwrap()
+}
+
+const CALLER: &str = "
+    fn pick<'a>(arg0: &'a i32, arg1: &'a i32) -> &'a i32;
+    let mut x: i32;
+    let mut y: i32;
+    let mut z: i32;
+    let mut r: &'r i32;
+    bb0: {
+        x = 1;
+        y = 2;
+        r = pick(&'L_x x, &'L_y y);
+        x = 3;
+        goto bb1;
+    }
+    bb1: {
+        z = copy *r;
+    }
+";
+
+/// Returns its second argument, which its signature doesn't say.
+const PICK: &str = "
+    let arg0: &'a i32;
+    let arg1: &'b i32;
+    let return: &'r i32;
+    bb0: {
+        return = copy arg1;
+    }
+";
+
+fn call_site(block: &str, statement: usize) -> CallSite {
+    CallSite {
+        block: block.to_string(),
+        statement,
+    }
+}
+
+fn errors(program: &Program) -> Vec<String> {
+    let facts = FactEmitter::from_program(program.clone(), Options::default()).unwrap();
+    let output = solver::check(&facts).unwrap();
+    let results = Results::new(&facts, output);
+    results
+        .errors()
+        .iter()
+        .map(|error| format!("{:?} {} at {}", error.kind, error.origin, error.node))
+        .collect()
+}
+
+#[test]
+fn inline_call() {
+    let mut program = parse(CALLER);
+    program.inline(&call_site("bb0", 2), &parse(PICK)).unwrap();
+    insta::assert_snapshot!(program.to_string(), @r###"
+    fn pick<'a>(arg0: &'a i32, arg1: &'a i32) -> &'a i32;
+    let mut x: i32;
+    let mut y: i32;
+    let mut z: i32;
+    let mut r: &'r i32;
+    let mut pick0_arg0: &'pick0_a i32;
+    let mut pick0_arg1: &'pick0_b i32;
+    let mut pick0_return: &'pick0_r i32;
+    bb0: {
+        x = 1;
+        y = 2;
+        pick0_arg0 = &'L_x x;
+        pick0_arg1 = &'L_y y;
+        goto pick0_bb0;
+    }
+    pick0_bb0: {
+        pick0_return = copy pick0_arg1;
+        goto pick0_exit;
+    }
+    pick0_exit: {
+        r = move pick0_return;
+        x = 3;
+        goto bb1;
+    }
+    bb1: {
+        z = copy *r;
+    }
+
+    "###);
+}
+
+#[test]
+fn inlining_is_more_precise_than_the_signature() {
+    let program = parse(CALLER);
+    let mut inlined = program.clone();
+    inlined.inline(&call_site("bb0", 2), &parse(PICK)).unwrap();
+
+    // Through the signature, the loan of `x` may be in `r`.
+    insta::assert_debug_snapshot!(errors(&program), @r###"
+    [
+        "InvalidatedOriginAccessed 'r at bb1[0]",
+    ]
+    "###);
+    insta::assert_debug_snapshot!(errors(&inlined), @"[]");
+}
+
+#[test]
+fn inline_call_without_result() {
+    let mut program = parse(
+        "
+        fn consume(arg0: i32) -> ();
+        let mut x: i32;
+        bb0: {
+            consume(copy x);
+            x = 1;
+        }
+    ",
+    );
+    let callee = parse(
+        "
+        let arg0: i32;
+        let y: i32;
+        bb0: {
+            y = copy arg0;
+            goto bb1, bb2;
+        }
+        bb1: { pass; }
+        bb2: { pass; }
+    ",
+    );
+    program.inline(&call_site("bb0", 0), &callee).unwrap();
+    insta::assert_snapshot!(program.to_string(), @r###"
+    fn consume(arg0: i32) -> ();
+    let mut x: i32;
+    let mut consume0_arg0: i32;
+    let mut consume0_y: i32;
+    bb0: {
+        consume0_arg0 = copy x;
+        goto consume0_bb0;
+    }
+    consume0_bb0: {
+        consume0_y = copy consume0_arg0;
+        goto consume0_bb1, consume0_bb2;
+    }
+    consume0_bb1: {
+        pass;
+        goto consume0_exit;
+    }
+    consume0_bb2: {
+        pass;
+        goto consume0_exit;
+    }
+    consume0_exit: {
+        x = 1;
+    }
+
+    "###);
+}
+
+#[test]
+fn renaming_avoids_the_caller_names() {
+    let mut program = parse(
+        "
+        fn pick<'a>(arg0: &'a i32, arg1: &'a i32) -> &'a i32;
+        let mut x: i32;
+        let mut r: &'pick0_r i32;
+        bb0: {
+            r = pick(&'L_x x, &'L_x x);
+        }
+    ",
+    );
+    program.inline(&call_site("bb0", 0), &parse(PICK)).unwrap();
+    insta::assert_snapshot!(program.to_string(), @r###"
+    fn pick<'a>(arg0: &'a i32, arg1: &'a i32) -> &'a i32;
+    let mut x: i32;
+    let mut r: &'pick0_r i32;
+    let mut pick1_arg0: &'pick1_a i32;
+    let mut pick1_arg1: &'pick1_b i32;
+    let mut pick1_return: &'pick1_r i32;
+    bb0: {
+        pick1_arg0 = &'L_x x;
+        pick1_arg1 = &'L_x x;
+        goto pick1_bb0;
+    }
+    pick1_bb0: {
+        pick1_return = copy pick1_arg1;
+        goto pick1_exit;
+    }
+    pick1_exit: {
+        r = move pick1_return;
+    }
+
+    "###);
+}
+
+#[test]
+fn merge_callee_declarations() {
+    let mut program = parse(
+        "
+        struct S<'a> { f: &'a i32 }
+        fn make<'a>(arg0: &'a i32) -> S<'a>;
+        let mut x: i32;
+        let mut s: S<'s>;
+        bb0: {
+            s = make(&'L_x x);
+        }
+    ",
+    );
+    let callee = parse(
+        "
+        struct S<'a> { f: &'a i32 }
+        fn wrap<'a>(arg0: &'a i32) -> S<'a>;
+        let arg0: &'a i32;
+        let return: S<'r>;
+        bb0: {
+            return = wrap(move arg0);
+        }
+    ",
+    );
+    program.inline(&call_site("bb0", 0), &callee).unwrap();
+    insta::assert_snapshot!(program.to_string(), @r###"
+    struct S<'a> { f: &'a i32 }
+    fn make<'a>(arg0: &'a i32) -> S<'a>;
+    fn wrap<'a>(arg0: &'a i32) -> S<'a>;
+    let mut x: i32;
+    let mut s: S<'s>;
+    let mut make0_arg0: &'make0_a i32;
+    let mut make0_return: S<'make0_r>;
+    bb0: {
+        make0_arg0 = &'L_x x;
+        goto make0_bb0;
+    }
+    make0_bb0: {
+        make0_return = wrap(move make0_arg0);
+        goto make0_exit;
+    }
+    make0_exit: {
+        s = move make0_return;
+    }
+
+    "###);
+}
+
+#[test]
+fn inline_errors() {
+    let inline = |caller: &str, site: CallSite, callee: &str| {
+        parse(caller)
+            .inline(&site, &parse(callee))
+            .unwrap_err()
+            .to_string()
+    };
+    let errors = vec![
+        inline(CALLER, call_site("bb2", 0), PICK),
+        inline(CALLER, call_site("bb0", 7), PICK),
+        inline(CALLER, call_site("bb0", 0), PICK),
+        inline(
+            CALLER,
+            call_site("bb0", 2),
+            "let arg0: &'a i32; bb0: { pass; }",
+        ),
+        inline(
+            CALLER,
+            call_site("bb0", 2),
+            "let arg0: &'a i32; let arg1: &'a i32; let arg2: i32; bb0: { pass; }",
+        ),
+        inline(
+            CALLER,
+            call_site("bb0", 2),
+            "let arg0: &'a i32; let arg1: &'a i32; bb0: { pass; }",
+        ),
+        inline(
+            CALLER,
+            call_site("bb0", 2),
+            "
+            fn pick<'a>(arg0: &'a i32) -> &'a i32;
+            let arg0: &'a i32;
+            let arg1: &'a i32;
+            let return: &'r i32;
+            bb0: { return = copy arg0; }
+            ",
+        ),
+    ];
+    insta::assert_debug_snapshot!(errors, @r###"
+    [
+        "there is no block `bb2`",
+        "`bb0` has no statement 7",
+        "statement 0 of `bb0` is not a call: `x = 1`",
+        "the callee of `pick` has no parameter `arg1`",
+        "the callee of `pick` has a parameter `arg2`, but the call has 2 arguments",
+        "the callee of `pick` has no `return` place to assign to `r`",
+        "the callee declares `pick` differently from the caller: `fn pick<'a>(arg0: &'a i32) -> &'a i32;`",
+    ]
+    "###);
+}
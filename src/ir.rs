@@ -0,0 +1,557 @@
+//! A MIR-like intermediate representation between the AST and the facts.
+//!
+//! Lowering flattens places into a base variable and a list of projections, and turns each
+//! statement into the list of its effects, in evaluation order. The fact emitter only looks at
+//! these effects, so new surface syntax only needs to be lowered, and doesn't need to know
+//! about facts.
+//!
+//! Each block ends with an explicit terminator. The blocks without successors go to an
+//! implicit exit block, where the locals die, and where the value of the return place, if the
+//! program has one, is returned.
+
+use std::fmt;
+
+use crate::ast::{self, AccessKind, Expr, Name, Ty};
+use crate::facts::Origin;
+use crate::typeck::{self, Projection, TypeEnv};
+
+pub mod move_paths;
+#[cfg(test)]
+mod test;
+
+/// The variable holding the value returned by the program, like `_0` in MIR.
+pub const RETURN_PLACE: &str = "return";
+
+/// The name of the implicit exit block of a program without a return place. With a return
+/// place, the exit block is named after it.
+pub const EXIT_BLOCK: &str = "exit";
+
+#[derive(Clone, Debug, Default)]
+pub struct Body {
+    pub blocks: Vec<BasicBlock>,
+
+    /// The origins instantiated by the calls and struct literals, e.g. `'MaybeNext0_a`, in the
+    /// order they were created.
+    pub call_origins: Vec<Origin>,
+}
+
+#[derive(Clone, Debug)]
+pub struct BasicBlock {
+    pub name: Name,
+
+    /// Never empty: a block without statements in the AST gets a `(pass)` statement.
+    pub statements: Vec<Statement>,
+    pub terminator: Terminator,
+}
+
+#[derive(Clone, Debug)]
+pub struct Statement {
+    /// The statement printed back from the AST, used as the `node_text`.
+    pub text: String,
+    pub effects: Vec<Effect>,
+
+    /// The ghost statements just before this one, which are checked at its node.
+    pub assertions: Vec<ast::Assertion>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Terminator {
+    /// Continues with the blocks at these indices, each with the label of its edge, if any.
+    Goto(Vec<(usize, Option<Name>)>),
+
+    /// Continues with the arm matching the value of `place`: a variant for a `match`, e.g.
+    /// `Some`, or an integer for a `switch`. The `_` arm has no label. Like `SwitchInt` in
+    /// MIR, the value was read by the last statement of the block.
+    Switch {
+        place: Place,
+        arms: Vec<(usize, Option<Name>)>,
+    },
+
+    /// Exits the function. Only the implicit exit block ends with it.
+    Return,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ReadKind {
+    Copy,
+    Move,
+
+    /// Reads which variant a value of a builtin enum is, for a `match`. Like in MIR, the read
+    /// is shallow: the payloads aren't read.
+    Discriminant,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum BorrowKind {
+    Shared,
+    Mut,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Effect {
+    /// Reads the value of `place`.
+    Read { place: Place, kind: ReadKind },
+
+    /// Creates the loan `&'origin place` or `&'origin mut place`.
+    Borrow {
+        origin: Origin,
+        place: Place,
+        kind: BorrowKind,
+    },
+
+    /// Overwrites `place` with a value of type `value_ty`.
+    Write { place: Place, value_ty: Ty },
+
+    /// A value of type `sub` is passed where a `sup` is expected, e.g. an argument of a call
+    /// with the type of the instantiated parameter.
+    Relate { sub: Ty, sup: Ty },
+
+    /// The value of `place` is dropped, and it is uninitialized.
+    Drop { place: Place },
+
+    /// The storage of the variable `local` is freed.
+    StorageDead { local: Name },
+
+    /// A call to the extern function `callee`, which may access every origin of its arguments,
+    /// of types `arg_tys`.
+    ExternCall { callee: Name, arg_tys: Vec<Ty> },
+
+    /// The subset `o1: o2`, declared by a ghost statement `'o1: 'o2;` before the statement.
+    Outlives { o1: Origin, o2: Origin },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Place {
+    pub base: Name,
+    pub projections: Vec<PlaceElem>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum PlaceElem {
+    Field(Name),
+    Deref,
+}
+
+impl Place {
+    pub fn projections(&self) -> Vec<Projection<'_>> {
+        self.projections
+            .iter()
+            .map(|elem| match elem {
+                PlaceElem::Field(field) => Projection::Field(field),
+                PlaceElem::Deref => Projection::Deref,
+            })
+            .collect()
+    }
+
+    /// Whether the place is part of the storage of its base variable, i.e. isn't behind a
+    /// reference.
+    pub fn is_local(&self) -> bool {
+        !self.projections.contains(&PlaceElem::Deref)
+    }
+}
+
+/// Lowers `place`, making its implicit dereferences explicit: `x.f` is lowered to `x.*.f`
+/// when `x` is a reference.
+pub fn lower_place(env: &TypeEnv<'_>, place: &ast::Place) -> eyre::Result<Place> {
+    let prefix_tys = env.place_prefix_tys(place)?;
+    Ok(Place {
+        base: typeck::projections(place).0.to_string(),
+        projections: prefix_tys
+            .into_iter()
+            .filter_map(|(projection, _)| projection)
+            .map(|projection| match projection {
+                Projection::Field(field) => PlaceElem::Field(field.to_string()),
+                Projection::Deref => PlaceElem::Deref,
+            })
+            .collect(),
+    })
+}
+
+/// Converts the place as written, without its implicit dereferences, see `lower_place`.
+impl From<&ast::Place> for Place {
+    fn from(place: &ast::Place) -> Self {
+        let (base, projections) = typeck::projections(place);
+        Place {
+            base: base.to_string(),
+            projections: projections
+                .into_iter()
+                .map(|projection| match projection {
+                    Projection::Field(field) => PlaceElem::Field(field.to_string()),
+                    Projection::Deref => PlaceElem::Deref,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Prints the place in the AST syntax: the trailing dereferences are a `*` prefix, and the
+/// others are `*` fields.
+impl fmt::Display for Place {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let prefix_derefs = self
+            .projections
+            .iter()
+            .rev()
+            .take_while(|elem| **elem == PlaceElem::Deref)
+            .count();
+        write!(f, "{}{}", "*".repeat(prefix_derefs), self.base)?;
+        for elem in &self.projections[..self.projections.len() - prefix_derefs] {
+            match elem {
+                PlaceElem::Field(field) => write!(f, ".{}", field)?,
+                PlaceElem::Deref => write!(f, ".*")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Lowers a program to the IR.
+pub fn lower(program: &ast::Program) -> eyre::Result<Body> {
+    let env = TypeEnv::new(program);
+    let has_return = program.variables.iter().any(|v| v.name == RETURN_PLACE);
+    let exit_block = program.basic_blocks.len();
+
+    let mut instances = Instances::default();
+    let mut blocks = vec![];
+    for block in &program.basic_blocks {
+        let mut statements = vec![];
+        let mut assertions = vec![];
+        let mut outlives = vec![];
+        for statement in &block.statements {
+            match statement {
+                ast::Statement::Assert(assertion) => {
+                    assertions.push(assertion.clone());
+                    continue;
+                }
+                ast::Statement::Outlives(o1, o2) => {
+                    outlives.push((o1, o2));
+                    continue;
+                }
+                _ => {}
+            }
+
+            let mut effects = outlives_effects(&mut outlives);
+            effects.extend(
+                lower_statement(&env, statement, &mut instances).map_err(|e| {
+                    e.wrap_err(format!("in statement `{}` of `{}`", statement, block.name))
+                })?,
+            );
+            let text = match statement {
+                // Like the statement of an empty block.
+                ast::Statement::Pass => "(pass)".to_string(),
+                _ => statement.to_string(),
+            };
+            statements.push(Statement {
+                text,
+                effects,
+                assertions: std::mem::take(&mut assertions),
+            });
+        }
+        let scrutinee_place = match &block.scrutinee {
+            Some(scrutinee) => Some(lower_place(&env, scrutinee.place())?),
+            None => None,
+        };
+        if let (Some(scrutinee), Some(place)) = (&block.scrutinee, &scrutinee_place) {
+            // A `switch` reads its integer, a `match` only the variant of its enum.
+            let kind = match scrutinee {
+                ast::Scrutinee::Match(_) => ReadKind::Discriminant,
+                ast::Scrutinee::Switch(_) => ReadKind::Copy,
+            };
+            let read = Effect::Read {
+                place: place.clone(),
+                kind,
+            };
+            let mut effects = outlives_effects(&mut outlives);
+            effects.push(read);
+            statements.push(Statement {
+                text: scrutinee.to_string(),
+                effects,
+                assertions: std::mem::take(&mut assertions),
+            });
+        }
+        if let Some((o1, o2)) = outlives.first() {
+            eyre::bail!(
+                "`{}: {}` must be followed by a statement of `{}`, where it is introduced",
+                o1,
+                o2,
+                block.name
+            );
+        }
+        if let Some(assertion) = assertions.first() {
+            eyre::bail!(
+                "`{}` must be followed by a statement of `{}`, where it is checked",
+                assertion,
+                block.name
+            );
+        }
+        if statements.is_empty() {
+            statements.push(Statement {
+                text: "(pass)".to_string(),
+                effects: vec![],
+                assertions: vec![],
+            });
+        }
+
+        let terminator = if block.successors.is_empty() {
+            Terminator::Goto(vec![(exit_block, None)])
+        } else {
+            let successors = block
+                .successors
+                .iter()
+                .map(|successor| {
+                    let index = program
+                        .basic_blocks
+                        .iter()
+                        .position(|b| b.name == successor.block)
+                        .ok_or_else(|| eyre::eyre!("no block named `{}`", successor.block))?;
+                    Ok((index, successor.label.clone()))
+                })
+                .collect::<eyre::Result<_>>()?;
+            match scrutinee_place {
+                Some(place) => Terminator::Switch {
+                    place,
+                    arms: successors,
+                },
+                None => Terminator::Goto(successors),
+            }
+        };
+
+        blocks.push(BasicBlock {
+            name: block.name.clone(),
+            statements,
+            terminator,
+        });
+    }
+
+    blocks.push(exit_block_of(program, has_return));
+
+    Ok(Body {
+        blocks,
+        call_origins: instances.origins,
+    })
+}
+
+/// The implicit exit of a program: the locals die in a `(storage dead)` statement. With a
+/// return place, the caller then reads the returned value in a `(return)` statement.
+fn exit_block_of(program: &ast::Program, has_return: bool) -> BasicBlock {
+    let storage_dead = program
+        .variables
+        .iter()
+        .filter(|v| v.name != RETURN_PLACE)
+        .map(|v| Effect::StorageDead {
+            local: v.name.clone(),
+        })
+        .collect();
+    let mut statements = vec![Statement {
+        text: "(storage dead)".to_string(),
+        effects: storage_dead,
+        assertions: vec![],
+    }];
+    let name = if has_return {
+        let read = Effect::Read {
+            place: Place {
+                base: RETURN_PLACE.to_string(),
+                projections: vec![],
+            },
+            kind: ReadKind::Copy,
+        };
+        statements.push(Statement {
+            text: "(return)".to_string(),
+            effects: vec![read],
+            assertions: vec![],
+        });
+        RETURN_PLACE
+    } else {
+        EXIT_BLOCK
+    };
+
+    BasicBlock {
+        name: name.to_string(),
+        statements,
+        terminator: Terminator::Return,
+    }
+}
+
+/// The calls and struct literals lowered so far, to name the origins each of them instantiates
+/// its signature, or its struct, with.
+#[derive(Default)]
+struct Instances {
+    count: usize,
+    origins: Vec<Origin>,
+}
+
+impl Instances {
+    /// Starts a new instance of the function or struct `name`, returning how to name its
+    /// origins: e.g. `'MaybeNext0_a` for the `'a` of the first call to `MaybeNext`. Items of a
+    /// module are named without their path: `'push1_a` for a call to `collections::push`.
+    fn next<'a>(&'a mut self, name: &'a str) -> impl FnMut(&Name) -> Name + 'a {
+        let name = name.rsplit("::").next().unwrap_or(name);
+        let instance = self.count;
+        self.count += 1;
+        move |origin| {
+            let origin = format!("'{}{}_{}", name, instance, origin.trim_start_matches('\''));
+            self.origins.push(origin.clone());
+            origin
+        }
+    }
+}
+
+/// Lowers `statement`, naming the origins instantiated by its calls with `instances`.
+fn lower_statement(
+    env: &TypeEnv<'_>,
+    statement: &ast::Statement,
+    instances: &mut Instances,
+) -> eyre::Result<Vec<Effect>> {
+    let mut effects = vec![];
+    match statement {
+        ast::Statement::Assign(place, expr) => {
+            let place_ty = env.place_ty(place)?;
+            let value_ty = lower_expr(env, expr, Some(&place_ty), &mut effects, instances)?;
+            effects.push(Effect::Write {
+                place: lower_place(env, place)?,
+                value_ty,
+            });
+        }
+        ast::Statement::Drop(expr) => {
+            lower_expr(env, expr, None, &mut effects, instances)?;
+        }
+        ast::Statement::DropPlace(place) => {
+            let lowered = lower_place(env, place)?;
+            if !lowered.is_local() {
+                eyre::bail!("cannot drop `{}`, which is behind a reference", place);
+            }
+            effects.push(Effect::Drop { place: lowered });
+        }
+        ast::Statement::Assert(_) | ast::Statement::Pass | ast::Statement::Outlives(..) => {}
+    }
+    Ok(effects)
+}
+
+/// The effects of the ghost statements `'o1: 'o2;` before a statement, which happen first.
+fn outlives_effects(outlives: &mut Vec<(&Name, &Name)>) -> Vec<Effect> {
+    outlives
+        .drain(..)
+        .map(|(o1, o2)| Effect::Outlives {
+            o1: o1.clone(),
+            o2: o2.clone(),
+        })
+        .collect()
+}
+
+/// Pushes the effects of evaluating `expr`, and returns its type. The `expected` type is used
+/// to infer the type generics of calls which don't appear in their arguments.
+fn lower_expr(
+    env: &TypeEnv<'_>,
+    expr: &Expr,
+    expected: Option<&Ty>,
+    effects: &mut Vec<Effect>,
+    instances: &mut Instances,
+) -> eyre::Result<Ty> {
+    match expr {
+        Expr::Access { kind, place } => {
+            let ty = env.place_ty(place)?;
+            let place = lower_place(env, place)?;
+            let (effect, ty) = match kind {
+                AccessKind::Copy | AccessKind::Move => {
+                    let kind = if *kind == AccessKind::Copy {
+                        ReadKind::Copy
+                    } else {
+                        ReadKind::Move
+                    };
+                    let effect = Effect::Read { place, kind };
+                    (effect, ty)
+                }
+                AccessKind::Borrow(origin) => {
+                    let effect = Effect::Borrow {
+                        origin: origin.clone(),
+                        place: place.clone(),
+                        kind: BorrowKind::Shared,
+                    };
+                    let ty = Ty::Ref {
+                        origin: origin.clone(),
+                        ty: Box::new(ty),
+                    };
+                    (effect, ty)
+                }
+                AccessKind::BorrowMut(origin) => {
+                    let effect = Effect::Borrow {
+                        origin: origin.clone(),
+                        place: place.clone(),
+                        kind: BorrowKind::Mut,
+                    };
+                    let ty = Ty::RefMut {
+                        origin: origin.clone(),
+                        ty: Box::new(ty),
+                    };
+                    (effect, ty)
+                }
+            };
+            effects.push(effect);
+            Ok(ty)
+        }
+        Expr::Number { .. } => Ok(Ty::I32),
+        Expr::Unit => Ok(Ty::Unit),
+        Expr::Variant { name, value } => {
+            let payload_ty = value
+                .as_ref()
+                .map(|value| lower_expr(env, value, None, effects, instances))
+                .transpose()?;
+            env.variant_ty(name, payload_ty.as_ref(), expected)
+        }
+        Expr::Struct { name, fields, base } => {
+            let mut field_tys = vec![];
+            for (field, value) in fields {
+                field_tys.push((field, lower_expr(env, value, None, effects, instances)?));
+            }
+
+            // The fields not given are read out of the base one by one, after the fields
+            // given, so that moving them leaves the others initialized in the base.
+            if let Some(base) = base {
+                let base = env.base_fields(name, fields, base)?;
+                let place = lower_place(env, base.place)?;
+                let kind = if base.kind == AccessKind::Copy {
+                    ReadKind::Copy
+                } else {
+                    ReadKind::Move
+                };
+                for (field, ty) in base.fields {
+                    let mut place = place.clone();
+                    place.projections.push(PlaceElem::Field(field.clone()));
+                    effects.push(Effect::Read { place, kind });
+                    field_tys.push((field, ty));
+                }
+            }
+
+            // Like a call to the struct's constructor, each literal instantiates the origins
+            // of the struct with fresh origins, e.g. `'S0_a`, and each field value flows into
+            // the origins of its field.
+            let (instantiated, ty) =
+                env.instantiate_struct(name, &field_tys, expected, instances.next(name))?;
+            for ((_, sub), sup) in field_tys.into_iter().zip(instantiated) {
+                effects.push(Effect::Relate { sub, sup });
+            }
+            Ok(ty)
+        }
+        Expr::Call { name, arguments } => {
+            let arg_tys = arguments
+                .iter()
+                .map(|argument| lower_expr(env, argument, None, effects, instances))
+                .collect::<eyre::Result<Vec<_>>>()?;
+
+            // Like rustc, each call instantiates the origins of the signature with fresh
+            // origins.
+            let (param_tys, ret_ty) =
+                env.instantiate_signature(name, &arg_tys, expected, instances.next(name))?;
+            if env.fn_prototype(name)?.kind == ast::FnKind::Extern {
+                effects.push(Effect::ExternCall {
+                    callee: name.clone(),
+                    arg_tys: arg_tys.clone(),
+                });
+            }
+            for (sub, sup) in arg_tys.into_iter().zip(param_tys) {
+                effects.push(Effect::Relate { sub, sup });
+            }
+            Ok(ret_ty)
+        }
+    }
+}
@@ -0,0 +1,130 @@
+//! The move paths of a body, like rustc's: the places whose initialization can be tracked on
+//! their own, i.e. the variables and their fields, but not the places behind references, which
+//! the body doesn't own. They form a tree, where the parent of `x.f` is `x`.
+//!
+//! The `child_path` and `path_*_at` facts are emitted from this tree, so that rules tracking
+//! initialization by move path and an initialization analysis of the body agree on the paths.
+
+use super::{Body, Effect, Place, PlaceElem, ReadKind};
+
+#[cfg(test)]
+mod test;
+
+/// How a statement uses a move path.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PathUse {
+    /// The path is overwritten as a whole: `path_assigned_at`.
+    Assigned,
+
+    /// The value of the path, or of a place behind it, is used: `path_accessed_at`.
+    Accessed,
+
+    /// The value is moved out of the path: `path_moved_at`. A move is also an access.
+    Moved,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MovePaths {
+    /// Each path after its parent, in the order the body first uses them.
+    paths: Vec<Place>,
+}
+
+impl MovePaths {
+    /// The paths of the places used by the effects of `body`, and their prefixes. Every
+    /// variable whose storage dies is a root, even if it is never used.
+    pub fn of(body: &Body) -> Self {
+        let mut paths = MovePaths::default();
+        for statement in body.blocks.iter().flat_map(|block| &block.statements) {
+            for effect in &statement.effects {
+                match effect {
+                    Effect::Read { place, .. }
+                    | Effect::Borrow { place, .. }
+                    | Effect::Write { place, .. }
+                    | Effect::Drop { place } => paths.insert(&move_path_of(place)),
+                    Effect::StorageDead { local } => paths.insert(&Place {
+                        base: local.clone(),
+                        projections: vec![],
+                    }),
+                    Effect::Relate { .. } | Effect::ExternCall { .. } | Effect::Outlives { .. } => {
+                    }
+                }
+            }
+        }
+        paths
+    }
+
+    /// Adds `path` and its prefixes, parents first.
+    fn insert(&mut self, path: &Place) {
+        for len in 0..=path.projections.len() {
+            let prefix = Place {
+                base: path.base.clone(),
+                projections: path.projections[..len].to_vec(),
+            };
+            if !self.paths.contains(&prefix) {
+                self.paths.push(prefix);
+            }
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Place> {
+        self.paths.iter()
+    }
+
+    /// The path `path` is a field of, if it isn't a variable.
+    pub fn parent(&self, path: &Place) -> Option<Place> {
+        let (_, projections) = path.projections.split_last()?;
+        Some(Place {
+            base: path.base.clone(),
+            projections: projections.to_vec(),
+        })
+    }
+
+    /// The paths which are fields of `path`.
+    pub fn children<'a>(&'a self, path: &'a Place) -> impl Iterator<Item = &'a Place> + 'a {
+        self.paths
+            .iter()
+            .filter(move |child| self.parent(child).as_ref() == Some(path))
+    }
+}
+
+/// The move path containing `place`: its longest prefix which isn't behind a reference, e.g.
+/// `x.f` for `x.f`, and `p` for `(*p).g`.
+pub fn move_path_of(place: &Place) -> Place {
+    let local = place
+        .projections
+        .iter()
+        .take_while(|elem| **elem != PlaceElem::Deref)
+        .count();
+    Place {
+        base: place.base.clone(),
+        projections: place.projections[..local].to_vec(),
+    }
+}
+
+/// How `effect` uses the move paths. A write through a reference accesses the path holding
+/// the reference rather than assigning it, and so does a move out of a place behind one.
+pub fn path_uses(effect: &Effect) -> Vec<(Place, PathUse)> {
+    match effect {
+        Effect::Read {
+            place,
+            kind: ReadKind::Move,
+        } if place.is_local() => vec![
+            (place.clone(), PathUse::Accessed),
+            (place.clone(), PathUse::Moved),
+        ],
+        Effect::Drop { place } => vec![
+            (place.clone(), PathUse::Accessed),
+            (place.clone(), PathUse::Moved),
+        ],
+        Effect::Write { place, .. } if place.is_local() => {
+            vec![(place.clone(), PathUse::Assigned)]
+        }
+        Effect::Read { place, .. } | Effect::Borrow { place, .. } | Effect::Write { place, .. } => {
+            vec![(move_path_of(place), PathUse::Accessed)]
+        }
+        Effect::StorageDead { .. }
+        | Effect::Relate { .. }
+        | Effect::ExternCall { .. }
+        | Effect::Outlives { .. } => vec![],
+    }
+}
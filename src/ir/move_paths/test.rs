@@ -0,0 +1,85 @@
+use super::*;
+use crate::ir::lower;
+
+const PROGRAM: &str = "
+    struct Pair<'a> { a: &'a i32, b: i32 }
+    let mut x: i32;
+    let mut y: i32;
+    let mut s: Pair<'s>;
+    let mut z: i32;
+    let mut p: &'p Pair<'q>;
+    bb0: {
+        x = 1;
+        s = Pair { a: &'L_x x, b: 2 };
+        s.b = copy x;
+        p = &'L_s s;
+        z = copy p.*.b;
+        y = move z;
+    }
+";
+
+fn body() -> Body {
+    lower(&crate::parse_ast(PROGRAM).unwrap()).unwrap()
+}
+
+#[test]
+fn move_path_tree() {
+    let body = body();
+    let paths = MovePaths::of(&body);
+    let tree: Vec<_> = paths
+        .iter()
+        .map(|path| match paths.parent(path) {
+            Some(parent) => format!("{} <- {}", path, parent),
+            None => path.to_string(),
+        })
+        .collect();
+    insta::assert_debug_snapshot!(tree, @r###"
+    [
+        "x",
+        "s",
+        "s.b <- s",
+        "p",
+        "z",
+        "y",
+    ]
+    "###);
+
+    let s = Place {
+        base: "s".to_string(),
+        projections: vec![],
+    };
+    let children: Vec<_> = paths.children(&s).map(|path| path.to_string()).collect();
+    insta::assert_debug_snapshot!(children, @r###"
+    [
+        "s.b",
+    ]
+    "###);
+}
+
+#[test]
+fn uses_of_each_statement() {
+    let body = body();
+    let uses: Vec<_> = body.blocks[0]
+        .statements
+        .iter()
+        .map(|statement| {
+            let uses: Vec<_> = statement
+                .effects
+                .iter()
+                .flat_map(path_uses)
+                .map(|(path, path_use)| format!("{:?} {}", path_use, path))
+                .collect();
+            format!("{}: {}", statement.text, uses.join(", "))
+        })
+        .collect();
+    insta::assert_debug_snapshot!(uses, @r###"
+    [
+        "x = 1: Assigned x",
+        "s = Pair { a: &'L_x x, b: 2 }: Accessed x, Assigned s",
+        "s.b = copy x: Accessed x, Assigned s.b",
+        "p = &'L_s s: Accessed s, Assigned p",
+        "z = copy p.*.b: Accessed p, Assigned z",
+        "y = move z: Accessed z, Moved z, Assigned y",
+    ]
+    "###);
+}
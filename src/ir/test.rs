@@ -0,0 +1,475 @@
+use super::*;
+
+fn lower_str(s: &str) -> Body {
+    lower(&crate::parse_ast(s).unwrap()).unwrap()
+}
+
+#[test]
+fn effects() {
+    let body = lower_str(
+        "
+        fn use<T, U>(t: T, u: U) -> ();
+        let x: i32;
+        let r: &'r mut i32;
+        bb0: {
+            r = &'L_x mut x;
+            use(copy x, move r);
+            goto bb1;
+        }
+        bb1: {
+        }
+    ",
+    );
+
+    insta::assert_debug_snapshot!(body, @r###"
+    Body {
+        blocks: [
+            BasicBlock {
+                name: "bb0",
+                statements: [
+                    Statement {
+                        text: "r = &'L_x mut x",
+                        effects: [
+                            Borrow {
+                                origin: "'L_x",
+                                place: Place {
+                                    base: "x",
+                                    projections: [],
+                                },
+                                kind: Mut,
+                            },
+                            Write {
+                                place: Place {
+                                    base: "r",
+                                    projections: [],
+                                },
+                                value_ty: RefMut {
+                                    origin: "'L_x",
+                                    ty: I32,
+                                },
+                            },
+                        ],
+                        assertions: [],
+                    },
+                    Statement {
+                        text: "use(copy x, move r)",
+                        effects: [
+                            Read {
+                                place: Place {
+                                    base: "x",
+                                    projections: [],
+                                },
+                                kind: Copy,
+                            },
+                            Read {
+                                place: Place {
+                                    base: "r",
+                                    projections: [],
+                                },
+                                kind: Move,
+                            },
+                            Relate {
+                                sub: I32,
+                                sup: I32,
+                            },
+                            Relate {
+                                sub: RefMut {
+                                    origin: "'r",
+                                    ty: I32,
+                                },
+                                sup: RefMut {
+                                    origin: "'r",
+                                    ty: I32,
+                                },
+                            },
+                        ],
+                        assertions: [],
+                    },
+                ],
+                terminator: Goto(
+                    [
+                        (
+                            1,
+                            None,
+                        ),
+                    ],
+                ),
+            },
+            BasicBlock {
+                name: "bb1",
+                statements: [
+                    Statement {
+                        text: "(pass)",
+                        effects: [],
+                        assertions: [],
+                    },
+                ],
+                terminator: Goto(
+                    [
+                        (
+                            2,
+                            None,
+                        ),
+                    ],
+                ),
+            },
+            BasicBlock {
+                name: "exit",
+                statements: [
+                    Statement {
+                        text: "(storage dead)",
+                        effects: [
+                            StorageDead {
+                                local: "x",
+                            },
+                            StorageDead {
+                                local: "r",
+                            },
+                        ],
+                        assertions: [],
+                    },
+                ],
+                terminator: Return,
+            },
+        ],
+        call_origins: [],
+    }
+    "###);
+}
+
+#[test]
+fn return_block() {
+    let body = lower_str(
+        "
+        let x: i32;
+        let return: &'r i32;
+        bb0: {
+            return = &'L_x x;
+        }
+    ",
+    );
+
+    let blocks: Vec<_> = body
+        .blocks
+        .iter()
+        .map(|b| (&b.name, &b.terminator))
+        .collect();
+    insta::assert_debug_snapshot!((blocks, &body.blocks[1].statements), @r###"
+    (
+        [
+            (
+                "bb0",
+                Goto(
+                    [
+                        (
+                            1,
+                            None,
+                        ),
+                    ],
+                ),
+            ),
+            (
+                "return",
+                Return,
+            ),
+        ],
+        [
+            Statement {
+                text: "(storage dead)",
+                effects: [
+                    StorageDead {
+                        local: "x",
+                    },
+                ],
+                assertions: [],
+            },
+            Statement {
+                text: "(return)",
+                effects: [
+                    Read {
+                        place: Place {
+                            base: "return",
+                            projections: [],
+                        },
+                        kind: Copy,
+                    },
+                ],
+                assertions: [],
+            },
+        ],
+    )
+    "###);
+}
+
+#[test]
+fn place_display() {
+    for place in &["x", "x.f.g", "*x", "**x.f", "x.*.f"] {
+        let ast_place = ast::Place {
+            base: place.split('.').next().unwrap().to_string(),
+            fields: place.split('.').skip(1).map(str::to_string).collect(),
+        };
+        assert_eq!(Place::from(&ast_place).to_string(), *place);
+    }
+}
+
+#[test]
+fn ghost_statements() {
+    let body = lower_str(
+        "
+        let x: i32;
+        bb0: {
+            assert_error;
+            x = 1;
+            assert_live('a);
+            assert_error;
+            x = 2;
+        }
+    ",
+    );
+    let assertions: Vec<Vec<String>> = body.blocks[0]
+        .statements
+        .iter()
+        .map(|s| s.assertions.iter().map(|a| a.to_string()).collect())
+        .collect();
+    insta::assert_debug_snapshot!(assertions, @r###"
+    [
+        [
+            "assert_error",
+        ],
+        [
+            "assert_live('a)",
+            "assert_error",
+        ],
+    ]
+    "###);
+}
+
+#[test]
+fn ghost_statement_at_end_of_block() {
+    let program = crate::parse_ast(
+        "
+        bb0: {
+            assert_error;
+        }
+    ",
+    )
+    .unwrap();
+    let err = lower(&program).unwrap_err();
+    insta::assert_snapshot!(format!("{:#}", err), @"`assert_error` must be followed by a statement of `bb0`, where it is checked");
+}
+
+#[test]
+fn implicit_derefs() {
+    let body = lower_str(
+        "
+        struct S { f: i32 }
+        struct T<'a> { s: &'a S }
+        let t: &'t T<'s>;
+        let x: i32;
+        bb0: {
+            x = copy t.s.f;
+        }
+    ",
+    );
+    insta::assert_debug_snapshot!(body.blocks[0].statements[0].effects[0], @r###"
+    Read {
+        place: Place {
+            base: "t",
+            projections: [
+                Deref,
+                Field(
+                    "s",
+                ),
+                Deref,
+                Field(
+                    "f",
+                ),
+            ],
+        },
+        kind: Copy,
+    }
+    "###);
+}
+
+#[test]
+fn variant_types() {
+    let program = crate::parse_ast(
+        "
+        let x: i32;
+        let r: Result<&'r i32, ()>;
+        bb0: {
+            r = Ok(&'L_x x);
+            Some(copy x);
+            match r { Ok => bb1, Err => bb1 }
+        }
+        bb1: {
+        }
+    ",
+    )
+    .unwrap();
+    let env = TypeEnv::new(&program);
+    let tys: Vec<String> = program.basic_blocks[0]
+        .statements
+        .iter()
+        .map(|statement| match statement {
+            ast::Statement::Assign(_, expr) | ast::Statement::Drop(expr) => env
+                .expr_ty(expr)
+                .map_or_else(|e| e.to_string(), |ty| ty.to_string()),
+            _ => unreachable!(),
+        })
+        .collect();
+    insta::assert_debug_snapshot!(tys, @r###"
+    [
+        "cannot infer type parameter `E` of `Result` in a `Ok`",
+        "Option<i32>",
+    ]
+    "###);
+
+    let body = lower(&program).unwrap();
+    let block = &body.blocks[0];
+    insta::assert_debug_snapshot!(
+        (&block.statements[2].text, &block.statements[2].effects, &block.terminator),
+        @r###"
+    (
+        "match r",
+        [
+            Read {
+                place: Place {
+                    base: "r",
+                    projections: [],
+                },
+                kind: Discriminant,
+            },
+        ],
+        Switch {
+            place: Place {
+                base: "r",
+                projections: [],
+            },
+            arms: [
+                (
+                    1,
+                    Some(
+                        "Ok",
+                    ),
+                ),
+                (
+                    1,
+                    Some(
+                        "Err",
+                    ),
+                ),
+            ],
+        },
+    )
+    "###
+    );
+}
+
+#[test]
+fn struct_literal_types() {
+    let program = crate::parse_ast(
+        "
+        extern type E;
+        struct S<'a, T> { f: &'a i32, g: T }
+        let y: &'y i32;
+        let z: S<'z, i32>;
+        let x: S<'x, i32>;
+        bb0: {
+            x = S { f: move y, ..move z };
+            S { f: move y, g: Some(1) };
+            S { f: move y };
+            S { f: move y, g: 1, f: move y };
+            S { f: move y, g: 1, h: 2 };
+            S { f: move y, ..&'L z };
+            S { f: move y, ..move y };
+            E { };
+            Option { Some: 1 };
+        }
+    ",
+    )
+    .unwrap();
+    let env = TypeEnv::new(&program);
+    let tys: Vec<String> = program.basic_blocks[0]
+        .statements
+        .iter()
+        .map(|statement| match statement {
+            ast::Statement::Assign(_, expr) | ast::Statement::Drop(expr) => env
+                .expr_ty(expr)
+                .map_or_else(|e| e.to_string(), |ty| ty.to_string()),
+            _ => unreachable!(),
+        })
+        .collect();
+    insta::assert_debug_snapshot!(tys, @r###"
+    [
+        "S<'a, i32>",
+        "S<'a, Option<i32>>",
+        "missing field `g` in a literal of `S`",
+        "the field `f` is given twice",
+        "`S` has no field `h`",
+        "the base `&'L z` is not a moved or copied place",
+        "the base `y` is a `&'y i32`, not a `S`",
+        "cannot build a value of the opaque type `E`",
+        "`Option` is an enum, its values are built by its variants",
+    ]
+    "###);
+
+    // The field given is read first, and then the other field is moved out of the base, on
+    // its own. Each field value flows into the field of the literal's fresh origins.
+    let mut program = program;
+    program.basic_blocks[0].statements.truncate(1);
+    let body = lower(&program).unwrap();
+    insta::assert_debug_snapshot!(&body.blocks[0].statements[0].effects, @r###"
+    [
+        Read {
+            place: Place {
+                base: "y",
+                projections: [],
+            },
+            kind: Move,
+        },
+        Read {
+            place: Place {
+                base: "z",
+                projections: [
+                    Field(
+                        "g",
+                    ),
+                ],
+            },
+            kind: Move,
+        },
+        Relate {
+            sub: Ref {
+                origin: "'y",
+                ty: I32,
+            },
+            sup: Ref {
+                origin: "'S0_a",
+                ty: I32,
+            },
+        },
+        Relate {
+            sub: I32,
+            sup: I32,
+        },
+        Write {
+            place: Place {
+                base: "x",
+                projections: [],
+            },
+            value_ty: Struct {
+                name: "S",
+                parameters: [
+                    Origin(
+                        "'S0_a",
+                    ),
+                    Ty(
+                        I32,
+                    ),
+                ],
+            },
+        },
+    ]
+    "###);
+}
@@ -1,45 +1,184 @@
-mod ast;
+pub mod annotations;
+pub mod ast;
 mod ast_parser;
+pub mod budget;
+#[cfg(feature = "capi")]
+pub mod capi;
+pub mod config;
+pub mod declarations;
+pub mod desugar;
+pub mod expected;
+pub mod fact_diff;
+pub mod fact_emitter;
 mod fact_parser;
+pub mod facts;
+pub mod features;
+pub mod generate;
+pub mod golden;
 mod graphviz;
+pub mod inline;
+pub mod ir;
+pub mod lints;
+pub mod mutation;
+pub mod passes;
+pub mod pipeline;
+pub mod precision;
+pub mod profile;
+pub mod resolve;
+pub mod results;
+pub mod scope;
+pub mod shrink;
+pub mod solver;
+pub mod subset_matrix;
+pub mod typeck;
+pub mod validate;
+pub mod variance;
+pub mod watch;
 
-use std::{path::PathBuf, process::Command};
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+    time::Instant,
+};
 
+pub use ast_parser::{
+    parse_ast, parse_ast_programs, parse_ast_with_declarations, DEFAULT_PROGRAM_NAME,
+};
+use config::Config;
 use eyre::Context;
-pub use fact_parser::generate_facts;
+pub use fact_emitter::{
+    emit_expectations, emit_facts, emit_facts_by_program, emit_trace, FactEmitter,
+};
+pub use fact_parser::{generate_facts, parse_fact_program};
+use facts::Facts;
+use itertools::Itertools;
+use passes::Pass;
+use solver::Algorithm;
+use std::str::FromStr;
 
-pub fn test_harness(dir_name: &str) -> eyre::Result<()> {
+/// Which solver computes the output relations.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Solver {
+    /// Runs `src/polonius.dl` with souffle.
+    Souffle,
+
+    /// Runs one of the native algorithms.
+    Native(Algorithm),
+
+    /// Runs all native algorithms, checking that they agree.
+    #[default]
+    Check,
+}
+
+impl FromStr for Solver {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> eyre::Result<Self> {
+        match s {
+            "souffle" => Ok(Solver::Souffle),
+            "check" => Ok(Solver::Check),
+            s => Ok(Solver::Native(s.parse()?)),
+        }
+    }
+}
+
+impl Solver {
+    /// Solves `facts`, writing the output relations to `output_path`. Souffle reads the facts
+    /// from `facts_path` instead, where they must already have been written.
+    pub fn solve(self, facts: &Facts, facts_path: &Path, output_path: &Path) -> eyre::Result<()> {
+        let rules_path = Path::new(config::DEFAULT_RULES_PATH);
+        self.solve_with_rules(rules_path, facts, facts_path, output_path)
+    }
+
+    /// Like `solve`, with souffle running the rules at `rules_path`.
+    pub fn solve_with_rules(
+        self,
+        rules_path: &Path,
+        facts: &Facts,
+        facts_path: &Path,
+        output_path: &Path,
+    ) -> eyre::Result<()> {
+        match self {
+            Solver::Souffle => run_souffle(rules_path, facts_path, output_path),
+            Solver::Native(algorithm) => {
+                solver::compute(facts, algorithm).write_to_dir(output_path)
+            }
+            Solver::Check => solver::check(facts)?.write_to_dir(output_path),
+        }
+    }
+}
+
+/// Solves the fact program in `dir_name`, and compares the errors with the expected ones. The
+/// example fails when it exceeds the limits of its `budget.txt`, see `budget`.
+pub fn test_harness(dir_name: &str, config: &Config) -> eyre::Result<()> {
     // let manifest_dir = PathBuf::from(std::env::var("CARGO_MANIFEST_DIR").unwrap());
     let manifest_dir = PathBuf::from(".");
 
-    let path = manifest_dir.join(&dir_name);
+    let path = manifest_dir.join(dir_name);
     let input_path = path.join("program.txt");
     let facts_path = path.join("facts");
     let data = std::fs::read_to_string(input_path)?;
 
+    // The time budget covers parsing and solving, not the checks of the example.
+    let start = Instant::now();
     std::fs::create_dir_all(&facts_path)?;
-    generate_facts(&data, &facts_path)?;
+    let mut facts = parse_fact_program(&data)?;
+    passes::run_passes(&mut facts, &config.passes);
+    facts.write_to_dir(&facts_path)?;
+    let mut elapsed = start.elapsed();
+
+    // Catch inconsistent examples before comparing outputs, as a diff wouldn't explain them.
+    golden::check_test_dir(&path)?;
 
+    let start = Instant::now();
     let output_path = path.join("output");
     std::fs::create_dir_all(&output_path)?;
 
-    let _ = Command::new("souffle")
-        .args(&[
-            manifest_dir.join("src/polonius.dl").display().to_string(),
-            "-F".to_string(),
-            facts_path.display().to_string(),
-            "-D".to_string(),
-            output_path.display().to_string(),
-        ])
-        .output()
-        .wrap_err("failed to run souffle")?;
+    config
+        .solver
+        .solve_with_rules(&config.rules_path, &facts, &facts_path, &output_path)?;
+    let output = solver::Output::read_from_dir(&output_path)?;
+    elapsed += start.elapsed();
+
+    let budget_path = path.join("budget.txt");
+    if budget_path.exists() {
+        let budget = budget::parse_budget(&std::fs::read_to_string(&budget_path)?)?;
+        let tuples =
+            facts.tuple_counts().iter().map(|(_, n)| n).sum::<usize>() + output.tuple_count();
+        budget
+            .check(elapsed, tuples, config.budget_scale)
+            .wrap_err_with(|| format!("`{}` is over its budget", path.display()))?;
+    }
 
     let dot_path = output_path.join("graph.dot");
     graphviz::create_graph(path.as_path(), dot_path.as_path());
 
-    if std::env::var("BLESS").is_ok() {
+    // Examples with an `expected.txt` list all their diagnostics, instead of only the
+    // `invalidated_origin_accessed` tuples.
+    let expected_path = path.join("expected.txt");
+    if expected_path.exists() {
+        let actual = results::Results::new(&facts, output).diagnostics();
+        if config.bless {
+            let lines: String = actual.iter().map(|d| format!("{}\n", d)).collect();
+            std::fs::write(&expected_path, lines).wrap_err("failed to bless output")?;
+        }
+        let data = std::fs::read_to_string(&expected_path)?;
+        let expected = expected::parse_expected(&data)
+            .wrap_err_with(|| format!("failed to parse `{}`", expected_path.display()))?;
+        let discrepancies = expected::compare(&expected, &actual);
+        if !discrepancies.is_empty() {
+            eyre::bail!(
+                "`{}`: the diagnostics differ from the expected ones:\n{}",
+                path.display(),
+                discrepancies
+            );
+        }
+        return Ok(());
+    }
+
+    if config.bless {
         let status = Command::new("cp")
-            .args(&[
+            .args([
                 output_path.join("invalidated_origin_accessed.csv"),
                 path.join("invalidated_origin_accessed.csv"),
             ])
@@ -50,15 +189,267 @@ pub fn test_harness(dir_name: &str) -> eyre::Result<()> {
         }
     }
 
-    let status = Command::new("diff")
-        .args(&[
-            path.join("invalidated_origin_accessed.csv"),
-            output_path.join("invalidated_origin_accessed.csv"),
-        ])
-        .status()
-        .wrap_err("failed to run diff")?;
+    let expected = solver::Output {
+        invalidated_origin_accessed: solver::read_csv(
+            &path.join("invalidated_origin_accessed.csv"),
+            2,
+        )?
+        .into_iter()
+        .map(|row| row.into_iter().collect_tuple().unwrap())
+        .collect(),
+        ..output.clone()
+    };
+    let diff = expected.diff(&output);
+    if !diff.is_empty() {
+        eyre::bail!(
+            "`{}`: the solver output differs from the expected one:\n{}",
+            path.display(),
+            diff.join("\n")
+        );
+    }
+
+    Ok(())
+}
+
+/// Emits the facts of the AST program at `input_path`, and checks the expectations of its ghost
+/// statements, e.g. `assert_live('a);`, against the solver output. The ghost statements in the
+/// body of a function defined by the program are checked against the facts of that body, see
+/// `FactEmitter::function_programs`. Each example chooses how liveness is modeled in its facts,
+/// through `config.emitter`.
+pub fn assertion_harness(input_path: &str, config: &Config) -> eyre::Result<()> {
+    let data = std::fs::read_to_string(input_path)
+        .wrap_err_with(|| format!("failed to read `{}`", input_path))?;
+    let (program, declarations) = parse_ast_with_declarations(&data)?;
+    // The expectations refer to the nodes by their simple names.
+    let options = fact_emitter::Options {
+        simple_node_names: true,
+        ..config.emitter.clone()
+    };
+    let functions = FactEmitter::function_programs(&program, &options)?
+        .into_iter()
+        .map(|(name, program, options)| (Some(name), program, options));
+
+    let mut unmet = vec![];
+    for (function, program, options) in std::iter::once((None, program, options)).chain(functions) {
+        let (mut facts, expectations) = FactEmitter::expectations(program, options)?;
+        passes::run_passes(&mut facts, &config.passes);
+
+        let scratch_path =
+            std::env::temp_dir().join(format!("polonius-assertions-{}", std::process::id()));
+        let output =
+            solve_output_with_rules(&facts, config.solver, &config.rules_path, &scratch_path);
+        let _ = std::fs::remove_dir_all(&scratch_path);
+
+        let results = results::Results::new(&facts, output?);
+        for expectation in results.unmet_expectations(&expectations) {
+            // Point at the declaration of the origin each expectation is about.
+            let declaration = match &expectation.assertion {
+                ast::Assertion::Live(origin) | ast::Assertion::Empty(origin) => {
+                    declarations.declared_at(origin)
+                }
+                ast::Assertion::EachIteration { arguments, .. } => arguments
+                    .iter()
+                    .find_map(|argument| declarations.declared_at(argument)),
+                ast::Assertion::Error => None,
+            };
+            let mut message = match declaration {
+                Some(declaration) => format!("{} ({})", expectation, declaration),
+                None => expectation.to_string(),
+            };
+            if let Some(function) = &function {
+                message = format!("{}, in the body of `{}`", message, function);
+            }
+            unmet.push(message);
+        }
+    }
+    if !unmet.is_empty() {
+        eyre::bail!(
+            "`{}`: unmet expectations:\n{}",
+            input_path,
+            unmet.iter().format("\n")
+        );
+    }
+    Ok(())
+}
+
+/// Emits the facts of the AST program at `input_path`, and checks that each fact annotated
+/// inline, e.g. `x = &'a y; //~ introduce_subset('a, 'x)`, is emitted at the node of its
+/// statement, see `annotations`. Every unmet annotation is reported at once.
+pub fn annotation_harness(input_path: &str, config: &Config) -> eyre::Result<()> {
+    let data = std::fs::read_to_string(input_path)
+        .wrap_err_with(|| format!("failed to read `{}`", input_path))?;
+    let unmet = annotations::check_annotations(&data, &config.emitter)
+        .wrap_err_with(|| format!("in `{}`", input_path))?;
+    if !unmet.is_empty() {
+        eyre::bail!(
+            "`{}`: unmet annotations:\n{}",
+            input_path,
+            unmet.iter().format("\n")
+        );
+    }
+    Ok(())
+}
 
-    assert!(status.success());
+/// Emits the facts of each AST example of the emission budgets file at `budgets_path`, found
+/// next to it, and checks that none emits more tuples in a relation than its limit, see
+/// `budget`. Every relation over its budget is reported at once.
+pub fn emission_budget_harness(budgets_path: &str, config: &Config) -> eyre::Result<()> {
+    let data = std::fs::read_to_string(budgets_path)
+        .wrap_err_with(|| format!("failed to read `{}`", budgets_path))?;
+    let budgets = budget::parse_emission_budgets(&data)
+        .wrap_err_with(|| format!("failed to parse `{}`", budgets_path))?;
 
+    let dir = Path::new(budgets_path).parent().unwrap_or(Path::new("."));
+    let mut exceeded = vec![];
+    for example in budgets.examples() {
+        let path = dir.join(example);
+        let program = std::fs::read_to_string(&path)
+            .wrap_err_with(|| format!("failed to read `{}`", path.display()))?;
+        let facts = emit_facts(&program, config.emitter.clone())?;
+        exceeded.extend(budgets.check(example, &facts));
+    }
+    if !exceeded.is_empty() {
+        eyre::bail!(
+            "`{}`: the emitted facts are over budget:\n{}",
+            budgets_path,
+            exceeded.join("\n")
+        );
+    }
+    Ok(())
+}
+
+/// Runs the datalog rules in `rules_path`, usually `src/polonius.dl`, over the facts in
+/// `facts_path`, writing the output relations to `output_path`.
+fn run_souffle(rules_path: &Path, facts_path: &Path, output_path: &Path) -> eyre::Result<()> {
+    let _ = Command::new("souffle")
+        .args([
+            rules_path.display().to_string(),
+            "-F".to_string(),
+            facts_path.display().to_string(),
+            "-D".to_string(),
+            output_path.display().to_string(),
+        ])
+        .output()
+        .wrap_err("failed to run souffle")?;
     Ok(())
 }
+
+/// Solves `facts` in a scratch directory, and reads back the output relations.
+fn solve_output(
+    facts: &Facts,
+    solver: Solver,
+    scratch_path: &Path,
+) -> eyre::Result<solver::Output> {
+    let rules_path = Path::new(config::DEFAULT_RULES_PATH);
+    solve_output_with_rules(facts, solver, rules_path, scratch_path)
+}
+
+/// Like `solve_output`, with souffle running the rules at `rules_path`.
+fn solve_output_with_rules(
+    facts: &Facts,
+    solver: Solver,
+    rules_path: &Path,
+    scratch_path: &Path,
+) -> eyre::Result<solver::Output> {
+    let facts_path = scratch_path.join("facts");
+    let output_path = scratch_path.join("output");
+    std::fs::create_dir_all(&facts_path)?;
+    std::fs::create_dir_all(&output_path)?;
+
+    facts.write_to_dir(&facts_path)?;
+    solver.solve_with_rules(rules_path, facts, &facts_path, &output_path)?;
+    solver::Output::read_from_dir(&output_path)
+}
+
+/// Solves `facts` in a scratch directory, returning the sorted `invalidated_origin_accessed`
+/// tuples.
+fn solve_errors(facts: &Facts, solver: Solver, scratch_path: &Path) -> eyre::Result<Vec<String>> {
+    let output = solve_output(facts, solver, scratch_path)?;
+    Ok(output
+        .invalidated_origin_accessed
+        .iter()
+        .map(|(origin, node)| format!("{}\t{}", origin, node))
+        .sorted()
+        .collect())
+}
+
+/// Solves the program at `input_path` with both `solvers`, e.g. souffle and a native
+/// algorithm, and reports the tuples of the output relations on which they disagree.
+pub fn compare_solvers(input_path: &Path, solvers: [Solver; 2]) -> eyre::Result<String> {
+    let data = std::fs::read_to_string(input_path)
+        .wrap_err_with(|| format!("failed to read `{}`", input_path.display()))?;
+    let facts = parse_fact_program(&data)?;
+
+    let scratch_path =
+        std::env::temp_dir().join(format!("polonius-compare-solvers-{}", std::process::id()));
+    let outputs = solvers
+        .iter()
+        .enumerate()
+        .map(|(i, &solver)| solve_output(&facts, solver, &scratch_path.join(i.to_string())))
+        .collect::<eyre::Result<Vec<_>>>();
+    let _ = std::fs::remove_dir_all(&scratch_path);
+    let outputs = outputs?;
+
+    let diff = outputs[0].diff(&outputs[1]);
+    if diff.is_empty() {
+        Ok("solver results agree\n".to_string())
+    } else {
+        Ok(diff.into_iter().map(|line| line + "\n").collect())
+    }
+}
+
+/// Emits the facts of the program at `input_path` once without any passes, then once with
+/// each of `passes` enabled (and once with all of them, if there are several), and reports
+/// how many tuples each configuration removed and whether the solver results changed.
+pub fn compare_modes(input_path: &Path, passes: &[Pass], solver: Solver) -> eyre::Result<String> {
+    let data = std::fs::read_to_string(input_path)
+        .wrap_err_with(|| format!("failed to read `{}`", input_path.display()))?;
+    let baseline = parse_fact_program(&data)?;
+
+    let mut modes: Vec<(String, Vec<Pass>)> = passes
+        .iter()
+        .map(|&pass| (pass.name().to_string(), vec![pass]))
+        .collect();
+    if passes.len() > 1 {
+        modes.push(("all".to_string(), passes.to_vec()));
+    }
+
+    let scratch_path =
+        std::env::temp_dir().join(format!("polonius-compare-modes-{}", std::process::id()));
+    let baseline_errors = solve_errors(&baseline, solver, &scratch_path.join("baseline"))?;
+
+    let mut report = String::new();
+    for (name, mode_passes) in modes {
+        let mut facts = baseline.clone();
+        passes::run_passes(&mut facts, &mode_passes);
+        let errors = solve_errors(&facts, solver, &scratch_path.join(&name))?;
+
+        report += &format!("mode `{}`:\n", name);
+        for ((relation, before), (_, after)) in baseline
+            .tuple_counts()
+            .into_iter()
+            .zip(facts.tuple_counts())
+        {
+            if before != after {
+                report += &format!(
+                    "    {}: {} -> {} ({:+})\n",
+                    relation,
+                    before,
+                    after,
+                    after as isize - before as isize
+                );
+            }
+        }
+        if errors == baseline_errors {
+            report += "    solver results unchanged\n";
+        } else {
+            report += &format!(
+                "    solver results CHANGED: {:?} -> {:?}\n",
+                baseline_errors, errors
+            );
+        }
+    }
+
+    let _ = std::fs::remove_dir_all(&scratch_path);
+    Ok(report)
+}
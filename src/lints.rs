@@ -0,0 +1,227 @@
+//! Lints over the facts emitted for a program, which usually point at mistakes in the program
+//! itself rather than borrow errors.
+
+use std::collections::BTreeSet;
+use std::fmt;
+
+use crate::ast::visit::{walk_basic_block, walk_expr, walk_statement, ProgramVisitor};
+use crate::ast::{
+    AccessKind, BasicBlock, Expr, FnPrototype, Name, Program, Statement, StructDecl, TypeAlias,
+    VariableDecl,
+};
+use crate::facts::{Facts, Origin};
+use crate::solver::Output;
+use crate::typeck;
+
+#[cfg(test)]
+mod test;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum WarningKind {
+    /// See `cleared_only_origins`.
+    ClearedOnly,
+
+    /// See `unused_borrows`.
+    UnusedBorrow,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Warning {
+    pub kind: WarningKind,
+    pub origin: Origin,
+
+    /// Where the origin is declared in the program, e.g. "in the type of `x`", or for a
+    /// borrow, "in statement `r = &'L_x x` of `bb0`".
+    pub declaration: String,
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            WarningKind::ClearedOnly => write!(
+                f,
+                "warning: origin `{}` is cleared but never accessed or related to another \
+                 origin, declared {}",
+                self.origin, self.declaration
+            ),
+            WarningKind::UnusedBorrow => write!(
+                f,
+                "warning: borrow `{}` is never used, {}",
+                self.origin, self.declaration
+            ),
+        }
+    }
+}
+
+/// Finds the origins which only ever appear in `clear_origin`. Clearing an origin has no
+/// effect if nothing flows into or out of it, so this is usually a mistyped origin name.
+pub fn cleared_only_origins(program: &Program, facts: &Facts) -> Vec<Warning> {
+    let used: BTreeSet<&Origin> = facts
+        .access_origins()
+        .chain(facts.drop_uses())
+        .chain(facts.invalidate_origins())
+        .map(|(o, _)| o)
+        .chain(facts.introduce_subsets().flat_map(|(o1, o2, _)| [o1, o2]))
+        .chain(facts.origin_equalities().flat_map(|(o1, o2, _)| [o1, o2]))
+        .collect();
+    let cleared_only: BTreeSet<&Origin> = facts
+        .clear_origins()
+        .map(|(o, _)| o)
+        .filter(|o| !used.contains(o))
+        .collect();
+
+    cleared_only
+        .into_iter()
+        .map(|origin| Warning {
+            kind: WarningKind::ClearedOnly,
+            origin: origin.clone(),
+            declaration: declaration(program, origin)
+                .unwrap_or_else(|| "nowhere in the program".to_string()),
+        })
+        .collect()
+}
+
+/// Finds the borrows of the program whose loan is never live in the solver `output`: neither its
+/// origin, nor an origin it flows into, is live at any node, so the reference it creates is
+/// never used. Besides pointing at dead borrows, it catches liveness and access facts which
+/// disagree, e.g. a use of the reference missing its `access_origin`.
+///
+/// A borrow passed to a call is used by the call, even when the origins of the callee's
+/// signature it flows into are dead after it, like in `push(&'L_v mut v, 1)`.
+pub fn unused_borrows(program: &Program, output: &Output) -> Vec<Warning> {
+    let mut borrows = BorrowFinder::default();
+    borrows.visit_program(program).unwrap();
+
+    let live: BTreeSet<(&Origin, &str)> = output
+        .origin_live
+        .iter()
+        .map(|(origin, node)| (origin, node.as_str()))
+        .collect();
+    let is_used = |loan: &Origin| {
+        borrows.call_arguments.contains(loan)
+            || live.iter().any(|(origin, _)| *origin == loan)
+            || output
+                .subset
+                .iter()
+                .any(|(o1, o2, node)| o1 == loan && live.contains(&(o2, node.as_str())))
+    };
+
+    borrows
+        .origins
+        .iter()
+        .copied()
+        .filter(|loan| !is_used(loan))
+        .map(|loan| Warning {
+            kind: WarningKind::UnusedBorrow,
+            declaration: declaration(program, loan)
+                .unwrap_or_else(|| "nowhere in the program".to_string()),
+            origin: loan.clone(),
+        })
+        .collect()
+}
+
+/// The origins of the borrow expressions of a program.
+#[derive(Default)]
+struct BorrowFinder<'p> {
+    origins: BTreeSet<&'p Name>,
+
+    /// The origins of the borrows passed as arguments to a call.
+    call_arguments: BTreeSet<&'p Name>,
+}
+
+impl<'p> ProgramVisitor<'p> for BorrowFinder<'p> {
+    fn visit_expr(&mut self, expr: &'p Expr) -> eyre::Result<()> {
+        match expr {
+            Expr::Access {
+                kind: AccessKind::Borrow(o) | AccessKind::BorrowMut(o),
+                ..
+            } => {
+                self.origins.insert(o);
+            }
+            Expr::Call { arguments, .. } => {
+                self.call_arguments.extend(arguments.iter().filter_map(
+                    |argument| match argument {
+                        Expr::Access {
+                            kind: AccessKind::Borrow(o) | AccessKind::BorrowMut(o),
+                            ..
+                        } => Some(o),
+                        _ => None,
+                    },
+                ));
+            }
+            _ => {}
+        }
+        walk_expr(self, expr)
+    }
+}
+
+/// Finds the first variable whose type contains `origin`, or the first borrow using it.
+fn declaration(program: &Program, origin: &str) -> Option<String> {
+    let mut finder = DeclarationFinder {
+        origin,
+        block: None,
+        statement: None,
+        declaration: None,
+    };
+    finder.visit_program(program).unwrap();
+    finder.declaration
+}
+
+struct DeclarationFinder<'o, 'p> {
+    origin: &'o str,
+    block: Option<&'p Name>,
+    statement: Option<&'p Statement>,
+    declaration: Option<String>,
+}
+
+impl<'p> ProgramVisitor<'p> for DeclarationFinder<'_, 'p> {
+    /// Only the variables and statements of the program declare its origins.
+    fn visit_struct_decl(&mut self, _decl: &'p StructDecl) -> eyre::Result<()> {
+        Ok(())
+    }
+
+    fn visit_type_alias(&mut self, _alias: &'p TypeAlias) -> eyre::Result<()> {
+        Ok(())
+    }
+
+    fn visit_fn_prototype(&mut self, _prototype: &'p FnPrototype) -> eyre::Result<()> {
+        Ok(())
+    }
+
+    fn visit_variable_decl(&mut self, variable: &'p VariableDecl) -> eyre::Result<()> {
+        let declares = typeck::origins_of_ty(&variable.ty)
+            .iter()
+            .any(|o| *o == self.origin);
+        if declares && self.declaration.is_none() {
+            self.declaration = Some(format!("in the type of `{}`", variable.name));
+        }
+        Ok(())
+    }
+
+    fn visit_basic_block(&mut self, block: &'p BasicBlock) -> eyre::Result<()> {
+        self.block = Some(&block.name);
+        walk_basic_block(self, block)
+    }
+
+    fn visit_statement(&mut self, statement: &'p Statement) -> eyre::Result<()> {
+        self.statement = Some(statement);
+        walk_statement(self, statement)
+    }
+
+    fn visit_expr(&mut self, expr: &'p Expr) -> eyre::Result<()> {
+        if let Expr::Access {
+            kind: AccessKind::Borrow(o) | AccessKind::BorrowMut(o),
+            ..
+        } = expr
+        {
+            if o == self.origin && self.declaration.is_none() {
+                self.declaration = Some(format!(
+                    "in statement `{}` of `{}`",
+                    self.statement.unwrap(),
+                    self.block.unwrap()
+                ));
+            }
+        }
+        walk_expr(self, expr)
+    }
+}
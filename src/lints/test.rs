@@ -0,0 +1,143 @@
+use super::*;
+use crate::fact_emitter::{emit_facts, Options};
+
+fn lint(s: &str) -> Vec<String> {
+    let program = crate::parse_ast(s).unwrap();
+    let facts = emit_facts(s, Options::default()).unwrap();
+    cleared_only_origins(&program, &facts)
+        .iter()
+        .map(|w| w.to_string())
+        .collect()
+}
+
+#[test]
+fn mistyped_origin() {
+    // `'r` is cleared when `r` is assigned, but `r` is never read, and the value `get` returns
+    // has the same origin, so nothing flows into `'r` either.
+    let warnings = lint(
+        "
+        fn get() -> &'r i32;
+        let r: &'r i32;
+        bb0: {
+            r = get();
+        }
+    ",
+    );
+
+    insta::assert_debug_snapshot!(warnings, @r###"
+    [
+        "warning: origin `'r` is cleared but never accessed or related to another origin, declared in the type of `r`",
+    ]
+    "###);
+}
+
+#[test]
+fn no_warnings() {
+    let warnings = lint(
+        "
+        fn use<T>(t: T) -> ();
+        let x: i32;
+        let y: &'y i32;
+        bb0: {
+            x = 3;
+            y = &'L_x x;
+            use(copy y);
+        }
+    ",
+    );
+
+    assert!(warnings.is_empty(), "{:?}", warnings);
+}
+
+fn unused(s: &str) -> Vec<String> {
+    let program = crate::parse_ast(s).unwrap();
+    let facts = emit_facts(s, Options::default()).unwrap();
+    let output = crate::solver::check(&facts).unwrap();
+    unused_borrows(&program, &output)
+        .iter()
+        .map(|w| w.to_string())
+        .collect()
+}
+
+#[test]
+fn dead_borrows() {
+    // The loan of `x` flows into `'p`, which is read later, but `q` is overwritten before its
+    // borrow of `y` is read, and `&'L_z z` is dropped right away.
+    let warnings = unused(
+        "
+        let x: i32;
+        let y: i32;
+        let z: i32;
+        let mut w: i32;
+        let p: &'p i32;
+        let mut q: &'q i32;
+        bb0: {
+            x = 1;
+            y = 2;
+            z = 3;
+            p = &'L_x x;
+            q = &'L_y y;
+            q = &'L_x2 x;
+            &'L_z z;
+            goto bb1;
+        }
+        bb1: {
+            w = copy *p;
+            w = copy *q;
+        }
+    ",
+    );
+
+    insta::assert_debug_snapshot!(warnings, @r###"
+    [
+        "warning: borrow `'L_y` is never used, in statement `q = &'L_y y` of `bb0`",
+        "warning: borrow `'L_z` is never used, in statement `&'L_z z` of `bb0`",
+    ]
+    "###);
+}
+
+#[test]
+fn borrows_used_through_calls_are_live() {
+    let warnings = unused(
+        "
+        fn id<'a>(r: &'a i32) -> &'a i32;
+        fn use<T>(t: T) -> ();
+        let x: i32;
+        let r: &'r i32;
+        bb0: {
+            x = 1;
+            r = id(&'L_x x);
+            use(copy r);
+        }
+    ",
+    );
+
+    assert!(warnings.is_empty(), "{:?}", warnings);
+}
+
+#[test]
+fn borrows_passed_to_calls_are_used() {
+    // The loan of `v` flows into `'push0_v`, which is dead after the call, but the call itself
+    // uses it. The loan of `w` isn't passed to the call.
+    let warnings = unused(
+        "
+        struct Vec<T> { item: T }
+        fn push<'v, T>(v: &'v mut Vec<T>, item: T) -> ();
+        let x: i32;
+        let mut v: Vec<i32>;
+        let mut w: Vec<i32>;
+        let r: &'r mut Vec<i32>;
+        bb0: {
+            x = 1;
+            push(&'L_v mut v, copy x);
+            r = &'L_w mut w;
+        }
+    ",
+    );
+
+    insta::assert_debug_snapshot!(warnings, @r###"
+    [
+        "warning: borrow `'L_w` is never used, in statement `r = &'L_w mut w` of `bb0`",
+    ]
+    "###);
+}
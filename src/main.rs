@@ -1,6 +1,690 @@
+use eyre::WrapErr;
+use polonius::config::Config;
+use polonius::profile::Profiler;
+use polonius::Solver;
+
+/// Printed by `polonius --help`: each subcommand with its flags, detailed in the doc comment of
+/// its function below.
+const USAGE: &str = "\
+usage: polonius <example-dir>..
+       polonius <subcommand> <args>..
+
+Without a subcommand, runs the test harness on each example, configured by the environment
+variables described in tests/README.md, e.g. SOLVER=souffle or BLESS=1.
+
+subcommands:
+  parse <file> [--format=pretty|debug|json]
+      print the AST of a program, with its macros expanded
+  emit <file> [options]
+      print the facts of an AST program, and lint warnings on stderr
+        --simple-node-names       name the nodes a, b, c..
+        --stable-node-names       name the nodes after their block and statement, e.g. bb1s0
+        --node-name-migration     print the simple -> stable name of each node instead
+        --introduce-temporaries   hoist borrows and calls in call arguments into temporaries
+        --explicit-liveness       emit liveness computed from the variables, like NLL
+        --external-origin <'a>    assume an origin is used by the caller (repeatable)
+        --origin-equalities       emit origin_equal instead of subsets both ways
+        --origin-kinds            print the origin_kind of each origin
+        --move-paths              emit the move paths and their uses
+        --refinements             emit the value of the scrutinee known in each arm
+        --only <program>          only emit these programs (repeatable)
+        --only-block <block>      only emit these blocks (repeatable)
+        --profile                 print the time and memory of each phase on stderr
+  solve <file> [options]
+      print the errors of a fact or AST program, and unused borrows
+        --solver <solver>         naive, optimized, location-insensitive or check (default)
+        --passes <a,b>            fact-reduction passes to run before solving
+        --cache <dir>             reuse the outputs of programs whose facts didn't change
+        --external-origin <'a>    as for emit
+        --origin-equalities       as for emit
+        --only, --only-block      as for emit
+        --profile                 as for emit, with the cache hits and misses
+  compare-modes <file> --passes <a,b> [--solver <solver>]
+      check that the passes don't change the solver output
+  compare-solvers <file> [--solvers <a>,<b>]
+      print the output tuples on which two solvers, e.g. souffle,check, disagree
+  compare-invalidation <file.ast>..
+      print the programs whose errors differ when approximating places by their local
+  diff-facts <example> <file.ast> [--stable-node-names]
+      compare the facts of an example with the ones emitted for its AST version
+  subset-matrix <file> [--node <node>] [--html]
+      print the subset relation as an origins x origins matrix
+  shrink <file> (--panics | --error <text> | --reports-errors)
+      shrink a program to a minimal one which still fails
+  mutate <file.ast>.. [--explicit-liveness] [--solver <solver>]
+      print the mutants of the facts which the expectations of a program don't kill
+  generate [--seed <n>] [--count <n>] [--focus <focus>] [--print]
+      check random programs, biased toward deref-borrows, struct-assignments or call-returns
+  watch <dir>
+      re-check the examples of a directory whenever they or the rules change
+  check-tests <example-dir>..
+      check that examples are internally consistent
+";
+
 fn main() -> eyre::Result<()> {
-    for arg in std::env::args().skip(1) {
-        polonius::test_harness(&arg)?;
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        Some("--help") | Some("-h") => {
+            print!("{}", USAGE);
+            Ok(())
+        }
+        Some("compare-modes") => compare_modes(&args[1..]),
+        Some("compare-solvers") => compare_solvers(&args[1..]),
+        Some("compare-invalidation") => compare_invalidation(&args[1..]),
+        Some("diff-facts") => diff_facts(&args[1..]),
+        Some("solve") => solve(&args[1..]),
+        Some("emit") => emit(&args[1..]),
+        Some("parse") => parse(&args[1..]),
+        Some("subset-matrix") => subset_matrix(&args[1..]),
+        Some("shrink") => shrink(&args[1..]),
+        Some("mutate") => mutate(&args[1..]),
+        Some("generate") => generate(&args[1..]),
+        Some("watch") => watch(&args[1..]),
+        Some("check-tests") => {
+            for arg in &args[1..] {
+                polonius::golden::check_test_dir(std::path::Path::new(positional(arg)?))?;
+            }
+            Ok(())
+        }
+        _ => {
+            let config = Config::from_env()?;
+            for arg in &args {
+                polonius::test_harness(positional(arg)?, &config)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+/// `polonius compare-modes <file> --passes reachability-prune,dedup [--solver <solver>]`
+fn compare_modes(args: &[String]) -> eyre::Result<()> {
+    let mut input = None;
+    let mut passes = polonius::passes::Pass::ALL.to_vec();
+    let mut solver = Solver::default();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--passes" => {
+                let list = args
+                    .next()
+                    .ok_or_else(|| eyre::eyre!("`--passes` expects a comma-separated list"))?;
+                passes = polonius::passes::parse_pass_list(list)?;
+            }
+            "--solver" => solver = solver_arg(args.next())?,
+            _ => input_arg(&mut input, arg)?,
+        }
+    }
+    let input = input.ok_or_else(|| eyre::eyre!("usage: compare-modes <file> [--passes ..]"))?;
+
+    print!(
+        "{}",
+        polonius::compare_modes(std::path::Path::new(input), &passes, solver)?
+    );
+    Ok(())
+}
+
+/// `polonius compare-solvers <file> [--solvers souffle,check]`
+///
+/// Prints the output tuples on which the two solvers disagree: `-` for the tuples only
+/// computed by the first solver, and `+` for the ones only computed by the second.
+fn compare_solvers(args: &[String]) -> eyre::Result<()> {
+    let mut input = None;
+    let mut solvers = [Solver::Souffle, Solver::Check];
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--solvers" => {
+                let list = args
+                    .next()
+                    .ok_or_else(|| eyre::eyre!("`--solvers` expects two solver names"))?;
+                match list.split(',').collect::<Vec<_>>()[..] {
+                    [a, b] => solvers = [a.parse()?, b.parse()?],
+                    _ => eyre::bail!("`--solvers` expects two solver names"),
+                }
+            }
+            _ => input_arg(&mut input, arg)?,
+        }
+    }
+    let input =
+        input.ok_or_else(|| eyre::eyre!("usage: compare-solvers <file> [--solvers a,b]"))?;
+
+    print!(
+        "{}",
+        polonius::compare_solvers(std::path::Path::new(input), solvers)?
+    );
+    Ok(())
+}
+
+/// `polonius compare-invalidation <file.ast>..`
+///
+/// Checks each program of the files with the field- and deref-sensitive invalidations, then
+/// approximating each place by its whole local, and prints the programs whose errors differ,
+/// see `precision::PrecisionReport`.
+fn compare_invalidation(args: &[String]) -> eyre::Result<()> {
+    if args.is_empty() {
+        eyre::bail!("usage: compare-invalidation <file.ast>..");
+    }
+    let options = Config::from_env()?.emitter;
+    let mut report = polonius::precision::PrecisionReport::default();
+    for file in args {
+        let file = positional(file)?;
+        let input =
+            std::fs::read_to_string(file).wrap_err_with(|| format!("failed to read `{}`", file))?;
+        report.add_input(file, &input, &options)?;
+    }
+    print!("{}", report);
+    Ok(())
+}
+
+/// `polonius diff-facts <example> <file.ast> [--stable-node-names]`
+///
+/// Compares the hand-written facts of an example, a fact program like `tests/*/program.txt`,
+/// with the facts emitted for its AST version, named like the example's nodes. Each tuple
+/// missing from the emitted facts is printed with the emitter feature responsible for it, when
+/// it matches a known gap of the emitter.
+fn diff_facts(args: &[String]) -> eyre::Result<()> {
+    let mut options = polonius::fact_emitter::Options {
+        simple_node_names: true,
+        ..Default::default()
+    };
+    let mut inputs = vec![];
+    for arg in args {
+        match arg.as_str() {
+            "--stable-node-names" => options.stable_node_names = true,
+            _ => inputs.push(positional(arg)?),
+        }
+    }
+    let (example, ast) = match inputs[..] {
+        [example, ast] => (example, ast),
+        _ => eyre::bail!("usage: diff-facts <example> <file.ast> [--stable-node-names]"),
+    };
+
+    let manual = polonius::parse_fact_program(&std::fs::read_to_string(example)?)?;
+    let generated = polonius::emit_facts(&std::fs::read_to_string(ast)?, options)?;
+    print!("{}", polonius::fact_diff::diff_facts(&manual, &generated));
+    Ok(())
+}
+
+/// `polonius solve <file> [--solver naive|optimized|location-insensitive|check]
+/// [--passes a,b] [--cache <dir>] [--external-origin <origin>].. [--origin-equalities]
+/// [--only <program>].. [--only-block <block>].. [--profile]`
+///
+/// Prints the `invalidated_origin_accessed` and `returned_reference_to_local` errors of the
+/// program, after running the given fact-reduction passes. The file is a fact program, or
+/// an AST program ending in `.ast`, whose `program` sections are each solved separately, after
+/// a `// program <name>` header. The body of each function defined by a program is solved
+/// after it, after a `// fn <name>` header. The borrows of an AST program which are never
+/// used are reported as lint warnings.
+///
+/// With `--cache`, the output of each program is stored in the directory, keyed on a hash of
+/// its facts, and only the programs whose facts changed since the last run are solved again.
+/// `--only` and `--only-block` restrict an AST input like for `emit`.
+fn solve(args: &[String]) -> eyre::Result<()> {
+    let config = Config::from_env()?;
+    let mut input = None;
+    let mut solver = config.solver;
+    let mut passes = config.passes;
+    let mut options = config.emitter;
+    let mut profile = false;
+    let mut cache = polonius::solver::cache::OutputCache::default();
+    let mut scope = polonius::scope::Scope::default();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--solver" => solver = solver_arg(args.next())?,
+            "--passes" => {
+                let list = args
+                    .next()
+                    .ok_or_else(|| eyre::eyre!("`--passes` expects a comma-separated list"))?;
+                passes = polonius::passes::parse_pass_list(list)?;
+            }
+            "--cache" => {
+                let dir = args
+                    .next()
+                    .ok_or_else(|| eyre::eyre!("`--cache` expects a directory"))?;
+                cache = polonius::solver::cache::OutputCache::in_dir(dir);
+            }
+            "--external-origin" => options.external_origins.push(external_origin(args.next())?),
+            "--origin-equalities" => options.origin_equalities = true,
+            "--only" => scope.programs.push(scope_arg(arg, args.next())?),
+            "--only-block" => scope.blocks.push(scope_arg(arg, args.next())?),
+            "--profile" => profile = true,
+            _ => input_arg(&mut input, arg)?,
+        }
+    }
+    let input = input.ok_or_else(|| eyre::eyre!("usage: solve <file> [--solver ..]"))?;
+
+    let mut profiler = Profiler::default();
+    let text = std::fs::read_to_string(input)?;
+    let programs = if input.ends_with(".ast") {
+        let programs = profiler.time("parse", || polonius::parse_ast_programs(&text))?;
+        let named = programs.len() > 1 || programs[0].0 != polonius::DEFAULT_PROGRAM_NAME;
+        let mut emitted = vec![];
+        for (name, program) in scope.apply(programs)? {
+            let facts = polonius::FactEmitter::from_program_profiled(
+                program.clone(),
+                options.clone(),
+                &mut profiler,
+            )?;
+            let functions = polonius::FactEmitter::function_programs(&program, &options)?;
+            emitted.push((
+                named.then(|| format!("program {}", name)),
+                Some(program),
+                facts,
+            ));
+            for (function, program, options) in functions {
+                let facts = polonius::FactEmitter::from_program_profiled(
+                    program.clone(),
+                    options,
+                    &mut profiler,
+                )
+                .wrap_err_with(|| format!("in the body of `{}`", function))?;
+                emitted.push((Some(format!("fn {}", function)), Some(program), facts));
+            }
+        }
+        emitted
+    } else if !scope.is_empty() {
+        eyre::bail!("`--only` and `--only-block` only apply to AST programs");
+    } else {
+        vec![(
+            None,
+            None,
+            profiler.time("parse", || polonius::parse_fact_program(&text))?,
+        )]
+    };
+
+    for (i, (header, program, mut facts)) in programs.into_iter().enumerate() {
+        if let Some(header) = header {
+            if i > 0 {
+                println!();
+            }
+            println!("// {}", header);
+        }
+        for &pass in &passes {
+            profiler.time(format!("pass {}", pass.name()), || pass.run(&mut facts));
+        }
+        let output = profiler.time("solve", || {
+            cache.get_or_solve(&format!("{:?}", solver), &facts, |facts| match solver {
+                Solver::Native(algorithm) => Ok(polonius::solver::compute(facts, algorithm)),
+                Solver::Check => polonius::solver::check(facts),
+                Solver::Souffle => eyre::bail!("`solve` only supports the native solvers"),
+            })
+        })?;
+        for (origin, node) in &output.invalidated_origin_accessed {
+            println!("invalidated_origin_accessed({}, {})", origin, node);
+        }
+        for (origin, node) in &output.returned_reference_to_local {
+            println!("returned_reference_to_local({}, {})", origin, node);
+        }
+        if let Some(program) = program {
+            let warnings = polonius::lints::unused_borrows(&program, &output);
+            for warning in &warnings {
+                eprintln!("{}", warning);
+            }
+            if config.strict && !warnings.is_empty() {
+                eyre::bail!(
+                    "`{}` has lint warnings, which are errors with `STRICT`",
+                    input
+                );
+            }
+        }
+    }
+    if profile {
+        let stats = cache.stats();
+        profiler.count("cache_hits", stats.hits);
+        profiler.count("cache_misses", stats.misses);
+        eprintln!("{}", profiler.to_json());
+    }
+    Ok(())
+}
+
+/// `polonius emit <file> [--simple-node-names] [--stable-node-names] [--introduce-temporaries]
+/// [--explicit-liveness] [--external-origin <origin>].. [--origin-equalities] [--origin-kinds]
+/// [--move-paths] [--refinements] [--profile] [--node-name-migration] [--only <program>]..
+/// [--only-block <block>]..`
+///
+/// Prints the facts emitted for a program in the AST language, and the lint warnings about
+/// them on stderr. For an input with several `program` sections, the facts of each program
+/// follow a `// program <name>` header. The facts of the body of each function defined by a
+/// program follow its own, after a `// fn <name>` header.
+///
+/// With `--node-name-migration`, prints the `simple -> stable` node name of each node instead.
+///
+/// `--only` and `--only-block`, which can be repeated, restrict the output to the selected
+/// programs, and to the selected blocks of each.
+///
+/// `--external-origin`, which can be repeated, assumes that an origin comes from an unanalyzed
+/// caller, see `fact_emitter::Options::external_origins`. `--origin-equalities` emits
+/// `origin_equal` for the origins which must be equal, see `Options::origin_equalities`.
+/// `--origin-kinds` prints the `origin_kind` of each origin before the nodes. `--move-paths`
+/// emits the move paths and their uses, see `Options::move_paths`, and `--refinements` the
+/// value of the scrutinee known in each arm of a `match` or `switch`, see
+/// `Options::refinements`.
+fn emit(args: &[String]) -> eyre::Result<()> {
+    let config = Config::from_env()?;
+    let mut input = None;
+    let mut options = config.emitter;
+    let mut writer = config.writer;
+    let mut profile = false;
+    let mut migration = false;
+    let mut scope = polonius::scope::Scope::default();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--only" => scope.programs.push(scope_arg(arg, args.next())?),
+            "--only-block" => scope.blocks.push(scope_arg(arg, args.next())?),
+            "--simple-node-names" => options.simple_node_names = true,
+            "--stable-node-names" => options.stable_node_names = true,
+            "--node-name-migration" => migration = true,
+            "--introduce-temporaries" => options.introduce_temporaries = true,
+            "--explicit-liveness" => options.liveness = polonius::fact_emitter::Liveness::Explicit,
+            "--external-origin" => options.external_origins.push(external_origin(args.next())?),
+            "--origin-equalities" => options.origin_equalities = true,
+            "--origin-kinds" => writer.include_origin_kinds = true,
+            "--move-paths" => options.move_paths = true,
+            "--refinements" => options.refinements = true,
+            "--profile" => profile = true,
+            _ => input_arg(&mut input, arg)?,
+        }
+    }
+    let input = input.ok_or_else(|| eyre::eyre!("usage: emit <file> [--simple-node-names ..]"))?;
+
+    let mut profiler = Profiler::default();
+    let text = std::fs::read_to_string(input)?;
+    let programs = profiler.time("parse", || polonius::parse_ast_programs(&text))?;
+    let named = programs.len() > 1 || programs[0].0 != polonius::DEFAULT_PROGRAM_NAME;
+    for (i, (name, program)) in scope.apply(programs)?.into_iter().enumerate() {
+        if named {
+            if i > 0 {
+                println!();
+            }
+            println!("// program {}", name);
+        }
+        if migration {
+            for (simple, stable) in polonius::FactEmitter::node_name_migration(&program)? {
+                println!("{} -> {}", simple, stable);
+            }
+            continue;
+        }
+
+        let facts = polonius::FactEmitter::from_program_profiled(
+            program.clone(),
+            options.clone(),
+            &mut profiler,
+        )?;
+        let warnings = profiler.time("lint", || {
+            polonius::lints::cleared_only_origins(&program, &facts)
+        });
+        for warning in &warnings {
+            eprintln!("{}", warning);
+        }
+        if config.strict && !warnings.is_empty() {
+            eyre::bail!(
+                "`{}` has lint warnings, which are errors with `STRICT`",
+                name
+            );
+        }
+        print!("{}", writer.to_string(&facts));
+
+        for (function, facts) in polonius::FactEmitter::from_functions(&program, options.clone())? {
+            println!();
+            println!("// fn {}", function);
+            print!("{}", writer.to_string(&facts));
+        }
+    }
+    if profile {
+        eprintln!("{}", profiler.to_json());
+    }
+    Ok(())
+}
+
+/// `polonius subset-matrix <file> [--node <node>] [--html]`
+///
+/// Prints the `subset` relation computed for a program as an origins × origins matrix, as CSV
+/// or as an HTML heatmap. Without `--node`, each cell counts the nodes where the subset holds.
+fn subset_matrix(args: &[String]) -> eyre::Result<()> {
+    let mut input = None;
+    let mut node = None;
+    let mut html = false;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--node" => {
+                node = Some(
+                    args.next()
+                        .ok_or_else(|| eyre::eyre!("`--node` expects a node name"))?,
+                )
+            }
+            "--html" => html = true,
+            _ => input_arg(&mut input, arg)?,
+        }
+    }
+    let input =
+        input.ok_or_else(|| eyre::eyre!("usage: subset-matrix <file> [--node <node>] [--html]"))?;
+
+    let facts = polonius::parse_fact_program(&std::fs::read_to_string(input)?)?;
+    let output = polonius::solver::check(&facts)?;
+    let matrix =
+        polonius::subset_matrix::SubsetMatrix::new(&output.subset, node.map(String::as_str));
+    if html {
+        print!("{}", matrix.to_html());
+    } else {
+        print!("{}", matrix.to_csv());
     }
     Ok(())
 }
+
+/// `polonius parse <file> [--format=debug|json|pretty]`
+fn parse(args: &[String]) -> eyre::Result<()> {
+    let mut input = None;
+    let mut format = "pretty";
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--format" => {
+                format = args
+                    .next()
+                    .ok_or_else(|| eyre::eyre!("`--format` expects debug, json or pretty"))?
+            }
+            arg if arg.starts_with("--format=") => format = &arg["--format=".len()..],
+            _ => input_arg(&mut input, arg)?,
+        }
+    }
+    let input =
+        input.ok_or_else(|| eyre::eyre!("usage: parse <file> [--format=debug|json|pretty]"))?;
+
+    let programs = polonius::parse_ast_programs(&std::fs::read_to_string(input)?)?;
+    let named = programs.len() > 1 || programs[0].0 != polonius::DEFAULT_PROGRAM_NAME;
+    match format {
+        // Named programs are an object keyed by name, in order of appearance.
+        "json" if named => {
+            let programs: serde_json::Map<_, _> = programs
+                .into_iter()
+                .map(|(name, program)| Ok((name, serde_json::to_value(program)?)))
+                .collect::<eyre::Result<_>>()?;
+            println!("{}", serde_json::to_string_pretty(&programs)?);
+        }
+        "json" => println!("{}", serde_json::to_string_pretty(&programs[0].1)?),
+        "debug" | "pretty" => {
+            for (i, (name, program)) in programs.iter().enumerate() {
+                if named {
+                    if i > 0 {
+                        println!();
+                    }
+                    println!("// program {}", name);
+                }
+                if format == "debug" {
+                    println!("{:#?}", program);
+                } else {
+                    print!("{}", program);
+                }
+            }
+        }
+        format => eyre::bail!(
+            "unknown format `{}`, valid formats are debug, json and pretty",
+            format
+        ),
+    }
+    Ok(())
+}
+
+/// `polonius watch <dir>`
+///
+/// Checks the examples of the directory, and re-checks them whenever they or the rules change,
+/// printing which outcomes changed.
+fn watch(args: &[String]) -> eyre::Result<()> {
+    let config = Config::from_env()?;
+    match args {
+        [dir] => polonius::watch::watch(std::path::Path::new(positional(dir)?), &config),
+        _ => eyre::bail!("usage: watch <dir>"),
+    }
+}
+
+/// `polonius shrink <file> (--panics | --error <text> | --reports-errors)`
+fn shrink(args: &[String]) -> eyre::Result<()> {
+    use polonius::shrink::Failure;
+
+    let mut input = None;
+    let mut failure = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--panics" => failure = Some(Failure::Panics),
+            "--error" => {
+                let text = args
+                    .next()
+                    .ok_or_else(|| eyre::eyre!("`--error` expects the text of the error"))?;
+                failure = Some(Failure::ErrorContains(text.clone()));
+            }
+            "--reports-errors" => failure = Some(Failure::ReportsErrors),
+            _ => input_arg(&mut input, arg)?,
+        }
+    }
+    let usage = "usage: shrink <file> (--panics | --error <text> | --reports-errors)";
+    let (input, failure) = match (input, failure) {
+        (Some(input), Some(failure)) => (input, failure),
+        _ => eyre::bail!(usage),
+    };
+
+    let program = polonius::parse_ast(&std::fs::read_to_string(input)?)?;
+
+    // Every candidate which panics would otherwise print its panic message.
+    std::panic::set_hook(Box::new(|_| {}));
+    let shrunk = polonius::shrink::shrink(&program, |p| failure.occurs_in(p));
+    let _ = std::panic::take_hook();
+
+    print!("{}", shrunk?);
+    Ok(())
+}
+
+/// `polonius mutate <file.ast>.. [--explicit-liveness] [--solver <solver>]`
+fn mutate(args: &[String]) -> eyre::Result<()> {
+    let mut inputs = vec![];
+    // The expectations refer to the nodes by their simple names.
+    let mut options = polonius::fact_emitter::Options {
+        simple_node_names: true,
+        ..Default::default()
+    };
+    let mut algorithm = polonius::solver::Algorithm::Naive;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--explicit-liveness" => options.liveness = polonius::fact_emitter::Liveness::Explicit,
+            "--solver" => match solver_arg(args.next())? {
+                Solver::Native(native) => algorithm = native,
+                _ => eyre::bail!("`mutate` only supports the native solvers"),
+            },
+            _ => inputs.push(positional(arg)?),
+        }
+    }
+    if inputs.is_empty() {
+        eyre::bail!("usage: mutate <file.ast>.. [--explicit-liveness] [--solver <solver>]");
+    }
+
+    for input in inputs {
+        let data = std::fs::read_to_string(input)?;
+        let (facts, expectations) = polonius::emit_expectations(&data, options.clone())?;
+        let report = polonius::mutation::run_mutants(&facts, &expectations, algorithm)
+            .wrap_err_with(|| format!("`{}`", input))?;
+        println!("{}:", input);
+        print!("{}", report);
+    }
+    Ok(())
+}
+
+/// `polonius generate [--seed <n>] [--count <n>] [--focus <focus>] [--print]`
+///
+/// Generates `--count` programs, 100 by default, from the seeds following `--seed`, biased
+/// toward the emitter paths of `--focus`: `deref-borrows`, `struct-assignments` or
+/// `call-returns`. Prints how many programs took each arm of `relate_tys`, and the shrunk
+/// programs on which the analysis panics or the solvers disagree. With `--print`, only prints
+/// the programs.
+fn generate(args: &[String]) -> eyre::Result<()> {
+    let usage = "usage: generate [--seed <n>] [--count <n>] [--focus <focus>] [--print]";
+    let mut seed = 0;
+    let mut count = 100;
+    let mut focus = None;
+    let mut print = false;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| eyre::eyre!("{}", usage));
+        match arg.as_str() {
+            "--seed" => seed = value()?.parse()?,
+            "--count" => count = value()?.parse()?,
+            "--focus" => focus = Some(value()?.parse()?),
+            "--print" => print = true,
+            _ => eyre::bail!("{}", usage),
+        }
+    }
+
+    if print {
+        for seed in seed..seed + count {
+            let program = polonius::generate::Generator::new(seed).program(focus)?;
+            println!("// seed {}\n{}", seed, program);
+        }
+        return Ok(());
+    }
+    std::panic::set_hook(Box::new(|_| {}));
+    let report = polonius::generate::run(seed..seed + count, focus);
+    let _ = std::panic::take_hook();
+    print!("{}", report?);
+    Ok(())
+}
+
+/// Sets the input of a subcommand taking a single one. An unknown flag, e.g. one of another
+/// subcommand, or a second input is an error, rather than replacing the input.
+fn input_arg<'a>(input: &mut Option<&'a String>, arg: &'a String) -> eyre::Result<()> {
+    let arg = positional(arg)?;
+    if let Some(input) = input {
+        eyre::bail!("expected a single input, got `{}` and `{}`", input, arg);
+    }
+    *input = Some(arg);
+    Ok(())
+}
+
+/// An input of a subcommand, which isn't a flag the subcommand doesn't know.
+fn positional(arg: &String) -> eyre::Result<&String> {
+    if arg.starts_with("--") {
+        eyre::bail!("unknown flag `{}`, see `polonius --help`", arg);
+    }
+    Ok(arg)
+}
+
+/// The program or block name following `--only` or `--only-block`.
+fn scope_arg(flag: &str, arg: Option<&String>) -> eyre::Result<String> {
+    let what = if flag == "--only" { "program" } else { "block" };
+    Ok(arg
+        .ok_or_else(|| eyre::eyre!("`{}` expects a {} name", flag, what))?
+        .clone())
+}
+
+fn solver_arg(arg: Option<&String>) -> eyre::Result<Solver> {
+    arg.ok_or_else(|| eyre::eyre!("`--solver` expects a solver name"))?
+        .parse()
+}
+
+/// The origin of `--external-origin`, with or without its leading `'`.
+fn external_origin(arg: Option<&String>) -> eyre::Result<String> {
+    let origin = arg.ok_or_else(|| eyre::eyre!("`--external-origin` expects an origin"))?;
+    Ok(format!("'{}", origin.trim_start_matches('\'')))
+}
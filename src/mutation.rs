@@ -0,0 +1,162 @@
+//! Mutation testing of the emitter: each mutant is the facts of an example with one emission
+//! decision perturbed, e.g. an `invalidate_origin` dropped or an `introduce_subset` flipped.
+//! A mutant is killed when the expectations of the example, its `assert_live`, `assert_empty`
+//! and `assert_error` statements, no longer hold. The surviving mutants point at the facts no
+//! expectation depends on, i.e. at the semantics the examples don't test.
+
+use std::fmt;
+
+use itertools::Itertools;
+
+use crate::fact_emitter::Expectation;
+use crate::facts::Facts;
+use crate::results::Results;
+use crate::solver::{self, Algorithm};
+
+#[cfg(test)]
+mod test;
+
+/// A perturbation of one tuple, identified by its index in its relation.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Mutation {
+    DropInvalidate(usize),
+    DropAccess(usize),
+    DropClear(usize),
+
+    /// Turns `introduce_subset('a, 'b)` into `introduce_subset('b, 'a)`.
+    FlipSubset(usize),
+}
+
+impl Mutation {
+    /// Every mutation of `facts`, in the order of the relations and of their tuples.
+    pub fn all(facts: &Facts) -> Vec<Mutation> {
+        let mut mutations = vec![];
+        mutations.extend((0..facts.invalidate_origin.len()).map(Mutation::DropInvalidate));
+        mutations.extend((0..facts.access_origin.len()).map(Mutation::DropAccess));
+        mutations.extend((0..facts.clear_origin.len()).map(Mutation::DropClear));
+        mutations.extend(
+            (0..facts.introduce_subset.len())
+                .filter(|&i| {
+                    let (o1, o2, _) = &facts.introduce_subset[i];
+                    o1 != o2
+                })
+                .map(Mutation::FlipSubset),
+        );
+        mutations
+    }
+
+    /// The kind of the mutation, e.g. `drop-invalidate`, to group the survivors.
+    pub fn kind(self) -> &'static str {
+        match self {
+            Mutation::DropInvalidate(_) => "drop-invalidate",
+            Mutation::DropAccess(_) => "drop-access",
+            Mutation::DropClear(_) => "drop-clear",
+            Mutation::FlipSubset(_) => "flip-subset",
+        }
+    }
+
+    /// Applies the mutation to `facts`, returning a description of the perturbed tuple.
+    pub fn apply(self, facts: &mut Facts) -> String {
+        match self {
+            Mutation::DropInvalidate(i) => {
+                let (origin, node) = facts.invalidate_origin.remove(i);
+                format!("drop invalidate_origin({}, {})", origin, node)
+            }
+            Mutation::DropAccess(i) => {
+                let (origin, node) = facts.access_origin.remove(i);
+                format!("drop access_origin({}, {})", origin, node)
+            }
+            Mutation::DropClear(i) => {
+                let (origin, node) = facts.clear_origin.remove(i);
+                format!("drop clear_origin({}, {})", origin, node)
+            }
+            Mutation::FlipSubset(i) => {
+                let (o1, o2, node) = &mut facts.introduce_subset[i];
+                std::mem::swap(o1, o2);
+                format!("flip introduce_subset({}, {}, {})", o2, o1, node)
+            }
+        }
+    }
+}
+
+/// A mutant, and whether the expectations caught it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Mutant {
+    pub mutation: Mutation,
+    pub description: String,
+    pub killed: bool,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Report {
+    pub mutants: Vec<Mutant>,
+}
+
+impl Report {
+    pub fn survivors(&self) -> impl Iterator<Item = &Mutant> {
+        self.mutants.iter().filter(|mutant| !mutant.killed)
+    }
+}
+
+/// Prints the surviving mutants, and the number of mutants killed and surviving, with the
+/// survivors counted by kind.
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for mutant in self.survivors() {
+            writeln!(f, "survived: {}", mutant.description)?;
+        }
+        let survivors = self.survivors().count();
+        write!(
+            f,
+            "{} mutants, {} killed, {} survived",
+            self.mutants.len(),
+            self.mutants.len() - survivors,
+            survivors
+        )?;
+        if survivors > 0 {
+            let kinds = self
+                .survivors()
+                .map(|mutant| mutant.mutation.kind())
+                .counts()
+                .into_iter()
+                .sorted()
+                .map(|(kind, count)| format!("{} {}", count, kind))
+                .join(", ");
+            write!(f, " ({})", kinds)?;
+        }
+        writeln!(f)
+    }
+}
+
+/// Runs every mutant of `facts` with `algorithm`, and checks `expectations` on each. It is an
+/// error for the expectations not to hold on `facts` themselves, as no mutant could be killed.
+pub fn run_mutants(
+    facts: &Facts,
+    expectations: &[Expectation],
+    algorithm: Algorithm,
+) -> eyre::Result<Report> {
+    let holds = |facts: &Facts| {
+        let results = Results::new(facts, solver::compute(facts, algorithm));
+        results.unmet_expectations(expectations).is_empty()
+    };
+    if expectations.is_empty() {
+        eyre::bail!("the program has no expectations to kill the mutants");
+    }
+    if !holds(facts) {
+        eyre::bail!("the expectations don't hold on the facts before mutating them");
+    }
+
+    let mutants = Mutation::all(facts)
+        .into_iter()
+        .map(|mutation| {
+            let mut mutant = facts.clone();
+            let description = mutation.apply(&mut mutant);
+            Mutant {
+                mutation,
+                description,
+                killed: !holds(&mutant),
+            }
+        })
+        .collect();
+    Ok(Report { mutants })
+}
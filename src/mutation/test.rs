@@ -0,0 +1,45 @@
+use super::*;
+use crate::fact_emitter::Options;
+
+fn mutate(input: &str) -> eyre::Result<String> {
+    let options = Options {
+        simple_node_names: true,
+        ..Options::default()
+    };
+    let (facts, expectations) = crate::emit_expectations(input, options)?;
+    Ok(run_mutants(&facts, &expectations, Algorithm::Naive)?.to_string())
+}
+
+#[test]
+fn survivors() {
+    let program = "
+        fn use<'a>(r: &'a i32) -> ();
+        let mut x: i32;
+        let p: &'p i32;
+        bb0: {
+            x = 22;
+            p = &'L_x x;
+            x = 23;
+            assert_error;
+            use(copy p);
+        }
+    ";
+    insta::assert_snapshot!(mutate(program).unwrap(), @r###"
+    survived: drop invalidate_origin('L_x, a)
+    survived: drop invalidate_origin('L_x, e)
+    survived: drop clear_origin('p, b)
+    survived: drop clear_origin('L_x, b)
+    survived: flip introduce_subset('p, 'use0_a, d)
+    8 mutants, 3 killed, 5 survived (2 drop-clear, 2 drop-invalidate, 1 flip-subset)
+
+    "###);
+}
+
+#[test]
+fn needs_expectations_that_hold() {
+    let program = "let x: i32; bb0: { x = 1; }";
+    insta::assert_snapshot!(mutate(program).unwrap_err().to_string(), @"the program has no expectations to kill the mutants");
+
+    let program = "let x: i32; bb0: { assert_error; x = 1; }";
+    insta::assert_snapshot!(mutate(program).unwrap_err().to_string(), @"the expectations don't hold on the facts before mutating them");
+}
@@ -0,0 +1,212 @@
+//! Optional fact-reduction passes, run on emitted facts before they are handed to the solver.
+
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+
+use crate::facts::{Facts, Node, Origin};
+
+#[cfg(test)]
+mod test;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Pass {
+    /// Removes the facts of nodes that cannot be reached from an entry node of the CFG.
+    ReachabilityPrune,
+
+    /// Removes duplicate tuples, keeping the first occurrence.
+    Dedup,
+
+    /// Removes the `introduce_subset` tuples implied by the others at the same node, leaving
+    /// the transitive closure to the solver.
+    TransitiveReduce,
+}
+
+impl Pass {
+    pub const ALL: &'static [Pass] =
+        &[Pass::ReachabilityPrune, Pass::Dedup, Pass::TransitiveReduce];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Pass::ReachabilityPrune => "reachability-prune",
+            Pass::Dedup => "dedup",
+            Pass::TransitiveReduce => "transitive-reduce",
+        }
+    }
+
+    pub fn run(self, facts: &mut Facts) {
+        match self {
+            Pass::ReachabilityPrune => reachability_prune(facts),
+            Pass::Dedup => dedup(facts),
+            Pass::TransitiveReduce => transitive_reduce(facts),
+        }
+    }
+}
+
+impl FromStr for Pass {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> eyre::Result<Self> {
+        Pass::ALL
+            .iter()
+            .copied()
+            .find(|pass| pass.name() == s)
+            .ok_or_else(|| {
+                let names: Vec<_> = Pass::ALL.iter().map(|pass| pass.name()).collect();
+                eyre::eyre!("unknown pass `{}`, valid passes are `{:?}`", s, names)
+            })
+    }
+}
+
+/// Parses a comma-separated list of pass names, e.g. `reachability-prune,dedup`.
+pub fn parse_pass_list(s: &str) -> eyre::Result<Vec<Pass>> {
+    s.split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(Pass::from_str)
+        .collect()
+}
+
+/// Runs each of `passes` over `facts`, in order.
+pub fn run_passes(facts: &mut Facts, passes: &[Pass]) {
+    for pass in passes {
+        pass.run(facts);
+    }
+}
+
+fn dedup(facts: &mut Facts) {
+    fn dedup_relation<T: Clone + Eq + std::hash::Hash>(rows: &mut Vec<T>) {
+        let mut seen = HashSet::new();
+        rows.retain(|row| seen.insert(row.clone()));
+    }
+
+    dedup_relation(&mut facts.access_origin);
+    dedup_relation(&mut facts.cfg_edge);
+    dedup_relation(&mut facts.clear_origin);
+    dedup_relation(&mut facts.introduce_subset);
+    dedup_relation(&mut facts.invalidate_origin);
+    dedup_relation(&mut facts.node_text);
+    dedup_relation(&mut facts.write_origin);
+    dedup_relation(&mut facts.origin_live_on_entry);
+    dedup_relation(&mut facts.incompatible_loans);
+    dedup_relation(&mut facts.node_is_exit);
+    dedup_relation(&mut facts.assert_empty);
+    dedup_relation(&mut facts.cfg_edge_label);
+    dedup_relation(&mut facts.loan_through_shared);
+    dedup_relation(&mut facts.origin_equal);
+    dedup_relation(&mut facts.origin_kind);
+    dedup_relation(&mut facts.child_path);
+    dedup_relation(&mut facts.path_assigned_at);
+    dedup_relation(&mut facts.path_accessed_at);
+    dedup_relation(&mut facts.path_moved_at);
+    dedup_relation(&mut facts.drop_used_at);
+    dedup_relation(&mut facts.discriminant_known);
+}
+
+/// Each `introduce_subset` tuple becomes a `subset` tuple at the successors, where the solver
+/// computes the transitive closure, so a tuple `'a <= 'c` is redundant if `'c` is reachable from
+/// `'a` through the other tuples of its node. The tuples are removed one at a time, so that the
+/// remaining ones still imply the removed ones, e.g. only one edge of a cycle is removed.
+fn transitive_reduce(facts: &mut Facts) {
+    let mut by_node: HashMap<&str, Vec<(&str, &str)>> = HashMap::new();
+    for (o1, o2, node) in facts.introduce_subsets() {
+        by_node.entry(node).or_default().push((o1, o2));
+    }
+
+    // How many copies of each tuple are redundant: duplicates imply each other.
+    let mut redundant: HashMap<(Origin, Origin, Node), usize> = HashMap::new();
+    for (node, mut edges) in by_node {
+        let mut i = 0;
+        while i < edges.len() {
+            let (from, to) = edges[i];
+            let others: Vec<_> = edges
+                .iter()
+                .enumerate()
+                .filter(|&(j, _)| j != i)
+                .map(|(_, &edge)| edge)
+                .collect();
+            if reaches(&others, from, to) {
+                *redundant
+                    .entry((from.to_string(), to.to_string(), node.to_string()))
+                    .or_default() += 1;
+                edges.remove(i);
+            } else {
+                i += 1;
+            }
+        }
+    }
+
+    facts
+        .introduce_subset
+        .retain(|tuple| match redundant.get_mut(tuple) {
+            Some(count) if *count > 0 => {
+                *count -= 1;
+                false
+            }
+            _ => true,
+        });
+}
+
+/// Whether there is a non-empty path from `from` to `to` along `edges`.
+fn reaches(edges: &[(&str, &str)], from: &str, to: &str) -> bool {
+    let mut stack = vec![from];
+    let mut visited = HashSet::new();
+    while let Some(origin) = stack.pop() {
+        for &(o1, o2) in edges {
+            if o1 == origin {
+                if o2 == to {
+                    return true;
+                }
+                if visited.insert(o2) {
+                    stack.push(o2);
+                }
+            }
+        }
+    }
+    false
+}
+
+fn reachability_prune(facts: &mut Facts) {
+    let reachable = reachable_nodes(facts);
+    let is_reachable = |node: &Node| reachable.contains(node);
+
+    facts.access_origin.retain(|(_, n)| is_reachable(n));
+    facts.cfg_edge.retain(|(n, _)| is_reachable(n));
+    facts.cfg_edge_label.retain(|(n, _, _)| is_reachable(n));
+    facts.clear_origin.retain(|(_, n)| is_reachable(n));
+    facts.introduce_subset.retain(|(_, _, n)| is_reachable(n));
+    facts.invalidate_origin.retain(|(_, n)| is_reachable(n));
+    facts.node_text.retain(|(_, n)| is_reachable(n));
+    facts.write_origin.retain(|(_, n)| is_reachable(n));
+    facts.origin_live_on_entry.retain(|(_, n)| is_reachable(n));
+    facts.incompatible_loans.retain(|(_, _, n)| is_reachable(n));
+    facts.node_is_exit.retain(is_reachable);
+    facts.assert_empty.retain(|(_, n)| is_reachable(n));
+    facts.loan_through_shared.retain(|(_, n)| is_reachable(n));
+    facts.origin_equal.retain(|(_, _, n)| is_reachable(n));
+    facts.path_assigned_at.retain(|(_, n)| is_reachable(n));
+    facts.path_accessed_at.retain(|(_, n)| is_reachable(n));
+    facts.path_moved_at.retain(|(_, n)| is_reachable(n));
+    facts.drop_used_at.retain(|(_, n)| is_reachable(n));
+    facts.discriminant_known.retain(|(_, _, n)| is_reachable(n));
+}
+
+/// The entry nodes are the first node of the program along with every node that has no
+/// predecessor.
+fn reachable_nodes(facts: &Facts) -> HashSet<Node> {
+    let index = facts.index_by_node();
+    let mut stack: Vec<&str> = facts
+        .node_texts()
+        .map(|(_, node)| node.as_str())
+        .enumerate()
+        .filter(|&(i, node)| i == 0 || index.predecessors(node).next().is_none())
+        .map(|(_, node)| node)
+        .collect();
+
+    let mut reachable = HashSet::new();
+    while let Some(node) = stack.pop() {
+        if reachable.insert(node.to_string()) {
+            stack.extend(index.successors(node).map(Node::as_str));
+        }
+    }
+    reachable
+}
@@ -0,0 +1,98 @@
+use super::*;
+
+fn facts() -> Facts {
+    crate::fact_parser::parse_fact_program(
+        r#"
+        a: "x = 1" {
+            invalidate_origin('L)
+            invalidate_origin('L)
+            goto b
+        }
+
+        b: "use(x)" {
+            access_origin('x)
+            goto
+        }
+
+        c: "loop" {
+            access_origin('y)
+            goto d
+        }
+
+        d: "loop" {
+            goto c
+        }
+        "#,
+    )
+    .unwrap()
+}
+
+#[test]
+fn dedup_removes_repeated_tuples() {
+    let mut facts = facts();
+    Pass::Dedup.run(&mut facts);
+    assert_eq!(
+        facts.invalidate_origin,
+        vec![("'L".to_string(), "a".to_string())]
+    );
+}
+
+#[test]
+fn reachability_prune_removes_unreachable_cycle() {
+    let mut facts = facts();
+    Pass::ReachabilityPrune.run(&mut facts);
+    assert_eq!(
+        facts.access_origin,
+        vec![("'x".to_string(), "b".to_string())]
+    );
+    assert_eq!(facts.cfg_edge, vec![("a".to_string(), "b".to_string())]);
+    assert_eq!(facts.node_text.len(), 2);
+}
+
+#[test]
+fn parse_list() {
+    assert_eq!(
+        parse_pass_list("reachability-prune, dedup").unwrap(),
+        vec![Pass::ReachabilityPrune, Pass::Dedup]
+    );
+    assert!(parse_pass_list("inline").is_err());
+}
+
+#[test]
+fn transitive_reduce_removes_implied_subsets() {
+    let mut facts = crate::fact_parser::parse_fact_program(
+        r#"
+        a: "x = 1" {
+            introduce_subset('a, 'b)
+            introduce_subset('b, 'c)
+            introduce_subset('a, 'c)
+            introduce_subset('a, 'b)
+            goto b
+        }
+
+        b: "loop" {
+            introduce_subset('x, 'y)
+            introduce_subset('y, 'x)
+            introduce_subset('a, 'c)
+            goto
+        }
+        "#,
+    )
+    .unwrap();
+    Pass::TransitiveReduce.run(&mut facts);
+
+    let tuples: Vec<_> = facts
+        .introduce_subset
+        .iter()
+        .map(|(o1, o2, n)| format!("{} <= {} @ {}", o1, o2, n))
+        .collect();
+    insta::assert_debug_snapshot!(tuples, @r###"
+    [
+        "'b <= 'c @ a",
+        "'a <= 'b @ a",
+        "'x <= 'y @ b",
+        "'y <= 'x @ b",
+        "'a <= 'c @ b",
+    ]
+    "###);
+}
@@ -0,0 +1,352 @@
+//! The analysis of a program as a sequence of named stages, from the parsed program to the
+//! solver output. Each stage declares the stages it depends on, and communicates with the later
+//! ones through typed artifacts: e.g. `lower` reads the `ast::Program` and adds the `ir::Body`,
+//! which `emit` reads to add the `Facts`. Adding an analysis is registering a stage which reads
+//! the artifacts it needs, instead of threading them through the emitter.
+
+use std::any::{type_name, Any, TypeId};
+use std::collections::HashMap;
+
+use crate::ast::Program;
+use crate::fact_emitter::{
+    AssumeExternalOrigins, CollectLoans, DefaultPolicy, EmitFacts, EmitLiveness, EmitMovePaths,
+    EmitOriginKinds, EmitRefinements, InvalidationPolicy, Liveness, Options,
+};
+use crate::facts::Facts;
+use crate::ir::{self, Body};
+use crate::passes::{self, Pass};
+use crate::profile::Profiler;
+use crate::{desugar, solver, typeck, validate, Solver};
+
+#[cfg(test)]
+mod test;
+
+/// A step of the analysis, reading the artifacts of the stages it depends on, and adding or
+/// replacing its own.
+pub trait Stage {
+    /// The name of the stage, used by the stages depending on it, and as the name of its phase
+    /// in the profile.
+    fn name(&self) -> &'static str;
+
+    /// The names of the stages which must run before this one.
+    fn dependencies(&self) -> &'static [&'static str] {
+        &[]
+    }
+
+    fn run(&self, artifacts: &mut Artifacts) -> eyre::Result<()>;
+}
+
+/// The artifacts produced so far, at most one of each type.
+#[derive(Default)]
+pub struct Artifacts {
+    artifacts: HashMap<TypeId, Box<dyn Any>>,
+}
+
+impl Artifacts {
+    /// Adds `artifact`, replacing the previous artifact of the same type.
+    pub fn insert<T: Any>(&mut self, artifact: T) {
+        self.artifacts.insert(TypeId::of::<T>(), Box::new(artifact));
+    }
+
+    pub fn contains<T: Any>(&self) -> bool {
+        self.artifacts.contains_key(&TypeId::of::<T>())
+    }
+
+    pub fn get<T: Any>(&self) -> eyre::Result<&T> {
+        self.artifacts
+            .get(&TypeId::of::<T>())
+            .map(|artifact| artifact.downcast_ref().unwrap())
+            .ok_or_else(missing::<T>)
+    }
+
+    pub fn get_mut<T: Any>(&mut self) -> eyre::Result<&mut T> {
+        self.artifacts
+            .get_mut(&TypeId::of::<T>())
+            .map(|artifact| artifact.downcast_mut().unwrap())
+            .ok_or_else(missing::<T>)
+    }
+
+    /// Removes the artifact of type `T`, e.g. for a stage to consume it, or to return it once
+    /// the pipeline has run.
+    pub fn take<T: Any>(&mut self) -> eyre::Result<T> {
+        self.artifacts
+            .remove(&TypeId::of::<T>())
+            .map(|artifact| *artifact.downcast().unwrap())
+            .ok_or_else(missing::<T>)
+    }
+}
+
+fn missing<T>() -> eyre::Report {
+    eyre::eyre!("no artifact of type `{}`", type_name::<T>())
+}
+
+/// The registered stages, run in registration order, except that a stage always runs after
+/// the stages it depends on.
+#[derive(Default)]
+pub struct Pipeline<'a> {
+    stages: Vec<Box<dyn Stage + 'a>>,
+}
+
+impl<'a> Pipeline<'a> {
+    /// The stages collecting the loans of the `ast::Program` artifact, up to `loans`, which adds
+    /// the `fact_emitter::Loans` artifact.
+    pub fn loans(options: &Options) -> Self {
+        let mut pipeline = Pipeline::default();
+        pipeline.register(Typeck);
+        if options.introduce_temporaries {
+            pipeline.register(Desugar);
+        }
+        pipeline
+            .register(Validate)
+            .register(Lower)
+            .register(CollectLoans {
+                options: options.clone(),
+            });
+        pipeline
+    }
+
+    /// The stages emitting the facts of the `ast::Program` artifact: `typeck`, `desugar` with
+    /// `options.introduce_temporaries`, `validate`, `lower`, `loans`, `emit`, and `liveness`
+    /// with explicit liveness, then `external` with `options.external_origins`, `move_paths`
+    /// with `options.move_paths`, `refinements` with `options.refinements`, and `origin_kinds`. They add the `Facts` and the `Vec<Expectation>` artifacts, and the
+    /// `Trace` when `trace` is set.
+    pub fn emit(options: &Options, policy: &'a dyn InvalidationPolicy, trace: bool) -> Self {
+        let mut pipeline = Pipeline::loans(options);
+        pipeline.register(EmitFacts {
+            policy,
+            trace,
+            origin_equalities: options.origin_equalities,
+        });
+        if options.liveness == Liveness::Explicit {
+            pipeline.register(EmitLiveness);
+        }
+        if !options.external_origins.is_empty() {
+            pipeline.register(AssumeExternalOrigins {
+                origins: options.external_origins.clone(),
+                liveness: options.liveness,
+            });
+        }
+        if options.move_paths {
+            pipeline.register(EmitMovePaths);
+        }
+        if options.refinements {
+            pipeline.register(EmitRefinements);
+        }
+        pipeline.register(EmitOriginKinds {
+            external_origins: options.external_origins.clone(),
+        });
+        pipeline
+    }
+
+    /// Like `emit`, followed by the `passes` stage running `fact_passes`, if any, and by the
+    /// `solve` stage, which adds the `solver::Output` artifact.
+    pub fn solve(options: &Options, fact_passes: &[Pass], solver: Solver) -> Pipeline<'static> {
+        let mut pipeline = Pipeline::emit(options, &DefaultPolicy, false);
+        if !fact_passes.is_empty() {
+            pipeline.register(RunPasses {
+                passes: fact_passes.to_vec(),
+            });
+        }
+        pipeline.register(Solve { solver });
+        pipeline
+    }
+
+    pub fn register(&mut self, stage: impl Stage + 'a) -> &mut Self {
+        self.stages.push(Box::new(stage));
+        self
+    }
+
+    /// The names of the stages, in the order they run. It is an error for two stages to have
+    /// the same name, for a stage to depend on a stage which isn't registered, or for stages
+    /// to depend on each other.
+    pub fn order(&self) -> eyre::Result<Vec<&'static str>> {
+        Ok(self
+            .ordered_stages()?
+            .into_iter()
+            .map(|stage| stage.name())
+            .collect())
+    }
+
+    /// Runs the stages in order, over `artifacts`, recording the time taken by each one in
+    /// `profiler`.
+    pub fn run(&self, artifacts: &mut Artifacts, profiler: &mut Profiler) -> eyre::Result<()> {
+        for stage in self.ordered_stages()? {
+            profiler.time(stage.name(), || stage.run(artifacts))?;
+        }
+        Ok(())
+    }
+
+    fn ordered_stages(&self) -> eyre::Result<Vec<&(dyn Stage + 'a)>> {
+        let mut indices = HashMap::new();
+        for (i, stage) in self.stages.iter().enumerate() {
+            if indices.insert(stage.name(), i).is_some() {
+                eyre::bail!("the stage `{}` is registered twice", stage.name());
+            }
+            for dependency in stage.dependencies() {
+                if !self.stages.iter().any(|s| s.name() == *dependency) {
+                    eyre::bail!(
+                        "the stage `{}` depends on `{}`, which isn't registered",
+                        stage.name(),
+                        dependency
+                    );
+                }
+            }
+        }
+
+        // A depth-first traversal from each stage in registration order, visiting the
+        // dependencies of a stage before the stage itself.
+        #[derive(Copy, Clone, PartialEq)]
+        enum State {
+            Visiting,
+            Done,
+        }
+        fn visit<'s, 'a>(
+            i: usize,
+            stages: &'s [Box<dyn Stage + 'a>],
+            indices: &HashMap<&str, usize>,
+            states: &mut Vec<Option<State>>,
+            order: &mut Vec<&'s (dyn Stage + 'a)>,
+        ) -> eyre::Result<()> {
+            match states[i] {
+                Some(State::Done) => return Ok(()),
+                Some(State::Visiting) => {
+                    eyre::bail!("the stage `{}` depends on itself", stages[i].name())
+                }
+                None => {}
+            }
+            states[i] = Some(State::Visiting);
+            for dependency in stages[i].dependencies() {
+                visit(indices[dependency], stages, indices, states, order)?;
+            }
+            states[i] = Some(State::Done);
+            order.push(&*stages[i]);
+            Ok(())
+        }
+
+        let mut states = vec![None; self.stages.len()];
+        let mut order = vec![];
+        for i in 0..self.stages.len() {
+            visit(i, &self.stages, &indices, &mut states, &mut order)?;
+        }
+        Ok(order)
+    }
+}
+
+/// Expands the type aliases of the program, see `typeck::expand_type_aliases`.
+pub struct Typeck;
+
+impl Stage for Typeck {
+    fn name(&self) -> &'static str {
+        "typeck"
+    }
+
+    fn run(&self, artifacts: &mut Artifacts) -> eyre::Result<()> {
+        typeck::expand_type_aliases(artifacts.get_mut::<Program>()?)
+    }
+}
+
+/// Hoists borrows and calls used as call arguments into temporaries, see
+/// `desugar::introduce_temporaries`.
+pub struct Desugar;
+
+impl Stage for Desugar {
+    fn name(&self) -> &'static str {
+        "desugar"
+    }
+
+    fn dependencies(&self) -> &'static [&'static str] {
+        &["typeck"]
+    }
+
+    fn run(&self, artifacts: &mut Artifacts) -> eyre::Result<()> {
+        desugar::introduce_temporaries(artifacts.get_mut::<Program>()?)
+    }
+}
+
+pub struct Validate;
+
+impl Stage for Validate {
+    fn name(&self) -> &'static str {
+        "validate"
+    }
+
+    fn dependencies(&self) -> &'static [&'static str] {
+        &["typeck"]
+    }
+
+    fn run(&self, artifacts: &mut Artifacts) -> eyre::Result<()> {
+        validate::validate(artifacts.get::<Program>()?)
+    }
+}
+
+/// Lowers the program to the IR, adding the `ir::Body`.
+pub struct Lower;
+
+impl Stage for Lower {
+    fn name(&self) -> &'static str {
+        "lower"
+    }
+
+    fn dependencies(&self) -> &'static [&'static str] {
+        &["validate"]
+    }
+
+    fn run(&self, artifacts: &mut Artifacts) -> eyre::Result<()> {
+        let body: Body = ir::lower(artifacts.get::<Program>()?)?;
+        artifacts.insert(body);
+        Ok(())
+    }
+}
+
+/// Runs fact-reduction passes over the `Facts`, see `passes::run_passes`.
+pub struct RunPasses {
+    pub passes: Vec<Pass>,
+}
+
+impl Stage for RunPasses {
+    fn name(&self) -> &'static str {
+        "passes"
+    }
+
+    fn dependencies(&self) -> &'static [&'static str] {
+        &["emit"]
+    }
+
+    fn run(&self, artifacts: &mut Artifacts) -> eyre::Result<()> {
+        passes::run_passes(artifacts.get_mut::<Facts>()?, &self.passes);
+        Ok(())
+    }
+}
+
+/// Solves the `Facts`, adding the `solver::Output`. Souffle runs in a scratch directory, which
+/// is removed afterwards.
+pub struct Solve {
+    pub solver: Solver,
+}
+
+impl Stage for Solve {
+    fn name(&self) -> &'static str {
+        "solve"
+    }
+
+    fn dependencies(&self) -> &'static [&'static str] {
+        &["emit"]
+    }
+
+    fn run(&self, artifacts: &mut Artifacts) -> eyre::Result<()> {
+        let facts = artifacts.get::<Facts>()?;
+        let output = match self.solver {
+            Solver::Native(algorithm) => solver::compute(facts, algorithm),
+            Solver::Check => solver::check(facts)?,
+            Solver::Souffle => {
+                let scratch_path =
+                    std::env::temp_dir().join(format!("polonius-pipeline-{}", std::process::id()));
+                let output = crate::solve_output(facts, self.solver, &scratch_path);
+                let _ = std::fs::remove_dir_all(&scratch_path);
+                output?
+            }
+        };
+        artifacts.insert(output);
+        Ok(())
+    }
+}
@@ -0,0 +1,223 @@
+use insta::assert_snapshot;
+
+use super::*;
+use crate::fact_emitter::{Expectation, Trace};
+use crate::FactEmitter;
+
+const INPUT: &str = "
+    let mut x: i32;
+    let r: &'r i32;
+    let y: i32;
+    bb0: {
+        r = &'L_x x;
+        x = 1;
+        y = copy *r;
+    }
+";
+
+/// A stage which appends its name to the `Vec<&str>` artifact.
+struct Record(&'static str, &'static [&'static str]);
+
+impl Stage for Record {
+    fn name(&self) -> &'static str {
+        self.0
+    }
+
+    fn dependencies(&self) -> &'static [&'static str] {
+        self.1
+    }
+
+    fn run(&self, artifacts: &mut Artifacts) -> eyre::Result<()> {
+        if !artifacts.contains::<Vec<&str>>() {
+            artifacts.insert(Vec::<&str>::new());
+        }
+        artifacts.get_mut::<Vec<&str>>()?.push(self.0);
+        Ok(())
+    }
+}
+
+#[test]
+fn dependencies_run_first() {
+    let mut pipeline = Pipeline::default();
+    pipeline
+        .register(Record("solve", &["emit"]))
+        .register(Record("emit", &["lower"]))
+        .register(Record("lower", &[]))
+        .register(Record("lints", &["lower"]));
+    assert_eq!(
+        pipeline.order().unwrap(),
+        ["lower", "emit", "solve", "lints"]
+    );
+
+    let mut artifacts = Artifacts::default();
+    let mut profiler = Profiler::default();
+    pipeline.run(&mut artifacts, &mut profiler).unwrap();
+    assert_eq!(
+        artifacts.get::<Vec<&str>>().unwrap(),
+        &["lower", "emit", "solve", "lints"]
+    );
+    let phases: Vec<_> = profiler.phases.iter().map(|p| p.name.as_str()).collect();
+    assert_eq!(phases, ["lower", "emit", "solve", "lints"]);
+}
+
+#[test]
+fn invalid_pipelines() {
+    let order = |stages: Vec<Record>| {
+        let mut pipeline = Pipeline::default();
+        for stage in stages {
+            pipeline.register(stage);
+        }
+        pipeline.order().unwrap_err().to_string()
+    };
+
+    assert_snapshot!(
+        order(vec![Record("emit", &[]), Record("emit", &[])]),
+        @"the stage `emit` is registered twice"
+    );
+    assert_snapshot!(
+        order(vec![Record("emit", &["lower"])]),
+        @"the stage `emit` depends on `lower`, which isn't registered"
+    );
+    assert_snapshot!(
+        order(vec![Record("emit", &["solve"]), Record("solve", &["emit"])]),
+        @"the stage `emit` depends on itself"
+    );
+}
+
+#[test]
+fn missing_artifacts() {
+    let mut artifacts = Artifacts::default();
+    assert_snapshot!(
+        artifacts.get::<Body>().unwrap_err().to_string(),
+        @"no artifact of type `polonius::ir::Body`"
+    );
+    assert_snapshot!(
+        Pipeline::emit(&Options::default(), &DefaultPolicy, false)
+            .run(&mut artifacts, &mut Profiler::default())
+            .unwrap_err()
+            .to_string(),
+        @"no artifact of type `polonius::ast::Program`"
+    );
+}
+
+#[test]
+fn emit_artifacts() {
+    let options = Options {
+        simple_node_names: true,
+        liveness: Liveness::Explicit,
+        ..Options::default()
+    };
+    let pipeline = Pipeline::emit(&options, &DefaultPolicy, true);
+    assert_eq!(
+        pipeline.order().unwrap(),
+        [
+            "typeck",
+            "validate",
+            "lower",
+            "loans",
+            "emit",
+            "liveness",
+            "origin_kinds"
+        ]
+    );
+
+    let mut artifacts = Artifacts::default();
+    artifacts.insert(crate::parse_ast(INPUT).unwrap());
+    pipeline
+        .run(&mut artifacts, &mut Profiler::default())
+        .unwrap();
+
+    // The artifacts of the earlier stages are still there for the later ones.
+    assert_eq!(artifacts.get::<Body>().unwrap().blocks[0].name, "bb0");
+    assert!(artifacts.get::<Vec<Expectation>>().unwrap().is_empty());
+    let facts = artifacts.get::<Facts>().unwrap();
+    assert_eq!(
+        facts,
+        &FactEmitter::from_program(crate::parse_ast(INPUT).unwrap(), options).unwrap()
+    );
+    assert!(!facts.origin_live_on_entry.is_empty());
+    assert!(!artifacts.get::<Trace>().unwrap().entries.is_empty());
+}
+
+#[test]
+fn solve_artifacts() {
+    let pipeline = Pipeline::solve(
+        &Options::default(),
+        &[Pass::ReachabilityPrune],
+        Solver::default(),
+    );
+    assert_eq!(
+        pipeline.order().unwrap(),
+        [
+            "typeck",
+            "validate",
+            "lower",
+            "loans",
+            "emit",
+            "origin_kinds",
+            "passes",
+            "solve"
+        ]
+    );
+
+    let mut artifacts = Artifacts::default();
+    artifacts.insert(crate::parse_ast(INPUT).unwrap());
+    pipeline
+        .run(&mut artifacts, &mut Profiler::default())
+        .unwrap();
+    let output = artifacts.take::<solver::Output>().unwrap();
+    assert_eq!(output.invalidated_origin_accessed.len(), 1);
+}
+
+#[test]
+fn external_origins() {
+    // Without the caller, nothing uses `'r` after the borrow of `x` is invalidated.
+    let input = "
+        let mut x: i32;
+        let r: &'r i32;
+        bb0: {
+            r = &'L_x x;
+            x = 1;
+        }
+    ";
+    let solve = |options: Options| {
+        let pipeline = Pipeline::solve(&options, &[], Solver::default());
+        let mut artifacts = Artifacts::default();
+        artifacts.insert(crate::parse_ast(input).unwrap());
+        pipeline
+            .run(&mut artifacts, &mut Profiler::default())
+            .unwrap();
+        (
+            pipeline.order().unwrap(),
+            artifacts
+                .take::<solver::Output>()
+                .unwrap()
+                .invalidated_origin_accessed,
+        )
+    };
+
+    let (order, errors_in_isolation) = solve(Options::default());
+    assert!(!order.contains(&"external"));
+    assert!(errors_in_isolation.is_empty());
+
+    let (order, errors) = solve(Options {
+        liveness: Liveness::Explicit,
+        external_origins: vec!["'r".to_string()],
+        ..Options::default()
+    });
+    assert_eq!(
+        order,
+        [
+            "typeck",
+            "validate",
+            "lower",
+            "loans",
+            "emit",
+            "liveness",
+            "external",
+            "origin_kinds",
+            "solve"
+        ]
+    );
+    assert_eq!(errors.len(), 1);
+}
@@ -0,0 +1,94 @@
+//! The precision of the field- and deref-sensitive invalidations: each program of a corpus is
+//! checked twice, once as usual and once approximating every place by its whole local, see
+//! `Options::complete_places`, and the programs whose errors differ are reported. The errors of
+//! the approximation are a superset, so each difference is a false positive avoided.
+
+use std::fmt;
+
+use eyre::WrapErr;
+
+use crate::fact_emitter::{FactEmitter, Options};
+use crate::results::{Error, Results};
+use crate::solver;
+
+#[cfg(test)]
+mod test;
+
+/// The errors of a program under both invalidations, when they differ.
+#[derive(Clone, Debug)]
+pub struct VerdictDifference {
+    /// The program, as `<file>:<program>`.
+    pub program: String,
+    pub precise: Vec<Error>,
+    pub complete_places: Vec<Error>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct PrecisionReport {
+    /// The number of programs compared.
+    pub programs: usize,
+    pub differences: Vec<VerdictDifference>,
+}
+
+impl PrecisionReport {
+    /// Checks each program of `input`, an AST input with one or several `program` sections,
+    /// under both invalidations, with the other `options` unchanged. The programs are named
+    /// after `file`.
+    pub fn add_input(&mut self, file: &str, input: &str, options: &Options) -> eyre::Result<()> {
+        let coarse = Options {
+            complete_places: true,
+            ..options.clone()
+        };
+        for (name, program) in crate::parse_ast_programs(input)? {
+            let label = format!("{}:{}", file, name);
+            let errors = |options: &Options| -> eyre::Result<Vec<Error>> {
+                let facts = FactEmitter::from_program(program.clone(), options.clone())?;
+                let output = solver::check(&facts)?;
+                Ok(Results::new(&facts, output).errors())
+            };
+            let precise = errors(options).wrap_err_with(|| format!("in `{}`", label))?;
+            let complete_places = errors(&coarse).wrap_err_with(|| format!("in `{}`", label))?;
+            self.programs += 1;
+            if precise != complete_places {
+                self.differences.push(VerdictDifference {
+                    program: label,
+                    precise,
+                    complete_places,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Prints the errors only reported under one of the invalidations, for each program where they
+/// differ, then the totals.
+impl fmt::Display for PrecisionReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for difference in &self.differences {
+            writeln!(
+                f,
+                "{}: {} errors, {} with complete places",
+                difference.program,
+                difference.precise.len(),
+                difference.complete_places.len()
+            )?;
+            for error in &difference.complete_places {
+                if !difference.precise.contains(error) {
+                    writeln!(f, "    only with complete places: {}", error)?;
+                }
+            }
+            for error in &difference.precise {
+                if !difference.complete_places.contains(error) {
+                    writeln!(f, "    only when precise: {}", error)?;
+                }
+            }
+        }
+        writeln!(
+            f,
+            "{} programs, {} with different errors",
+            self.programs,
+            self.differences.len()
+        )
+    }
+}
@@ -0,0 +1,57 @@
+use super::*;
+
+const CORPUS: &str = "
+    program fields {
+        struct Pair { a: i32, b: i32 }
+        fn use<T>(t: T) -> ();
+        let mut x: Pair;
+        let r: &'r i32;
+        bb0: {
+            x = Pair { a: 1, b: 2 };
+            r = &'L_x_a x.a;
+            x.b = 3;
+            use(copy r);
+        }
+    }
+
+    program reborrow {
+        fn use<T>(t: T) -> ();
+        let mut y: i32;
+        let mut z: i32;
+        let mut p: &'p mut i32;
+        let q: &'q i32;
+        bb0: {
+            p = &'L_y mut y;
+            q = &'L_p *p;
+            p = &'L_z mut z;
+            use(copy q);
+        }
+    }
+
+    program conflict {
+        fn use<T>(t: T) -> ();
+        let mut x: i32;
+        let r: &'r i32;
+        bb0: {
+            r = &'L_x x;
+            x = 1;
+            use(copy r);
+        }
+    }
+";
+
+#[test]
+fn complete_places_report_more_errors() {
+    let mut report = PrecisionReport::default();
+    report
+        .add_input("corpus.ast", CORPUS, &Options::default())
+        .unwrap();
+    insta::assert_snapshot!(report.to_string(), @r###"
+    corpus.ast:fields: 0 errors, 1 with complete places
+        only with complete places: invalidated origin `'r` is accessed at `bb0[3]`
+    corpus.ast:reborrow: 0 errors, 1 with complete places
+        only with complete places: invalidated origin `'q` is accessed at `bb0[3]`
+    3 programs, 2 with different errors
+
+    "###);
+}
@@ -0,0 +1,101 @@
+//! Wall-time and memory measurements of the phases of the pipeline, reported by `--profile`.
+
+use std::fmt::Write;
+use std::time::{Duration, Instant};
+
+#[cfg(test)]
+mod test;
+
+#[derive(Clone, Debug, Default)]
+pub struct Profiler {
+    pub phases: Vec<Phase>,
+
+    /// Counts reported along with the phases, e.g. the hits of a cache.
+    pub counters: Vec<(String, u64)>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Phase {
+    pub name: String,
+    pub wall_time: Duration,
+
+    /// The peak resident memory of the process at the end of the phase, in kilobytes. This is
+    /// the peak since the process started, so a phase only raised it if it is larger than the
+    /// one of the previous phase. Only available on Linux.
+    pub peak_memory_kb: Option<u64>,
+}
+
+impl Profiler {
+    /// Runs `f` as the phase `name`.
+    pub fn time<T>(&mut self, name: impl Into<String>, f: impl FnOnce() -> T) -> T {
+        let start = Instant::now();
+        let result = f();
+        self.phases.push(Phase {
+            name: name.into(),
+            wall_time: start.elapsed(),
+            peak_memory_kb: peak_memory_kb(),
+        });
+        result
+    }
+
+    /// Records the counter `name`, replacing its previous value.
+    pub fn count(&mut self, name: impl Into<String>, value: u64) {
+        let name = name.into();
+        self.counters.retain(|(n, _)| *n != name);
+        self.counters.push((name, value));
+    }
+
+    /// Formats the phases as a JSON object, e.g.
+    /// `{"phases":[{"name":"parse","wall_time_ms":0.042,"peak_memory_kb":2048}]}`, followed by
+    /// the counters, if any, e.g. `"counters":{"cache_hits":3}`.
+    pub fn to_json(&self) -> String {
+        let mut json = String::from("{\"phases\":[");
+        for (i, phase) in self.phases.iter().enumerate() {
+            if i > 0 {
+                json.push(',');
+            }
+            let peak_memory_kb = match phase.peak_memory_kb {
+                Some(kb) => kb.to_string(),
+                None => "null".to_string(),
+            };
+            write!(
+                json,
+                "{{\"name\":\"{}\",\"wall_time_ms\":{:.3},\"peak_memory_kb\":{}}}",
+                escape(&phase.name),
+                phase.wall_time.as_secs_f64() * 1000.0,
+                peak_memory_kb
+            )
+            .unwrap();
+        }
+        json.push(']');
+        if !self.counters.is_empty() {
+            json.push_str(",\"counters\":{");
+            for (i, (name, value)) in self.counters.iter().enumerate() {
+                if i > 0 {
+                    json.push(',');
+                }
+                write!(json, "\"{}\":{}", escape(name), value).unwrap();
+            }
+            json.push('}');
+        }
+        json.push('}');
+        json
+    }
+}
+
+/// Escapes `name` for a JSON string.
+fn escape(name: &str) -> String {
+    name.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Reads the peak resident set size, `VmHWM`, from `/proc/self/status`.
+fn peak_memory_kb() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmHWM:"))?;
+    line.trim_start_matches("VmHWM:")
+        .trim()
+        .trim_end_matches("kB")
+        .trim()
+        .parse()
+        .ok()
+}
@@ -0,0 +1,50 @@
+use super::*;
+
+#[test]
+fn records_phases() {
+    let mut profiler = Profiler::default();
+    let value = profiler.time("parse", || 22);
+    profiler.time("solve", || ());
+
+    assert_eq!(value, 22);
+    let names: Vec<_> = profiler.phases.iter().map(|p| p.name.as_str()).collect();
+    assert_eq!(names, ["parse", "solve"]);
+}
+
+#[test]
+fn json() {
+    let profiler = Profiler {
+        phases: vec![
+            Phase {
+                name: "parse".to_string(),
+                wall_time: Duration::from_micros(1500),
+                peak_memory_kb: Some(2048),
+            },
+            Phase {
+                name: "pass \"dedup\"".to_string(),
+                wall_time: Duration::from_millis(2),
+                peak_memory_kb: None,
+            },
+        ],
+        ..Profiler::default()
+    };
+
+    insta::assert_snapshot!(profiler.to_json(), @r###"
+    {"phases":[{"name":"parse","wall_time_ms":1.500,"peak_memory_kb":2048},{"name":"pass \"dedup\"","wall_time_ms":2.000,"peak_memory_kb":null}]}
+    "###);
+}
+
+#[test]
+fn counters() {
+    let mut profiler = Profiler::default();
+    profiler.time("solve", || ());
+    profiler.count("cache_hits", 1);
+    profiler.count("cache_misses", 2);
+    profiler.count("cache_hits", 3);
+    profiler.phases[0].wall_time = Duration::from_millis(1);
+    profiler.phases[0].peak_memory_kb = None;
+
+    insta::assert_snapshot!(profiler.to_json(), @r###"
+    {"phases":[{"name":"solve","wall_time_ms":1.000,"peak_memory_kb":null}],"counters":{"cache_misses":2,"cache_hits":3}}
+    "###);
+}
@@ -0,0 +1,138 @@
+//! Name resolution of the `mod` namespaces, so that programs imported from MIR can keep their
+//! path-qualified names, like `collections::Vec`, rather than renaming every item to a unique
+//! identifier.
+//!
+//! The modules are flattened once parsed, before anything else sees the program: each item is
+//! declared in the program under its path, and the names in the types of its declaration are
+//! resolved to the paths of the items they refer to. The rest of the pipeline only deals with
+//! items named by a path, which are compared like any other name.
+
+use std::collections::HashSet;
+
+use crate::ast::visit::{walk_ty_mut, ProgramVisitorMut};
+use crate::ast::{GenericDecl, Module, Name, Program, Ty};
+
+#[cfg(test)]
+mod test;
+
+/// Declares the items of `modules`, and of their submodules, in `program`, after its own items.
+///
+/// Like in Rust 2015, a name used in a module is looked up in the module, then in each of its
+/// ancestors up to the root, e.g. `Vec` in `collections::linked` is the first of
+/// `collections::linked::Vec`, `collections::Vec` and `Vec` which is declared. A name which isn't
+/// declared anywhere is left unchanged, for the type parameters and builtin types, unless it is
+/// a path, which is an error. The items of the root refer to the items of modules by their
+/// whole path, which needs no resolution.
+pub fn flatten_modules(program: &mut Program, modules: Vec<Module>) -> eyre::Result<()> {
+    let mut items = Items::default();
+    for module in &modules {
+        items.collect(module, "");
+    }
+    for module in modules {
+        flatten_module(program, module, "", &items)?;
+    }
+    Ok(())
+}
+
+/// The paths of the types declared by the modules, which their declarations can refer to.
+#[derive(Default)]
+struct Items {
+    types: HashSet<Name>,
+}
+
+impl Items {
+    fn collect(&mut self, module: &Module, prefix: &str) {
+        let path = format!("{}{}", prefix, module.name);
+        let types = (module.extern_types.iter())
+            .chain(module.struct_decls.iter().map(|decl| &decl.name))
+            .chain(module.type_aliases.iter().map(|alias| &alias.name));
+        for name in types {
+            self.types.insert(format!("{}::{}", path, name));
+        }
+        for submodule in &module.modules {
+            self.collect(submodule, &format!("{}::", path));
+        }
+    }
+}
+
+fn flatten_module(
+    program: &mut Program,
+    module: Module,
+    prefix: &str,
+    items: &Items,
+) -> eyre::Result<()> {
+    let path = format!("{}{}", prefix, module.name);
+    let qualified = |name: &Name| format!("{}::{}", path, name);
+    let resolver = |generic_decls: &[GenericDecl]| Resolver {
+        module: &path,
+        items,
+        parameters: generic_decls
+            .iter()
+            .filter_map(|decl| match decl {
+                GenericDecl::Ty(name, _) => Some(name.clone()),
+                GenericDecl::Origin(_) => None,
+            })
+            .collect(),
+    };
+
+    for name in &module.extern_types {
+        program.extern_types.push(qualified(name));
+    }
+    for mut decl in module.struct_decls {
+        resolver(&decl.generic_decls).visit_struct_decl(&mut decl)?;
+        decl.name = qualified(&decl.name);
+        program.struct_decls.push(decl);
+    }
+    for mut alias in module.type_aliases {
+        resolver(&alias.generic_decls).visit_type_alias(&mut alias)?;
+        alias.name = qualified(&alias.name);
+        program.type_aliases.push(alias);
+    }
+    for mut prototype in module.fn_prototypes {
+        resolver(&prototype.generic_decls).visit_fn_prototype(&mut prototype)?;
+        prototype.name = qualified(&prototype.name);
+        program.fn_prototypes.push(prototype);
+    }
+    for submodule in module.modules {
+        flatten_module(program, submodule, &format!("{}::", path), items)?;
+    }
+    Ok(())
+}
+
+/// Resolves the names of the types in the declaration of an item of `module`.
+struct Resolver<'a> {
+    module: &'a str,
+    items: &'a Items,
+
+    /// The type parameters of the item, which shadow the items of the same name.
+    parameters: Vec<Name>,
+}
+
+impl Resolver<'_> {
+    fn resolve(&self, name: &str) -> eyre::Result<Name> {
+        if self.parameters.iter().any(|parameter| parameter == name) {
+            return Ok(name.to_string());
+        }
+        let mut scope = Some(self.module);
+        while let Some(module) = scope {
+            let path = format!("{}::{}", module, name);
+            if self.items.types.contains(&path) {
+                return Ok(path);
+            }
+            scope = module.rsplit_once("::").map(|(parent, _)| parent);
+        }
+        if name.contains("::") && !self.items.types.contains(name) {
+            eyre::bail!("cannot find `{}` in `{}`", name, self.module);
+        }
+        Ok(name.to_string())
+    }
+}
+
+impl ProgramVisitorMut for Resolver<'_> {
+    fn visit_ty(&mut self, ty: &mut Ty) -> eyre::Result<()> {
+        if let Ty::Struct { name, .. } = ty {
+            *name = self.resolve(name)?;
+        }
+        walk_ty_mut(self, ty)
+    }
+}
@@ -0,0 +1,133 @@
+use crate::parse_ast;
+
+const INPUT: &str = "
+    extern type Alloc;
+    mod collections {
+        extern type RawVec;
+        mod linked {
+            struct Node<'a, T> { next: &'a Node<'a, T>, value: T }
+            struct List<'a, T> { head: &'a Node<'a, T>, buf: RawVec, alloc: Alloc }
+            fn push<'a, T>(list: &'a mut List<'a, T>, value: T) -> ();
+        }
+        struct Vec<'a, T> { buf: RawVec, first: &'a T }
+        type VecRef<'a, 'v, T> = &'a Vec<'v, T>;
+        fn len<'a, T>(v: &'a Vec<'a, T>) -> i32;
+        fn head<'a, T>(list: &'a linked::List<'a, T>) -> &'a T;
+    }
+    struct Vec<'a> { v: collections::Vec<'a, i32> }
+    let v: collections::Vec<'v, i32>;
+    let mut l: collections::linked::List<'l, i32>;
+    let n: i32;
+    bb0: {
+        n = collections::len(&'L_v v);
+        collections::linked::push(&'L_l mut l, 1);
+    }
+";
+
+#[test]
+fn flatten_modules() {
+    let program = parse_ast(INPUT).unwrap();
+    let names: Vec<_> = (program.extern_types.iter())
+        .chain(program.struct_decls.iter().map(|decl| &decl.name))
+        .chain(program.type_aliases.iter().map(|alias| &alias.name))
+        .chain(
+            program
+                .fn_prototypes
+                .iter()
+                .map(|prototype| &prototype.name),
+        )
+        .collect();
+    insta::assert_debug_snapshot!(names, @r###"
+    [
+        "Alloc",
+        "collections::RawVec",
+        "Vec",
+        "collections::Vec",
+        "collections::linked::Node",
+        "collections::linked::List",
+        "collections::VecRef",
+        "collections::len",
+        "collections::head",
+        "collections::linked::push",
+    ]
+    "###);
+
+    // The items are printed back in their modules, with the resolved paths.
+    insta::assert_snapshot!(program.to_string(), @r###"
+    extern type Alloc;
+    mod collections {
+        extern type RawVec;
+        mod linked {
+            struct Node<'a, T> { next: &'a collections::linked::Node<'a, T>, value: T }
+            struct List<'a, T> { head: &'a collections::linked::Node<'a, T>, buf: collections::RawVec, alloc: Alloc }
+            fn push<'a, T>(arg0: &'a mut collections::linked::List<'a, T>, arg1: T) -> ();
+        }
+        struct Vec<'a, T> { buf: collections::RawVec, first: &'a T }
+        type VecRef<'a, 'v, T> = &'a collections::Vec<'v, T>;
+        fn len<'a, T>(arg0: &'a collections::Vec<'a, T>) -> i32;
+        fn head<'a, T>(arg0: &'a collections::linked::List<'a, T>) -> &'a T;
+    }
+    struct Vec<'a> { v: collections::Vec<'a, i32> }
+    let v: collections::Vec<'v, i32>;
+    let mut l: collections::linked::List<'l, i32>;
+    let n: i32;
+    bb0: {
+        n = collections::len(&'L_v v);
+        collections::linked::push(&'L_l mut l, 1);
+    }
+
+    "###);
+    let printed = program.to_string();
+    assert_eq!(parse_ast(&printed).unwrap().to_string(), printed);
+}
+
+#[test]
+fn resolution() {
+    // The type parameter `Vec` shadows the struct, and `Box` is left to the type checker.
+    let program = parse_ast(
+        "
+        mod a {
+            mod b {
+                fn g(v: Vec) -> ();
+            }
+            struct Vec { }
+            fn f<Vec>(v: Vec) -> Box;
+        }
+        ",
+    )
+    .unwrap();
+    insta::assert_snapshot!(program.to_string(), @r###"
+    mod a {
+        mod b {
+            fn g(arg0: a::Vec) -> ();
+        }
+        struct Vec {  }
+        fn f<Vec>(arg0: Vec) -> Box;
+    }
+
+    "###);
+
+    let error = |input| format!("{:#}", parse_ast(input).unwrap_err());
+    insta::assert_snapshot!(error("mod a { fn f(v: b::Vec) -> (); }"), @"cannot find `b::Vec` in `a`");
+
+    // The items of a sibling module are only found by their path.
+    let program = parse_ast("mod a { struct Vec { } } mod b { fn f(v: Vec) -> (); }").unwrap();
+    insta::assert_snapshot!(program.fn_prototypes[0].to_string(), @"fn b::f(arg0: Vec) -> ();");
+}
+
+#[test]
+fn calls_to_module_functions() {
+    // The origins of a call are named after the function, without its path.
+    let facts = crate::emit_facts(INPUT, Default::default()).unwrap();
+    let origins: Vec<_> = facts
+        .origins()
+        .into_iter()
+        .filter(|origin| origin.contains(char::is_numeric))
+        .collect();
+    insta::assert_debug_snapshot!(origins, @r###"
+    [
+        "'len0_a",
+        "'push1_a",
+    ]
+    "###);
+}
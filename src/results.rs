@@ -0,0 +1,300 @@
+//! Queries over the solved relations, so that tools built on top of the analysis don't need to
+//! scrape the relation tuples themselves.
+
+use std::collections::BTreeSet;
+use std::fmt;
+
+use crate::ast::Assertion;
+use crate::fact_emitter::Expectation;
+use crate::facts::{Facts, Node, NodeIndex, Origin};
+use crate::solver::{self, Algorithm, Output};
+
+#[cfg(test)]
+mod test;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ErrorKind {
+    /// An invalidated origin is accessed, i.e. a loan is used after being invalidated.
+    InvalidatedOriginAccessed,
+
+    /// The returned value references a local that died before returning.
+    ReturnedReferenceToLocal,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Error {
+    pub kind: ErrorKind,
+    pub origin: Origin,
+    pub node: Node,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            ErrorKind::InvalidatedOriginAccessed => write!(
+                f,
+                "invalidated origin `{}` is accessed at `{}`",
+                self.origin, self.node
+            ),
+            ErrorKind::ReturnedReferenceToLocal => write!(
+                f,
+                "origin `{}` references a local after it died, and is returned at `{}`",
+                self.origin, self.node
+            ),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Error,
+    Warning,
+    Note,
+}
+
+impl Severity {
+    pub const ALL: [Severity; 3] = [Severity::Error, Severity::Warning, Severity::Note];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Note => "note",
+        }
+    }
+}
+
+/// A finding of the analysis at a node, e.g. an error, printed as
+/// `error invalidated_origin_accessed('L_x) @ c`: its severity, the relation it comes from,
+/// and the origins or loans involved.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub relation: String,
+    pub origins: Vec<Origin>,
+    pub node: Node,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {}({}) @ {}",
+            self.severity.name(),
+            self.relation,
+            self.origins.join(", "),
+            self.node
+        )
+    }
+}
+
+/// The outcome of the analysis of a program.
+#[derive(Clone, Debug)]
+pub struct Results {
+    output: Output,
+
+    /// The origins of the loans: the facts don't distinguish them from the other origins, but
+    /// only loans are ever invalidated.
+    loans: BTreeSet<Origin>,
+
+    /// The facts which were solved, for the expectations about the facts themselves, like
+    /// `assert_each_iteration`.
+    facts: Facts,
+}
+
+impl Results {
+    pub fn new(facts: &Facts, output: Output) -> Self {
+        let loans = facts
+            .invalidate_origins()
+            .map(|(origin, _)| origin.clone())
+            .collect();
+        Results {
+            output,
+            loans,
+            facts: facts.clone(),
+        }
+    }
+
+    /// Solves `facts` with one of the native algorithms.
+    pub fn compute(facts: &Facts, algorithm: Algorithm) -> Self {
+        Self::new(facts, solver::compute(facts, algorithm))
+    }
+
+    /// The solved relations, for queries not covered here.
+    pub fn output(&self) -> &Output {
+        &self.output
+    }
+
+    /// The loans which may still be used at `node` or later: either the loan's own origin is
+    /// live, or it is a subset of a live origin.
+    pub fn loans_live_at(&self, node: &str) -> BTreeSet<&Origin> {
+        let live: BTreeSet<&Origin> = self
+            .output
+            .origin_live
+            .iter()
+            .filter(|(_, n)| n == node)
+            .map(|(origin, _)| origin)
+            .collect();
+
+        self.loans
+            .iter()
+            .filter(|loan| {
+                live.contains(loan)
+                    || self
+                        .output
+                        .subset
+                        .iter()
+                        .any(|(o1, o2, n)| o1 == *loan && n == node && live.contains(o2))
+            })
+            .collect()
+    }
+
+    /// The origins which the origin `loan` is a subset of, at any node.
+    pub fn origins_containing(&self, loan: &str) -> BTreeSet<&Origin> {
+        self.output
+            .subset
+            .iter()
+            .filter(|(o1, ..)| o1 == loan)
+            .map(|(_, o2, _)| o2)
+            .collect()
+    }
+
+    /// The expectations of the program's ghost statements which the results don't meet.
+    pub fn unmet_expectations<'e>(&self, expectations: &'e [Expectation]) -> Vec<&'e Expectation> {
+        expectations
+            .iter()
+            .filter(|expectation| {
+                let node = &expectation.node;
+                match &expectation.assertion {
+                    Assertion::Live(origin) => !self
+                        .output
+                        .origin_live
+                        .contains(&(origin.clone(), node.clone())),
+                    Assertion::Error => !self.errors().iter().any(|e| e.node == *node),
+                    Assertion::Empty(origin) => self
+                        .output
+                        .assert_empty_violated
+                        .iter()
+                        .any(|(o, _, n)| o == origin && n == node),
+                    Assertion::EachIteration {
+                        relation,
+                        arguments,
+                    } => !self.holds_on_each_iteration(relation, arguments, node),
+                }
+            })
+            .collect()
+    }
+
+    /// Whether every path from `header` around a loop back to it goes through a node where
+    /// `relation(arguments.., node)` holds. It doesn't hold when `header` isn't in a loop.
+    fn holds_on_each_iteration(&self, relation: &str, arguments: &[String], header: &str) -> bool {
+        let holds: BTreeSet<&str> = self
+            .facts
+            .relations()
+            .into_iter()
+            .filter(|(name, _)| *name == relation)
+            .flat_map(|(_, rows)| rows)
+            .filter_map(|row| {
+                let (node, args) = row.split_last()?;
+                let matches = args
+                    .iter()
+                    .copied()
+                    .eq(arguments.iter().map(String::as_str));
+                matches.then_some(*node)
+            })
+            .collect();
+
+        let index = self.facts.index_by_node();
+        let in_loop = reaches_back(&index, header, |_| true);
+        let skips_fact =
+            !holds.contains(header) && reaches_back(&index, header, |node| !holds.contains(node));
+        in_loop && !skips_fact
+    }
+
+    /// The errors, sorted by node. An invalidated origin accessed at a `(return)` node is only
+    /// reported as a returned reference to a local.
+    pub fn errors(&self) -> Vec<Error> {
+        let returned = self
+            .output
+            .returned_reference_to_local
+            .iter()
+            .map(|(origin, node)| (ErrorKind::ReturnedReferenceToLocal, origin, node));
+        let accessed = self
+            .output
+            .invalidated_origin_accessed
+            .iter()
+            .filter(|tuple| !self.output.returned_reference_to_local.contains(tuple))
+            .map(|(origin, node)| (ErrorKind::InvalidatedOriginAccessed, origin, node));
+
+        let mut errors: Vec<Error> = returned
+            .chain(accessed)
+            .map(|(kind, origin, node)| Error {
+                kind,
+                origin: origin.clone(),
+                node: node.clone(),
+            })
+            .collect();
+        errors.sort_by(|e1, e2| (&e1.node, &e1.origin).cmp(&(&e2.node, &e2.origin)));
+        errors
+    }
+
+    /// All the findings, sorted: the `errors`, the loan conflicts as warnings, and the
+    /// violations of `assert_empty` as notes.
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        let diagnostic = |severity, relation: &str, origins: &[&Origin], node: &Node| Diagnostic {
+            severity,
+            relation: relation.to_string(),
+            origins: origins.iter().map(|o| o.to_string()).collect(),
+            node: node.clone(),
+        };
+
+        let mut diagnostics = vec![];
+        for error in self.errors() {
+            let relation = match error.kind {
+                ErrorKind::InvalidatedOriginAccessed => "invalidated_origin_accessed",
+                ErrorKind::ReturnedReferenceToLocal => "returned_reference_to_local",
+            };
+            diagnostics.push(diagnostic(
+                Severity::Error,
+                relation,
+                &[&error.origin],
+                &error.node,
+            ));
+        }
+        for (l1, l2, node) in &self.output.loan_conflict {
+            diagnostics.push(diagnostic(
+                Severity::Warning,
+                "loan_conflict",
+                &[l1, l2],
+                node,
+            ));
+        }
+        for (origin, o2, node) in &self.output.assert_empty_violated {
+            diagnostics.push(diagnostic(
+                Severity::Note,
+                "assert_empty_violated",
+                &[origin, o2],
+                node,
+            ));
+        }
+        diagnostics.sort();
+        diagnostics
+    }
+}
+
+/// Whether a path from `header`, through the nodes which are `allowed`, leads back to it.
+fn reaches_back(index: &NodeIndex<'_>, header: &str, allowed: impl Fn(&str) -> bool) -> bool {
+    let mut visited = BTreeSet::new();
+    let mut stack = vec![header];
+    while let Some(node) = stack.pop() {
+        for successor in index.successors(node) {
+            if successor == header {
+                return true;
+            }
+            if allowed(successor) && visited.insert(successor.as_str()) {
+                stack.push(successor);
+            }
+        }
+    }
+    false
+}
@@ -0,0 +1,274 @@
+use super::*;
+use crate::fact_emitter::Options;
+
+fn results(s: &str) -> Results {
+    let options = Options {
+        simple_node_names: true,
+        ..Options::default()
+    };
+    let facts = crate::emit_facts(s, options).unwrap();
+    Results::compute(&facts, Algorithm::Naive)
+}
+
+const PROGRAM: &str = "
+    let x: i32;
+    let p: &'p i32;
+    let mut q: &'q i32;
+    bb0: {
+        p = &'L_x x;
+        q = copy p;
+        x = 1;
+        q = copy q;
+    }
+";
+
+#[test]
+fn loans_live_at() {
+    let results = results(PROGRAM);
+    insta::assert_debug_snapshot!(results.loans_live_at("a"), @"{}");
+    insta::assert_debug_snapshot!(results.loans_live_at("b"), @r###"
+    {
+        "'L_x",
+    }
+    "###);
+    insta::assert_debug_snapshot!(results.loans_live_at("d"), @r###"
+    {
+        "'L_x",
+    }
+    "###);
+}
+
+#[test]
+fn origins_containing() {
+    let results = results(PROGRAM);
+    insta::assert_debug_snapshot!(results.origins_containing("'L_x"), @r###"
+    {
+        "'p",
+        "'q",
+    }
+    "###);
+}
+
+#[test]
+fn errors() {
+    let results = results(PROGRAM);
+    let errors: Vec<_> = results.errors().iter().map(|e| e.to_string()).collect();
+    insta::assert_debug_snapshot!(errors, @r###"
+    [
+        "invalidated origin `'q` is accessed at `d`",
+    ]
+    "###);
+}
+
+#[test]
+fn returned_reference_is_not_reported_twice() {
+    let results = results(
+        "
+        let x: i32;
+        let return: &'r i32;
+        bb0: {
+            return = &'L_x x;
+        }
+    ",
+    );
+    insta::assert_debug_snapshot!(results.errors(), @r###"
+    [
+        Error {
+            kind: ReturnedReferenceToLocal,
+            origin: "'r",
+            node: "c",
+        },
+    ]
+    "###);
+}
+
+#[test]
+fn unmet_expectations() {
+    let options = Options {
+        simple_node_names: true,
+        ..Options::default()
+    };
+    let (facts, expectations) = crate::emit_expectations(
+        "
+        let x: i32;
+        let p: &'p i32;
+        let mut q: &'q i32;
+        bb0: {
+            p = &'L_x x;
+            assert_live('p);
+            q = copy p;
+            assert_error;
+            x = 1;
+            assert_live('q);
+            assert_error;
+            q = copy q;
+        }
+    ",
+        options,
+    )
+    .unwrap();
+    let results = Results::compute(&facts, Algorithm::Naive);
+    let unmet: Vec<_> = results
+        .unmet_expectations(&expectations)
+        .iter()
+        .map(|e| e.to_string())
+        .collect();
+    insta::assert_debug_snapshot!(unmet, @r###"
+    [
+        "`assert_error` at `c`",
+    ]
+    "###);
+}
+
+#[test]
+fn empty_origin_expectations() {
+    // `'L_x` flows into `'p`, and from `'p` into `'q`, until `p` and `q` are overwritten.
+    let options = Options {
+        simple_node_names: true,
+        ..Options::default()
+    };
+    let (facts, expectations) = crate::emit_expectations(
+        "
+        let x: i32;
+        let y: i32;
+        let mut p: &'p i32;
+        let mut q: &'q i32;
+        bb0: {
+            assert_empty('p);
+            p = &'L_x x;
+            assert_empty('p);
+            q = copy p;
+            assert_empty('q);
+            p = &'L_y y;
+            q = &'L_y2 y;
+            assert_empty('L_x);
+            pass;
+        }
+    ",
+        options,
+    )
+    .unwrap();
+    let results = Results::compute(&facts, Algorithm::Naive);
+    let unmet: Vec<_> = results
+        .unmet_expectations(&expectations)
+        .iter()
+        .map(|e| e.to_string())
+        .collect();
+    insta::assert_debug_snapshot!(unmet, @r###"
+    [
+        "`assert_empty('p)` at `b`",
+        "`assert_empty('q)` at `c`",
+    ]
+    "###);
+}
+
+#[test]
+fn field_write_errors() {
+    // Only the loans overlapping `x.f` are invalidated by writing it.
+    let results = results(
+        "
+        struct Inner { g: i32, h: i32 }
+        struct Outer { f: Inner, g: i32 }
+        let mut x: Outer;
+        let i: Inner;
+        let a: &'a Outer;
+        let c: &'c i32;
+        let d: &'d i32;
+        let mut u: &'u i32;
+        bb0: {
+            a = &'L_x x;
+            c = &'L_xfg x.f.g;
+            d = &'L_xg x.g;
+            x.f = move i;
+            u = copy c;
+            u = copy d;
+            u = &'L_a a.*.g;
+        }
+    ",
+    );
+    let errors: Vec<_> = results.errors().iter().map(|e| e.to_string()).collect();
+    insta::assert_debug_snapshot!(errors, @r###"
+    [
+        "invalidated origin `'c` is accessed at `e`",
+        "invalidated origin `'a` is accessed at `g`",
+    ]
+    "###);
+}
+
+#[test]
+fn diagnostics() {
+    let results = results(PROGRAM);
+    let diagnostics: Vec<_> = results
+        .diagnostics()
+        .iter()
+        .map(|d| d.to_string())
+        .collect();
+    insta::assert_debug_snapshot!(diagnostics, @r###"
+    [
+        "error invalidated_origin_accessed('q) @ d",
+    ]
+    "###);
+}
+
+#[test]
+fn each_iteration_expectations() {
+    // `x` is assigned on the back edge through bb2, but not on the one through bb3. bb0 isn't
+    // in the loop.
+    let options = Options {
+        simple_node_names: true,
+        ..Options::default()
+    };
+    let (facts, expectations) = crate::emit_expectations(
+        "
+        let mut x: i32;
+        let mut r: &'r i32;
+        let mut n: i32;
+        bb0: {
+            assert_each_iteration(invalidate_origin('L_x));
+            r = &'L_x x;
+            goto bb1;
+        }
+        bb1: {
+            assert_each_iteration(invalidate_origin('L_x));
+            assert_each_iteration(access_origin('r));
+            assert_each_iteration(introduce_subset('L_x, 'r));
+            n = copy *r;
+            goto bb2, bb3, bb4;
+        }
+        bb2: {
+            x = 1;
+            r = &'L_x x;
+            goto bb1;
+        }
+        bb3: {
+            goto bb1;
+        }
+        bb4: {
+        }
+    ",
+        options.clone(),
+    )
+    .unwrap();
+    let results = Results::compute(&facts, Algorithm::Naive);
+    let unmet: Vec<_> = results
+        .unmet_expectations(&expectations)
+        .iter()
+        .map(|e| e.to_string())
+        .collect();
+    insta::assert_debug_snapshot!(unmet, @r###"
+    [
+        "`assert_each_iteration(invalidate_origin('L_x))` at `a`",
+        "`assert_each_iteration(invalidate_origin('L_x))` at `b`",
+        "`assert_each_iteration(introduce_subset('L_x, 'r))` at `b`",
+    ]
+    "###);
+
+    let err = |s: &str| {
+        format!(
+            "{:#}",
+            crate::emit_expectations(s, options.clone()).unwrap_err()
+        )
+    };
+    insta::assert_snapshot!(err("let x: i32; bb0: { assert_each_iteration(cfg_edge('a)); x = 1; }"), @"in `assert_each_iteration(cfg_edge('a))`: `cfg_edge` isn't a relation of the facts about a node");
+    insta::assert_snapshot!(err("let x: i32; bb0: { assert_each_iteration(invalidate_origin()); x = 1; }"), @"in `assert_each_iteration(invalidate_origin())`: `invalidate_origin` has 1 arguments besides the node");
+}
@@ -0,0 +1,71 @@
+//! Restricting the analysis of a large input to the parts being debugged, e.g. one function of
+//! a corpus, or a few blocks of it. The whole input is still parsed, but only the selected
+//! programs and blocks are emitted and solved.
+
+use crate::ast::{Name, Program};
+use crate::shrink::remove_dangling_successors;
+
+#[cfg(test)]
+mod test;
+
+/// The programs, and the blocks within them, to analyze. An empty list selects everything.
+#[derive(Clone, Debug, Default)]
+pub struct Scope {
+    /// The names of the programs to keep, e.g. `foo` for a `program foo { .. }` section.
+    pub programs: Vec<Name>,
+
+    /// The names of the blocks to keep in each selected program.
+    pub blocks: Vec<Name>,
+}
+
+impl Scope {
+    /// Whether the scope selects everything.
+    pub fn is_empty(&self) -> bool {
+        self.programs.is_empty() && self.blocks.is_empty()
+    }
+
+    /// Keeps the selected programs, in their order in the input, and the selected blocks of
+    /// each. The successors to removed blocks are removed as well. Selecting a program or a block
+    /// which doesn't exist is an error, rather than an empty analysis.
+    pub fn apply(&self, programs: Vec<(Name, Program)>) -> eyre::Result<Vec<(Name, Program)>> {
+        if let Some(unknown) = self
+            .programs
+            .iter()
+            .find(|name| programs.iter().all(|(n, _)| n != *name))
+        {
+            let known: Vec<&str> = programs.iter().map(|(n, _)| n.as_str()).collect();
+            eyre::bail!(
+                "no program named `{}`, expected one of: {}",
+                unknown,
+                known.join(", ")
+            );
+        }
+
+        let mut selected = vec![];
+        for (name, mut program) in programs {
+            if !self.programs.is_empty() && !self.programs.contains(&name) {
+                continue;
+            }
+            if !self.blocks.is_empty() {
+                self.restrict_blocks(&name, &mut program)?;
+            }
+            selected.push((name, program));
+        }
+        Ok(selected)
+    }
+
+    fn restrict_blocks(&self, name: &str, program: &mut Program) -> eyre::Result<()> {
+        if let Some(unknown) = self
+            .blocks
+            .iter()
+            .find(|block| program.basic_blocks.iter().all(|b| b.name != **block))
+        {
+            eyre::bail!("program `{}` has no block named `{}`", name, unknown);
+        }
+        program
+            .basic_blocks
+            .retain(|block| self.blocks.contains(&block.name));
+        remove_dangling_successors(program);
+        Ok(())
+    }
+}
@@ -0,0 +1,70 @@
+use super::*;
+
+fn parse(s: &str) -> Vec<(Name, Program)> {
+    crate::parse_ast_programs(s).unwrap()
+}
+
+const INPUT: &str = "
+    program first {
+        let mut x: i32;
+        bb0: { x = 1; goto bb1, bb2; }
+        bb1: { x = 2; goto bb3; }
+        bb2: { goto bb3; }
+        bb3: { }
+    }
+    program second {
+        let y: i32;
+        bb0: { y = 1; }
+    }
+";
+
+#[test]
+fn select_programs() {
+    let scope = Scope {
+        programs: vec!["second".to_string()],
+        ..Scope::default()
+    };
+    let programs = scope.apply(parse(INPUT)).unwrap();
+    let names: Vec<_> = programs.iter().map(|(name, _)| name.as_str()).collect();
+    assert_eq!(names, ["second"]);
+
+    let everything = Scope::default().apply(parse(INPUT)).unwrap();
+    let names: Vec<_> = everything.iter().map(|(name, _)| name.as_str()).collect();
+    assert_eq!(names, ["first", "second"]);
+}
+
+#[test]
+fn select_blocks() {
+    let scope = Scope {
+        programs: vec!["first".to_string()],
+        blocks: vec!["bb0".to_string(), "bb2".to_string()],
+    };
+    let programs = scope.apply(parse(INPUT)).unwrap();
+    insta::assert_snapshot!(programs[0].1.to_string(), @r###"
+    let mut x: i32;
+    bb0: {
+        x = 1;
+        goto bb2;
+    }
+    bb2: {
+    }
+
+    "###);
+}
+
+#[test]
+fn unknown_selections() {
+    let scope = Scope {
+        programs: vec!["third".to_string()],
+        ..Scope::default()
+    };
+    let err = scope.apply(parse(INPUT)).unwrap_err();
+    insta::assert_snapshot!(err.to_string(), @"no program named `third`, expected one of: first, second");
+
+    let scope = Scope {
+        programs: vec!["second".to_string()],
+        blocks: vec!["bb1".to_string()],
+    };
+    let err = scope.apply(parse(INPUT)).unwrap_err();
+    insta::assert_snapshot!(err.to_string(), @"program `second` has no block named `bb1`");
+}
@@ -0,0 +1,137 @@
+//! Delta debugging: shrinks a program on which the analysis misbehaves to a minimal program on
+//! which it still does, e.g. to turn a huge imported program into a reproducer.
+//!
+//! The shrinker greedily removes chunks of blocks, statements, successors and declarations,
+//! keeping each removal after which the program still fails, until no single item can be
+//! removed anymore.
+
+use std::panic::{self, AssertUnwindSafe};
+
+use crate::ast::Program;
+use crate::fact_emitter::{FactEmitter, Options};
+use crate::results::Results;
+use crate::solver;
+
+#[cfg(test)]
+mod test;
+
+/// How the analysis of a program fails.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Failure {
+    /// Emitting or solving the facts panics.
+    Panics,
+
+    /// Emitting or solving the facts returns an error whose message contains this text, e.g.
+    /// when the solvers disagree.
+    ErrorContains(String),
+
+    /// The analysis reports borrow errors, e.g. in a program that rustc accepts.
+    ReportsErrors,
+}
+
+impl Failure {
+    /// Whether analyzing `program` fails this way. Panics are caught, but still reported by
+    /// the panic hook.
+    pub fn occurs_in(&self, program: &Program) -> bool {
+        let analysis = panic::catch_unwind(AssertUnwindSafe(|| analyze(program)));
+        match (self, analysis) {
+            (Failure::Panics, analysis) => analysis.is_err(),
+            (Failure::ErrorContains(text), Ok(Err(e))) => format!("{:#}", e).contains(text),
+            (Failure::ReportsErrors, Ok(Ok(results))) => !results.errors().is_empty(),
+            _ => false,
+        }
+    }
+}
+
+pub(crate) fn analyze(program: &Program) -> eyre::Result<Results> {
+    let facts = FactEmitter::from_program(program.clone(), Options::default())?;
+    let output = solver::check(&facts)?;
+    Ok(Results::new(&facts, output))
+}
+
+/// Shrinks `program`, on which `is_failing` must hold, to a smaller program on which it still
+/// holds, and from which no block, statement, successor or declaration can be removed.
+pub fn shrink(
+    program: &Program,
+    mut is_failing: impl FnMut(&Program) -> bool,
+) -> eyre::Result<Program> {
+    if !is_failing(program) {
+        eyre::bail!("the program to shrink doesn't fail");
+    }
+
+    let is_failing: &mut dyn FnMut(&Program) -> bool = &mut is_failing;
+    let mut program = program.clone();
+    loop {
+        // Removing blocks first removes their statements all at once.
+        let mut shrunk = shrink_list(&mut program, &|p| &mut p.basic_blocks, is_failing);
+        for i in 0..program.basic_blocks.len() {
+            shrunk |= shrink_list(
+                &mut program,
+                &|p| &mut p.basic_blocks[i].statements,
+                is_failing,
+            );
+            shrunk |= shrink_list(
+                &mut program,
+                &|p| &mut p.basic_blocks[i].successors,
+                is_failing,
+            );
+        }
+        shrunk |= shrink_list(&mut program, &|p| &mut p.variables, is_failing);
+        shrunk |= shrink_list(&mut program, &|p| &mut p.fn_prototypes, is_failing);
+        shrunk |= shrink_list(&mut program, &|p| &mut p.type_aliases, is_failing);
+        shrunk |= shrink_list(&mut program, &|p| &mut p.struct_decls, is_failing);
+        shrunk |= shrink_list(&mut program, &|p| &mut p.extern_types, is_failing);
+        if !shrunk {
+            return Ok(program);
+        }
+    }
+}
+
+/// Tries to remove chunks of the items of `list(program)`, halving the chunk size down to
+/// single items. Returns whether any were removed.
+fn shrink_list<T>(
+    program: &mut Program,
+    list: &dyn Fn(&mut Program) -> &mut Vec<T>,
+    is_failing: &mut dyn FnMut(&Program) -> bool,
+) -> bool {
+    let mut shrunk = false;
+    let mut chunk_size = list(program).len();
+    while chunk_size > 0 {
+        let mut start = 0;
+        while start < list(program).len() {
+            let mut candidate = program.clone();
+            let items = list(&mut candidate);
+            let end = (start + chunk_size).min(items.len());
+            items.drain(start..end);
+            remove_dangling_successors(&mut candidate);
+
+            if is_failing(&candidate) {
+                *program = candidate;
+                shrunk = true;
+            } else {
+                start += chunk_size;
+            }
+        }
+        chunk_size /= 2;
+    }
+    shrunk
+}
+
+/// Removes the successors naming blocks which were removed. A `match` missing some of its arms
+/// becomes a `goto` to the remaining ones.
+pub(crate) fn remove_dangling_successors(program: &mut Program) {
+    let names: Vec<_> = program
+        .basic_blocks
+        .iter()
+        .map(|block| block.name.clone())
+        .collect();
+    for block in &mut program.basic_blocks {
+        let len = block.successors.len();
+        block
+            .successors
+            .retain(|successor| names.contains(&successor.block));
+        if block.successors.len() != len {
+            block.scrutinee = None;
+        }
+    }
+}
@@ -0,0 +1,114 @@
+use super::*;
+
+fn parse(s: &str) -> Program {
+    crate::parse_ast(s).unwrap()
+}
+
+#[test]
+fn shrink_to_borrow_error() {
+    let program = parse(
+        "
+        struct Pair<T> { first: T, second: T }
+        fn id<T>(t: T) -> T;
+        let mut x: i32;
+        let mut y: i32;
+        let mut p: &'p i32;
+        let mut pair: Pair<i32>;
+        bb0: {
+            y = 1;
+            x = 2;
+            pair.first = copy y;
+            goto bb1, bb2;
+        }
+        bb1: {
+            p = &'L_x x;
+            y = id(copy y);
+            goto bb3;
+        }
+        bb2: {
+            pair.second = copy x;
+            goto bb3;
+        }
+        bb3: {
+            x = 3;
+            y = copy *p;
+        }
+    ",
+    );
+    let shrunk = shrink(&program, |p| Failure::ReportsErrors.occurs_in(p)).unwrap();
+    insta::assert_snapshot!(shrunk.to_string(), @r###"
+    let mut x: i32;
+    let mut y: i32;
+    let mut p: &'p i32;
+    bb1: {
+        p = &'L_x x;
+        goto bb3;
+    }
+    bb3: {
+        x = 3;
+        y = copy *p;
+    }
+
+    "###);
+}
+
+#[test]
+fn shrink_with_custom_predicate() {
+    let program = parse(
+        "
+        let mut x: i32;
+        let mut y: i32;
+        bb0: {
+            x = 1;
+            y = 2;
+            goto bb1;
+        }
+        bb1: {
+            x = copy y;
+        }
+    ",
+    );
+    let shrunk = shrink(&program, |p| {
+        p.basic_blocks
+            .iter()
+            .flat_map(|block| &block.statements)
+            .any(|statement| statement.to_string() == "y = 2")
+    })
+    .unwrap();
+    insta::assert_snapshot!(shrunk.to_string(), @r###"
+    bb0: {
+        y = 2;
+    }
+
+    "###);
+}
+
+#[test]
+fn shrink_error_contains() {
+    let program = parse(
+        "
+        let x: i32;
+        let y: i32;
+        bb0: {
+            x = 1;
+            y = copy z;
+        }
+    ",
+    );
+    let failure = Failure::ErrorContains("`z`".to_string());
+    let shrunk = shrink(&program, |p| failure.occurs_in(p)).unwrap();
+    insta::assert_snapshot!(shrunk.to_string(), @r###"
+    let y: i32;
+    bb0: {
+        y = copy z;
+    }
+
+    "###);
+}
+
+#[test]
+fn program_must_fail() {
+    let program = parse("let x: i32; bb0: { x = 1; }");
+    let err = shrink(&program, |p| Failure::ReportsErrors.occurs_in(p)).unwrap_err();
+    insta::assert_snapshot!(format!("{:#}", err), @"the program to shrink doesn't fail");
+}
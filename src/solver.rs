@@ -0,0 +1,643 @@
+//! Native implementations of the rules in `polonius.dl`, over the same `Facts` given to
+//! souffle.
+//!
+//! There are several algorithms computing the same results:
+//!
+//! * `Naive` evaluates every rule over all the known tuples until nothing changes. It is slow,
+//!   but is a direct transcription of the datalog rules.
+//! * `Optimized` propagates only the newly discovered tuples, using per-node indices.
+//! * `LocationInsensitive` ignores the CFG entirely: every subset holds everywhere and
+//!   nothing is ever cleared. It over-approximates the errors of the other two, and can be used
+//!   as a quick pre-pass.
+
+use eyre::WrapErr;
+use itertools::Itertools;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::facts::{Facts, Node, Origin};
+
+pub mod cache;
+
+#[cfg(test)]
+mod test;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Algorithm {
+    Naive,
+    Optimized,
+    LocationInsensitive,
+}
+
+impl Algorithm {
+    pub const ALL: &'static [Algorithm] = &[
+        Algorithm::Naive,
+        Algorithm::Optimized,
+        Algorithm::LocationInsensitive,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Algorithm::Naive => "naive",
+            Algorithm::Optimized => "optimized",
+            Algorithm::LocationInsensitive => "location-insensitive",
+        }
+    }
+}
+
+impl FromStr for Algorithm {
+    type Err = eyre::Report;
+
+    fn from_str(s: &str) -> eyre::Result<Self> {
+        Algorithm::ALL
+            .iter()
+            .copied()
+            .find(|algorithm| algorithm.name() == s)
+            .ok_or_else(|| {
+                let names: Vec<_> = Algorithm::ALL.iter().map(|a| a.name()).collect();
+                eyre::eyre!(
+                    "unknown algorithm `{}`, valid algorithms are `{:?}`",
+                    s,
+                    names
+                )
+            })
+    }
+}
+
+/// The output relations of `polonius.dl`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Output {
+    pub subset: BTreeSet<(Origin, Origin, Node)>,
+    pub origin_invalidated: BTreeSet<(Origin, Node)>,
+    pub invalidated_origin_accessed: BTreeSet<(Origin, Node)>,
+
+    /// The origins which may be accessed at a node or later, before being defined again, and
+    /// the origins which the facts state are live on entry to a node.
+    pub origin_live: BTreeSet<(Origin, Node)>,
+
+    /// The errors of `invalidated_origin_accessed` at `(return)` nodes: the returned value
+    /// references a local that died before returning.
+    pub returned_reference_to_local: BTreeSet<(Origin, Node)>,
+
+    /// The `incompatible_loans` which are both live at a node: a loan is live if its origin
+    /// is, or if it is a subset of a live origin.
+    pub loan_conflict: BTreeSet<(Origin, Origin, Node)>,
+
+    /// The origins asserted to be empty at a node by `assert_empty`, along with an origin which
+    /// is a subset of them there.
+    pub assert_empty_violated: BTreeSet<(Origin, Origin, Node)>,
+}
+
+impl Output {
+    /// The number of tuples of all the relations.
+    pub fn tuple_count(&self) -> usize {
+        self.subset.len()
+            + self.origin_invalidated.len()
+            + self.invalidated_origin_accessed.len()
+            + self.origin_live.len()
+            + self.returned_reference_to_local.len()
+            + self.loan_conflict.len()
+            + self.assert_empty_violated.len()
+    }
+
+    /// The rows of each relation, named like in `polonius.dl`.
+    pub fn relations(&self) -> Vec<(&'static str, Vec<Vec<&str>>)> {
+        vec![
+            (
+                "subset",
+                self.subset
+                    .iter()
+                    .map(|(o1, o2, n)| vec![o1.as_str(), o2.as_str(), n.as_str()])
+                    .collect(),
+            ),
+            (
+                "origin_invalidated",
+                self.origin_invalidated
+                    .iter()
+                    .map(|(o, n)| vec![o.as_str(), n.as_str()])
+                    .collect(),
+            ),
+            (
+                "invalidated_origin_accessed",
+                self.invalidated_origin_accessed
+                    .iter()
+                    .map(|(o, n)| vec![o.as_str(), n.as_str()])
+                    .collect(),
+            ),
+            (
+                "origin_live",
+                self.origin_live
+                    .iter()
+                    .map(|(o, n)| vec![o.as_str(), n.as_str()])
+                    .collect(),
+            ),
+            (
+                "returned_reference_to_local",
+                self.returned_reference_to_local
+                    .iter()
+                    .map(|(o, n)| vec![o.as_str(), n.as_str()])
+                    .collect(),
+            ),
+            (
+                "loan_conflict",
+                self.loan_conflict
+                    .iter()
+                    .map(|(l1, l2, n)| vec![l1.as_str(), l2.as_str(), n.as_str()])
+                    .collect(),
+            ),
+            (
+                "assert_empty_violated",
+                self.assert_empty_violated
+                    .iter()
+                    .map(|(o1, o2, n)| vec![o1.as_str(), o2.as_str(), n.as_str()])
+                    .collect(),
+            ),
+        ]
+    }
+
+    /// Writes one `<relation>.csv` file per relation into `output_path`, in the format
+    /// souffle uses for its outputs.
+    pub fn write_to_dir(&self, output_path: &Path) -> eyre::Result<()> {
+        for (name, rows) in self.relations() {
+            let path = output_path.join(name).with_extension("csv");
+            let contents: String = rows
+                .into_iter()
+                .map(|row| format!("{}\n", row.iter().format("\t")))
+                .collect();
+            std::fs::write(&path, contents)
+                .wrap_err_with(|| format!("failed to write output to `{}`", path.display()))?;
+        }
+        Ok(())
+    }
+
+    /// Reads the `<relation>.csv` files in `output_path`, as written by souffle or by
+    /// `write_to_dir`.
+    pub fn read_from_dir(output_path: &Path) -> eyre::Result<Output> {
+        fn pairs(output_path: &Path, name: &str) -> eyre::Result<BTreeSet<(String, String)>> {
+            Ok(read_csv(&output_path.join(name).with_extension("csv"), 2)?
+                .into_iter()
+                .map(|row| row.into_iter().collect_tuple().unwrap())
+                .collect())
+        }
+
+        fn triples(
+            output_path: &Path,
+            name: &str,
+        ) -> eyre::Result<BTreeSet<(String, String, String)>> {
+            Ok(read_csv(&output_path.join(name).with_extension("csv"), 3)?
+                .into_iter()
+                .map(|row| row.into_iter().collect_tuple().unwrap())
+                .collect())
+        }
+
+        Ok(Output {
+            subset: triples(output_path, "subset")?,
+            origin_invalidated: pairs(output_path, "origin_invalidated")?,
+            invalidated_origin_accessed: pairs(output_path, "invalidated_origin_accessed")?,
+            origin_live: pairs(output_path, "origin_live")?,
+            returned_reference_to_local: pairs(output_path, "returned_reference_to_local")?,
+            loan_conflict: triples(output_path, "loan_conflict")?,
+            assert_empty_violated: triples(output_path, "assert_empty_violated")?,
+        })
+    }
+
+    /// Lists the tuples that differ between `self` and `other`, one per line: `- relation(..)`
+    /// for the tuples only in `self`, and `+ relation(..)` for the tuples only in `other`.
+    pub fn diff(&self, other: &Output) -> Vec<String> {
+        fn diff_relation<T: Ord>(
+            name: &str,
+            before: &BTreeSet<T>,
+            after: &BTreeSet<T>,
+            format: impl Fn(&T) -> String,
+            lines: &mut Vec<String>,
+        ) {
+            for tuple in before.difference(after) {
+                lines.push(format!("- {}({})", name, format(tuple)));
+            }
+            for tuple in after.difference(before) {
+                lines.push(format!("+ {}({})", name, format(tuple)));
+            }
+        }
+        let pair = |(o, n): &(Origin, Node)| format!("{}, {}", o, n);
+        let triple = |(o1, o2, n): &(Origin, Origin, Node)| format!("{}, {}, {}", o1, o2, n);
+
+        let mut lines = vec![];
+        diff_relation("subset", &self.subset, &other.subset, triple, &mut lines);
+        diff_relation(
+            "origin_invalidated",
+            &self.origin_invalidated,
+            &other.origin_invalidated,
+            pair,
+            &mut lines,
+        );
+        diff_relation(
+            "invalidated_origin_accessed",
+            &self.invalidated_origin_accessed,
+            &other.invalidated_origin_accessed,
+            pair,
+            &mut lines,
+        );
+        diff_relation(
+            "origin_live",
+            &self.origin_live,
+            &other.origin_live,
+            pair,
+            &mut lines,
+        );
+        diff_relation(
+            "returned_reference_to_local",
+            &self.returned_reference_to_local,
+            &other.returned_reference_to_local,
+            pair,
+            &mut lines,
+        );
+        diff_relation(
+            "loan_conflict",
+            &self.loan_conflict,
+            &other.loan_conflict,
+            triple,
+            &mut lines,
+        );
+        diff_relation(
+            "assert_empty_violated",
+            &self.assert_empty_violated,
+            &other.assert_empty_violated,
+            triple,
+            &mut lines,
+        );
+        lines
+    }
+}
+
+/// Reads the tab-separated rows of the csv file at `path`, which must each have `arity`
+/// columns.
+pub fn read_csv(path: &Path, arity: usize) -> eyre::Result<Vec<Vec<String>>> {
+    let contents = std::fs::read_to_string(path)
+        .wrap_err_with(|| format!("failed to read `{}`", path.display()))?;
+    contents
+        .lines()
+        .enumerate()
+        .map(|(i, line)| {
+            let row: Vec<String> = line.split('\t').map(str::to_string).collect();
+            if row.len() != arity {
+                eyre::bail!(
+                    "`{}`, line {}: expected {} columns, found {}",
+                    path.display(),
+                    i + 1,
+                    arity,
+                    row.len()
+                );
+            }
+            Ok(row)
+        })
+        .collect()
+}
+
+pub fn compute(facts: &Facts, algorithm: Algorithm) -> Output {
+    match algorithm {
+        Algorithm::Naive => naive(facts),
+        Algorithm::Optimized => optimized(facts),
+        Algorithm::LocationInsensitive => location_insensitive(facts),
+    }
+}
+
+/// Runs every algorithm, and checks that the naive and optimized ones compute the same
+/// results, and that the location-insensitive errors are a superset of them. Returns the
+/// output of the optimized algorithm.
+pub fn check(facts: &Facts) -> eyre::Result<Output> {
+    let naive = compute(facts, Algorithm::Naive);
+    let optimized = compute(facts, Algorithm::Optimized);
+    if naive != optimized {
+        eyre::bail!(
+            "the naive and optimized algorithms disagree:\nnaive: {:#?}\noptimized: {:#?}",
+            naive,
+            optimized
+        );
+    }
+
+    let insensitive = compute(facts, Algorithm::LocationInsensitive);
+    let missing: Vec<_> = optimized
+        .invalidated_origin_accessed
+        .difference(&insensitive.invalidated_origin_accessed)
+        .collect();
+    if !missing.is_empty() {
+        eyre::bail!(
+            "the location-insensitive algorithm missed the errors {:?}",
+            missing
+        );
+    }
+
+    Ok(optimized)
+}
+
+/// The `introduce_subset`s, and both directions of each `origin_equal`: the native solvers
+/// don't unify equal origins.
+fn introduced_subsets(facts: &Facts) -> impl Iterator<Item = (&Origin, &Origin, &Node)> {
+    let equalities = facts
+        .origin_equalities()
+        .flat_map(|(o1, o2, n)| [(o1, o2, n), (o2, o1, n)]);
+    facts.introduce_subsets().chain(equalities)
+}
+
+fn naive(facts: &Facts) -> Output {
+    let index = facts.index_by_node();
+
+    let mut subset: BTreeSet<(&str, &str, &str)> = BTreeSet::new();
+    loop {
+        let mut new = BTreeSet::new();
+        for (n1, n2) in facts.cfg_edges() {
+            let node = index.at(n1);
+            let equalities = node
+                .origin_equal
+                .iter()
+                .flat_map(|&(o1, o2)| [(o1, o2), (o2, o1)]);
+            for (o1, o2) in node.introduce_subset.iter().copied().chain(equalities) {
+                new.insert((o1.as_str(), o2.as_str(), n2.as_str()));
+            }
+            for &(o1, o2, n) in &subset {
+                if n == n1 && !index.is_cleared(o1, n1) && !index.is_cleared(o2, n1) {
+                    new.insert((o1, o2, n2.as_str()));
+                }
+            }
+        }
+        for &(o1, o2, n) in &subset {
+            for &(o2b, o3, nb) in &subset {
+                if o2 == o2b && n == nb {
+                    new.insert((o1, o3, n));
+                }
+            }
+        }
+
+        let len = subset.len();
+        subset.extend(new);
+        if subset.len() == len {
+            break;
+        }
+    }
+
+    let mut origin_invalidated: BTreeSet<(&str, &str)> = BTreeSet::new();
+    loop {
+        let mut new = BTreeSet::new();
+        for (n1, n2) in facts.cfg_edges() {
+            let invalidated = facts
+                .invalidate_origins()
+                .map(|(o, n)| (o.as_str(), n.as_str()))
+                .chain(origin_invalidated.iter().copied());
+            for (o, n) in invalidated {
+                if n == n1 && !index.is_cleared(o, n1) {
+                    new.insert((o, n2.as_str()));
+                }
+            }
+            for &(o1, o2, n) in &subset {
+                if n == n1 && !index.is_cleared(o2, n1) && index.is_invalidated(o1, n1) {
+                    new.insert((o2, n2.as_str()));
+                }
+            }
+        }
+
+        let len = origin_invalidated.len();
+        origin_invalidated.extend(new);
+        if origin_invalidated.len() == len {
+            break;
+        }
+    }
+
+    let mut origin_live: BTreeSet<(&str, &str)> = facts
+        .access_origins()
+        .chain(facts.drop_uses())
+        .chain(facts.origins_live_on_entry())
+        .map(|(o, n)| (o.as_str(), n.as_str()))
+        .collect();
+    loop {
+        let mut new = BTreeSet::new();
+        for (n1, n2) in facts.cfg_edges() {
+            for &(o, n) in &origin_live {
+                if n == n2 && !index.is_written(o, n1) {
+                    new.insert((o, n1.as_str()));
+                }
+            }
+        }
+
+        let len = origin_live.len();
+        origin_live.extend(new);
+        if origin_live.len() == len {
+            break;
+        }
+    }
+
+    finish(facts, subset, origin_invalidated, origin_live)
+}
+
+fn optimized(facts: &Facts) -> Output {
+    let index = facts.index_by_node();
+
+    // `subset`, indexed by node, then by the smaller origin.
+    let mut subset: HashMap<&str, HashMap<&str, HashSet<&str>>> = HashMap::new();
+    // The same tuples, indexed by node, then by the larger origin.
+    let mut superset: HashMap<&str, HashMap<&str, HashSet<&str>>> = HashMap::new();
+
+    let mut worklist: Vec<(&str, &str, &str)> = vec![];
+    for (o1, o2, n1) in introduced_subsets(facts) {
+        for n2 in index.successors(n1) {
+            worklist.push((o1.as_str(), o2.as_str(), n2.as_str()));
+        }
+    }
+
+    while let Some((o1, o2, n)) = worklist.pop() {
+        if !subset
+            .entry(n)
+            .or_default()
+            .entry(o1)
+            .or_default()
+            .insert(o2)
+        {
+            continue;
+        }
+        superset
+            .entry(n)
+            .or_default()
+            .entry(o2)
+            .or_default()
+            .insert(o1);
+
+        // Transitive closure, with the new tuple on either side of the join.
+        if let Some(smaller) = superset[n].get(o1) {
+            worklist.extend(smaller.iter().map(|&o0| (o0, o2, n)));
+        }
+        if let Some(larger) = subset[n].get(o2) {
+            worklist.extend(larger.iter().map(|&o3| (o1, o3, n)));
+        }
+
+        if !index.is_cleared(o1, n) && !index.is_cleared(o2, n) {
+            worklist.extend(index.successors(n).map(|n2| (o1, o2, n2.as_str())));
+        }
+    }
+
+    let mut origin_invalidated: HashSet<(&str, &str)> = HashSet::new();
+    let mut worklist: Vec<(&str, &str)> = vec![];
+    for (o1, n1) in facts.invalidate_origins() {
+        let o1 = o1.as_str();
+        let larger = subset
+            .get(n1.as_str())
+            .and_then(|s| s.get(o1))
+            .into_iter()
+            .flatten()
+            .copied();
+        for o in std::iter::once(o1).chain(larger) {
+            if !index.is_cleared(o, n1) {
+                worklist.extend(index.successors(n1).map(|n2| (o, n2.as_str())));
+            }
+        }
+    }
+    while let Some((o, n)) = worklist.pop() {
+        if origin_invalidated.insert((o, n)) && !index.is_cleared(o, n) {
+            worklist.extend(index.successors(n).map(|n2| (o, n2.as_str())));
+        }
+    }
+
+    // Liveness flows backwards, from the accesses to the previous definitions.
+    let mut origin_live: HashSet<(&str, &str)> = HashSet::new();
+    let mut worklist: Vec<(&str, &str)> = facts
+        .access_origins()
+        .chain(facts.drop_uses())
+        .chain(facts.origins_live_on_entry())
+        .map(|(o, n)| (o.as_str(), n.as_str()))
+        .collect();
+    while let Some((o, n)) = worklist.pop() {
+        if origin_live.insert((o, n)) {
+            worklist.extend(
+                index
+                    .predecessors(n)
+                    .filter(|n1| !index.is_written(o, n1))
+                    .map(|n1| (o, n1.as_str())),
+            );
+        }
+    }
+
+    let subset = subset
+        .into_iter()
+        .flat_map(|(n, s)| {
+            s.into_iter()
+                .flat_map(move |(o1, o2s)| o2s.into_iter().map(move |o2| (o1, o2, n)))
+        })
+        .collect();
+    finish(
+        facts,
+        subset,
+        origin_invalidated.into_iter().collect(),
+        origin_live.into_iter().collect(),
+    )
+}
+
+fn location_insensitive(facts: &Facts) -> Output {
+    let mut subset: HashMap<&str, HashSet<&str>> = HashMap::new();
+    for (o1, o2, _) in introduced_subsets(facts) {
+        subset.entry(o1).or_default().insert(o2);
+    }
+
+    let mut invalidated: HashSet<&str> = HashSet::new();
+    let mut worklist: Vec<&str> = facts
+        .invalidate_origins()
+        .map(|(o, _)| o.as_str())
+        .collect();
+    while let Some(o) = worklist.pop() {
+        if invalidated.insert(o) {
+            worklist.extend(subset.get(o).into_iter().flatten().copied());
+        }
+    }
+
+    // Without locations, the subsets and invalidations hold at every node.
+    let nodes: BTreeSet<&str> = facts.node_texts().map(|(_, n)| n.as_str()).collect();
+    let subset = subset
+        .iter()
+        .flat_map(|(&o1, o2s)| o2s.iter().map(move |&o2| (o1, o2)))
+        .flat_map(|(o1, o2)| nodes.iter().map(move |&n| (o1, o2, n)))
+        .collect();
+    let origin_invalidated = invalidated
+        .iter()
+        .flat_map(|&o| nodes.iter().map(move |&n| (o, n)))
+        .collect();
+    let live: BTreeSet<&str> = facts
+        .access_origins()
+        .chain(facts.drop_uses())
+        .chain(facts.origins_live_on_entry())
+        .map(|(o, _)| o.as_str())
+        .collect();
+    let origin_live = live
+        .iter()
+        .flat_map(|&o| nodes.iter().map(move |&n| (o, n)))
+        .collect();
+    finish(facts, subset, origin_invalidated, origin_live)
+}
+
+/// Computes the errors, and converts the intermediate relations into an `Output`.
+fn finish(
+    facts: &Facts,
+    subset: BTreeSet<(&str, &str, &str)>,
+    origin_invalidated: BTreeSet<(&str, &str)>,
+    origin_live: BTreeSet<(&str, &str)>,
+) -> Output {
+    let invalidated_origin_accessed: BTreeSet<(Origin, Node)> = facts
+        .access_origin
+        .iter()
+        .chain(&facts.drop_used_at)
+        .filter(|(o, n)| origin_invalidated.contains(&(o.as_str(), n.as_str())))
+        .cloned()
+        .collect();
+    let returns: HashSet<&Node> = facts
+        .node_text
+        .iter()
+        .filter(|(text, _)| text == "(return)")
+        .map(|(_, n)| n)
+        .collect();
+    let returned_reference_to_local = invalidated_origin_accessed
+        .iter()
+        .filter(|(_, n)| returns.contains(n))
+        .cloned()
+        .collect();
+
+    // The loans live at each node, to find the incompatible loans live at the same node.
+    let mut loan_live: HashMap<&str, HashSet<&str>> = HashMap::new();
+    for &(o, n) in &origin_live {
+        loan_live.entry(n).or_default().insert(o);
+    }
+    for &(o1, o2, n) in &subset {
+        if origin_live.contains(&(o2, n)) {
+            loan_live.entry(n).or_default().insert(o1);
+        }
+    }
+    let mut loan_conflict = BTreeSet::new();
+    for (l1, l2, _) in facts.incompatible_loans() {
+        for (n, live) in &loan_live {
+            if live.contains(l1.as_str()) && live.contains(l2.as_str()) {
+                loan_conflict.insert((l1.clone(), l2.clone(), n.to_string()));
+            }
+        }
+    }
+
+    let assert_empty_violated = subset
+        .iter()
+        .filter(|&&(_, o2, n)| facts.asserted_empty().any(|(o, node)| o == o2 && node == n))
+        .map(|&(o1, o2, n)| (o2.to_string(), o1.to_string(), n.to_string()))
+        .collect();
+
+    Output {
+        subset: subset
+            .into_iter()
+            .map(|(o1, o2, n)| (o1.to_string(), o2.to_string(), n.to_string()))
+            .collect(),
+        origin_invalidated: origin_invalidated
+            .into_iter()
+            .map(|(o, n)| (o.to_string(), n.to_string()))
+            .collect(),
+        invalidated_origin_accessed,
+        origin_live: origin_live
+            .into_iter()
+            .map(|(o, n)| (o.to_string(), n.to_string()))
+            .collect(),
+        returned_reference_to_local,
+        loan_conflict,
+        assert_empty_violated,
+    }
+}
@@ -0,0 +1,86 @@
+//! Caches the solver output of each function, keyed on a hash of its facts, so that re-solving
+//! a multi-function input after an edit, e.g. in watch mode or over a corpus, only solves the
+//! functions whose facts changed.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use eyre::WrapErr;
+
+use super::Output;
+use crate::facts::{binary, Facts};
+
+#[cfg(test)]
+mod test;
+
+/// The outputs solved so far, in memory, and optionally in a directory, to be reused by later
+/// runs. The cache doesn't know how the outputs are computed: the `solver` given to
+/// `get_or_solve` is part of the key, and callers solving with e.g. different rules need
+/// different solver names or cache directories.
+#[derive(Clone, Debug, Default)]
+pub struct OutputCache {
+    outputs: HashMap<(String, u64), Output>,
+    dir: Option<PathBuf>,
+    stats: CacheStats,
+}
+
+/// How many outputs were found in the cache, and how many had to be solved.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+}
+
+impl OutputCache {
+    /// A cache which also stores the outputs in `dir`, one subdirectory of `<relation>.csv`
+    /// files per output, as written by `Output::write_to_dir`.
+    pub fn in_dir(dir: impl Into<PathBuf>) -> Self {
+        OutputCache {
+            dir: Some(dir.into()),
+            ..OutputCache::default()
+        }
+    }
+
+    /// Returns the output of `solver` over `facts`, computing it with `solve` unless the same
+    /// solver already solved the same facts.
+    pub fn get_or_solve(
+        &mut self,
+        solver: &str,
+        facts: &Facts,
+        solve: impl FnOnce(&Facts) -> eyre::Result<Output>,
+    ) -> eyre::Result<Output> {
+        let key = (
+            solver.to_string(),
+            binary::content_hash(&binary::encode(facts)),
+        );
+        if let Some(output) = self.outputs.get(&key) {
+            self.stats.hits += 1;
+            return Ok(output.clone());
+        }
+
+        let path = self
+            .dir
+            .as_ref()
+            .map(|dir| dir.join(format!("{}-{:016x}", key.0, key.1)));
+        // An incomplete directory, e.g. written by an interrupted run, is solved again.
+        if let Some(output) = path.as_ref().and_then(|p| Output::read_from_dir(p).ok()) {
+            self.stats.hits += 1;
+            self.outputs.insert(key, output.clone());
+            return Ok(output);
+        }
+
+        self.stats.misses += 1;
+        let output = solve(facts)?;
+        if let Some(path) = path {
+            std::fs::create_dir_all(&path)
+                .wrap_err_with(|| format!("failed to create `{}`", path.display()))?;
+            output.write_to_dir(&path)?;
+        }
+        self.outputs.insert(key, output.clone());
+        Ok(output)
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        self.stats
+    }
+}
@@ -0,0 +1,51 @@
+use super::*;
+use crate::solver::check;
+
+fn facts(program: &str) -> Facts {
+    crate::emit_facts(program, Default::default()).unwrap()
+}
+
+const FIRST: &str = "
+    let mut x: i32;
+    let r: &'r i32;
+    bb0: { r = &'L_x x; x = 1; copy r; }
+";
+
+const SECOND: &str = "
+    let mut x: i32;
+    bb0: { x = 1; }
+";
+
+#[test]
+fn solves_changed_facts_only() {
+    let mut cache = OutputCache::default();
+    let first = cache.get_or_solve("check", &facts(FIRST), check).unwrap();
+    cache.get_or_solve("check", &facts(SECOND), check).unwrap();
+    let again = cache.get_or_solve("check", &facts(FIRST), check).unwrap();
+    assert_eq!(first, again);
+    assert_eq!(cache.stats(), CacheStats { hits: 1, misses: 2 });
+
+    // Another solver doesn't reuse the output.
+    cache.get_or_solve("naive", &facts(FIRST), check).unwrap();
+    assert_eq!(cache.stats(), CacheStats { hits: 1, misses: 3 });
+}
+
+#[test]
+fn reused_across_runs() {
+    let dir = std::env::temp_dir().join(format!("polonius-cache-test-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+
+    let output = OutputCache::in_dir(&dir)
+        .get_or_solve("check", &facts(FIRST), check)
+        .unwrap();
+    let mut cache = OutputCache::in_dir(&dir);
+    let cached = cache
+        .get_or_solve("check", &facts(FIRST), |_| {
+            eyre::bail!("the output should be cached")
+        })
+        .unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(output, cached);
+    assert_eq!(cache.stats(), CacheStats { hits: 1, misses: 0 });
+}
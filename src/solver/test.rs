@@ -0,0 +1,213 @@
+use super::*;
+
+fn errors(output: &Output) -> Vec<String> {
+    output
+        .invalidated_origin_accessed
+        .iter()
+        .map(|(o, n)| format!("{}\t{}", o, n))
+        .collect()
+}
+
+fn check_example(program: &str, expected: &str) {
+    let facts = crate::parse_fact_program(program).unwrap();
+    let output = check(&facts).unwrap();
+    let expected: Vec<_> = expected.lines().map(str::to_string).collect();
+    assert_eq!(errors(&output), expected);
+}
+
+#[test]
+fn example_a() {
+    check_example(
+        include_str!("../../tests/example-a/program.txt"),
+        include_str!("../../tests/example-a/invalidated_origin_accessed.csv"),
+    );
+}
+
+#[test]
+fn vec_temp() {
+    check_example(
+        include_str!("../../tests/vec-temp/program.txt"),
+        include_str!("../../tests/vec-temp/invalidated_origin_accessed.csv"),
+    );
+}
+
+#[test]
+fn killing_and_murder() {
+    check_example(
+        include_str!("../../tests/killing-and-murder/program.txt"),
+        include_str!("../../tests/killing-and-murder/invalidated_origin_accessed.csv"),
+    );
+    check_example(
+        include_str!("../../tests/killing-and-murder-err/program.txt"),
+        include_str!("../../tests/killing-and-murder-err/invalidated_origin_accessed.csv"),
+    );
+}
+
+#[test]
+fn location_insensitive_over_approximates() {
+    let facts =
+        crate::parse_fact_program(include_str!("../../tests/canonical-liveness/program.txt"))
+            .unwrap();
+    assert!(compute(&facts, Algorithm::Optimized)
+        .invalidated_origin_accessed
+        .is_empty());
+    assert_eq!(
+        errors(&compute(&facts, Algorithm::LocationInsensitive)),
+        vec!["'x\tf"]
+    );
+}
+
+#[test]
+fn subset_transitive_closure() {
+    let facts = crate::parse_fact_program(
+        r#"
+        a: "" {
+            introduce_subset('a, 'b)
+            introduce_subset('b, 'c)
+            goto b
+        }
+
+        b: "" {
+            clear_origin('c)
+            goto c
+        }
+
+        c: "" {
+            goto
+        }
+        "#,
+    )
+    .unwrap();
+    let output = check(&facts).unwrap();
+    let subsets: Vec<_> = output
+        .subset
+        .iter()
+        .map(|(o1, o2, n)| format!("{} <= {} @ {}", o1, o2, n))
+        .collect();
+    assert_eq!(
+        subsets,
+        vec![
+            "'a <= 'b @ b",
+            "'a <= 'b @ c",
+            "'a <= 'c @ b",
+            "'b <= 'c @ b"
+        ]
+    );
+}
+
+#[test]
+fn output_round_trip() {
+    let facts = crate::parse_fact_program(include_str!("../../tests/example-a/program.txt"));
+    let output = check(&facts.unwrap()).unwrap();
+
+    let path = std::env::temp_dir().join(format!("polonius-output-{}", std::process::id()));
+    std::fs::create_dir_all(&path).unwrap();
+    output.write_to_dir(&path).unwrap();
+    let read = Output::read_from_dir(&path);
+    std::fs::remove_dir_all(&path).unwrap();
+
+    assert_eq!(read.unwrap(), output);
+}
+
+#[test]
+fn output_diff() {
+    let mut before = Output::default();
+    before
+        .invalidated_origin_accessed
+        .insert(("'a".to_string(), "b".to_string()));
+    before
+        .subset
+        .insert(("'a".to_string(), "'b".to_string(), "c".to_string()));
+    let mut after = before.clone();
+    after.subset.clear();
+    after
+        .returned_reference_to_local
+        .insert(("'r".to_string(), "d".to_string()));
+
+    insta::assert_snapshot!(before.diff(&after).join("\n"), @r###"
+    - subset('a, 'b, c)
+    + returned_reference_to_local('r, d)
+    "###);
+}
+
+#[test]
+fn writes_are_not_uses() {
+    // `'x` is written at `b` before being accessed at `c`, so it isn't live at `a`.
+    let facts = crate::parse_fact_program(
+        "
+        a: \"x = 1\" { goto b }
+        b: \"x = &'L y\" { write_origin('x) clear_origin('x) goto c }
+        c: \"use(x)\" { access_origin('x) goto }
+    ",
+    )
+    .unwrap();
+    let output = compute(&facts, Algorithm::Naive);
+    assert_eq!(
+        output.origin_live,
+        compute(&facts, Algorithm::Optimized).origin_live
+    );
+    insta::assert_debug_snapshot!(output.origin_live, @r###"
+    {
+        (
+            "'x",
+            "c",
+        ),
+    }
+    "###);
+}
+
+#[test]
+fn loan_conflicts() {
+    // `'L1` flows into `'p` at `b`, but `'L2` only flows into `'q` at `c`, where both are live.
+    let facts = crate::parse_fact_program(
+        "
+        a: \"p = &'L1 mut x\" { clear_origin('L1) introduce_subset('L1, 'p) goto b }
+        b: \"q = &'L2 x\" {
+            invalidate_origin('L1)
+            clear_origin('L2)
+            introduce_subset('L2, 'q)
+            incompatible_loans('L1, 'L2)
+            goto c
+        }
+        c: \"use(p, q)\" { access_origin('p) access_origin('q) goto }
+    ",
+    )
+    .unwrap();
+    let output = check(&facts).unwrap();
+    insta::assert_debug_snapshot!(output.loan_conflict, @r###"
+    {
+        (
+            "'L1",
+            "'L2",
+            "c",
+        ),
+    }
+    "###);
+}
+
+#[test]
+fn assert_empty_violations() {
+    // `'L` flows into `'p` at `b`, until `p` is overwritten at `b`.
+    let facts = crate::parse_fact_program(
+        "
+        a: \"p = &'L x\" { assert_empty('p) clear_origin('L) introduce_subset('L, 'p) goto b }
+        b: \"p = 1\" { assert_empty('p) clear_origin('p) goto c }
+        c: \"use(p)\" { assert_empty('p) access_origin('p) goto }
+    ",
+    )
+    .unwrap();
+    let output = compute(&facts, Algorithm::Naive);
+    assert_eq!(
+        output.assert_empty_violated,
+        compute(&facts, Algorithm::Optimized).assert_empty_violated
+    );
+    insta::assert_debug_snapshot!(output.assert_empty_violated, @r###"
+    {
+        (
+            "'p",
+            "'L",
+            "b",
+        ),
+    }
+    "###);
+}
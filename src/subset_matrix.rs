@@ -0,0 +1,96 @@
+//! Exports the `subset` relation as an origins × origins adjacency matrix, which stays readable
+//! when the subset graph is too dense for graphviz, e.g. after instantiating a signature with
+//! many origins.
+
+use std::collections::BTreeSet;
+use std::fmt::Write;
+
+use crate::facts::{Node, Origin};
+
+#[cfg(test)]
+mod test;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SubsetMatrix {
+    /// The origins of the rows and columns, sorted.
+    pub origins: Vec<Origin>,
+
+    /// `counts[i][j]` is the number of nodes where `origins[i]` is a subset of `origins[j]`.
+    pub counts: Vec<Vec<usize>>,
+}
+
+impl SubsetMatrix {
+    /// Builds the matrix of the `subset` tuples at `node`, or aggregated over all the nodes if
+    /// `node` is `None`.
+    pub fn new(subset: &BTreeSet<(Origin, Origin, Node)>, node: Option<&str>) -> Self {
+        let tuples: Vec<_> = subset
+            .iter()
+            .filter(|(.., n)| node.is_none_or(|node| n == node))
+            .collect();
+        let origins: Vec<Origin> = tuples
+            .iter()
+            .flat_map(|(o1, o2, _)| vec![o1.clone(), o2.clone()])
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect();
+
+        let position = |origin: &Origin| origins.binary_search(origin).unwrap();
+        let mut counts = vec![vec![0; origins.len()]; origins.len()];
+        for (o1, o2, _) in tuples {
+            counts[position(o1)][position(o2)] += 1;
+        }
+        SubsetMatrix { origins, counts }
+    }
+
+    /// Comma-separated rows, with a header row and a header column of origin names.
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::new();
+        for origin in &self.origins {
+            write!(csv, ",{}", origin).unwrap();
+        }
+        csv.push('\n');
+        for (origin, row) in self.origins.iter().zip(&self.counts) {
+            csv.push_str(origin);
+            for count in row {
+                write!(csv, ",{}", count).unwrap();
+            }
+            csv.push('\n');
+        }
+        csv
+    }
+
+    /// An HTML table where the darker cells hold the larger counts.
+    pub fn to_html(&self) -> String {
+        let max = self
+            .counts
+            .iter()
+            .flatten()
+            .copied()
+            .max()
+            .unwrap_or(0)
+            .max(1);
+        let escape = |origin: &str| html_escape::encode_text(origin).to_string();
+
+        let mut html = String::from("<table>\n<tr><th>⊆</th>");
+        for origin in &self.origins {
+            write!(html, "<th>{}</th>", escape(origin)).unwrap();
+        }
+        html.push_str("</tr>\n");
+        for (origin, row) in self.origins.iter().zip(&self.counts) {
+            write!(html, "<tr><th>{}</th>", escape(origin)).unwrap();
+            for &count in row {
+                // From white to red, as the count grows.
+                let shade = 255 - 255 * count / max;
+                write!(
+                    html,
+                    "<td style=\"background-color: rgb(255, {0}, {0})\">{1}</td>",
+                    shade, count
+                )
+                .unwrap();
+            }
+            html.push_str("</tr>\n");
+        }
+        html.push_str("</table>\n");
+        html
+    }
+}
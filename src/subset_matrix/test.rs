@@ -0,0 +1,45 @@
+use super::*;
+
+fn subsets() -> BTreeSet<(Origin, Origin, Node)> {
+    [("'a", "'b", "n1"), ("'a", "'b", "n2"), ("'b", "'c", "n2")]
+        .iter()
+        .map(|&(o1, o2, n)| (o1.to_string(), o2.to_string(), n.to_string()))
+        .collect()
+}
+
+#[test]
+fn aggregated_csv() {
+    let matrix = SubsetMatrix::new(&subsets(), None);
+    insta::assert_snapshot!(matrix.to_csv(), @r###"
+    ,'a,'b,'c
+    'a,0,2,0
+    'b,0,0,1
+    'c,0,0,0
+
+    "###);
+}
+
+#[test]
+fn per_node_csv() {
+    let matrix = SubsetMatrix::new(&subsets(), Some("n1"));
+    insta::assert_snapshot!(matrix.to_csv(), @r###"
+    ,'a,'b
+    'a,0,1
+    'b,0,0
+
+    "###);
+}
+
+#[test]
+fn html_heatmap() {
+    let matrix = SubsetMatrix::new(&subsets(), None);
+    insta::assert_snapshot!(matrix.to_html(), @r###"
+    <table>
+    <tr><th>⊆</th><th>'a</th><th>'b</th><th>'c</th></tr>
+    <tr><th>'a</th><td style="background-color: rgb(255, 255, 255)">0</td><td style="background-color: rgb(255, 0, 0)">2</td><td style="background-color: rgb(255, 255, 255)">0</td></tr>
+    <tr><th>'b</th><td style="background-color: rgb(255, 255, 255)">0</td><td style="background-color: rgb(255, 255, 255)">0</td><td style="background-color: rgb(255, 128, 128)">1</td></tr>
+    <tr><th>'c</th><td style="background-color: rgb(255, 255, 255)">0</td><td style="background-color: rgb(255, 255, 255)">0</td><td style="background-color: rgb(255, 255, 255)">0</td></tr>
+    </table>
+
+    "###);
+}
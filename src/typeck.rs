@@ -0,0 +1,1009 @@
+//! Computes the types of places and expressions in a program.
+//!
+//! Places are written `x.f.g`. A dereference is written either as a `*` field (`x.*.f` is
+//! `(*x).f`) or, like in Rust, as a `*` prefix on the whole place (`*x.f` is `*(x.f)`).
+//! Also like in Rust, a field of a reference to a struct is a field of the struct: `x.f` is
+//! `(*x).f` when `x` is a reference, and the dereference is implicit.
+//!
+//! `Option` and `Result` are builtin enums, see `BUILTIN_ENUMS`.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::OnceLock;
+
+use eyre::WrapErr;
+
+use crate::ast::visit::{walk_fn_prototype_mut, walk_struct_decl_mut, ProgramVisitorMut};
+use crate::ast::{self, Expr, GenericDecl, Name, Parameter, Place, Ty};
+use crate::features::{Feature, Unsupported};
+
+/// The enums known to every program, until programs can declare their own, along with their
+/// variants. The payload of a variant is a place, named after the variant, of the enum value:
+/// `x.Some` is the payload of `x` as a `Some`, like the downcast `(x as Some).0` of MIR. The
+/// payloads of different variants are disjoint places, so moving out of one is a partial move
+/// which leaves the loans of the others alone.
+pub const BUILTIN_ENUMS: &[(&str, &[&str])] =
+    &[("Option", &["Some", "None"]), ("Result", &["Ok", "Err"])];
+
+/// The payloads of the builtin enums, as the fields of a struct. A program declaring a struct
+/// or an extern type with the same name as a builtin enum shadows it.
+fn builtin_enum_decls() -> &'static [ast::StructDecl] {
+    static DECLS: OnceLock<Vec<ast::StructDecl>> = OnceLock::new();
+    DECLS.get_or_init(|| {
+        crate::parse_ast("struct Option<T> { Some: T } struct Result<T, E> { Ok: T, Err: E }")
+            .expect("the builtin enums should parse")
+            .struct_decls
+    })
+}
+
+/// The fields a struct literal takes from its base, see `TypeEnv::base_fields`.
+pub struct BaseFields<'e, 'p> {
+    pub place: &'e Place,
+    pub kind: ast::AccessKind,
+
+    /// The fields not given by the literal, with their types in the base.
+    pub fields: Vec<(&'p Name, Ty)>,
+}
+
+pub struct TypeEnv<'p> {
+    program: &'p ast::Program,
+}
+
+/// One step of a place projection.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Projection<'a> {
+    Field(&'a str),
+    Deref,
+}
+
+impl<'p> TypeEnv<'p> {
+    pub fn new(program: &'p ast::Program) -> Self {
+        Self { program }
+    }
+
+    pub fn is_extern_type(&self, name: &str) -> bool {
+        self.program.extern_types.iter().any(|t| t == name)
+    }
+
+    /// The declaration of the struct `name`, or the payloads of the builtin enum `name`.
+    pub fn struct_decl(&self, name: &str) -> eyre::Result<&'p ast::StructDecl> {
+        self.program
+            .struct_decls
+            .iter()
+            .chain(builtin_enum_decls())
+            .find(|s| s.name == name)
+            .ok_or_else(|| eyre::eyre!("no struct named `{}`", name))
+    }
+
+    /// The variants of `name`, if it is a builtin enum the program doesn't shadow.
+    pub fn enum_variants(&self, name: &str) -> Option<&'static [&'static str]> {
+        let shadowed =
+            self.is_extern_type(name) || self.program.struct_decls.iter().any(|s| s.name == name);
+        BUILTIN_ENUMS
+            .iter()
+            .find(|(n, _)| *n == name && !shadowed)
+            .map(|(_, variants)| *variants)
+    }
+
+    /// Returns the type of the value built by the variant `variant`, whose payload, if it has
+    /// one, is of type `payload_ty`. The type parameters of the enum are inferred from the
+    /// payload, and then from the `expected` type: the type of a `None`, or the error type of
+    /// an `Ok`, only comes from the place the value is stored in.
+    pub fn variant_ty(
+        &self,
+        variant: &str,
+        payload_ty: Option<&Ty>,
+        expected: Option<&Ty>,
+    ) -> eyre::Result<Ty> {
+        let name = BUILTIN_ENUMS
+            .iter()
+            .find(|(_, variants)| variants.contains(&variant))
+            .map(|(name, _)| *name)
+            .ok_or_else(|| eyre::eyre!("no variant named `{}`", variant))?;
+        if self.enum_variants(name).is_none() {
+            eyre::bail!(
+                "`{}` is a variant of the builtin `{}`, which the program shadows",
+                variant,
+                name
+            );
+        }
+        let decl = self.struct_decl(name)?;
+        let payload = decl.field_decls.iter().find(|f| f.name == variant);
+
+        let mut substitution = HashMap::new();
+        match (payload, payload_ty) {
+            (Some(payload), Some(payload_ty)) => infer_ty_generics(
+                &decl.generic_decls,
+                &payload.ty,
+                payload_ty,
+                &mut substitution,
+            ),
+            (None, None) => {}
+            (Some(_), None) => eyre::bail!("`{}` expects a value", variant),
+            (None, Some(_)) => eyre::bail!("`{}` has no value", variant),
+        }
+        if let Some(expected) = expected {
+            let ty = Ty::Struct {
+                name: name.to_string(),
+                parameters: ty_generics(&decl.generic_decls)
+                    .into_iter()
+                    .map(|generic| {
+                        Parameter::Ty(Ty::Struct {
+                            name: generic,
+                            parameters: vec![],
+                        })
+                    })
+                    .collect(),
+            };
+            infer_ty_generics(&decl.generic_decls, &ty, expected, &mut substitution);
+        }
+
+        let parameters = ty_generics(&decl.generic_decls)
+            .into_iter()
+            .map(|generic| {
+                substitution.remove(&generic).ok_or_else(|| {
+                    eyre::eyre!(
+                        "cannot infer type parameter `{}` of `{}` in a `{}`",
+                        generic,
+                        name,
+                        variant
+                    )
+                })
+            })
+            .collect::<eyre::Result<_>>()?;
+        Ok(Ty::Struct {
+            name: name.to_string(),
+            parameters,
+        })
+    }
+
+    pub fn fn_prototype(&self, name: &str) -> eyre::Result<&'p ast::FnPrototype> {
+        self.program
+            .fn_prototypes
+            .iter()
+            .find(|f| f.name == name)
+            .ok_or_else(|| eyre::eyre!("no function named `{}`", name))
+    }
+
+    pub fn variables(&self) -> &'p [ast::VariableDecl] {
+        &self.program.variables
+    }
+
+    pub fn variable_ty(&self, name: &str) -> eyre::Result<&'p Ty> {
+        self.program
+            .variables
+            .iter()
+            .find(|v| v.name == name)
+            .map(|v| &v.ty)
+            .ok_or_else(|| eyre::eyre!("no variable named `{}`", name))
+    }
+
+    /// Returns the type of each field of the struct type `ty`, with the struct's generic
+    /// parameters substituted. Extern types are opaque, and have no fields.
+    pub fn field_tys(&self, ty: &Ty) -> eyre::Result<Vec<(&'p Name, Ty)>> {
+        let (name, parameters) = match ty {
+            Ty::Struct { name, parameters } => (name, parameters),
+            _ => return Ok(vec![]),
+        };
+        if self.is_extern_type(name) {
+            return Ok(vec![]);
+        }
+
+        let decl = self.struct_decl(name)?;
+        let substitution = substitution(&decl.generic_decls, parameters)?;
+        decl.field_decls
+            .iter()
+            .map(|field| Ok((&field.name, subst_ty(&field.ty, &substitution)?)))
+            .collect()
+    }
+
+    /// Returns the subsets required by the bounds of the struct types in `ty`: for a bound
+    /// `T: 'a`, every origin in the type substituted for `T` must be a subset of the origin
+    /// substituted for `'a`. Each subset is returned along with the struct type requiring it.
+    pub fn outlives_bounds(&self, ty: &Ty) -> eyre::Result<Vec<(Name, Name, Ty)>> {
+        let mut subsets = vec![];
+        let mut stack = vec![ty];
+        while let Some(ty) = stack.pop() {
+            match ty {
+                Ty::Ref { ty, .. } | Ty::RefMut { ty, .. } => stack.push(ty),
+                Ty::I32 | Ty::Unit | Ty::Fn { .. } => {}
+                Ty::Struct { name, parameters } => {
+                    stack.extend(parameters.iter().rev().filter_map(|p| match p {
+                        Parameter::Ty(ty) => Some(ty),
+                        Parameter::Origin(_) => None,
+                    }));
+                    let decl = match self.struct_decl(name) {
+                        Ok(decl) if !parameters.is_empty() => decl,
+                        _ => continue,
+                    };
+
+                    let substitution = substitution(&decl.generic_decls, parameters)?;
+                    for generic in &decl.generic_decls {
+                        let (generic, bounds) = match generic {
+                            GenericDecl::Ty(generic, bounds) => (generic, bounds),
+                            GenericDecl::Origin(_) => continue,
+                        };
+                        let origins = match &substitution[generic] {
+                            Parameter::Ty(ty) => origins_of_ty(ty),
+                            Parameter::Origin(origin) => vec![origin],
+                        };
+                        for bound in bounds {
+                            let bound = match substitution.get(bound) {
+                                Some(Parameter::Origin(origin)) => origin,
+                                Some(Parameter::Ty(_)) => {
+                                    return Err(Unsupported {
+                                        feature: Feature::TypeBounds,
+                                        span: format!("{}: {}", generic, bound),
+                                    }
+                                    .into())
+                                }
+                                None => bound,
+                            };
+                            for origin in &origins {
+                                subsets.push(((*origin).clone(), bound.clone(), ty.clone()));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        Ok(subsets)
+    }
+
+    /// Returns the origins a destructor may observe when a value of type `ty` is dropped: every
+    /// origin of a struct type with a destructor, and the drop origins of the fields of the
+    /// other structs, including the payloads of the builtin enums. Dropping a reference, or a
+    /// value of an extern type, runs no destructor. Like a `Drop` impl without `#[may_dangle]`,
+    /// a destructor may observe all the origins of its type, including the ones of its type
+    /// parameters.
+    pub fn drop_origins(&self, ty: &Ty) -> eyre::Result<Vec<Name>> {
+        let mut origins = vec![];
+        let mut visited = HashSet::new();
+        let mut stack = vec![ty.clone()];
+        while let Some(ty) = stack.pop() {
+            let name = match &ty {
+                Ty::Struct { name, .. } if !self.is_extern_type(name) => name,
+                _ => continue,
+            };
+            let decl = match self.struct_decl(name) {
+                Ok(decl) => decl,
+                Err(_) => continue,
+            };
+            if !visited.insert(ty.clone()) {
+                continue;
+            }
+            if decl.has_destructor {
+                origins.extend(origins_of_ty(&ty).into_iter().cloned());
+            } else {
+                let fields = self.field_tys(&ty)?;
+                stack.extend(fields.into_iter().rev().map(|(_, field_ty)| field_ty));
+            }
+        }
+        let mut unique = vec![];
+        for origin in origins {
+            if !unique.contains(&origin) {
+                unique.push(origin);
+            }
+        }
+        Ok(unique)
+    }
+
+    /// Returns the type of `field` in the struct type `ty`.
+    pub fn field_ty(&self, ty: &Ty, field: &str) -> eyre::Result<Ty> {
+        if let Ty::Struct { name, .. } = ty {
+            if self.is_extern_type(name) {
+                eyre::bail!("cannot access field `{}` of opaque type `{}`", field, name);
+            }
+        }
+
+        self.field_tys(ty)?
+            .into_iter()
+            .find(|(name, _)| *name == field)
+            .map(|(_, ty)| ty)
+            .ok_or_else(|| eyre::eyre!("type `{:?}` has no field `{}`", ty, field))
+    }
+
+    /// Returns the types of every prefix of `place`, starting with the type of the base
+    /// variable and ending with the type of the place itself, along with the projection
+    /// that was applied to reach each of them. The implicit dereferences of references whose
+    /// fields are accessed are included, as `Deref` projections.
+    pub fn place_prefix_tys<'a>(
+        &self,
+        place: &'a Place,
+    ) -> eyre::Result<Vec<(Option<Projection<'a>>, Ty)>> {
+        let (base, projections) = projections(place);
+        self.projection_tys(base, projections)
+    }
+
+    /// Like `place_prefix_tys`, for a place already split into its base and projections.
+    pub fn projection_tys<'a>(
+        &self,
+        base: &str,
+        projections: Vec<Projection<'a>>,
+    ) -> eyre::Result<Vec<(Option<Projection<'a>>, Ty)>> {
+        let mut tys = vec![(None, self.variable_ty(base)?.clone())];
+        for projection in projections {
+            if let Projection::Field(_) = projection {
+                while let Ty::Ref { ty, .. } | Ty::RefMut { ty, .. } = &tys.last().unwrap().1 {
+                    let ty = (**ty).clone();
+                    tys.push((Some(Projection::Deref), ty));
+                }
+            }
+            let prev = &tys.last().unwrap().1;
+            let ty = match projection {
+                Projection::Field(field) => self.field_ty(prev, field)?,
+                Projection::Deref => match prev {
+                    Ty::Ref { ty, .. } | Ty::RefMut { ty, .. } => (**ty).clone(),
+                    _ => eyre::bail!("cannot dereference `{}`, of type `{:?}`", base, prev),
+                },
+            };
+            tys.push((Some(projection), ty));
+        }
+        Ok(tys)
+    }
+
+    pub fn place_ty(&self, place: &Place) -> eyre::Result<Ty> {
+        Ok(self.place_prefix_tys(place)?.pop().unwrap().1)
+    }
+
+    /// Returns the type of `expr`. The origins in the result are the ones the expression
+    /// was written with, so callers that need a fresh type should use `freshen_ty`.
+    pub fn expr_ty(&self, expr: &Expr) -> eyre::Result<Ty> {
+        Ok(match expr {
+            Expr::Access { kind, place } => {
+                let ty = self.place_ty(place)?;
+                match kind {
+                    ast::AccessKind::Copy | ast::AccessKind::Move => ty,
+                    ast::AccessKind::Borrow(origin) => Ty::Ref {
+                        origin: origin.clone(),
+                        ty: Box::new(ty),
+                    },
+                    ast::AccessKind::BorrowMut(origin) => Ty::RefMut {
+                        origin: origin.clone(),
+                        ty: Box::new(ty),
+                    },
+                }
+            }
+            Expr::Number { .. } => Ty::I32,
+            Expr::Unit => Ty::Unit,
+            Expr::Call { name, arguments } => {
+                let arg_tys = arguments
+                    .iter()
+                    .map(|argument| self.expr_ty(argument))
+                    .collect::<eyre::Result<Vec<_>>>()?;
+                self.instantiate_signature(name, &arg_tys, None, |origin| origin.clone())?
+                    .1
+            }
+            Expr::Variant { name, value } => {
+                let payload_ty = value.as_ref().map(|v| self.expr_ty(v)).transpose()?;
+                self.variant_ty(name, payload_ty.as_ref(), None)?
+            }
+            Expr::Struct { name, fields, base } => {
+                let mut field_tys = fields
+                    .iter()
+                    .map(|(field, value)| Ok((field, self.expr_ty(value)?)))
+                    .collect::<eyre::Result<Vec<_>>>()?;
+                if let Some(base) = base {
+                    field_tys.extend(self.base_fields(name, fields, base)?.fields);
+                }
+                self.instantiate_struct(name, &field_tys, None, |origin| origin.clone())?
+                    .1
+            }
+        })
+    }
+
+    /// Returns the fields a literal of the struct `name` takes from its `base`, i.e. the ones
+    /// not in `fields`. The base must be a place of the same struct, moved or copied.
+    pub fn base_fields<'e>(
+        &self,
+        name: &str,
+        fields: &[(Name, Expr)],
+        base: &'e Expr,
+    ) -> eyre::Result<BaseFields<'e, 'p>> {
+        let (kind, place) = match base {
+            Expr::Access {
+                kind: kind @ (ast::AccessKind::Copy | ast::AccessKind::Move),
+                place,
+            } => (kind.clone(), place),
+            _ => eyre::bail!("the base `{}` is not a moved or copied place", base),
+        };
+        let ty = self.place_ty(place)?;
+        if !matches!(&ty, Ty::Struct { name: n, .. } if n == name) {
+            eyre::bail!("the base `{}` is a `{}`, not a `{}`", place, ty, name);
+        }
+        let remaining = self
+            .field_tys(&ty)?
+            .into_iter()
+            .filter(|(field, _)| fields.iter().all(|(f, _)| f != *field))
+            .collect();
+        Ok(BaseFields {
+            place,
+            kind,
+            fields: remaining,
+        })
+    }
+
+    /// Instantiates the struct `name` for a literal whose fields are of types `field_tys`,
+    /// like a call to its constructor: the type generics are inferred from the field types,
+    /// and then from the `expected` type if there is one, and each origin generic is replaced
+    /// by `instantiate_origin(origin)`. Every field must be given exactly once. Returns the
+    /// instantiated type of each field, in the order of `field_tys`, and the struct type.
+    pub fn instantiate_struct(
+        &self,
+        name: &str,
+        field_tys: &[(&Name, Ty)],
+        expected: Option<&Ty>,
+        mut instantiate_origin: impl FnMut(&Name) -> Name,
+    ) -> eyre::Result<(Vec<Ty>, Ty)> {
+        if self.is_extern_type(name) {
+            eyre::bail!("cannot build a value of the opaque type `{}`", name);
+        }
+        if self.enum_variants(name).is_some() {
+            eyre::bail!(
+                "`{}` is an enum, its values are built by its variants",
+                name
+            );
+        }
+        let decl = self.struct_decl(name)?;
+        for (i, (field, _)) in field_tys.iter().enumerate() {
+            if decl.field_decls.iter().all(|f| f.name != **field) {
+                eyre::bail!("`{}` has no field `{}`", name, field);
+            }
+            if field_tys[..i].iter().any(|(f, _)| f == field) {
+                eyre::bail!("the field `{}` is given twice", field);
+            }
+        }
+        if let Some(missing) = decl
+            .field_decls
+            .iter()
+            .find(|f| field_tys.iter().all(|(field, _)| **field != f.name))
+        {
+            eyre::bail!(
+                "missing field `{}` in a literal of `{}`",
+                missing.name,
+                name
+            );
+        }
+
+        let mut substitution = HashMap::new();
+        for (field, ty) in field_tys {
+            let decl_ty = &decl
+                .field_decls
+                .iter()
+                .find(|f| f.name == **field)
+                .unwrap()
+                .ty;
+            infer_ty_generics(&decl.generic_decls, decl_ty, ty, &mut substitution);
+        }
+        let generic_ty = Ty::Struct {
+            name: name.to_string(),
+            parameters: decl
+                .generic_decls
+                .iter()
+                .map(|generic| match generic {
+                    GenericDecl::Origin(origin) => Parameter::Origin(origin.clone()),
+                    GenericDecl::Ty(generic, _) => Parameter::Ty(Ty::Struct {
+                        name: generic.clone(),
+                        parameters: vec![],
+                    }),
+                })
+                .collect(),
+        };
+        if let Some(expected) = expected {
+            infer_ty_generics(
+                &decl.generic_decls,
+                &generic_ty,
+                expected,
+                &mut substitution,
+            );
+        }
+        for generic in &decl.generic_decls {
+            match generic {
+                GenericDecl::Ty(generic, _) => {
+                    if !substitution.contains_key(generic) {
+                        eyre::bail!("cannot infer type parameter `{}` of `{}`", generic, name);
+                    }
+                }
+                GenericDecl::Origin(origin) => {
+                    let instance = Parameter::Origin(instantiate_origin(origin));
+                    substitution.insert(origin.clone(), instance);
+                }
+            }
+        }
+
+        let instantiated = field_tys
+            .iter()
+            .map(|(field, _)| {
+                let decl_ty = &decl
+                    .field_decls
+                    .iter()
+                    .find(|f| f.name == **field)
+                    .unwrap()
+                    .ty;
+                subst_ty(decl_ty, &substitution)
+            })
+            .collect::<eyre::Result<_>>()?;
+        Ok((instantiated, subst_ty(&generic_ty, &substitution)?))
+    }
+
+    /// Instantiates the signature of the function `name` for a call with arguments of types
+    /// `arg_tys`: the type generics are inferred from the argument types, and then from the
+    /// `expected` return type if there is one, and each origin generic is replaced by
+    /// `instantiate_origin(origin)`. Returns the instantiated parameter and return types.
+    pub fn instantiate_signature(
+        &self,
+        name: &str,
+        arg_tys: &[Ty],
+        expected: Option<&Ty>,
+        mut instantiate_origin: impl FnMut(&Name) -> Name,
+    ) -> eyre::Result<(Vec<Ty>, Ty)> {
+        let prototype = self.fn_prototype(name)?;
+        if prototype.arg_tys.len() != arg_tys.len() {
+            eyre::bail!(
+                "`{}` expects {} arguments, but {} were given",
+                name,
+                prototype.arg_tys.len(),
+                arg_tys.len()
+            );
+        }
+
+        // Infer the type generics from the argument types.
+        let mut substitution = HashMap::new();
+        for (param_ty, arg_ty) in prototype.arg_tys.iter().zip(arg_tys) {
+            infer_ty_generics(
+                &prototype.generic_decls,
+                param_ty,
+                arg_ty,
+                &mut substitution,
+            );
+        }
+        if let Some(expected) = expected {
+            infer_ty_generics(
+                &prototype.generic_decls,
+                &prototype.ret_ty,
+                expected,
+                &mut substitution,
+            );
+        }
+        for generic in &prototype.generic_decls {
+            match generic {
+                GenericDecl::Ty(generic, _) => {
+                    if !substitution.contains_key(generic) {
+                        eyre::bail!("cannot infer type parameter `{}` of `{}`", generic, name);
+                    }
+                }
+                GenericDecl::Origin(origin) => {
+                    let instance = Parameter::Origin(instantiate_origin(origin));
+                    substitution.insert(origin.clone(), instance);
+                }
+            }
+        }
+
+        let param_tys = prototype
+            .arg_tys
+            .iter()
+            .map(|ty| subst_ty(ty, &substitution))
+            .collect::<eyre::Result<_>>()?;
+        Ok((param_tys, subst_ty(&prototype.ret_ty, &substitution)?))
+    }
+}
+
+/// Replaces every use of a type alias in the types of `program` by the aliased type, with the
+/// alias' generics substituted, and removes the aliases. Aliases can use other aliases, but
+/// not themselves.
+pub fn expand_type_aliases(program: &mut ast::Program) -> eyre::Result<()> {
+    let aliases = std::mem::take(&mut program.type_aliases);
+    for (i, alias) in aliases.iter().enumerate() {
+        if let Some(first) = aliases[..i].iter().find(|a| a.name == alias.name) {
+            eyre::bail!(
+                "the type alias `{}` is declared twice, as `{}` and as `{}`",
+                alias.name,
+                first,
+                alias
+            );
+        }
+        if program.struct_decls.iter().any(|s| s.name == alias.name)
+            || program.extern_types.contains(&alias.name)
+        {
+            eyre::bail!(
+                "`{}` is declared both as a type and as a type alias",
+                alias.name
+            );
+        }
+        let mut declared = vec![];
+        for generic in &alias.generic_decls {
+            match generic {
+                GenericDecl::Origin(origin) => declared.push(origin),
+                GenericDecl::Ty(generic, bounds) if !bounds.is_empty() => eyre::bail!(
+                    "bounds on the type parameters of type aliases are not supported, as on `{}` \
+                     of `{}`",
+                    generic,
+                    alias.name
+                ),
+                GenericDecl::Ty(..) => {}
+            }
+        }
+        if let Some(origin) = origins_of_ty(&alias.ty)
+            .into_iter()
+            .find(|origin| !declared.contains(origin))
+        {
+            eyre::bail!(
+                "the type alias `{}` names the undeclared origin `{}`",
+                alias.name,
+                origin
+            );
+        }
+    }
+    if aliases.is_empty() {
+        return Ok(());
+    }
+
+    let mut expander = AliasExpander {
+        aliases: &aliases,
+        shadowed: vec![],
+    };
+    expander.visit_program(program)
+}
+
+/// The names of the type parameters in `generic_decls`, which shadow the aliases.
+fn ty_generics(generic_decls: &[GenericDecl]) -> Vec<Name> {
+    generic_decls
+        .iter()
+        .filter_map(|generic| match generic {
+            GenericDecl::Ty(name, _) => Some(name.clone()),
+            GenericDecl::Origin(_) => None,
+        })
+        .collect()
+}
+
+struct AliasExpander<'a> {
+    aliases: &'a [ast::TypeAlias],
+
+    /// The type parameters of the declaration being visited.
+    shadowed: Vec<Name>,
+}
+
+impl ProgramVisitorMut for AliasExpander<'_> {
+    fn visit_struct_decl(&mut self, decl: &mut ast::StructDecl) -> eyre::Result<()> {
+        self.shadowed = ty_generics(&decl.generic_decls);
+        walk_struct_decl_mut(self, decl)?;
+        self.shadowed.clear();
+        Ok(())
+    }
+
+    fn visit_fn_prototype(&mut self, prototype: &mut ast::FnPrototype) -> eyre::Result<()> {
+        self.shadowed = ty_generics(&prototype.generic_decls);
+        walk_fn_prototype_mut(self, prototype)?;
+        self.shadowed.clear();
+        Ok(())
+    }
+
+    fn visit_ty(&mut self, ty: &mut Ty) -> eyre::Result<()> {
+        *ty = self.expand(ty, &self.shadowed, &mut vec![])?;
+        Ok(())
+    }
+
+    /// Statements don't contain types.
+    fn visit_basic_block(&mut self, _block: &mut ast::BasicBlock) -> eyre::Result<()> {
+        Ok(())
+    }
+}
+
+impl AliasExpander<'_> {
+    /// Expands the aliases in `ty`, except for the names in `shadowed`. The `expanding` stack
+    /// holds the aliases whose expansion contains `ty`, to detect recursive aliases.
+    fn expand(&self, ty: &Ty, shadowed: &[Name], expanding: &mut Vec<Name>) -> eyre::Result<Ty> {
+        let expand_all = |tys: &[Ty], expanding: &mut Vec<Name>| {
+            tys.iter()
+                .map(|ty| self.expand(ty, shadowed, expanding))
+                .collect::<eyre::Result<Vec<_>>>()
+        };
+
+        Ok(match ty {
+            Ty::Ref { origin, ty } => Ty::Ref {
+                origin: origin.clone(),
+                ty: Box::new(self.expand(ty, shadowed, expanding)?),
+            },
+            Ty::RefMut { origin, ty } => Ty::RefMut {
+                origin: origin.clone(),
+                ty: Box::new(self.expand(ty, shadowed, expanding)?),
+            },
+            Ty::I32 => Ty::I32,
+            Ty::Unit => Ty::Unit,
+            Ty::Fn {
+                bound_origins,
+                arg_tys,
+                ret_ty,
+            } => Ty::Fn {
+                bound_origins: bound_origins.clone(),
+                arg_tys: expand_all(arg_tys, expanding)?,
+                ret_ty: Box::new(self.expand(ret_ty, shadowed, expanding)?),
+            },
+            Ty::Struct { name, parameters } => {
+                let parameters = parameters
+                    .iter()
+                    .map(|parameter| {
+                        Ok(match parameter {
+                            Parameter::Origin(o) => Parameter::Origin(o.clone()),
+                            Parameter::Ty(ty) => {
+                                Parameter::Ty(self.expand(ty, shadowed, expanding)?)
+                            }
+                        })
+                    })
+                    .collect::<eyre::Result<Vec<_>>>()?;
+                let alias = match self.aliases.iter().find(|alias| alias.name == *name) {
+                    Some(alias) if !shadowed.contains(name) => alias,
+                    _ => {
+                        return Ok(Ty::Struct {
+                            name: name.clone(),
+                            parameters,
+                        })
+                    }
+                };
+                if expanding.contains(name) {
+                    eyre::bail!("the type alias `{}` is recursive", name);
+                }
+
+                let substitution = substitution(&alias.generic_decls, &parameters)
+                    .wrap_err_with(|| format!("in a use of the type alias `{}`", name))?;
+                let aliased = subst_ty(&alias.ty, &substitution)?;
+                expanding.push(name.clone());
+                let expanded = self.expand(&aliased, shadowed, expanding)?;
+                expanding.pop();
+                expanded
+            }
+        })
+    }
+}
+
+/// Splits a place into its base variable and the list of projections applied to it.
+pub fn projections(place: &Place) -> (&str, Vec<Projection<'_>>) {
+    let base = place.base.trim_start_matches('*');
+    let prefix_derefs = place.base.len() - base.len();
+
+    let mut projections: Vec<_> = place
+        .fields
+        .iter()
+        .map(|field| match field.as_str() {
+            "*" => Projection::Deref,
+            field => Projection::Field(field),
+        })
+        .collect();
+    projections.extend(std::iter::repeat_n(Projection::Deref, prefix_derefs));
+    (base, projections)
+}
+
+/// Maps each generic declared in `generic_decls` to the corresponding entry in `parameters`.
+pub fn substitution(
+    generic_decls: &[GenericDecl],
+    parameters: &[Parameter],
+) -> eyre::Result<HashMap<Name, Parameter>> {
+    if generic_decls.len() != parameters.len() {
+        eyre::bail!(
+            "expected {} generic parameters, found {}",
+            generic_decls.len(),
+            parameters.len()
+        );
+    }
+
+    generic_decls
+        .iter()
+        .zip(parameters)
+        .map(|(decl, parameter)| match (decl, parameter) {
+            (GenericDecl::Origin(name), Parameter::Origin(_))
+            | (GenericDecl::Ty(name, _), Parameter::Ty(_)) => Ok((name.clone(), parameter.clone())),
+            (GenericDecl::Origin(name), Parameter::Ty(ty)) => {
+                eyre::bail!("expected an origin for `{}`, found type `{:?}`", name, ty)
+            }
+            (GenericDecl::Ty(name, _), Parameter::Origin(origin)) => {
+                eyre::bail!("expected a type for `{}`, found origin `{}`", name, origin)
+            }
+        })
+        .collect()
+}
+
+/// Replaces the generic origins and types in `ty` according to `substitution`.
+pub fn subst_ty(ty: &Ty, substitution: &HashMap<Name, Parameter>) -> eyre::Result<Ty> {
+    let subst_origin = |origin: &Name| -> eyre::Result<Name> {
+        match substitution.get(origin) {
+            None => Ok(origin.clone()),
+            Some(Parameter::Origin(o)) => Ok(o.clone()),
+            Some(Parameter::Ty(ty)) => {
+                eyre::bail!("expected an origin for `{}`, found type `{:?}`", origin, ty)
+            }
+        }
+    };
+
+    Ok(match ty {
+        Ty::Ref { origin, ty } => Ty::Ref {
+            origin: subst_origin(origin)?,
+            ty: Box::new(subst_ty(ty, substitution)?),
+        },
+        Ty::RefMut { origin, ty } => Ty::RefMut {
+            origin: subst_origin(origin)?,
+            ty: Box::new(subst_ty(ty, substitution)?),
+        },
+        Ty::I32 => Ty::I32,
+        Ty::Unit => Ty::Unit,
+        Ty::Struct { name, parameters } if parameters.is_empty() => match substitution.get(name) {
+            Some(Parameter::Ty(ty)) => ty.clone(),
+            Some(Parameter::Origin(o)) => {
+                eyre::bail!("expected a type for `{}`, found origin `{}`", name, o)
+            }
+            None => ty.clone(),
+        },
+        Ty::Struct { name, parameters } => Ty::Struct {
+            name: name.clone(),
+            parameters: parameters
+                .iter()
+                .map(|parameter| {
+                    Ok(match parameter {
+                        Parameter::Origin(o) => Parameter::Origin(subst_origin(o)?),
+                        Parameter::Ty(ty) => Parameter::Ty(subst_ty(ty, substitution)?),
+                    })
+                })
+                .collect::<eyre::Result<_>>()?,
+        },
+        Ty::Fn {
+            bound_origins,
+            arg_tys,
+            ret_ty,
+        } => {
+            // The bound origins shadow the generics with the same name.
+            let mut substitution = substitution.clone();
+            for origin in bound_origins {
+                substitution.remove(origin);
+            }
+            Ty::Fn {
+                bound_origins: bound_origins.clone(),
+                arg_tys: arg_tys
+                    .iter()
+                    .map(|ty| subst_ty(ty, &substitution))
+                    .collect::<eyre::Result<_>>()?,
+                ret_ty: Box::new(subst_ty(ret_ty, &substitution)?),
+            }
+        }
+    })
+}
+
+/// Binds the type generics of `generic_decls` appearing in `pattern` by structurally matching
+/// it against `ty`. Mismatches are ignored, they are reported when relating the types.
+fn infer_ty_generics(
+    generic_decls: &[GenericDecl],
+    pattern: &Ty,
+    ty: &Ty,
+    substitution: &mut HashMap<Name, Parameter>,
+) {
+    match (pattern, ty) {
+        (Ty::Struct { name, parameters }, _)
+            if parameters.is_empty()
+                && generic_decls
+                    .iter()
+                    .any(|g| matches!(g, GenericDecl::Ty(n, _) if n == name)) =>
+        {
+            substitution
+                .entry(name.clone())
+                .or_insert_with(|| Parameter::Ty(ty.clone()));
+        }
+        (Ty::Ref { ty: p, .. }, Ty::Ref { ty: t, .. })
+        | (Ty::RefMut { ty: p, .. }, Ty::RefMut { ty: t, .. })
+        | (Ty::Ref { ty: p, .. }, Ty::RefMut { ty: t, .. }) => {
+            infer_ty_generics(generic_decls, p, t, substitution)
+        }
+        (
+            Ty::Struct {
+                name: n1,
+                parameters: p1,
+            },
+            Ty::Struct {
+                name: n2,
+                parameters: p2,
+            },
+        ) if n1 == n2 => {
+            for (p1, p2) in p1.iter().zip(p2) {
+                if let (Parameter::Ty(p1), Parameter::Ty(p2)) = (p1, p2) {
+                    infer_ty_generics(generic_decls, p1, p2, substitution);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Returns all the origins appearing in `ty`, in order of appearance. The origins bound by
+/// function pointer types are not included, as they are not origins of the value.
+pub fn origins_of_ty(ty: &Ty) -> Vec<&Name> {
+    let mut origins = vec![];
+    let mut stack = vec![ty];
+    while let Some(ty) = stack.pop() {
+        match ty {
+            Ty::Ref { origin, ty } | Ty::RefMut { origin, ty } => {
+                origins.push(origin);
+                stack.push(ty);
+            }
+            Ty::I32 | Ty::Unit => {}
+            Ty::Struct { parameters, .. } => {
+                for parameter in parameters.iter().rev() {
+                    match parameter {
+                        Parameter::Origin(o) => origins.push(o),
+                        Parameter::Ty(ty) => stack.push(ty),
+                    }
+                }
+            }
+            Ty::Fn {
+                bound_origins,
+                arg_tys,
+                ret_ty,
+            } => {
+                let free = arg_tys
+                    .iter()
+                    .chain(Some(&**ret_ty))
+                    .flat_map(origins_of_ty)
+                    .filter(|origin| !bound_origins.contains(origin));
+                origins.extend(free);
+            }
+        }
+    }
+    origins
+}
+
+/// Replaces every origin in `ty` with a fresh origin named after `prefix`, except for the
+/// origins bound by function pointer types.
+pub fn freshen_ty(ty: &Ty, prefix: &str) -> Ty {
+    fn fresh(prefix: &str, counter: &mut usize) -> Name {
+        let origin = format!("'{}_{}", prefix, counter);
+        *counter += 1;
+        origin
+    }
+
+    fn freshen_origin(origin: &Name, bound: &[Name], prefix: &str, counter: &mut usize) -> Name {
+        if bound.contains(origin) {
+            origin.clone()
+        } else {
+            fresh(prefix, counter)
+        }
+    }
+
+    fn freshen(ty: &Ty, bound: &[Name], prefix: &str, counter: &mut usize) -> Ty {
+        match ty {
+            Ty::Ref { origin, ty } => Ty::Ref {
+                origin: freshen_origin(origin, bound, prefix, counter),
+                ty: Box::new(freshen(ty, bound, prefix, counter)),
+            },
+            Ty::RefMut { origin, ty } => Ty::RefMut {
+                origin: freshen_origin(origin, bound, prefix, counter),
+                ty: Box::new(freshen(ty, bound, prefix, counter)),
+            },
+            Ty::I32 => Ty::I32,
+            Ty::Unit => Ty::Unit,
+            Ty::Struct { name, parameters } => Ty::Struct {
+                name: name.clone(),
+                parameters: parameters
+                    .iter()
+                    .map(|parameter| match parameter {
+                        Parameter::Origin(origin) => {
+                            Parameter::Origin(freshen_origin(origin, bound, prefix, counter))
+                        }
+                        Parameter::Ty(ty) => Parameter::Ty(freshen(ty, bound, prefix, counter)),
+                    })
+                    .collect(),
+            },
+            Ty::Fn {
+                bound_origins,
+                arg_tys,
+                ret_ty,
+            } => {
+                let bound: Vec<_> = bound.iter().chain(bound_origins).cloned().collect();
+                Ty::Fn {
+                    bound_origins: bound_origins.clone(),
+                    arg_tys: arg_tys
+                        .iter()
+                        .map(|ty| freshen(ty, &bound, prefix, counter))
+                        .collect(),
+                    ret_ty: Box::new(freshen(ret_ty, &bound, prefix, counter)),
+                }
+            }
+        }
+    }
+
+    freshen(ty, &[], prefix, &mut 0)
+}
@@ -0,0 +1,437 @@
+//! Well-formedness checks on a parsed program, run before emitting facts.
+
+use std::collections::{BTreeSet, HashSet};
+
+use eyre::WrapErr;
+use itertools::Itertools;
+
+use crate::ast::visit::{walk_expr, walk_ty, ProgramVisitor};
+use crate::ast::{
+    AccessKind, Expr, GenericDecl, Place, Program, Scrutinee, Statement, Successor, Ty,
+};
+use crate::declarations::{ItemDeclaration, ItemKind};
+use crate::ir;
+use crate::typeck::{self, TypeEnv};
+
+#[cfg(test)]
+mod test;
+
+/// Checks that the generic arguments of every struct type in the program match the generics
+/// declared by the struct, in number and kind. Generics can be declared in any order, e.g.
+/// `struct Pair<T, 'a, U>`, so each argument is matched against the declaration at the same
+/// position. The bounds of type parameters, like `T: 'a`, must name origins declared by the
+/// struct.
+///
+/// Items must have distinct names, as later passes use the first declaration with a name.
+///
+/// Like rustc, it also rejects mutable borrows of locals not declared with `let mut`, and
+/// assignments to them once they may have been initialized, and checks that each `match` has
+/// exactly one arm per variant.
+pub fn validate(program: &Program) -> eyre::Result<()> {
+    check_duplicates(program)?;
+    let env = TypeEnv::new(program);
+
+    for decl in &program.struct_decls {
+        check_bounds(&decl.generic_decls)
+            .wrap_err_with(|| format!("in the generics of `{}`", decl.name))?;
+        for field in &decl.field_decls {
+            check_ty(&env, &decl.generic_decls, &field.ty).wrap_err_with(|| {
+                format!("in the type of field `{}` of `{}`", field.name, decl.name)
+            })?;
+        }
+    }
+
+    for prototype in &program.fn_prototypes {
+        if let Some(GenericDecl::Ty(generic, _)) = prototype
+            .generic_decls
+            .iter()
+            .find(|g| matches!(g, GenericDecl::Ty(_, bounds) if !bounds.is_empty()))
+        {
+            eyre::bail!(
+                "bounds on the type parameters of functions are not supported, as on `{}` of `{}`",
+                generic,
+                prototype.name
+            );
+        }
+        for ty in prototype.arg_tys.iter().chain(Some(&prototype.ret_ty)) {
+            check_ty(&env, &prototype.generic_decls, ty)
+                .wrap_err_with(|| format!("in the signature of `{}`", prototype.name))?;
+        }
+    }
+
+    for variable in &program.variables {
+        check_ty(&env, &[], &variable.ty)
+            .wrap_err_with(|| format!("in the type of `{}`", variable.name))?;
+    }
+
+    for block in &program.basic_blocks {
+        match &block.scrutinee {
+            Some(Scrutinee::Match(place)) => check_match(&env, place, &block.successors)
+                .wrap_err_with(|| format!("in the `match` of `{}`", block.name))?,
+            Some(Scrutinee::Switch(place)) => check_switch(&env, place, &block.successors)
+                .wrap_err_with(|| format!("in the `switch` of `{}`", block.name))?,
+            None => {}
+        }
+    }
+
+    check_mutability(program, &env)
+}
+
+/// Rejects the items declared more than once, showing the line of both declarations. The
+/// parser calls it with the items it records, see `OriginDeclarations::items`, as the AST
+/// doesn't keep source positions.
+pub(crate) fn check_declared_once(items: &[ItemDeclaration]) -> eyre::Result<()> {
+    for (i, item) in items.iter().enumerate() {
+        if let Some(first) = items[..i]
+            .iter()
+            .find(|first| first.kind == item.kind && first.name == item.name)
+        {
+            let duplicate = eyre::eyre!(
+                "the {} `{}` is declared twice, at line {} and at line {}",
+                item.kind.name(),
+                item.name,
+                first.line,
+                item.line
+            );
+            return Err(match &item.kind {
+                ItemKind::Field(decl) => duplicate.wrap_err(format!("in `{}`", decl)),
+                _ => duplicate,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Rejects the types, functions, variables, blocks and struct fields declared more than once,
+/// showing both declarations. A parsed program is checked with the lines of its declarations
+/// by `check_declared_once`, so this is for the programs built without the parser, and for
+/// the items of modules and macros.
+fn check_duplicates(program: &Program) -> eyre::Result<()> {
+    let types = program
+        .extern_types
+        .iter()
+        .map(|name| (name.as_str(), format!("extern type {};", name)))
+        .chain(
+            program
+                .struct_decls
+                .iter()
+                .map(|decl| (decl.name.as_str(), decl.to_string())),
+        );
+    check_unique("type", types)?;
+
+    let fns = program
+        .fn_prototypes
+        .iter()
+        .map(|prototype| (prototype.name.as_str(), prototype.to_string()));
+    check_unique("function", fns)?;
+
+    for decl in &program.struct_decls {
+        let fields = decl
+            .field_decls
+            .iter()
+            .map(|field| (field.name.as_str(), field.to_string()));
+        check_unique("field", fields).wrap_err_with(|| format!("in `{}`", decl.name))?;
+    }
+
+    let variables = program.variables.iter().map(|variable| {
+        let mutable = if variable.mutable { "mut " } else { "" };
+        (
+            variable.name.as_str(),
+            format!("let {}{};", mutable, variable),
+        )
+    });
+    check_unique("variable", variables)?;
+
+    // Blocks are printed whole, with their terminator, on a single line.
+    let blocks = program.basic_blocks.iter().map(|block| {
+        let printed = block.to_string();
+        (
+            block.name.as_str(),
+            printed.lines().map(str::trim).join(" "),
+        )
+    });
+    check_unique("block", blocks)
+}
+
+/// Checks that the `(name, declaration)` pairs of `items` have distinct names.
+fn check_unique<'a>(
+    kind: &str,
+    items: impl Iterator<Item = (&'a str, String)>,
+) -> eyre::Result<()> {
+    let mut declared: Vec<(&str, String)> = vec![];
+    for (name, declaration) in items {
+        if let Some((_, first)) = declared.iter().find(|(n, _)| *n == name) {
+            eyre::bail!(
+                "the {} `{}` is declared twice, as `{}` and as `{}`",
+                kind,
+                name,
+                first,
+                declaration
+            );
+        }
+        declared.push((name, declaration));
+    }
+    Ok(())
+}
+
+/// Checks that `scrutinee` is a builtin enum, and that the arms of its `match` are its
+/// variants, each exactly once.
+fn check_match(env: &TypeEnv<'_>, scrutinee: &Place, arms: &[Successor]) -> eyre::Result<()> {
+    let ty = env.place_ty(scrutinee)?;
+    let variants = match &ty {
+        Ty::Struct { name, .. } => env.enum_variants(name),
+        _ => None,
+    }
+    .ok_or_else(|| eyre::eyre!("cannot match on `{}`, of type `{}`", scrutinee, ty))?;
+
+    for (i, arm) in arms.iter().enumerate() {
+        let variant = arm.label.as_deref().unwrap_or_default();
+        if !variants.contains(&variant) {
+            eyre::bail!("`{}` is not a variant of `{}`", variant, ty);
+        }
+        if arms[..i].iter().any(|a| a.label == arm.label) {
+            eyre::bail!("the variant `{}` has several arms", variant);
+        }
+    }
+    if let Some(missing) = variants
+        .iter()
+        .find(|v| arms.iter().all(|a| a.label.as_deref() != Some(**v)))
+    {
+        eyre::bail!("the variant `{}` has no arm", missing);
+    }
+    Ok(())
+}
+
+/// Checks that `scrutinee` is an `i32`, and that the arms of its `switch` are distinct values,
+/// followed by a single `_` arm.
+fn check_switch(env: &TypeEnv<'_>, scrutinee: &Place, arms: &[Successor]) -> eyre::Result<()> {
+    let ty = env.place_ty(scrutinee)?;
+    if ty != Ty::I32 {
+        eyre::bail!("cannot switch on `{}`, of type `{}`", scrutinee, ty);
+    }
+    let values = match arms.split_last() {
+        Some((otherwise, values)) if otherwise.label.is_none() => values,
+        _ => eyre::bail!("the last arm must be `_`"),
+    };
+    for (i, arm) in values.iter().enumerate() {
+        let value = match &arm.label {
+            Some(value) => value
+                .parse::<i32>()
+                .map_err(|_| eyre::eyre!("`{}` is not a value of `{}`", value, ty))?,
+            None => eyre::bail!("the `_` arm to `{}` is not the last one", arm.block),
+        };
+        if values[..i].iter().any(|a| a.label == arm.label) {
+            eyre::bail!("the value `{}` has several arms", value);
+        }
+    }
+    Ok(())
+}
+
+/// Computes the immutable locals which may be initialized on entry to each block, and checks
+/// the statements of each block against them. A block is checked once its entry state has
+/// reached a fixpoint, so that errors are only reported once.
+fn check_mutability(program: &Program, env: &TypeEnv<'_>) -> eyre::Result<()> {
+    // The return place isn't a binding, like `_0` in MIR.
+    let immutable = ImmutableLocals {
+        env,
+        names: program
+            .variables
+            .iter()
+            .filter(|v| !v.mutable && v.name != ir::RETURN_PLACE)
+            .map(|v| v.name.as_str())
+            .collect(),
+    };
+
+    let successors: Vec<Vec<usize>> = program
+        .basic_blocks
+        .iter()
+        .map(|block| {
+            block
+                .successors
+                .iter()
+                .filter_map(|s| program.basic_blocks.iter().position(|b| b.name == s.block))
+                .collect()
+        })
+        .collect();
+
+    let mut entry_states: Vec<BTreeSet<&str>> = vec![BTreeSet::new(); program.basic_blocks.len()];
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for (index, block) in program.basic_blocks.iter().enumerate() {
+            let mut initialized = entry_states[index].clone();
+            for statement in &block.statements {
+                if let Some(local) = assigned_local(statement, &immutable) {
+                    initialized.insert(local);
+                }
+            }
+            for &successor in &successors[index] {
+                let state = &mut entry_states[successor];
+                let len = state.len();
+                state.extend(initialized.iter().copied());
+                changed |= state.len() != len;
+            }
+        }
+    }
+
+    for (index, block) in program.basic_blocks.iter().enumerate() {
+        let mut initialized = entry_states[index].clone();
+        for statement in &block.statements {
+            check_statement_mutability(statement, &immutable, &initialized)
+                .wrap_err_with(|| format!("in statement `{}` of `{}`", statement, block.name))?;
+            if let Some(local) = assigned_local(statement, &immutable) {
+                initialized.insert(local);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// The locals not declared with `let mut`.
+struct ImmutableLocals<'e, 'p> {
+    env: &'e TypeEnv<'p>,
+    names: HashSet<&'p str>,
+}
+
+impl ImmutableLocals<'_, '_> {
+    /// The immutable local whose storage is `place`, or contains it. Places behind a
+    /// reference, including through an implicit dereference like `x.f` for a reference `x`,
+    /// aren't part of the storage of the local holding the reference.
+    fn local_of<'a>(&self, place: &'a Place) -> Option<&'a str> {
+        let (base, _) = typeck::projections(place);
+        if !self.names.contains(base) {
+            return None;
+        }
+        // Places which don't type check are reported when emitting facts.
+        let place = ir::lower_place(self.env, place).unwrap_or_else(|_| place.into());
+        if place.is_local() {
+            Some(base)
+        } else {
+            None
+        }
+    }
+}
+
+/// The immutable local whose storage is written by `statement`, if any. Writes through a
+/// reference don't write the storage of the local holding the reference.
+fn assigned_local<'a>(statement: &'a Statement, immutable: &ImmutableLocals) -> Option<&'a str> {
+    match statement {
+        Statement::Assign(place, _) => immutable.local_of(place),
+        Statement::Drop(_)
+        | Statement::DropPlace(_)
+        | Statement::Assert(_)
+        | Statement::Pass
+        | Statement::Outlives(..) => None,
+    }
+}
+
+fn check_statement_mutability(
+    statement: &Statement,
+    immutable: &ImmutableLocals,
+    initialized: &BTreeSet<&str>,
+) -> eyre::Result<()> {
+    let expr = match statement {
+        Statement::Assign(_, expr) | Statement::Drop(expr) => expr,
+        Statement::DropPlace(_)
+        | Statement::Assert(_)
+        | Statement::Pass
+        | Statement::Outlives(..) => return Ok(()),
+    };
+    MutableBorrowChecker { immutable }.visit_expr(expr)?;
+
+    if let Some(local) = assigned_local(statement, immutable) {
+        if initialized.contains(local) {
+            eyre::bail!(
+                "cannot assign twice to `{}`, which is not declared with `let mut`",
+                local
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Rejects the mutable borrows of immutable locals in an expression.
+struct MutableBorrowChecker<'i, 'e, 'p> {
+    immutable: &'i ImmutableLocals<'e, 'p>,
+}
+
+impl<'ast> ProgramVisitor<'ast> for MutableBorrowChecker<'_, '_, '_> {
+    fn visit_expr(&mut self, expr: &'ast Expr) -> eyre::Result<()> {
+        if let Expr::Access {
+            kind: AccessKind::BorrowMut(_),
+            place,
+        } = expr
+        {
+            if let Some(local) = self.immutable.local_of(place) {
+                eyre::bail!(
+                    "cannot borrow `{}` as mutable, as `{}` is not declared with `let mut`",
+                    place,
+                    local
+                );
+            }
+        }
+        walk_expr(self, expr)
+    }
+}
+
+/// Checks that the bounds of the type parameters are origins declared in the same generics.
+fn check_bounds(generics: &[GenericDecl]) -> eyre::Result<()> {
+    for generic in generics {
+        if let GenericDecl::Ty(name, bounds) = generic {
+            for bound in bounds {
+                let declared = generics
+                    .iter()
+                    .any(|g| matches!(g, GenericDecl::Origin(o) if o == bound));
+                if !declared {
+                    eyre::bail!("the bound `{}: {}` names an undeclared origin", name, bound);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Checks the struct and function pointer types in `ty`, where `generics` are in scope.
+fn check_ty(env: &TypeEnv<'_>, generics: &[GenericDecl], ty: &Ty) -> eyre::Result<()> {
+    TyChecker { env, generics }.visit_ty(ty)
+}
+
+struct TyChecker<'a, 'p> {
+    env: &'a TypeEnv<'p>,
+    generics: &'a [GenericDecl],
+}
+
+impl<'ast> ProgramVisitor<'ast> for TyChecker<'_, '_> {
+    fn visit_ty(&mut self, ty: &'ast Ty) -> eyre::Result<()> {
+        match ty {
+            Ty::Struct { name, parameters } => {
+                let is_generic = self
+                    .generics
+                    .iter()
+                    .any(|g| matches!(g, GenericDecl::Ty(n, _) if n == name));
+                if is_generic {
+                    if !parameters.is_empty() {
+                        eyre::bail!("type parameter `{}` cannot have generic arguments", name);
+                    }
+                    return Ok(());
+                }
+
+                // Undeclared types are treated as opaque.
+                if let Ok(decl) = self.env.struct_decl(name) {
+                    typeck::substitution(&decl.generic_decls, parameters)
+                        .wrap_err_with(|| format!("in the generic arguments of `{}`", name))?;
+                }
+            }
+            Ty::Fn { bound_origins, .. } => {
+                for (i, origin) in bound_origins.iter().enumerate() {
+                    if bound_origins[..i].contains(origin) {
+                        eyre::bail!("origin `{}` is bound more than once in `{}`", origin, ty);
+                    }
+                }
+            }
+            Ty::Ref { .. } | Ty::RefMut { .. } | Ty::I32 | Ty::Unit => {}
+        }
+        walk_ty(self, ty)
+    }
+}
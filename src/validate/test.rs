@@ -0,0 +1,273 @@
+use super::*;
+
+fn validate_str(s: &str) -> eyre::Result<()> {
+    validate(&crate::parse_ast(s).unwrap())
+}
+
+#[test]
+fn generics_in_any_order() {
+    validate_str(
+        "
+        struct Pair<T, 'a, U> { first: &'a T, second: U }
+        fn make<U, 'b>(u: U, r: &'b i32) -> Pair<i32, 'b, U>;
+        let p: Pair<i32, 'p, ()>;
+    ",
+    )
+    .unwrap();
+}
+
+#[test]
+fn origin_for_type_parameter() {
+    let err = validate_str(
+        "
+        struct Pair<T, 'a> { first: &'a T }
+        let p: Pair<'p, i32>;
+    ",
+    )
+    .unwrap_err();
+    insta::assert_snapshot!(format!("{:#}", err), @"in the type of `p`: in the generic arguments of `Pair`: expected a type for `T`, found origin `'p`");
+}
+
+#[test]
+fn wrong_number_of_arguments() {
+    let err = validate_str(
+        "
+        struct Wrapper<'a> { r: &'a i32 }
+        fn get<'x>(w: Wrapper<'x, i32>) -> ();
+    ",
+    )
+    .unwrap_err();
+    insta::assert_snapshot!(format!("{:#}", err), @"in the signature of `get`: in the generic arguments of `Wrapper`: expected 1 generic parameters, found 2");
+}
+
+#[test]
+fn origin_bound_twice() {
+    let err = validate_str(
+        "
+        let f: for<'x, 'x> fn(&'x i32);
+    ",
+    )
+    .unwrap_err();
+    insta::assert_snapshot!(format!("{:#}", err), @"in the type of `f`: origin `'x` is bound more than once in `for<'x, 'x> fn(&'x i32)`");
+}
+
+#[test]
+fn deferred_initialization() {
+    // Like in Rust, an immutable local can be initialized once on each path.
+    validate_str(
+        "
+        let x: i32;
+        let r: &'r mut i32;
+        let mut y: i32;
+        bb0: {
+            y = 1;
+            y = 2;
+            r = &'L_y mut y;
+            *r = 3;
+            goto bb1, bb2;
+        }
+        bb1: {
+            x = 1;
+        }
+        bb2: {
+            x = 2;
+        }
+    ",
+    )
+    .unwrap();
+}
+
+#[test]
+fn assign_twice() {
+    let err = validate_str(
+        "
+        let x: i32;
+        bb0: {
+            x = 1;
+            x = 2;
+        }
+    ",
+    )
+    .unwrap_err();
+    insta::assert_snapshot!(format!("{:#}", err), @"in statement `x = 2` of `bb0`: cannot assign twice to `x`, which is not declared with `let mut`");
+}
+
+#[test]
+fn assign_in_loop() {
+    let err = validate_str(
+        "
+        let x: i32;
+        bb0: {
+            goto bb1;
+        }
+        bb1: {
+            x = 1;
+            goto bb1;
+        }
+    ",
+    )
+    .unwrap_err();
+    insta::assert_snapshot!(format!("{:#}", err), @"in statement `x = 1` of `bb1`: cannot assign twice to `x`, which is not declared with `let mut`");
+}
+
+#[test]
+fn mutable_borrow_of_immutable_local() {
+    let err = validate_str(
+        "
+        struct Pair { first: i32 }
+        fn use<'a>(r: &'a mut i32) -> ();
+        let p: Pair;
+        bb0: {
+            use(&'L_p mut p.first);
+        }
+    ",
+    )
+    .unwrap_err();
+    insta::assert_snapshot!(format!("{:#}", err), @"in statement `use(&'L_p mut p.first)` of `bb0`: cannot borrow `p.first` as mutable, as `p` is not declared with `let mut`");
+}
+
+#[test]
+fn undeclared_bound() {
+    let err = validate_str(
+        "
+        struct Wrapper<'a, T: 'b> { r: &'a T }
+    ",
+    )
+    .unwrap_err();
+    insta::assert_snapshot!(format!("{:#}", err), @"in the generics of `Wrapper`: the bound `T: 'b` names an undeclared origin");
+}
+
+#[test]
+fn bound_on_fn_generic() {
+    let err = validate_str(
+        "
+        fn wrap<'b, U: 'b>(r: &'b U) -> ();
+    ",
+    )
+    .unwrap_err();
+    insta::assert_snapshot!(format!("{:#}", err), @"bounds on the type parameters of functions are not supported, as on `U` of `wrap`");
+}
+
+#[test]
+fn write_through_implicit_deref() {
+    // `r.first` is `(*r).first`, which isn't part of the storage of `r`.
+    validate_str(
+        "
+        struct Pair { first: i32 }
+        fn use<'a>(r: &'a mut i32) -> ();
+        let mut p: Pair;
+        let r: &'r mut Pair;
+        bb0: {
+            r = &'L_p mut p;
+            r.first = 1;
+            r.first = 2;
+            use(&'L_r mut r.first);
+        }
+    ",
+    )
+    .unwrap();
+}
+
+#[test]
+fn match_arms() {
+    let program = |terminator: &str| {
+        format!(
+            "
+            let x: i32;
+            let o: Option<i32>;
+            bb0: {{ {} }}
+            bb1: {{ }}
+            ",
+            terminator
+        )
+    };
+    validate_str(&program("match o { None => bb1, Some => bb1 }")).unwrap();
+
+    let errors: Vec<String> = [
+        "match x { Some => bb1, None => bb1 }",
+        "match o { Some => bb1, Ok => bb1 }",
+        "match o { Some => bb1, Some => bb1, None => bb1 }",
+        "match o { Some => bb1 }",
+    ]
+    .iter()
+    .map(|terminator| format!("{:#}", validate_str(&program(terminator)).unwrap_err()))
+    .collect();
+    insta::assert_debug_snapshot!(errors, @r###"
+    [
+        "in the `match` of `bb0`: cannot match on `x`, of type `i32`",
+        "in the `match` of `bb0`: `Ok` is not a variant of `Option<i32>`",
+        "in the `match` of `bb0`: the variant `Some` has several arms",
+        "in the `match` of `bb0`: the variant `None` has no arm",
+    ]
+    "###);
+}
+
+#[test]
+fn switch_arms() {
+    let program = |terminator: &str| {
+        format!(
+            "
+            let x: i32;
+            let o: Option<i32>;
+            bb0: {{ {} }}
+            bb1: {{ }}
+            ",
+            terminator
+        )
+    };
+    validate_str(&program("switch x { 1 => bb1, 0 => bb1, _ => bb1 }")).unwrap();
+    validate_str(&program("switch x { _ => bb1 }")).unwrap();
+
+    let errors: Vec<String> = [
+        "switch o { 0 => bb1, _ => bb1 }",
+        "switch x { 0 => bb1 }",
+        "switch x { _ => bb1, 0 => bb1 }",
+        "switch x { 0 => bb1, 0 => bb1, _ => bb1 }",
+        "switch x { 2147483648 => bb1, _ => bb1 }",
+    ]
+    .iter()
+    .map(|terminator| format!("{:#}", validate_str(&program(terminator)).unwrap_err()))
+    .collect();
+    insta::assert_debug_snapshot!(errors, @r###"
+    [
+        "in the `switch` of `bb0`: cannot switch on `o`, of type `Option<i32>`",
+        "in the `switch` of `bb0`: the last arm must be `_`",
+        "in the `switch` of `bb0`: the last arm must be `_`",
+        "in the `switch` of `bb0`: the value `0` has several arms",
+        "in the `switch` of `bb0`: `2147483648` is not a value of `i32`",
+    ]
+    "###);
+}
+
+#[test]
+fn duplicate_declarations() {
+    // The parser rejects the items declared twice, at the lines of both declarations.
+    let errors: Vec<String> = [
+        "extern type S;\nstruct S { a: i32 }",
+        "struct S {\n    a: i32,\n    b: (),\n    a: &'a i32,\n}",
+        "fn f() -> ();\nfn f(x: i32) -> i32;",
+        "let x: i32;\nlet x: i32;",
+        "bb0: { }\nbb1: { }\nbb0: { pass; goto bb1; }",
+        "bb0: { goto bb0; }\n\nbb0: { goto bb0; }",
+        "fn f() -> () {\n    let x: i32;\n    let x: i32;\n    bb0: { }\n}",
+    ]
+    .iter()
+    .map(|program| format!("{:#}", crate::parse_ast(program).unwrap_err()))
+    .collect();
+    insta::assert_debug_snapshot!(errors, @r###"
+    [
+        "the type `S` is declared twice, at line 1 and at line 2",
+        "in `S`: the field `a` is declared twice, at line 2 and at line 4",
+        "the function `f` is declared twice, at line 1 and at line 2",
+        "the variable `x` is declared twice, at line 1 and at line 2",
+        "the block `bb0` is declared twice, at line 1 and at line 3",
+        "the block `bb0` is declared twice, at line 1 and at line 3",
+        "in the body of `f`: the variable `x` is declared twice, at line 2 and at line 3",
+    ]
+    "###);
+
+    // A program built without the parser has no lines, so the declarations are printed.
+    let mut program = crate::parse_ast("let x: i32; let mut y: &'y i32; bb0: { }").unwrap();
+    program.variables[1].name = "x".to_string();
+    insta::assert_snapshot!(format!("{:#}", validate(&program).unwrap_err()), @"the variable `x` is declared twice, as `let x: i32;` and as `let mut x: &'y i32;`");
+}
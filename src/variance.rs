@@ -0,0 +1,180 @@
+//! The variance of the positions of a type, which decides the direction of the subsets required
+//! to relate two types, following rustc:
+//!
+//! * the origin of a reference is covariant, like the type behind a `&`,
+//! * the type behind a `&mut` is invariant,
+//! * the arguments of a function pointer are contravariant, and its result covariant,
+//! * the generic parameters of a struct have the variance inferred from its fields.
+
+use crate::ast::{self, Ty};
+use crate::features::{Feature, Unsupported};
+use crate::typeck::TypeEnv;
+
+#[cfg(test)]
+mod test;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Variance {
+    Covariant,
+    Contravariant,
+    Invariant,
+}
+
+impl Variance {
+    /// The variance of a position of variance `other`, nested in a position of variance `self`.
+    pub fn xform(self, other: Variance) -> Variance {
+        match (self, other) {
+            (Variance::Covariant, v) => v,
+            (Variance::Invariant, _) => Variance::Invariant,
+            (Variance::Contravariant, Variance::Covariant) => Variance::Contravariant,
+            (Variance::Contravariant, Variance::Contravariant) => Variance::Covariant,
+            (Variance::Contravariant, Variance::Invariant) => Variance::Invariant,
+        }
+    }
+}
+
+/// The variance of the positions of `name`, an origin or a type parameter, in `ty`: `None` if
+/// it doesn't appear in `ty`, and invariant if it appears with different variances. The
+/// variances of the structs in `ty` are inferred from their declarations in `env`.
+pub fn variance_of(env: &TypeEnv<'_>, ty: &Ty, name: &str) -> eyre::Result<Option<Variance>> {
+    let mut variances = [None];
+    collect_variances(
+        env,
+        ty,
+        Variance::Covariant,
+        &[name],
+        &mut variances,
+        &mut vec![],
+    )?;
+    Ok(variances[0])
+}
+
+/// The variance of each generic parameter of the struct `name`, inferred from how the
+/// parameters are used in its fields, like rustc does: e.g. a parameter only used behind a
+/// `&mut` is invariant. Unused parameters are treated as covariant.
+pub fn struct_variances(env: &TypeEnv<'_>, name: &str) -> eyre::Result<Vec<Variance>> {
+    struct_variances_visiting(env, name, &mut vec![])
+}
+
+fn struct_variances_visiting(
+    env: &TypeEnv<'_>,
+    name: &str,
+    visiting: &mut Vec<String>,
+) -> eyre::Result<Vec<Variance>> {
+    let decl = env.struct_decl(name).map_err(|_| Unsupported {
+        feature: Feature::OpaqueGenericTypes,
+        span: name.to_string(),
+    })?;
+    // A recursive use of the struct doesn't constrain its parameters any further.
+    if visiting.iter().any(|v| v == name) {
+        return Ok(vec![Variance::Covariant; decl.generic_decls.len()]);
+    }
+
+    let names: Vec<&str> = decl
+        .generic_decls
+        .iter()
+        .map(|g| match g {
+            ast::GenericDecl::Origin(n) | ast::GenericDecl::Ty(n, _) => n.as_str(),
+        })
+        .collect();
+    visiting.push(name.to_string());
+    let mut variances = vec![None; names.len()];
+    for field in &decl.field_decls {
+        collect_variances(
+            env,
+            &field.ty,
+            Variance::Covariant,
+            &names,
+            &mut variances,
+            visiting,
+        )?;
+    }
+    visiting.pop();
+
+    Ok(variances
+        .into_iter()
+        .map(|v| v.unwrap_or(Variance::Covariant))
+        .collect())
+}
+
+/// Records in `variances` the variance of each use of the origins or type parameters `names`
+/// in `ty`, which appears in a position of variance `variance`.
+fn collect_variances(
+    env: &TypeEnv<'_>,
+    ty: &Ty,
+    variance: Variance,
+    names: &[&str],
+    variances: &mut [Option<Variance>],
+    visiting: &mut Vec<String>,
+) -> eyre::Result<()> {
+    match ty {
+        Ty::Ref { origin, ty } => {
+            record_variance(names, variances, origin, variance);
+            collect_variances(env, ty, variance, names, variances, visiting)
+        }
+        Ty::RefMut { origin, ty } => {
+            record_variance(names, variances, origin, variance);
+            let variance = variance.xform(Variance::Invariant);
+            collect_variances(env, ty, variance, names, variances, visiting)
+        }
+        Ty::I32 | Ty::Unit => Ok(()),
+        Ty::Struct { name, parameters } if parameters.is_empty() => {
+            // Either a type parameter, or a struct without parameters.
+            record_variance(names, variances, name, variance);
+            Ok(())
+        }
+        Ty::Struct { name, parameters } => {
+            let inner = struct_variances_visiting(env, name, visiting)?;
+            for (parameter, v) in parameters.iter().zip(inner) {
+                match parameter {
+                    ast::Parameter::Origin(origin) => {
+                        record_variance(names, variances, origin, variance.xform(v))
+                    }
+                    ast::Parameter::Ty(ty) => {
+                        collect_variances(env, ty, variance.xform(v), names, variances, visiting)?
+                    }
+                }
+            }
+            Ok(())
+        }
+        Ty::Fn {
+            bound_origins,
+            arg_tys,
+            ret_ty,
+        } => {
+            // The origins bound by the function pointer shadow the ones with the same name.
+            let names: Vec<&str> = names
+                .iter()
+                .map(|&n| {
+                    if bound_origins.iter().any(|b| b == n) {
+                        ""
+                    } else {
+                        n
+                    }
+                })
+                .collect();
+            let contravariant = variance.xform(Variance::Contravariant);
+            for ty in arg_tys {
+                collect_variances(env, ty, contravariant, &names, variances, visiting)?;
+            }
+            collect_variances(env, ret_ty, variance, &names, variances, visiting)
+        }
+    }
+}
+
+/// Combines `variance` with the variance already recorded for `name`, if it is one of
+/// `names`: a name used with different variances is invariant.
+fn record_variance(
+    names: &[&str],
+    variances: &mut [Option<Variance>],
+    name: &str,
+    variance: Variance,
+) {
+    if let Some(i) = names.iter().position(|n| *n == name) {
+        variances[i] = match variances[i] {
+            None => Some(variance),
+            Some(v) if v == variance => Some(v),
+            Some(_) => Some(Variance::Invariant),
+        };
+    }
+}
@@ -0,0 +1,97 @@
+use super::*;
+use Variance::*;
+
+/// The variance of `name` in the type of the variable `v` declared by `program`.
+fn variance_in(program: &str, name: &str) -> Option<Variance> {
+    let program = crate::parse_ast(program).unwrap();
+    let ty = &program.variables.iter().find(|v| v.name == "v").unwrap().ty;
+    variance_of(&TypeEnv::new(&program), ty, name).unwrap()
+}
+
+#[test]
+fn xform_table() {
+    let all = [Covariant, Contravariant, Invariant];
+    let expected = [
+        [Covariant, Contravariant, Invariant],
+        [Contravariant, Covariant, Invariant],
+        [Invariant, Invariant, Invariant],
+    ];
+    for (outer, row) in all.iter().zip(expected) {
+        for (inner, expected) in all.iter().zip(row) {
+            assert_eq!(outer.xform(*inner), expected, "{:?} of {:?}", inner, outer);
+        }
+    }
+}
+
+#[test]
+fn references() {
+    let program = "let v: &'a mut &'b &'c i32;";
+    assert_eq!(variance_in(program, "'a"), Some(Covariant));
+    assert_eq!(variance_in(program, "'b"), Some(Invariant));
+    assert_eq!(variance_in(program, "'c"), Some(Invariant));
+    assert_eq!(variance_in(program, "'d"), None);
+
+    let program = "let v: &'a &'b i32;";
+    assert_eq!(variance_in(program, "'b"), Some(Covariant));
+}
+
+#[test]
+fn fn_pointers() {
+    let program = "let v: fn(&'a i32, &'b mut &'c i32) -> &'d i32;";
+    assert_eq!(variance_in(program, "'a"), Some(Contravariant));
+    assert_eq!(variance_in(program, "'b"), Some(Contravariant));
+    assert_eq!(variance_in(program, "'c"), Some(Invariant));
+    assert_eq!(variance_in(program, "'d"), Some(Covariant));
+
+    // An argument of an argument is covariant again, and of a result contravariant.
+    let program = "let v: fn(fn(&'a i32)) -> fn(&'b i32);";
+    assert_eq!(variance_in(program, "'a"), Some(Covariant));
+    assert_eq!(variance_in(program, "'b"), Some(Contravariant));
+
+    // Behind a `&mut`, everything is invariant.
+    let program = "let v: &'r mut fn(&'a i32) -> &'b i32;";
+    assert_eq!(variance_in(program, "'a"), Some(Invariant));
+    assert_eq!(variance_in(program, "'b"), Some(Invariant));
+}
+
+#[test]
+fn bound_origins_shadow() {
+    let program = "let v: fn(&'a i32) -> for<'a> fn(&'a i32);";
+    assert_eq!(variance_in(program, "'a"), Some(Contravariant));
+}
+
+#[test]
+fn mixed_uses_are_invariant() {
+    let program = "let v: fn(&'a i32) -> &'a i32;";
+    assert_eq!(variance_in(program, "'a"), Some(Invariant));
+}
+
+#[test]
+fn structs() {
+    let program = "
+        struct Sink<'s> { f: fn(&'s i32) }
+        struct Cell<'c, T> { r: &'c mut T }
+        let v: Sink<'a>;
+        let w: Cell<'b, Sink<'d>>;
+    ";
+    let parsed = crate::parse_ast(program).unwrap();
+    let env = TypeEnv::new(&parsed);
+    assert_eq!(struct_variances(&env, "Sink").unwrap(), [Contravariant]);
+    assert_eq!(
+        struct_variances(&env, "Cell").unwrap(),
+        [Covariant, Invariant]
+    );
+
+    assert_eq!(variance_in(program, "'a"), Some(Contravariant));
+    let w = &parsed.variables[1].ty;
+    assert_eq!(variance_of(&env, w, "'b").unwrap(), Some(Covariant));
+    assert_eq!(variance_of(&env, w, "'d").unwrap(), Some(Invariant));
+
+    // A struct containing a contravariant struct behind a function argument is covariant.
+    let program = "
+        struct Sink<'s> { f: fn(&'s i32) }
+        struct Source<'t> { f: fn(Sink<'t>) }
+        let v: Source<'a>;
+    ";
+    assert_eq!(variance_in(program, "'a"), Some(Covariant));
+}
@@ -0,0 +1,168 @@
+//! `polonius watch <dir>`: re-runs the examples of a directory when they, or the rules, change.
+//!
+//! Each example is a `tests/<example>` directory of facts checked with `test_harness`, or an
+//! AST program checked with `assertion_harness`. There are no file system notifications
+//! without extra dependencies, so the modification times are polled instead.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use eyre::WrapErr;
+
+use crate::config::Config;
+
+#[cfg(test)]
+mod test;
+
+/// How often the modification times are polled.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// The result of checking an example.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    Pass,
+
+    /// The example ran, but the solver output isn't the expected one.
+    Fail(String),
+
+    /// The example couldn't run, e.g. its program doesn't parse.
+    Error(String),
+}
+
+/// Prints the outcome on one line, with the first line of its message.
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let first_line = |message: &str| message.lines().next().unwrap_or("").to_string();
+        match self {
+            Outcome::Pass => write!(f, "pass"),
+            Outcome::Fail(message) => write!(f, "FAIL: {}", first_line(message)),
+            Outcome::Error(message) => write!(f, "ERROR: {}", first_line(message)),
+        }
+    }
+}
+
+/// The examples in `dir`, sorted: its subdirectories with a `program.txt`, and its `.ast` files.
+pub fn examples(dir: &Path) -> eyre::Result<Vec<PathBuf>> {
+    let entries =
+        std::fs::read_dir(dir).wrap_err_with(|| format!("failed to read `{}`", dir.display()))?;
+    let mut examples = vec![];
+    for entry in entries {
+        let path = entry?.path();
+        let is_fact_example = path.join("program.txt").is_file();
+        let is_ast_example = path.extension().is_some_and(|ext| ext == "ast");
+        if is_fact_example || is_ast_example {
+            examples.push(path);
+        }
+    }
+    examples.sort();
+    Ok(examples)
+}
+
+/// Checks the example at `path`. Inputs which don't parse or can't be emitted are errors, and
+/// the other problems reported by the harnesses are failures.
+pub fn check_example(path: &Path, config: &Config) -> Outcome {
+    let path_str = path.display().to_string();
+    let (input, result) = if path.is_dir() {
+        let input = std::fs::read_to_string(path.join("program.txt"))
+            .map_err(eyre::Report::from)
+            .and_then(|data| crate::parse_fact_program(&data).map(|_| ()));
+        (input, crate::test_harness(&path_str, config))
+    } else {
+        let input = std::fs::read_to_string(path)
+            .map_err(eyre::Report::from)
+            .and_then(|data| crate::emit_expectations(&data, config.emitter.clone()).map(|_| ()));
+        (input, crate::assertion_harness(&path_str, config))
+    };
+    match (input, result) {
+        (Err(e), _) => Outcome::Error(format!("{:#}", e)),
+        (Ok(()), Err(e)) => Outcome::Fail(format!("{:#}", e)),
+        (Ok(()), Ok(())) => Outcome::Pass,
+    }
+}
+
+/// Describes how the outcomes changed, one line per example whose outcome is new or changed,
+/// followed by the totals.
+pub fn delta(
+    before: &BTreeMap<PathBuf, Outcome>,
+    after: &BTreeMap<PathBuf, Outcome>,
+) -> Vec<String> {
+    let mut lines = vec![];
+    for (path, outcome) in after {
+        if before.get(path) != Some(outcome) {
+            lines.push(format!("{}: {}", path.display(), outcome));
+        }
+    }
+    for path in before.keys().filter(|path| !after.contains_key(*path)) {
+        lines.push(format!("{}: removed", path.display()));
+    }
+
+    let count = |f: fn(&Outcome) -> bool| after.values().filter(|o| f(o)).count();
+    lines.push(format!(
+        "{} passed, {} failed, {} errors",
+        count(|o| *o == Outcome::Pass),
+        count(|o| matches!(o, Outcome::Fail(_))),
+        count(|o| matches!(o, Outcome::Error(_))),
+    ));
+    lines
+}
+
+/// The latest modification time of the files of the example at `path`.
+fn modified(path: &Path) -> Option<SystemTime> {
+    let files: Vec<PathBuf> = if path.is_dir() {
+        vec![
+            path.join("program.txt"),
+            path.join("invalidated_origin_accessed.csv"),
+            path.join("expected.txt"),
+        ]
+    } else {
+        vec![path.to_path_buf()]
+    };
+    files
+        .iter()
+        .filter_map(|file| std::fs::metadata(file).and_then(|m| m.modified()).ok())
+        .max()
+}
+
+/// Checks the examples of `dir`, then re-checks the ones that change, or all of them when the
+/// rules at `config.rules_path` change, printing the delta after each run. Never returns,
+/// unless `dir` can't be read.
+pub fn watch(dir: &Path, config: &Config) -> eyre::Result<()> {
+    let mut outcomes: BTreeMap<PathBuf, Outcome> = BTreeMap::new();
+    let mut stamps: BTreeMap<PathBuf, Option<SystemTime>> = BTreeMap::new();
+    let mut rules_stamp = None;
+    loop {
+        let new_rules_stamp = modified(&config.rules_path);
+        let rules_changed = new_rules_stamp != rules_stamp;
+        rules_stamp = new_rules_stamp;
+
+        let mut new_outcomes = BTreeMap::new();
+        let mut new_stamps = BTreeMap::new();
+        let mut changed = rules_changed;
+        for example in examples(dir)? {
+            let stamp = modified(&example);
+            let outcome = match outcomes.get(&example) {
+                Some(outcome) if !rules_changed && stamps.get(&example) == Some(&stamp) => {
+                    outcome.clone()
+                }
+                _ => {
+                    changed = true;
+                    check_example(&example, config)
+                }
+            };
+            new_stamps.insert(example.clone(), stamp);
+            new_outcomes.insert(example, outcome);
+        }
+        changed |= new_outcomes.len() != outcomes.len();
+
+        if changed {
+            for line in delta(&outcomes, &new_outcomes) {
+                println!("{}", line);
+            }
+        }
+        outcomes = new_outcomes;
+        stamps = new_stamps;
+        std::thread::sleep(POLL_INTERVAL);
+    }
+}
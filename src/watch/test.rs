@@ -0,0 +1,88 @@
+use super::*;
+
+/// A scratch directory with a passing and a broken fact example, and an AST example with an
+/// unmet expectation.
+fn example_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("polonius-watch-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+
+    let pass = dir.join("pass");
+    std::fs::create_dir_all(&pass).unwrap();
+    std::fs::write(
+        pass.join("program.txt"),
+        "a: \"x = 1\" { goto b }\nb: \"use(x)\" { access_origin('x) goto }\n",
+    )
+    .unwrap();
+    std::fs::write(pass.join("invalidated_origin_accessed.csv"), "").unwrap();
+
+    let broken = dir.join("broken");
+    std::fs::create_dir_all(&broken).unwrap();
+    std::fs::write(broken.join("program.txt"), "a: {").unwrap();
+
+    std::fs::write(
+        dir.join("unmet.ast"),
+        "let x: i32; bb0: { assert_error; x = 1; }",
+    )
+    .unwrap();
+    std::fs::write(dir.join("notes.txt"), "not an example").unwrap();
+    dir
+}
+
+#[test]
+fn example_outcomes() {
+    let dir = example_dir("outcomes");
+    let config = Config::from_vars(|_| None).unwrap();
+    let outcomes: Vec<String> = examples(&dir)
+        .unwrap()
+        .iter()
+        .map(|example| {
+            let name = example.file_name().unwrap().to_string_lossy().to_string();
+            let outcome = check_example(example, &config).to_string();
+            format!(
+                "{}: {}",
+                name,
+                outcome.replace(&dir.display().to_string(), "<dir>")
+            )
+        })
+        .collect();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    insta::assert_debug_snapshot!(outcomes, @r###"
+    [
+        "broken: ERROR: failed to parse input: error at 1:4: expected ['\"']",
+        "pass: pass",
+        "unmet.ast: FAIL: `<dir>/unmet.ast`: unmet expectations:",
+    ]
+    "###);
+}
+
+#[test]
+fn delta_lists_changed_outcomes() {
+    let before: BTreeMap<PathBuf, Outcome> = vec![
+        (PathBuf::from("a"), Outcome::Pass),
+        (
+            PathBuf::from("b"),
+            Outcome::Fail("differs\n+ tuple".to_string()),
+        ),
+        (PathBuf::from("c"), Outcome::Pass),
+    ]
+    .into_iter()
+    .collect();
+    let after: BTreeMap<PathBuf, Outcome> = vec![
+        (PathBuf::from("a"), Outcome::Pass),
+        (PathBuf::from("b"), Outcome::Pass),
+        (
+            PathBuf::from("d"),
+            Outcome::Error("doesn't parse".to_string()),
+        ),
+    ]
+    .into_iter()
+    .collect();
+
+    insta::assert_snapshot!(delta(&before, &after).join("\n"), @r###"
+    b: pass
+    d: ERROR: doesn't parse
+    c: removed
+    2 passed, 0 failed, 1 errors
+    "###);
+}
@@ -1,16 +1,86 @@
-use polonius;
+use polonius::config::Config;
+use polonius::fact_emitter::Liveness;
+
+fn config_with(liveness: Liveness) -> eyre::Result<Config> {
+    let mut config = Config::from_env()?;
+    config.emitter.liveness = liveness;
+    Ok(config)
+}
 
 #[test]
 fn example_a() -> eyre::Result<()> {
-    polonius::test_harness("tests/example-a")
+    polonius::test_harness("tests/example-a", &Config::from_env()?)
 }
 
 #[test]
 fn issue_47680() -> eyre::Result<()> {
-    polonius::test_harness("tests/issue-47680")
+    polonius::test_harness("tests/issue-47680", &Config::from_env()?)
 }
 
 #[test]
 fn vec_temp() -> eyre::Result<()> {
-    polonius::test_harness("tests/vec-temp")
+    polonius::test_harness("tests/vec-temp", &Config::from_env()?)
+}
+
+#[test]
+fn assertions() -> eyre::Result<()> {
+    polonius::assertion_harness(
+        "tests/assertions.ast",
+        &config_with(Liveness::AccessImpliesLive)?,
+    )
+}
+
+#[test]
+fn loops() -> eyre::Result<()> {
+    polonius::assertion_harness(
+        "tests/loops.ast",
+        &config_with(Liveness::AccessImpliesLive)?,
+    )
+}
+
+#[test]
+fn explicit_liveness() -> eyre::Result<()> {
+    polonius::assertion_harness(
+        "tests/explicit-liveness.ast",
+        &config_with(Liveness::Explicit)?,
+    )
+}
+
+#[test]
+fn emission_budgets() -> eyre::Result<()> {
+    polonius::emission_budget_harness("tests/emission-budgets.txt", &Config::from_env()?)
+}
+
+#[test]
+fn explicit_liveness_needs_explicit_facts() -> eyre::Result<()> {
+    // `'second` is never accessed after `p.first` is borrowed, so it's only live when
+    // liveness comes from the variables.
+    let config = config_with(Liveness::AccessImpliesLive)?;
+    let err = polonius::assertion_harness("tests/explicit-liveness.ast", &config).unwrap_err();
+    assert!(
+        err.to_string().contains("`assert_live('second)` at"),
+        "{}",
+        err
+    );
+    // The message points at where the origin is declared.
+    assert!(
+        err.to_string()
+            .contains("origin `'second` declared in the type of `p` at line 14"),
+        "{}",
+        err
+    );
+    Ok(())
+}
+
+#[test]
+fn annotations() -> eyre::Result<()> {
+    polonius::annotation_harness("tests/annotations.ast", &Config::from_env()?)
+}
+
+#[test]
+fn function_bodies() -> eyre::Result<()> {
+    polonius::assertion_harness(
+        "tests/function-bodies.ast",
+        &config_with(Liveness::AccessImpliesLive)?,
+    )
 }